@@ -1,19 +1,88 @@
 //! Simple single-threaded Approximator for AST
 
-use crate::ast::{helper::Simple, simplify::Simplify};
+use crate::{
+    ast::{
+        helper::{NumberCompare, Simple},
+        simplify::Simplify,
+    },
+    extrapolation::{self, Extrapolation},
+    functions::ForeignFunction,
+    identifier::{ModifierType, OtherSymbol},
+    matrix::{self, Vector},
+    quadrature::{self, Quadrature},
+    solver::{self, Domain, LinearSolution, Polynomial},
+};
+use num_complex::Complex64;
 
 use super::prelude::*;
 
+/// How many times L'Hôpital's rule is applied before falling back to
+/// numeric extrapolation when evaluating a limit.
+const MAX_LHOPITAL_DEPTH: usize = 4;
+
+/// How deeply user defined functions may call each other, or themselves,
+/// before evaluation is stopped.
+pub const MAX_CALL_DEPTH: usize = 64;
+
+/// How many terms a sum or product may have, since every term is evaluated
+/// on its own.
+pub const MAX_TERMS: u64 = 1_000_000;
+
+/// The difference of the sides of an equation, which is zero when the
+/// equation holds.
+fn difference(lhs: &MathExpr, rhs: &MathExpr) -> MathExpr {
+    MathExpr::Subtract(
+        lhs.clone().boxed(),
+        Factor::Parenthesis(rhs.clone().boxed()).into(),
+    )
+}
+
 /// A simple single-threaded evaluator for an AST.
+#[derive(Clone)]
 pub struct Approximator {
     /// the MathContext holding all the defined functions
     context: MathContext,
+    /// How many user defined functions are currently being evaluated.
+    depth: usize,
+    /// How results that can not be exact are approximated.
+    precision: Precision,
+    /// The variables bound or unbound by the operations being evaluated,
+    /// like the index of a sum, with the value they had before. User defined
+    /// functions are evaluated without them, so a function sees the
+    /// variables of the context it was defined in.
+    shadowed: Vec<(MathIdentifier, Option<MathExpr>)>,
 }
 
 impl Approximator {
     /// Makes a new Approximator
     pub const fn new(context: MathContext) -> Self {
-        Self { context }
+        Self {
+            context,
+            depth: 0,
+            precision: Precision::Double,
+            shadowed: Vec::new(),
+        }
+    }
+    /// Makes a new Approximator that approximates with the given precision,
+    /// for example `Precision::Digits(50)` for 50 significant digits.
+    ///
+    /// # Errors
+    /// [EvalError::InvalidPrecision] if the precision has no digits.
+    pub fn with_precision(
+        context: MathContext,
+        precision: Precision,
+    ) -> Result<Self, EvalError> {
+        if let Precision::Digits(0) = precision {
+            return Err(EvalError::InvalidPrecision);
+        }
+        Ok(Self {
+            precision,
+            ..Self::new(context)
+        })
+    }
+    /// The precision results that can not be exact are approximated with.
+    pub const fn precision(&self) -> Precision {
+        self.precision
     }
     ///returns a reference to the [MathContext] used for evaluating functions
     pub const fn context(&self) -> &MathContext {
@@ -21,15 +90,45 @@ impl Approximator {
     }
     ///returns a mutable reference to the [MathContext] used for evaluating
     /// functions
-    pub fn context_mut(&mut self) -> &mut MathContext {
+    pub const fn context_mut(&mut self) -> &mut MathContext {
         &mut self.context
     }
+    /// Creates a child scope where `ident` is bound to `value`.
+    pub(crate) fn bind(
+        &self,
+        ident: MathIdentifier,
+        value: MathExpr,
+    ) -> Approximator {
+        let mut shadowed = self.shadowed.clone();
+        shadowed
+            .push((ident.clone(), self.context.variables.get(&ident).cloned()));
+        Approximator {
+            context: self.context.bind(ident, value),
+            depth: self.depth,
+            precision: self.precision,
+            shadowed,
+        }
+    }
+    /// Creates a child scope where `ident` is not defined, for example the
+    /// unknown of an equation.
+    pub(crate) fn unbind(&self, ident: &MathIdentifier) -> Approximator {
+        let mut shadowed = self.shadowed.clone();
+        shadowed
+            .push((ident.clone(), self.context.variables.get(ident).cloned()));
+        Approximator {
+            context: self.context.unbind(ident),
+            depth: self.depth,
+            precision: self.precision,
+            shadowed,
+        }
+    }
     ///Evaluates a MathExpr
     ///
     /// # Errors
     /// [EvalError]
     /// This can error if it can not be completed or it is wrong
     pub fn eval_expr(&self, expr: Simple) -> Result<Value, EvalError> {
+        let span = expr.span();
         match expr.expr() {
             MathExpr::Term(term) => self.eval_term(term.simple(&self.context)?),
             MathExpr::Add(a, b) => {
@@ -41,6 +140,21 @@ impl Approximator {
                     - self.eval_term(b.simple(&self.context)?)?
             }
         }
+        .map_err(|e| e.at(span))
+    }
+    /// Compiles the expression to evaluate it at many points, where the
+    /// variables have a different value at each point, see [CompiledExpr].
+    ///
+    /// # Errors
+    /// [EvalError] if the expression can not be simplified, or a part of it
+    /// that does not depend on the variables can not be evaluated to a real
+    /// number.
+    pub fn compile(
+        &self,
+        expr: &MathExpr,
+        variables: &[MathIdentifier],
+    ) -> Result<CompiledExpr, EvalError> {
+        CompiledExpr::new(self, expr, variables)
     }
     ///Evaluates a Term
     ///
@@ -48,6 +162,7 @@ impl Approximator {
     /// [EvalError]
     /// This can error if it can not complete
     fn eval_term(&self, term: Simple) -> Result<Value, EvalError> {
+        let span = term.span();
         match term.expr().get_term_or_wrap() {
             Term::Factor(factor) => {
                 self.eval_factor(factor.simple(&self.context)?)
@@ -60,6 +175,7 @@ impl Approximator {
                     / self.eval_factor(b.simple(&self.context)?)?
             }
         }
+        .map_err(|e| e.at(span))
     }
     ///Evaluates a Factor
     ///
@@ -70,19 +186,37 @@ impl Approximator {
     /// # Panics
     ///  this implementation currently panics when it can not under
     fn eval_factor(&self, factor: Simple) -> Result<Value, EvalError> {
-        Ok(match factor.get_factor_or_wrap() {
+        let factor = factor.get_factor_or_wrap();
+        let span = factor.span();
+        self.eval_unplaced_factor(factor).map_err(|e| e.at(span))
+    }
+    /// Evaluates a Factor like [Approximator::eval_factor], but errors are
+    /// not yet at the span of the factor itself.
+    fn eval_unplaced_factor(&self, factor: Factor) -> Result<Value, EvalError> {
+        Ok(match factor {
             Factor::Constant(c) => Value::Scalar(c),
+            Factor::Error(span) => {
+                return Err(EvalError::InvalidSyntax.at(Some(span)))
+            }
             Factor::Parenthesis(expr) => {
                 self.eval_expr(expr.simple(&self.context)?)?
             }
 
-            Factor::Variable(x) => self.eval_expr(
-                self.context
-                    .variables
-                    .get(&x)
-                    .map(|v| v.clone().simple(&self.context))
-                    .ok_or(EvalError::NotDefined)??,
-            )?,
+            Factor::Variable(x, _) => match self.context.variables.get(&x) {
+                Some(value) => {
+                    self.eval_expr(value.clone().simple(&self.context)?)?
+                }
+                // \mathrm{i} is the imaginary unit unless it is redefined
+                None if x == MathIdentifier::imaginary_unit() => {
+                    Value::Complex(Complex64::i())
+                }
+                None => match &x {
+                    MathIdentifier::Modifier(ModifierType::Hat, base) => {
+                        self.unit_vector(base)?
+                    }
+                    _ => self.index(&x)?,
+                },
+            },
             Factor::FunctionCall(func_call) => {
                 match self.context.functions.get(&func_call.function_name) {
                     Some(func) => match func {
@@ -91,15 +225,29 @@ impl Approximator {
                                 .arguments
                                 .iter()
                                 .map(|expr| {
-                                    self.eval_expr(
-                                        expr.clone().simple(&self.context)?,
-                                    )
+                                    Ok(self
+                                        .eval_expr(
+                                            expr.clone()
+                                                .simple(&self.context)?,
+                                        )?
+                                        .approximate(self.precision))
                                 })
                                 .collect();
-                            n.run(args?)?
+                            let args = args?;
+                            match &args[..] {
+                                [Value::Matrix(m)]
+                                    if self.context.element_wise
+                                        && !n.accepts(&args) =>
+                                {
+                                    Value::Matrix(m.map(|value| {
+                                        n.run(vec![value.clone()])
+                                    })?)
+                                }
+                                _ => n.run(args)?,
+                            }
                         }
-                        MathFunction::Foreign(_) => {
-                            unreachable!("this should never be reatched")
+                        MathFunction::Foreign(f) => {
+                            self.call_foreign(f, &func_call.arguments)?
                         }
                     },
                     None => panic!(
@@ -108,44 +256,725 @@ impl Approximator {
                     ),
                 }
             }
-            Factor::Power { base, exponent } => {
-                let base_val =
-                    self.eval_factor(base.simple(&self.context)?)?.scalar()?;
-                let exp_val = self
-                    .eval_expr(exponent.simple(&self.context)?)?
-                    .scalar()?;
-                Value::Scalar(base_val.powf(exp_val))
-            }
-            Factor::Root { degree, radicand } => Value::Scalar(
-                match degree.as_ref().map(|expr| {
-                    self.eval_expr(expr.clone().simple(&self.context)?)
-                }) {
-                    None => self
-                        .eval_expr(radicand.simple(&self.context)?)?
-                        .scalar()?
-                        .sqrt(),
+            Factor::Power { base, exponent, .. } => {
+                let base_val = self.eval_factor(base.simple(&self.context)?)?;
+                match base_val {
+                    Value::Matrix(m) if self.is_transpose(&exponent) => {
+                        Value::Matrix(m.transpose())
+                    }
+                    _ => {
+                        let exp_val =
+                            self.eval_expr(exponent.simple(&self.context)?)?;
+                        base_val.pow_with(&exp_val, self.precision)?
+                    }
+                }
+            }
+            Factor::Root {
+                degree, radicand, ..
+            } => {
+                let radicand_val =
+                    self.eval_expr(radicand.simple(&self.context)?)?;
+                let degree_val = match degree {
                     Some(degree) => {
-                        let radicand_val = self
-                            .eval_expr(radicand.simple(&self.context)?)?
-                            .scalar()?;
-                        let degree_val = degree?.scalar()?;
-                        radicand_val.powf(1.0 / degree_val)
+                        self.eval_expr(degree.simple(&self.context)?)?
                     }
-                },
-            ),
+                    None => Value::from(2.0),
+                };
+                radicand_val.root_with(&degree_val, self.precision)?
+            }
             Factor::Fraction(a, b) => {
                 let a_val = self.eval_expr(a.simple(&self.context)?)?;
                 let b_val = self.eval_expr(b.simple(&self.context)?)?;
                 (a_val / b_val)?
             }
-            Factor::Abs(val) => Value::Scalar(
-                self.eval_expr(val.simple(&self.context)?)?.scalar()?.abs(),
-            ),
-            Factor::Matrix(matrix) => Value::Matrix(matrix.map(|expr| {
+            Factor::Abs(val) => {
+                self.eval_expr(val.simple(&self.context)?)?.abs()?
+            }
+            Factor::Matrix(matrix, _) => Value::Matrix(matrix.map(|expr| {
                 self.eval_expr(expr.clone().simple(&self.context)?)
             })?),
+            Factor::Sum(sum) => {
+                self.eval_indexed(&sum, Value::from(0.0), |acc, value| {
+                    acc + value
+                })?
+            }
+            Factor::Product(product) => {
+                self.eval_indexed(&product, Value::from(1.0), |acc, value| {
+                    acc.mul(&MulType::Implicit, &value)
+                })?
+            }
+            Factor::Integral(integral) => {
+                Value::from(self.integrate(&integral)?.value)
+            }
+            Factor::Limit(limit) => Value::from(self.limit(&limit)?),
+            Factor::Derivative(derivative) => self.eval_expr(
+                Factor::Derivative(derivative).simple(&self.context)?,
+            )?,
         })
     }
+    /// The unit vector `\hat{v}` in the direction of `v`, when `\hat{v}` is
+    /// not defined itself.
+    ///
+    /// # Errors
+    /// [EvalError::NotDefined] if `v` is not a matrix,
+    /// [IncompatibleMatrixSizes::Vector] if it is not a vector, or
+    /// [EvalError::DivideByZero] if it is zero.
+    fn unit_vector(&self, base: &MathExpr) -> Result<Value, EvalError> {
+        let Value::Matrix(m) =
+            self.eval_expr(base.clone().simple(&self.context)?)?
+        else {
+            return Err(EvalError::NotDefined);
+        };
+        m.as_vector()?;
+        let length = m.norm(&Value::from(Number::from(2)))?;
+        if solver::is_zero(&length) {
+            return Err(EvalError::DivideByZero);
+        }
+        Value::Matrix(m) / length
+    }
+    /// Indexes a matrix variable with an undefined indexed identifier, like
+    /// the element `A_{2,3}`, the element `v_1` of a vector or the row
+    /// `A_{2,:}`, where indices start at 1. When `I` is not defined, `I_n`
+    /// and `\mathbb{1}_n` are the identity matrix of size `n`.
+    ///
+    /// # Errors
+    /// [EvalError::NotDefined] if the identifier does not index a matrix
+    /// variable, [IncompatibleMatrixSizes::Index] if an index is outside of
+    /// the matrix, or [IncompatibleMatrixSizes::Vector] for a single index
+    /// into a matrix that is not a vector.
+    fn index(&self, ident: &MathIdentifier) -> Result<Value, EvalError> {
+        let (MathIdentifier::Index { name, .. }, Some(entries)) =
+            (ident, ident.index_entries())
+        else {
+            return Err(EvalError::NotDefined);
+        };
+        let Some(base) = self.context.variables.get(name) else {
+            // The identity matrix I_n or \mathbb{1}_n
+            let one = MathIdentifier::Modifier(
+                ModifierType::Mathbb,
+                MathExpr::from(Factor::Constant(Number::from(1))).boxed(),
+            );
+            return match entries[..] {
+                [size]
+                    if **name == MathIdentifier::from_single_ident("I")
+                        || **name == one =>
+                {
+                    let size =
+                        self.eval_expr(size.clone().simple(&self.context)?)?;
+                    let (size, _) = matrix::shape(&size, &size)?;
+                    Ok(Value::Matrix(Matrix::identity(size)))
+                }
+                _ => Err(EvalError::NotDefined),
+            };
+        };
+        let Value::Matrix(m) =
+            self.eval_expr(base.clone().simple(&self.context)?)?
+        else {
+            return Err(EvalError::NotDefined);
+        };
+        // The positions along a dimension of the given size
+        let positions = |entry: &MathExpr, size: usize| {
+            let all = MathIdentifier::from_single_ident(":");
+            if *entry == MathExpr::from(all) {
+                return Ok((0..size).collect::<Vec<usize>>());
+            }
+            let index = self
+                .eval_expr(entry.clone().simple(&self.context)?)?
+                .integer()?;
+            match usize::try_from(index) {
+                Ok(position @ 1..) if position <= size => {
+                    Ok(vec![position - 1])
+                }
+                _ => Err(EvalError::from(IncompatibleMatrixSizes::Index {
+                    index,
+                    size,
+                })),
+            }
+        };
+        let (rows, columns) = match entries[..] {
+            [index] => {
+                let vector = m.as_vector()?;
+                let elements = positions(index, vector.get_size())?;
+                let values: Vec<Value> =
+                    elements.iter().map(|&i| vector.get(i).clone()).collect();
+                let len = values.len();
+                return Ok(match (len, vector) {
+                    (1, _) => values[0].clone(),
+                    (_, Vector::Row(_)) => {
+                        Value::Matrix(Matrix::new(values, 1, len))
+                    }
+                    (_, Vector::Column(_)) => {
+                        Value::Matrix(Matrix::new(values, len, 1))
+                    }
+                });
+            }
+            [row, column] => (
+                positions(row, m.row_count())?,
+                positions(column, m.column_count())?,
+            ),
+            _ => {
+                return Err(EvalError::ArgumentLengthMismatch {
+                    expected: vec![1, 2],
+                    found: entries.len(),
+                })
+            }
+        };
+        let values: Vec<Value> = rows
+            .iter()
+            .flat_map(|&row| columns.iter().map(move |&column| (row, column)))
+            .map(|(row, column)| m.get(row, column).clone())
+            .collect();
+        Ok(match values.len() {
+            1 => values[0].clone(),
+            _ => Value::Matrix(Matrix::new(values, rows.len(), columns.len())),
+        })
+    }
+    /// Whether the exponent of a matrix means its transpose, `A^T` or
+    /// `A^\top`, where `T` is not a defined variable.
+    fn is_transpose(&self, exponent: &MathExpr) -> bool {
+        let MathExpr::Term(Term::Factor(Factor::Variable(ident, _))) = exponent
+        else {
+            return false;
+        };
+        *ident == MathIdentifier::from_single_symbol(OtherSymbol::Top)
+            || (*ident == MathIdentifier::from_single_ident("T")
+                && !self.context.variables.contains_key(ident))
+    }
+    ///Evaluates a user defined function, where the parameters are bound to
+    /// the evaluated arguments in a child scope
+    ///
+    /// # Errors
+    /// [EvalError::ArgumentLengthMismatch] if the amount of arguments is
+    /// wrong, [EvalError::RecursionLimit] if the functions call each other
+    /// more than [MAX_CALL_DEPTH] times deep, or another [EvalError] if the
+    /// arguments or body can not be evaluated
+    fn call_foreign(
+        &self,
+        function: &ForeignFunction,
+        arguments: &[MathExpr],
+    ) -> Result<Value, EvalError> {
+        if arguments.len() != function.input.len() {
+            return Err(EvalError::ArgumentLengthMismatch {
+                expected: vec![function.input.len()],
+                found: arguments.len(),
+            });
+        }
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(EvalError::RecursionLimit {
+                depth: MAX_CALL_DEPTH,
+            });
+        }
+        // The function is evaluated where it was defined, so the variables of
+        // the caller, like the index of a sum around the call, are restored
+        let mut context = self.context.clone();
+        for (ident, value) in self.shadowed.iter().rev() {
+            match value {
+                Some(value) => {
+                    context.variables.insert(ident.clone(), value.clone())
+                }
+                None => context.variables.remove(ident),
+            };
+        }
+        let mut scope = Approximator {
+            context,
+            depth: self.depth + 1,
+            precision: self.precision,
+            shadowed: Vec::new(),
+        };
+        for (ident, argument) in function.input.iter().zip(arguments) {
+            let value =
+                self.eval_expr(argument.clone().simple(&self.context)?)?;
+            scope = scope.bind(ident.clone(), value.into());
+        }
+        scope.eval_expr(function.expr.clone().simple(scope.context())?)
+    }
+    ///Numerically integrates a definite integral using adaptive quadrature,
+    /// see [quadrature::integrate]
+    ///
+    /// The returned [Quadrature] holds both the value and the estimated error
+    /// of the value. An integral inside a larger expression only keeps its
+    /// value when evaluated by [Approximator::eval_expr].
+    ///
+    /// # Errors
+    /// [EvalError]
+    /// This can error if the bounds or the integrand can not be evaluated to
+    /// scalars
+    pub fn integrate(
+        &self,
+        integral: &Integral,
+    ) -> Result<Quadrature, EvalError> {
+        let lower = self
+            .eval_expr(integral.lower.clone().simple(&self.context)?)?
+            .scalar()?;
+        let upper = self
+            .eval_expr(integral.upper.clone().simple(&self.context)?)?
+            .scalar()?;
+        quadrature::integrate(
+            |x| {
+                let scope = self.bind(
+                    integral.variable.clone(),
+                    Factor::Constant(x.into()).into(),
+                );
+                scope
+                    .eval_expr(integral.body.clone().simple(scope.context())?)?
+                    .scalar()
+            },
+            lower,
+            upper,
+        )
+    }
+    ///Evaluates the body of an indexed operation for every value of the
+    /// index and combines the results using `fold`
+    ///
+    /// `empty` is returned when the range is empty, for example
+    /// `\sum_{i=1}^{0}`.
+    ///
+    /// # Errors
+    /// [EvalError]
+    /// This can error if the bounds are not integers or the body can not be
+    /// evaluated
+    fn eval_indexed(
+        &self,
+        operation: &IndexedOperation,
+        empty: Value,
+        fold: impl Fn(Value, Value) -> Result<Value, EvalError>,
+    ) -> Result<Value, EvalError> {
+        let lower = self.eval_integer(&operation.lower)?;
+        let upper = self.eval_integer(&operation.upper)?;
+        let terms = (i128::from(upper) - i128::from(lower) + 1).max(0);
+        if terms > i128::from(MAX_TERMS) {
+            return Err(EvalError::TooManyTerms {
+                found: u64::try_from(terms).unwrap_or(u64::MAX),
+                max: MAX_TERMS,
+            });
+        }
+        let mut result = None;
+        for index in lower..=upper {
+            let scope = self.bind(
+                operation.index.clone(),
+                Factor::Constant(index.into()).into(),
+            );
+            let value = scope
+                .eval_expr(operation.body.clone().simple(scope.context())?)?;
+            result = Some(match result {
+                Some(acc) => fold(acc, value)?,
+                None => value,
+            });
+        }
+        Ok(result.unwrap_or(empty))
+    }
+    ///Evaluates a limit
+    ///
+    /// This first tries substituting the target into the body, which is the
+    /// limit if the body approaches it from the sides of the limit, then
+    /// L'Hôpital's rule if the body is an indeterminate fraction, and last
+    /// numeric extrapolation towards the target.
+    ///
+    /// # Errors
+    /// [EvalError]
+    /// [EvalError::LimitDoesNotExist] if the limit does not exist, or another
+    /// error if the target or body can not be evaluated
+    pub fn limit(&self, limit: &Limit) -> Result<f64, EvalError> {
+        let target = self
+            .eval_expr(limit.target.clone().simple(&self.context)?)?
+            .scalar()?;
+        self.limit_of(&limit.body, &limit.variable, target, limit.direction, 0)
+    }
+    ///Evaluates the limit of `body` as `variable` approaches `target`, where
+    /// `depth` is how many times L'Hôpital's rule has been applied
+    fn limit_of(
+        &self,
+        body: &MathExpr,
+        variable: &MathIdentifier,
+        target: f64,
+        direction: LimitDirection,
+        depth: usize,
+    ) -> Result<f64, EvalError> {
+        // Substituting infinity can give misleading results, for example
+        // (1+1/x)^x would become 1^\infty = 1.
+        if target.is_finite() {
+            let substituted = body
+                .substitute(variable, &Factor::Constant(target.into()).into())
+                .simple(&self.context)
+                .and_then(|simple| self.eval_expr(simple)?.scalar());
+            if let Ok(value) = substituted {
+                if value.is_finite()
+                    && self.approaches(body, variable, target, direction, value)
+                {
+                    return Ok(value);
+                }
+            }
+        }
+        if depth < MAX_LHOPITAL_DEPTH {
+            if let Some(value) =
+                self.limit_by_lhopital(body, variable, target, direction, depth)
+            {
+                return Ok(value);
+            }
+        }
+        self.limit_by_extrapolation(body, variable, target, direction)
+    }
+    ///Evaluates `body` where `variable` is `x`
+    fn eval_at(
+        &self,
+        body: &MathExpr,
+        variable: &MathIdentifier,
+        x: f64,
+    ) -> Result<f64, EvalError> {
+        let scope =
+            self.bind(variable.clone(), Factor::Constant(x.into()).into());
+        scope
+            .eval_expr(body.clone().simple(scope.context())?)?
+            .scalar()
+    }
+    ///Whether `body` approaches `value` as `variable` approaches the finite
+    /// `target` from the sides of `direction`, so `value` is the limit.
+    ///
+    /// This is not the case at a jump, like the one of `\lfloor x\rfloor` at
+    /// 1, or if `body` can not be evaluated next to `target`.
+    fn approaches(
+        &self,
+        body: &MathExpr,
+        variable: &MathIdentifier,
+        target: f64,
+        direction: LimitDirection,
+        value: f64,
+    ) -> bool {
+        let at = |x: f64| self.eval_at(body, variable, x);
+        let from_side = |sign: f64| {
+            extrapolation::approaches(|h| at(target + sign * h), value)
+                .unwrap_or(false)
+        };
+        match direction {
+            LimitDirection::Both => from_side(-1.0) && from_side(1.0),
+            LimitDirection::Above => from_side(1.0),
+            LimitDirection::Below => from_side(-1.0),
+        }
+    }
+    ///Evaluates the limit of a fraction using L'Hôpital's rule, returning
+    /// `None` if the body is not a fraction of the form 0/0 or
+    /// \infty/\infty, or if the rule did not help
+    fn limit_by_lhopital(
+        &self,
+        body: &MathExpr,
+        variable: &MathIdentifier,
+        target: f64,
+        direction: LimitDirection,
+        depth: usize,
+    ) -> Option<f64> {
+        let (numerator, denominator): (MathExpr, MathExpr) = match body {
+            MathExpr::Term(Term::Factor(Factor::Fraction(n, d))) => {
+                (*n.clone(), *d.clone())
+            }
+            MathExpr::Term(Term::Divide(n, d)) => {
+                ((**n).clone().into(), d.clone().into())
+            }
+            _ => return None,
+        };
+        let limit_of = |expr: &MathExpr| {
+            self.limit_of(expr, variable, target, direction, depth + 1)
+                .ok()
+        };
+        let top = limit_of(&numerator)?;
+        let bottom = limit_of(&denominator)?;
+        let indeterminate = (top.is_zero() && bottom.is_zero())
+            || (top.is_infinite() && bottom.is_infinite());
+        if !indeterminate {
+            return (bottom.is_finite() && !bottom.is_zero())
+                .then_some(top / bottom);
+        }
+        let derivative = Factor::Fraction(
+            numerator.derivative(variable, &self.context).ok()?.boxed(),
+            denominator
+                .derivative(variable, &self.context)
+                .ok()?
+                .boxed(),
+        );
+        limit_of(&derivative.into())
+    }
+    ///Evaluates the limit by sampling the body closer and closer to the
+    /// target, see [extrapolation::limit_at_zero]
+    fn limit_by_extrapolation(
+        &self,
+        body: &MathExpr,
+        variable: &MathIdentifier,
+        target: f64,
+        direction: LimitDirection,
+    ) -> Result<f64, EvalError> {
+        let at = |x: f64| self.eval_at(body, variable, x);
+        let from_side = |sign: f64| {
+            extrapolation::limit_at_zero(|h| match target.is_finite() {
+                true => at(target + sign * h),
+                // x = 1/h approaches infinity as h approaches zero
+                false => at(target.signum() / h),
+            })
+        };
+        let (below, above) = match (target.is_finite(), direction) {
+            (false, _) => {
+                let side = from_side(1.0)?;
+                (side, side)
+            }
+            (true, LimitDirection::Both) => (from_side(-1.0)?, from_side(1.0)?),
+            (true, LimitDirection::Above) => {
+                let side = from_side(1.0)?;
+                (side, side)
+            }
+            (true, LimitDirection::Below) => {
+                let side = from_side(-1.0)?;
+                (side, side)
+            }
+        };
+        match (below, above) {
+            (Extrapolation::Finite(a), Extrapolation::Finite(b))
+                if (a - b).abs() <= 1e-6 * a.abs().max(1.0) =>
+            {
+                Ok((a + b) / 2.0)
+            }
+            (Extrapolation::Infinite(a), Extrapolation::Infinite(b))
+                if a == b =>
+            {
+                Ok(a)
+            }
+            _ => Err(EvalError::LimitDoesNotExist),
+        }
+    }
+    ///Solves an equation for `unknown`, giving the solutions in `domain`
+    ///
+    /// Polynomials of degree up to two are solved in closed form, which is
+    /// exact when possible, see [Polynomial::roots]. Other equations are
+    /// solved numerically between [-SEARCH_RANGE, SEARCH_RANGE], which only
+    /// finds real solutions, see [solver::find_roots]. An expression is
+    /// solved for when it is zero.
+    ///
+    /// [-SEARCH_RANGE, SEARCH_RANGE]: solver::SEARCH_RANGE
+    ///
+    /// # Errors
+    /// [EvalError]
+    /// [EvalError::InfinitelyManySolutions] if every value is a solution,
+    /// [EvalError::ExpectedSingleEquation] for a system of several equations,
+    /// or another error if the equation can not be evaluated
+    pub fn solve(
+        &self,
+        equation: &Ast,
+        unknown: &MathIdentifier,
+        domain: Domain,
+    ) -> Result<Vec<Value>, EvalError> {
+        let difference = match equation {
+            Ast::Expression(expr) => expr.clone(),
+            Ast::Equality(lhs, rhs) => difference(lhs, rhs),
+            Ast::System(equations) => match &equations[..] {
+                [(lhs, rhs)] => difference(lhs, rhs),
+                _ => {
+                    return Err(EvalError::ExpectedSingleEquation {
+                        found: equations.len(),
+                    })
+                }
+            },
+        };
+        // The unknown may have a value, like x in the REPL
+        let scope = self.unbind(unknown);
+        if let Some(polynomial) = scope.polynomial(&difference, unknown)? {
+            if let Some(roots) = polynomial.roots(self.precision)? {
+                return Ok(roots
+                    .into_iter()
+                    .filter(|root| domain.contains(root))
+                    .collect());
+            }
+        }
+        // Newton's method is only needed for roots without a sign change
+        let derivative = difference.derivative(unknown, &scope.context).ok();
+        let at = |expr: Option<&MathExpr>, x: f64| {
+            let Some(expr) = expr else {
+                return Ok(f64::NAN);
+            };
+            let point =
+                scope.bind(unknown.clone(), Factor::Constant(x.into()).into());
+            match point.eval_expr(expr.clone().simple(point.context())?)? {
+                // Outside of the real domain, like \sqrt{x} for negative x
+                Value::Complex(_) => Ok(f64::NAN),
+                value => value.scalar(),
+            }
+        };
+        let roots = solver::find_roots(
+            |x| at(Some(&difference), x),
+            |x| at(derivative.as_ref(), x),
+            -solver::SEARCH_RANGE,
+            solver::SEARCH_RANGE,
+        )?;
+        Ok(roots.into_iter().map(Value::from).collect())
+    }
+    ///Solves a system of linear equations for the unknowns, see
+    /// [solver::solve_linear]
+    ///
+    /// The coefficients of the unknowns are their partial derivatives, so
+    /// they may be any expressions that do not depend on the unknowns.
+    ///
+    /// # Errors
+    /// [EvalError]
+    /// [EvalError::NotLinear] if an equation is not linear in the unknowns,
+    /// or another error if the equations can not be evaluated
+    pub fn solve_system(
+        &self,
+        equations: &[(MathExpr, MathExpr)],
+        unknowns: &[MathIdentifier],
+    ) -> Result<LinearSolution, EvalError> {
+        let mut scope = self.clone();
+        for unknown in unknowns {
+            scope = scope.unbind(unknown);
+        }
+        let origin: Vec<_> = unknowns
+            .iter()
+            .map(|unknown| (unknown.clone(), Factor::Constant(0.into()).into()))
+            .collect();
+        let mut coefficients =
+            Vec::with_capacity(equations.len() * unknowns.len());
+        let mut constants = Vec::with_capacity(equations.len());
+        for (lhs, rhs) in equations {
+            let difference = difference(lhs, rhs);
+            for unknown in unknowns {
+                let coefficient = difference
+                    .derivative(unknown, &scope.context)?
+                    .simple(&scope.context)?
+                    .expr();
+                if unknowns.iter().any(|u| coefficient.depends_on(u)) {
+                    return Err(EvalError::NotLinear);
+                }
+                coefficients.push(
+                    scope.eval_expr(coefficient.simple(&scope.context)?)?,
+                );
+            }
+            // The equation is a x = -c, where c is the value at the origin
+            let constant =
+                difference.substitute_all(&origin).simple(&scope.context)?;
+            constants.push((&scope.eval_expr(constant)? * Number::from(-1))?);
+        }
+        solver::solve_linear(
+            &Matrix::new(coefficients, equations.len(), unknowns.len()),
+            &Matrix::new(constants, equations.len(), 1),
+        )
+    }
+    ///The expression as a polynomial in `unknown`, or None if it is not one
+    fn polynomial(
+        &self,
+        expr: &MathExpr,
+        unknown: &MathIdentifier,
+    ) -> Result<Option<Polynomial>, EvalError> {
+        if !expr.depends_on(unknown) {
+            let value = self.eval_expr(expr.clone().simple(&self.context)?)?;
+            return Ok(Some(Polynomial::constant(value)));
+        }
+        let (lhs, rhs, subtract) = match expr {
+            MathExpr::Term(term) => {
+                return self.term_polynomial(term, unknown);
+            }
+            MathExpr::Add(lhs, rhs) => (lhs, rhs, false),
+            MathExpr::Subtract(lhs, rhs) => (lhs, rhs, true),
+        };
+        let (Some(lhs), Some(rhs)) = (
+            self.polynomial(lhs, unknown)?,
+            self.term_polynomial(rhs, unknown)?,
+        ) else {
+            return Ok(None);
+        };
+        Ok(Some(match subtract {
+            true => lhs.sub(&rhs)?,
+            false => lhs.add(&rhs)?,
+        }))
+    }
+    ///The term as a polynomial in `unknown`, see [Approximator::polynomial]
+    fn term_polynomial(
+        &self,
+        term: &Term,
+        unknown: &MathIdentifier,
+    ) -> Result<Option<Polynomial>, EvalError> {
+        match term {
+            Term::Factor(factor) => self.factor_polynomial(factor, unknown),
+            Term::Multiply(MulType::Oslash, numerator, denominator) => self
+                .divided_polynomial(
+                    &(**numerator).clone().into(),
+                    &denominator.clone().into(),
+                    unknown,
+                ),
+            Term::Multiply(mul_type, lhs, rhs) => {
+                let lhs = self.polynomial(&(**lhs).clone().into(), unknown)?;
+                let rhs = self.factor_polynomial(rhs, unknown)?;
+                match (lhs, rhs) {
+                    (Some(lhs), Some(rhs)) => {
+                        Ok(Some(lhs.mul(mul_type, &rhs)?))
+                    }
+                    _ => Ok(None),
+                }
+            }
+            Term::Divide(numerator, denominator) => self.divided_polynomial(
+                &(**numerator).clone().into(),
+                &denominator.clone().into(),
+                unknown,
+            ),
+        }
+    }
+    ///The factor as a polynomial in `unknown`, see
+    /// [Approximator::polynomial]
+    fn factor_polynomial(
+        &self,
+        factor: &Factor,
+        unknown: &MathIdentifier,
+    ) -> Result<Option<Polynomial>, EvalError> {
+        match factor {
+            Factor::Variable(ident, _) if ident == unknown => {
+                Ok(Some(Polynomial::unknown()))
+            }
+            Factor::Parenthesis(expr) => self.polynomial(expr, unknown),
+            Factor::Power { base, exponent, .. }
+                if !exponent.depends_on(unknown) =>
+            {
+                // Only natural powers of polynomials are polynomials
+                let exponent = self
+                    .eval_integer(exponent)
+                    .ok()
+                    .and_then(|exponent| u32::try_from(exponent).ok());
+                match (exponent, self.factor_polynomial(base, unknown)?) {
+                    (Some(exponent), Some(base)) => {
+                        Ok(Some(base.pow(exponent)?))
+                    }
+                    _ => Ok(None),
+                }
+            }
+            Factor::Fraction(numerator, denominator) => {
+                self.divided_polynomial(numerator, denominator, unknown)
+            }
+            _ if !MathExpr::from(factor.clone()).depends_on(unknown) => {
+                self.polynomial(&factor.clone().into(), unknown)
+            }
+            _ => Ok(None),
+        }
+    }
+    ///The fraction as a polynomial in `unknown` if the denominator does not
+    /// depend on it, see [Approximator::polynomial]
+    fn divided_polynomial(
+        &self,
+        numerator: &MathExpr,
+        denominator: &MathExpr,
+        unknown: &MathIdentifier,
+    ) -> Result<Option<Polynomial>, EvalError> {
+        if denominator.depends_on(unknown) {
+            return Ok(None);
+        }
+        let denominator =
+            self.eval_expr(denominator.clone().simple(&self.context)?)?;
+        match self.polynomial(numerator, unknown)? {
+            Some(numerator) => Ok(Some(numerator.div(&denominator)?)),
+            None => Ok(None),
+        }
+    }
+    ///Evaluates a MathExpr that has to be an integer
+    ///
+    /// # Errors
+    /// [EvalError]
+    /// [EvalError::ExpectedInteger] if the value is not a finite integer
+    fn eval_integer(&self, expr: &MathExpr) -> Result<i64, EvalError> {
+        self.eval_expr(expr.clone().simple(&self.context)?)?
+            .integer()
+    }
 }
 
 #[cfg(test)]
@@ -154,13 +983,33 @@ mod tests {
     use crate::{
         ast::{helper::NumberCompare, simplify::Simplify},
         prelude::*,
+        solver::{Domain, LinearSolution},
     };
+    use num_complex::Complex64;
     #[allow(unused_imports)]
     use pretty_assertions::assert_eq;
-    use tokio::{
-        join,
-        sync::mpsc::{self},
-    };
+    use std::f64::consts::PI;
+
+    /// Asserts that evaluating failed with an error matching the pattern, at
+    /// a known span in the LaTeX. The optional format arguments say what
+    /// was evaluated.
+    macro_rules! assert_error_at {
+        ($found:expr, $pattern:pat $(if $guard:expr)? $(,)?) => {
+            assert_error_at!($found, $pattern $(if $guard)?, "{}", "error")
+        };
+        ($found:expr, $pattern:pat $(if $guard:expr)?, $($context:tt)+) => {
+            let found = $found;
+            assert!(
+                matches!(
+                    &found,
+                    Err(EvalError::At { source, .. })
+                        if matches!(**source, $pattern $(if $guard)?)
+                ),
+                "{}: found {found:?}",
+                format_args!($($context)+)
+            );
+        };
+    }
 
     fn eval_test_from_ast(expected: f64, ast: Ast) {
         let context = MathContext::new();
@@ -168,7 +1017,9 @@ mod tests {
 
         let expr = match ast {
             Ast::Expression(expr) => expr,
-            Ast::Equality(_, _) => panic!("Cannot evaluate statement."),
+            Ast::Equality(_, _) | Ast::System(_) => {
+                panic!("Cannot evaluate statement.")
+            }
         };
 
         let value = match approximator
@@ -180,31 +1031,29 @@ mod tests {
 
         let found = match value {
             Value::Scalar(val) => val,
+            Value::Complex(c) => panic!("Unexpected complex number {c}"),
             Value::Matrix(m) => panic!("Unexpected matrix {m:?}"),
         };
 
-        if !found.equals(&expected) {
+        if !found.to_f64().equals(&expected) {
             panic!("Found {} expected {}", found, expected);
         }
     }
 
     async fn eval_test_from_str(expected: f64, text: &str) {
-        let (tx, rx): (TokenSender, TokenReceiver) = mpsc::channel(32);
-
-        let context = MathContext::new();
-        let lexer = Lexer::new(tx);
-
-        let parser = Parser::new(rx, context);
-
-        let future1 = lexer.tokenize(text);
-        let future2 = parser.parse();
-
-        let ((), ast) = join!(future1, future2);
-        let ast = ast.unwrap();
-
+        let ast = parse(text, &MathContext::new()).await.unwrap();
         eval_test_from_ast(expected, ast);
     }
 
+    async fn eval_standard_math(text: &str) -> Result<Value, EvalError> {
+        let context = MathContext::standard_math();
+        let Ast::Expression(expr) = parse(text, &context).await.unwrap() else {
+            panic!("Cannot evaluate statement.");
+        };
+        let approximator = Approximator::new(context);
+        approximator.eval_expr(expr.simple(approximator.context())?)
+    }
+
     #[test]
     fn eval_1_plus_1() {
         eval_test_from_ast(
@@ -246,4 +1095,996 @@ mod tests {
     async fn markdown_example() {
         eval_test_from_str(0.5, "\\frac{2\\sqrt{9}+5}{3(3+4)+1}").await;
     }
+    #[tokio::test]
+    async fn sum_of_squares() {
+        eval_test_from_str(30.0, "\\sum_{i=1}^{4} i^2").await;
+    }
+    #[tokio::test]
+    async fn product_factorial() {
+        eval_test_from_str(120.0, "\\prod_{k=1}^{5} k").await;
+    }
+    #[tokio::test]
+    async fn nested_sum() {
+        eval_test_from_str(18.0, "\\sum_{i=1}^{3}\\sum_{j=1}^{i} j + 8").await;
+    }
+    #[tokio::test]
+    async fn sum_with_too_many_terms() {
+        let found = eval_standard_math("\\sum_{i=1}^{10^{12}} i").await;
+        assert_error_at!(found, EvalError::TooManyTerms { .. });
+    }
+    #[tokio::test]
+    async fn empty_sum_and_product() {
+        eval_test_from_str(1.0, "\\sum_{i=1}^{0} i + \\prod_{i=1}^{0} i").await;
+    }
+    #[tokio::test]
+    async fn integral_of_polynomial() {
+        eval_test_from_str(2.0, "\\int_{0}^{2} x dx").await;
+    }
+    #[tokio::test]
+    async fn nested_integral_mathrm_differential() {
+        eval_test_from_str(
+            1.0,
+            "\\int_0^1\\int_0^2 xy \\mathrm{d}x \\mathrm{d}y",
+        )
+        .await;
+    }
+    #[tokio::test]
+    async fn limit_by_substitution() {
+        eval_test_from_str(4.0, "\\lim_{x \\to 2} x^2").await;
+    }
+    #[tokio::test]
+    async fn limit_by_lhopital() {
+        let found =
+            eval_standard_math("\\lim_{x \\to 1} \\frac{x^2-1}{x-1}").await;
+        assert_eq!(found.unwrap(), Value::from(2.0));
+    }
+    #[tokio::test]
+    async fn limit_by_extrapolation() {
+        let found = eval_standard_math("\\lim_{x \\to 0} \\frac{\\sin x}{x}")
+            .await
+            .unwrap()
+            .scalar()
+            .unwrap();
+        assert!((found - 1.0).abs() < 1e-6, "found {found}");
+    }
+    #[tokio::test]
+    async fn limit_at_infinity() {
+        let found =
+            eval_standard_math("\\lim_{x \\to \\infty} (1+\\frac{1}{x})^x")
+                .await
+                .unwrap()
+                .scalar()
+                .unwrap();
+        assert!((found - std::f64::consts::E).abs() < 1e-6, "found {found}");
+    }
+    #[tokio::test]
+    async fn limit_one_sided() {
+        let found = eval_standard_math("\\lim_{x \\to 0^-} \\frac{1}{x}")
+            .await
+            .unwrap()
+            .scalar()
+            .unwrap();
+        assert_eq!(found, f64::NEG_INFINITY);
+    }
+    #[tokio::test]
+    async fn limit_does_not_exist() {
+        let found = eval_standard_math("\\lim_{x \\to 0} \\frac{|x|}{x}").await;
+        assert_error_at!(found, EvalError::LimitDoesNotExist);
+    }
+    #[tokio::test]
+    async fn limits_at_jumps() {
+        for (text, expected) in [
+            ("\\lim_{x \\to 0^+} \\operatorname{sgn}(x)", 1.0),
+            ("\\lim_{x \\to 0^-} \\operatorname{sgn}(x)", -1.0),
+            ("\\lim_{x \\to 1^-} \\lfloor x \\rfloor", 0.0),
+            ("\\lim_{x \\to 1^+} \\lfloor x \\rfloor", 1.0),
+            ("\\lim_{x \\to 2^-} (\\lceil x \\rceil + x)", 4.0),
+            ("\\lim_{x \\to 2.5} \\lfloor x \\rfloor", 2.0),
+        ] {
+            let found = eval_standard_math(text).await.and_then(|v| v.scalar());
+            assert!(
+                found.as_ref().is_ok_and(|f| (f - expected).abs() < 1e-6),
+                "{text}: found {found:?}"
+            );
+        }
+        for text in [
+            "\\lim_{x \\to 1} \\lfloor x \\rfloor",
+            "\\lim_{x \\to 0} \\operatorname{sgn}(x)",
+        ] {
+            let found = eval_standard_math(text).await;
+            assert_error_at!(found, EvalError::LimitDoesNotExist, "{text}");
+        }
+    }
+    async fn eval_with_functions(text: &str) -> Result<Value, EvalError> {
+        let mut context = MathContext::standard_math();
+        context.add_ident_function(
+            "f",
+            MathFunction::new_foreign(
+                Factor::Power {
+                    base: Factor::from(MathIdentifier::from_single_ident("t"))
+                        .into(),
+                    exponent: 3.0.into(),
+                    span: NodeSpan::NONE,
+                }
+                .into(),
+                vec![MathIdentifier::from_single_ident("t")],
+            ),
+        );
+        let Ast::Expression(g) =
+            parse("x^2 y", &MathContext::new()).await.unwrap()
+        else {
+            panic!("Cannot evaluate statement.");
+        };
+        context.add_ident_function(
+            "g",
+            MathFunction::new_foreign(
+                g,
+                vec![
+                    MathIdentifier::from_single_ident("x"),
+                    MathIdentifier::from_single_ident("y"),
+                ],
+            ),
+        );
+        // h(n) = h(n-1) + 1 never stops calling itself
+        let n = MathIdentifier::from_single_ident("n");
+        context.add_ident_function(
+            "h",
+            MathFunction::new_foreign(1.0.into(), vec![n.clone()]),
+        );
+        let Ast::Expression(recursive) =
+            parse("h(n-1)+1", &context).await.unwrap()
+        else {
+            panic!("Cannot evaluate statement.");
+        };
+        context.add_ident_function(
+            "h",
+            MathFunction::new_foreign(recursive, vec![n.clone()]),
+        );
+        // q(t) = t + x uses the x of the context it is defined in
+        let Ast::Expression(q) =
+            parse("t+x", &MathContext::new()).await.unwrap()
+        else {
+            panic!("Cannot evaluate statement.");
+        };
+        context.add_ident_function(
+            "q",
+            MathFunction::new_foreign(
+                q,
+                vec![MathIdentifier::from_single_ident("t")],
+            ),
+        );
+        let context =
+            context.bind(MathIdentifier::from_single_ident("x"), 2.0.into());
+        let Ast::Expression(expr) = parse(text, &context).await.unwrap() else {
+            panic!("Cannot evaluate statement.");
+        };
+        let approximator = Approximator::new(context);
+        approximator.eval_expr(expr.simple(approximator.context())?)
+    }
+    async fn eval_test(expected: f64, text: &str) {
+        let found = eval_with_functions(text).await.unwrap().scalar().unwrap();
+        assert!((found - expected).abs() < 1e-9, "found {found}");
+    }
+    /// Compares the elements of the matrix row by row.
+    async fn eval_matrix_derivative_test(
+        expected: &[f64],
+        columns: usize,
+        text: &str,
+    ) {
+        let Value::Matrix(m) = eval_with_functions(text).await.unwrap() else {
+            panic!("Expected a matrix");
+        };
+        assert_eq!(m.column_count(), columns);
+        let found: Vec<f64> = (0..m.row_count())
+            .flat_map(|row| {
+                (0..m.column_count())
+                    .map(|column| m.get(row, column).scalar().unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(found, expected);
+    }
+    #[tokio::test]
+    async fn leibniz_derivative_at_variable() {
+        eval_test(4.0, "\\frac{d}{dx} x^2").await;
+    }
+    #[tokio::test]
+    async fn second_derivative() {
+        eval_test(12.0, "\\frac{d^2}{dx^2} x^3").await;
+    }
+    #[tokio::test]
+    async fn prime_derivative_of_function() {
+        eval_test(27.0, "f'(3)").await;
+    }
+    #[tokio::test]
+    async fn prime_derivative_of_native_function() {
+        // x is bound to 2, so the parameter of \sin is named differently
+        eval_test(4f64.cos(), "\\sin'(x^2)").await;
+    }
+    #[tokio::test]
+    async fn chain_rule_of_native_function() {
+        eval_test(4.0 * 4f64.cos(), "\\frac{d}{dx} \\sin(x^2)").await;
+    }
+    #[tokio::test]
+    async fn derivative_of_product_with_a_zero_factor() {
+        eval_test(-1.0, "\\frac{d}{dx} \\prod_{k=1}^{3} (x-k)").await;
+    }
+    #[tokio::test]
+    async fn derivative_of_absolute_value() {
+        eval_test(12.0, "\\frac{d}{dx} |2-x^3|").await;
+    }
+    #[tokio::test]
+    async fn gradient_of_function_at_point() {
+        eval_matrix_derivative_test(&[12.0, 9.0], 1, "\\nabla g(3, 2)").await;
+    }
+    #[tokio::test]
+    async fn hessian_of_function_at_point() {
+        eval_matrix_derivative_test(
+            &[4.0, 6.0, 6.0, 0.0],
+            2,
+            "\\operatorname{H} g(3, 2)",
+        )
+        .await;
+    }
+    #[tokio::test]
+    async fn jacobian_of_vector() {
+        eval_matrix_derivative_test(
+            &[4.0, 3.0],
+            1,
+            "\\operatorname{J}_{x} \\begin{bmatrix}x^2 \\\\ 3x\\end{bmatrix}",
+        )
+        .await;
+    }
+    #[tokio::test]
+    async fn call_user_defined_function() {
+        eval_test(25.0, "g(2, 3) + g(1, 13)").await;
+    }
+    #[tokio::test]
+    async fn user_defined_function_with_matrix_argument() {
+        eval_matrix_derivative_test(
+            &[4.0, 8.0],
+            1,
+            "g(x, \\begin{bmatrix}1 \\\\ 2\\end{bmatrix})",
+        )
+        .await;
+    }
+    #[tokio::test]
+    async fn user_defined_function_argument_count() {
+        let found = eval_with_functions("g(1)").await;
+        assert_error_at!(
+            found,
+            EvalError::ArgumentLengthMismatch { found: 1, .. }
+        );
+    }
+    #[tokio::test]
+    async fn user_defined_function_is_lexically_scoped() {
+        // The index of the product is not the x that q was defined with
+        eval_test(8.0, "\\prod_{x=1}^{3} q(0)").await;
+    }
+    #[tokio::test]
+    async fn endless_recursion_is_stopped() {
+        let found = eval_with_functions("h(1)").await;
+        assert_error_at!(found, EvalError::RecursionLimit { .. });
+    }
+    async fn standard_math_test(expected: f64, text: &str) {
+        let found = eval_standard_math(text).await.unwrap().scalar().unwrap();
+        assert!((found - expected).abs() < 1e-9, "found {found}");
+    }
+    #[tokio::test]
+    async fn inverse_and_hyperbolic_functions() {
+        standard_math_test(
+            std::f64::consts::PI / 2.0,
+            "\\arcsin(1) + \\arccos(1) + \\tanh(0)",
+        )
+        .await;
+        standard_math_test(1.0, "\\cosh 0 \\cdot \\sec 0").await;
+    }
+    #[tokio::test]
+    async fn logarithms_in_any_base() {
+        standard_math_test(3.0, "\\log_{2} 8").await;
+        standard_math_test(5.0, "\\lg 100 + \\log(1000)").await;
+        standard_math_test(1.0, "\\ln(\\exp(1))").await;
+    }
+    #[tokio::test]
+    async fn functions_of_many_arguments() {
+        standard_math_test(5.0, "\\max(1, 5, 3)").await;
+        standard_math_test(-2.0, "\\min(4, -2)").await;
+        standard_math_test(6.0, "\\gcd(12, 18, 30)").await;
+        standard_math_test(12.0, "\\operatorname{lcm}(4, 6)").await;
+    }
+    #[tokio::test]
+    async fn rounding_and_sign() {
+        standard_math_test(5.0, "\\lfloor 2.5 \\rfloor + \\lceil 2.5 \\rceil")
+            .await;
+        standard_math_test(-1.0, "\\operatorname{sgn}(-3)").await;
+    }
+    #[tokio::test]
+    async fn discrete_functions_are_exact() {
+        for (text, expected) in [
+            ("\\max(\\frac{1}{3}, 0)", "\\frac{1}{3}"),
+            ("\\min(\\frac{1}{3}, \\frac{1}{4}, 1)", "\\frac{1}{4}"),
+            (
+                "\\lfloor \\frac{123456789012345678901}{10} \\rfloor",
+                "12345678901234567890",
+            ),
+            (
+                "\\lceil \\frac{123456789012345678901}{10} \\rceil",
+                "12345678901234567891",
+            ),
+            ("\\lfloor -\\frac{1}{2} \\rfloor", "-1"),
+            ("\\operatorname{sgn}(-\\frac{1}{3})", "-1"),
+        ] {
+            let found = eval_standard_math(text).await.unwrap();
+            assert_eq!(found.to_string(), expected, "{text}");
+        }
+    }
+    #[tokio::test]
+    async fn fractions_are_exact() {
+        let found = eval_standard_math("\\frac{1}{3}+\\frac{1}{6}").await;
+        assert_eq!(found.unwrap(), Value::Scalar(Number::fraction(1, 2)));
+        let found = eval_standard_math("0.1+0.2").await.unwrap();
+        assert_eq!(found.to_string(), "\\frac{3}{10}");
+        let found = eval_standard_math("\\sqrt{\\frac{4}{9}}").await;
+        assert_eq!(found.unwrap(), Value::Scalar(Number::fraction(2, 3)));
+    }
+    async fn complex_test(expected: &str, text: &str) {
+        let found = eval_standard_math(text).await.unwrap();
+        assert_eq!(found.to_string(), expected);
+    }
+    #[tokio::test]
+    async fn matrices_are_displayed_as_latex() {
+        complex_test(
+            "\\begin{bmatrix}1 & \\frac{1}{2} \\\\-2 & 2i\\end{bmatrix}",
+            "\\begin{pmatrix}1&\\frac{1}{2}\\\\-2&\\sqrt{-4}\\end{pmatrix}",
+        )
+        .await;
+    }
+    #[tokio::test]
+    async fn square_root_of_negative_is_imaginary() {
+        complex_test("2i", "\\sqrt{-4}").await;
+        complex_test("-1", "i^2").await;
+        complex_test("-i", "\\frac{1}{\\mathrm{i}}").await;
+        complex_test("5+5i", "(1+2i)(3-i)").await;
+    }
+    #[tokio::test]
+    async fn complex_functions() {
+        complex_test("-1", "e^{i\\pi}").await;
+        complex_test("5", "|3+4i|").await;
+        let found = eval_standard_math("\\ln(-1)").await.unwrap();
+        assert_eq!(found, Value::Complex(Complex64::new(0.0, PI)));
+        let found = eval_standard_math("\\sin(i)").await.unwrap();
+        assert_eq!(found, Value::Complex(Complex64::new(0.0, 1f64.sinh())));
+        let found = eval_with_functions("f(2i)").await.unwrap();
+        assert_eq!(found, Value::Complex(Complex64::new(0.0, -8.0)));
+    }
+    #[tokio::test]
+    async fn imaginary_i_can_be_turned_off() {
+        let mut context = MathContext::standard_math();
+        context.set_imaginary_i(false);
+        let Ast::Expression(expr) = parse("i", &context).await.unwrap() else {
+            panic!("Cannot evaluate statement.");
+        };
+        let approximator = Approximator::new(context);
+        let found = approximator
+            .eval_expr(expr.simple(approximator.context()).unwrap());
+        assert_error_at!(found, EvalError::NotDefined);
+    }
+    #[tokio::test]
+    async fn complex_argument_is_not_real() {
+        let found = eval_standard_math("\\max(1, i)").await;
+        assert_error_at!(found, EvalError::ArgumentType { position: 2, .. });
+    }
+    #[tokio::test]
+    async fn fifty_significant_digits() {
+        let context = MathContext::standard_math();
+        let approximator =
+            Approximator::with_precision(context, Precision::Digits(50))
+                .unwrap();
+        for (text, expected) in [
+            (
+                "\\pi",
+                "3.14159265358979323846264338327950288419716939937510",
+            ),
+            (
+                "\\sqrt{2}",
+                "1.41421356237309504880168872420969807856967187537694",
+            ),
+            (
+                "e^{2}",
+                "7.38905609893065022723042746057500781318031557055184",
+            ),
+            (
+                "\\sin 1",
+                "0.84147098480789650665250232163029899962256306079837",
+            ),
+        ] {
+            let Ast::Expression(expr) =
+                parse(text, approximator.context()).await.unwrap()
+            else {
+                panic!("Cannot evaluate statement.");
+            };
+            let found = approximator
+                .eval_expr(expr.simple(approximator.context()).unwrap())
+                .unwrap();
+            let Value::Scalar(found @ Number::Big(_)) = found else {
+                panic!("Expected a big float but found {found}");
+            };
+            let error = (found.clone() - expected.parse().unwrap()).abs();
+            assert!(error.to_f64() < 1e-48, "found {found} for {text}");
+        }
+    }
+    #[tokio::test]
+    async fn thirty_digit_magnitude() {
+        let context = MathContext::standard_math();
+        let approximator =
+            Approximator::with_precision(context, Precision::Digits(30))
+                .unwrap();
+        let Ast::Expression(expr) =
+            parse("\\sin(10^{30})", approximator.context())
+                .await
+                .unwrap()
+        else {
+            panic!("Cannot evaluate statement.");
+        };
+        let found = approximator
+            .eval_expr(expr.simple(approximator.context()).unwrap())
+            .unwrap()
+            .scalar()
+            .unwrap();
+        assert!((found + 0.090_116_901_912_138_06).abs() < 1e-15, "{found}");
+        let big = Number::Big(Box::new(
+            "123456789012345678901234567890".parse().unwrap(),
+        ));
+        assert_eq!(big.to_f64(), 1.234_567_890_123_456_8e29);
+    }
+    #[test]
+    fn precision_without_digits() {
+        let found = Approximator::with_precision(
+            MathContext::standard_math(),
+            Precision::Digits(0),
+        );
+        assert!(matches!(found, Err(EvalError::InvalidPrecision)));
+    }
+    async fn solve_standard_math(
+        text: &str,
+        domain: Domain,
+    ) -> Result<Vec<Value>, EvalError> {
+        let context = MathContext::standard_math();
+        let equation = parse(text, &context).await.unwrap();
+        let x = MathIdentifier::from_single_ident("x");
+        Approximator::new(context).solve(&equation, &x, domain)
+    }
+    async fn solve_test(expected: &[&str], text: &str, domain: Domain) {
+        let found = solve_standard_math(text, domain).await.unwrap();
+        let found: Vec<String> =
+            found.iter().map(ToString::to_string).collect();
+        assert_eq!(found, expected);
+    }
+    #[tokio::test]
+    async fn solving_polynomials_exactly() {
+        solve_test(&["2", "3"], "x^2-5x+6=0", Domain::Real).await;
+        solve_test(&["-4"], "2x+1=x-3", Domain::Real).await;
+        solve_test(&["-\\frac{1}{2}"], "(2x+1)^2=0", Domain::Real).await;
+        solve_test(&[], "x^2+1=0", Domain::Real).await;
+        solve_test(&["-i", "i"], "x^2+1=0", Domain::Complex).await;
+        solve_test(&[], "x=x+1", Domain::Real).await;
+    }
+    #[tokio::test]
+    async fn solving_numerically() {
+        let found = solve_standard_math("\\cos x = x", Domain::Real).await;
+        let found: Vec<f64> =
+            found.unwrap().iter().map(|x| x.scalar().unwrap()).collect();
+        assert_eq!(found.len(), 1);
+        assert!((found[0] - 0.739_085_133_215_160_6).abs() < 1e-9);
+        let found = solve_standard_math("x^3=8", Domain::Real).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert!((found[0].scalar().unwrap() - 2.0).abs() < 1e-9);
+    }
+    #[tokio::test]
+    async fn every_value_is_a_solution() {
+        let found = solve_standard_math("2(x+1)=2x+2", Domain::Real).await;
+        assert!(
+            matches!(found, Err(EvalError::InfinitelyManySolutions)),
+            "found {found:?}"
+        );
+        let found =
+            solve_standard_math("(\\sin x)^2+(\\cos x)^2=1", Domain::Real)
+                .await;
+        assert!(
+            matches!(found, Err(EvalError::InfinitelyManySolutions)),
+            "found {found:?}"
+        );
+    }
+    #[tokio::test]
+    async fn solving_linear_systems() {
+        let context = MathContext::standard_math();
+        let Ast::System(equations) = parse(
+            "\\begin{cases} 2x + y = 3 \\\\ x - \\frac{y}{2} = \\pi \\end{cases}",
+            &context,
+        )
+        .await
+        .unwrap() else {
+            panic!("Expected a system");
+        };
+        let unknowns = [
+            MathIdentifier::from_single_ident("x"),
+            MathIdentifier::from_single_ident("y"),
+        ];
+        let LinearSolution::Unique(found) = Approximator::new(context)
+            .solve_system(&equations, &unknowns)
+            .unwrap()
+        else {
+            panic!("Expected a unique solution");
+        };
+        let (x, y) = (found.get(0, 0).scalar(), found.get(1, 0).scalar());
+        assert!((x.unwrap() - (3.0 + 2.0 * PI) / 4.0).abs() < 1e-12);
+        assert!((y.unwrap() - (3.0 - 2.0 * PI) / 2.0).abs() < 1e-12);
+    }
+    #[tokio::test]
+    async fn nonlinear_system_is_an_error() {
+        let context = MathContext::standard_math();
+        let Ast::System(equations) =
+            parse("xy = 1; x + y = 2", &context).await.unwrap()
+        else {
+            panic!("Expected a system");
+        };
+        let unknowns = [
+            MathIdentifier::from_single_ident("x"),
+            MathIdentifier::from_single_ident("y"),
+        ];
+        let found =
+            Approximator::new(context).solve_system(&equations, &unknowns);
+        assert!(
+            matches!(found, Err(EvalError::NotLinear)),
+            "found {found:?}"
+        );
+    }
+    #[tokio::test]
+    async fn solve_function_on_matrices() {
+        let found = eval_standard_math(
+            "\\operatorname{solve}(\\begin{pmatrix}2&1\\\\1&3\\end{pmatrix}, \\begin{pmatrix}3\\\\5\\end{pmatrix})",
+        )
+        .await
+        .unwrap();
+        let Value::Matrix(found) = found else {
+            panic!("Expected a matrix");
+        };
+        assert_eq!(found.get(0, 0), &Value::Scalar(Number::fraction(4, 5)));
+        assert_eq!(found.get(1, 0), &Value::Scalar(Number::fraction(7, 5)));
+        let found = eval_standard_math(
+            "\\operatorname{solve}(\\begin{pmatrix}1&1\\\\1&1\\end{pmatrix}, \\begin{pmatrix}1\\\\2\\end{pmatrix})",
+        )
+        .await;
+        assert_error_at!(found, EvalError::NoSolution);
+    }
+    /// Evaluates text with the matrix `A` in standard math, expecting a
+    /// matrix.
+    async fn eval_with_matrix_a(
+        text: &str,
+    ) -> Result<Matrix<Value>, EvalError> {
+        let a = "\\begin{pmatrix}2&1\\\\4&3\\end{pmatrix}";
+        match eval_standard_math(&text.replace('A', a)).await? {
+            Value::Matrix(m) => Ok(m),
+            found => panic!("Expected a matrix, found {found}"),
+        }
+    }
+    #[tokio::test]
+    async fn inverse_and_transpose() {
+        let exact = |values: [(i64, i64); 4]| {
+            let values = values
+                .into_iter()
+                .map(|(n, d)| Value::Scalar(Number::fraction(n, d)))
+                .collect();
+            Matrix::new(values, 2, 2)
+        };
+        let inverse = exact([(3, 2), (-1, 2), (-2, 1), (1, 1)]);
+        assert_eq!(eval_with_matrix_a("A^{-1}").await.unwrap(), inverse);
+        assert_eq!(
+            eval_with_matrix_a("\\operatorname{inv}(A)").await.unwrap(),
+            inverse
+        );
+        assert_eq!(eval_with_matrix_a("\\frac{1}{A}").await.unwrap(), inverse);
+        let transpose = exact([(2, 1), (4, 1), (1, 1), (3, 1)]);
+        assert_eq!(eval_with_matrix_a("A^T").await.unwrap(), transpose);
+        assert_eq!(eval_with_matrix_a("A^\\top").await.unwrap(), transpose);
+    }
+    #[tokio::test]
+    async fn matrix_properties() {
+        let scalar = |text: &str| {
+            let text = text.replace(
+                'A',
+                "\\begin{pmatrix}1&2&3\\\\2&4&6\\\\1&0&1\\end{pmatrix}",
+            );
+            async move { eval_standard_math(&text).await.unwrap() }
+        };
+        assert_eq!(scalar("\\det(A)").await, Value::from(Number::from(0)));
+        assert_eq!(
+            scalar("\\operatorname{tr}(A)").await,
+            Value::from(Number::from(6))
+        );
+        assert_eq!(
+            scalar("\\operatorname{rank}(A)").await,
+            Value::from(Number::from(2))
+        );
+        let found = eval_standard_math(
+            "\\det(\\begin{pmatrix}0&2&1\\\\1&1&1\\\\2&1&0\\end{pmatrix})",
+        )
+        .await;
+        assert_eq!(found.unwrap(), Value::from(Number::from(3)));
+    }
+    #[tokio::test]
+    async fn singular_matrix_has_no_inverse() {
+        let found =
+            eval_standard_math("\\begin{pmatrix}1&2\\\\2&4\\end{pmatrix}^{-1}")
+                .await;
+        assert_error_at!(found, EvalError::SingularMatrix);
+        let found =
+            eval_standard_math("\\det(\\begin{pmatrix}1&2\\end{pmatrix})")
+                .await;
+        assert_error_at!(
+            found,
+            EvalError::IncompatibleMatrixSizes {
+                source: IncompatibleMatrixSizes::Square {
+                    rows: 1,
+                    columns: 2
+                }
+            }
+        );
+    }
+    #[tokio::test]
+    async fn decompositions_are_rows_of_matrices() {
+        let found = eval_with_matrix_a("\\operatorname{lu}(A)").await.unwrap();
+        assert_eq!((found.row_count(), found.column_count()), (1, 3));
+        let Value::Matrix(u) = found.get(0, 2) else {
+            panic!("Expected a matrix");
+        };
+        assert_eq!(u.get(1, 0), &Value::from(Number::from(0)));
+        let found = eval_standard_math(
+            "\\operatorname{chol}(\\begin{pmatrix}4&2\\\\2&5\\end{pmatrix})",
+        )
+        .await
+        .unwrap();
+        let expected = [2, 0, 1, 2].map(|x| Value::from(Number::from(x)));
+        assert_eq!(found, Value::Matrix(Matrix::new(expected.to_vec(), 2, 2)));
+        let found = eval_with_matrix_a("\\operatorname{eig}(A)").await.unwrap();
+        let Value::Matrix(values) = found.get(0, 0) else {
+            panic!("Expected a matrix");
+        };
+        // The eigenvalues of A are \frac{5 \pm \sqrt{17}}{2}
+        let smallest = (5.0 - 17f64.sqrt()) / 2.0;
+        assert!((values.get(0, 0).scalar().unwrap() - smallest).abs() < 1e-12);
+    }
+    /// Evaluates text where `A` is a 2x3 matrix and `v` a vector.
+    async fn eval_with_matrix_variables(
+        text: &str,
+    ) -> Result<Value, EvalError> {
+        let mut context = MathContext::standard_math();
+        for (name, value) in [
+            ("A", "\\begin{pmatrix}1&2&3\\\\4&5&6\\end{pmatrix}"),
+            ("v", "(7,8,9)"),
+            ("u", "(1,0,0)"),
+        ] {
+            let Ast::Expression(expr) = parse(value, &context).await.unwrap()
+            else {
+                panic!("Expected an expression");
+            };
+            context
+                .variables
+                .insert(MathIdentifier::from_single_ident(name), expr);
+        }
+        let Ast::Expression(expr) = parse(text, &context).await.unwrap() else {
+            panic!("Cannot evaluate statement.");
+        };
+        let approximator = Approximator::new(context);
+        approximator.eval_expr(expr.simple(approximator.context())?)
+    }
+    #[tokio::test]
+    async fn indexing_matrices() {
+        let number = |x: i64| Value::from(Number::from(x));
+        let found = eval_with_matrix_variables("A_{2,3} + v_1").await;
+        assert_eq!(found.unwrap(), number(13));
+        let found = eval_with_matrix_variables("A_{2,:}").await.unwrap();
+        let row = Matrix::new(vec![number(4), number(5), number(6)], 1, 3);
+        assert_eq!(found, Value::Matrix(row));
+        let found = eval_with_matrix_variables("A_{:,2}").await.unwrap();
+        let column = Matrix::new(vec![number(2), number(5)], 2, 1);
+        assert_eq!(found, Value::Matrix(column));
+    }
+    #[tokio::test]
+    async fn index_out_of_range() {
+        for (text, index, size) in
+            [("A_{3,1}", 3, 2), ("A_{1,0}", 0, 3), ("v_4", 4, 3)]
+        {
+            let found = eval_with_matrix_variables(text).await;
+            assert_error_at!(
+                found,
+                EvalError::IncompatibleMatrixSizes {
+                    source: IncompatibleMatrixSizes::Index { index: i, size: s }
+                } if i == index && s == size,
+                "{text}"
+            );
+        }
+        let found = eval_with_matrix_variables("A_1").await;
+        assert_error_at!(
+            found,
+            EvalError::IncompatibleMatrixSizes {
+                source: IncompatibleMatrixSizes::Vector { .. }
+            }
+        );
+    }
+    #[tokio::test]
+    async fn norms() {
+        let number = |x: i64| Value::from(Number::from(x));
+        for (text, expected) in [
+            ("\\|(3,4)\\|", 5),
+            ("\\|(3,-4)\\|_1", 7),
+            ("\\|(3,-4)\\|_\\infty", 4),
+            ("\\|\\begin{pmatrix}1&2\\\\2&4\\end{pmatrix}\\|", 5),
+            ("2\\|(3,4)\\|\\|-2\\|", 20),
+        ] {
+            let found = eval_standard_math(text).await;
+            assert_eq!(found.unwrap(), number(expected), "{text}");
+        }
+    }
+    #[tokio::test]
+    async fn unit_vectors_angles_and_projections() {
+        let fraction = |n, d| Value::from(Number::fraction(n, d));
+        let found = eval_standard_math("\\hat{(3,4)}").await.unwrap();
+        let unit = Matrix::new(vec![fraction(3, 5), fraction(4, 5)], 1, 2);
+        assert_eq!(found, Value::Matrix(unit));
+        let found =
+            eval_with_matrix_variables("\\operatorname{proj}_u v").await;
+        let projection = Matrix::new(
+            vec![fraction(7, 1), fraction(0, 1), fraction(0, 1)],
+            1,
+            3,
+        );
+        assert_eq!(found.unwrap(), Value::Matrix(projection));
+        for (text, expected) in [
+            ("\\angle(u, u\\times v)", PI / 2.0),
+            ("\\angle(v, 2v)", 0.0),
+            ("\\angle(u, -u)", PI),
+            ("\\angle(u, v)", (7.0 / 194f64.sqrt()).acos()),
+        ] {
+            let found = eval_with_matrix_variables(text).await;
+            let found = found.unwrap().scalar().unwrap();
+            assert!((found - expected).abs() < 1e-12, "{text}: found {found}");
+        }
+        let found = eval_standard_math("\\hat{(0,0)}").await;
+        assert_error_at!(found, EvalError::DivideByZero);
+    }
+    #[tokio::test]
+    async fn constructed_matrices() {
+        let number = |x: i64| Value::from(Number::from(x));
+        let numbers = |values: &[i64], rows| {
+            let values: Vec<Value> =
+                values.iter().map(|&x| number(x)).collect();
+            let columns = values.len() / rows;
+            Value::Matrix(Matrix::new(values, rows, columns))
+        };
+        for (text, expected) in [
+            ("I_3", numbers(&[1, 0, 0, 0, 1, 0, 0, 0, 1], 3)),
+            ("\\mathbb{1}_2", numbers(&[1, 0, 0, 1], 2)),
+            ("0_{2\\times 3}", numbers(&[0, 0, 0, 0, 0, 0], 2)),
+            ("0_2+I_2", numbers(&[1, 0, 0, 1], 2)),
+            ("\\operatorname{diag}(1,2)", numbers(&[1, 0, 0, 2], 2)),
+            ("\\operatorname{diag}((3,4))", numbers(&[3, 0, 0, 4], 2)),
+            ("(1,\\ldots,4)", numbers(&[1, 2, 3, 4], 1)),
+            ("(1,3,\\ldots,8)", numbers(&[1, 3, 5, 7], 1)),
+            ("(3,2,\\ldots,1)", numbers(&[3, 2, 1], 1)),
+        ] {
+            let found = eval_standard_math(text).await;
+            assert_eq!(found.unwrap(), expected, "{text}");
+        }
+        for text in ["(1,\\ldots,0)", "(1,1,\\ldots,3)"] {
+            let found = eval_standard_math(text).await;
+            assert_error_at!(found, EvalError::InvalidRange, "{text}");
+        }
+        let found = eval_standard_math("I_0").await;
+        assert_error_at!(
+            found,
+            EvalError::IncompatibleMatrixSizes {
+                source: IncompatibleMatrixSizes::Size { size: 0 }
+            }
+        );
+        for text in [
+            "I_{100000}",
+            "0_{100000\\times 100000}",
+            "(1,\\ldots,10^{10})",
+        ] {
+            let found = eval_standard_math(text).await;
+            assert_error_at!(
+                found,
+                EvalError::IncompatibleMatrixSizes {
+                    source: IncompatibleMatrixSizes::TooLarge { .. }
+                },
+                "{text}"
+            );
+        }
+    }
+    #[tokio::test]
+    async fn rotation_matrices() {
+        for (text, expected) in [
+            (
+                "\\operatorname{rot}(\\frac{\\pi}{2})",
+                vec![0.0, -1.0, 1.0, 0.0],
+            ),
+            (
+                "\\operatorname{rot}(\\frac{\\pi}{2}, (0,0,2))",
+                vec![0.0, -1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0],
+            ),
+            (
+                "\\operatorname{rot}(\\pi, (1,1,0))",
+                vec![0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0],
+            ),
+        ] {
+            let Value::Matrix(found) = eval_standard_math(text).await.unwrap()
+            else {
+                panic!("Expected a matrix for {text}");
+            };
+            let found = found.rows().concat();
+            assert_eq!(found.len(), expected.len(), "{text}");
+            for (found, expected) in found.iter().zip(expected) {
+                let found = found.scalar().unwrap();
+                assert!(
+                    (found - expected).abs() < 1e-12,
+                    "{text}: found {found}"
+                );
+            }
+        }
+    }
+    /// A 2x2 matrix of exact fractions, row by row.
+    fn exact_2x2(values: [(i64, i64); 4]) -> Matrix<Value> {
+        let values = values
+            .into_iter()
+            .map(|(n, d)| Value::Scalar(Number::fraction(n, d)))
+            .collect();
+        Matrix::new(values, 2, 2)
+    }
+    #[tokio::test]
+    async fn element_wise_operations() {
+        for (text, expected) in [
+            ("A\\odot A", [(4, 1), (1, 1), (16, 1), (9, 1)]),
+            ("A\\oslash A", [(1, 1), (1, 1), (1, 1), (1, 1)]),
+            ("2\\oslash A", [(1, 1), (2, 1), (1, 2), (2, 3)]),
+            ("A+1", [(3, 1), (2, 1), (5, 1), (4, 1)]),
+            ("1-A", [(-1, 1), (0, 1), (-3, 1), (-2, 1)]),
+            ("\\frac{A}{2}", [(1, 1), (1, 2), (2, 1), (3, 2)]),
+        ] {
+            let found = eval_with_matrix_a(text).await;
+            assert_eq!(found.unwrap(), exact_2x2(expected), "{text}");
+        }
+        let found = eval_standard_math("(1,2)\\odot(3,4,5)").await;
+        assert_error_at!(found, EvalError::IncompatibleMatrixSizes { .. });
+    }
+    #[tokio::test]
+    async fn vector_products_are_kept_by_simplification() {
+        let found = eval_standard_math("2(1,2)\\cdot(3,4)").await;
+        assert_eq!(found.unwrap(), Value::from(Number::from(22)));
+        let found = eval_standard_math("(1,0,0)\\times(0,1,0)").await;
+        let number = |x: i64| Value::from(Number::from(x));
+        let z = Matrix::new(vec![number(0), number(0), number(1)], 3, 1);
+        assert_eq!(found.unwrap(), Value::Matrix(z));
+    }
+    #[tokio::test]
+    async fn integer_matrix_powers() {
+        for (text, expected) in [
+            ("A^3", [(36, 1), (23, 1), (92, 1), (59, 1)]),
+            ("A^1", [(2, 1), (1, 1), (4, 1), (3, 1)]),
+            ("A^0", [(1, 1), (0, 1), (0, 1), (1, 1)]),
+            ("A^{-2}", [(13, 4), (-5, 4), (-5, 1), (2, 1)]),
+        ] {
+            let found = eval_with_matrix_a(text).await;
+            assert_eq!(found.unwrap(), exact_2x2(expected), "{text}");
+        }
+        let found = eval_standard_math("(1,2)^2").await;
+        assert_error_at!(
+            found,
+            EvalError::IncompatibleMatrixSizes {
+                source: IncompatibleMatrixSizes::Square { .. }
+            }
+        );
+    }
+    #[tokio::test]
+    async fn large_matrix_powers_are_approximated() {
+        let found =
+            eval_standard_math("\\begin{pmatrix}2\\end{pmatrix}^{100000000}")
+                .await;
+        let infinity = Value::from(Number::Float(f64::INFINITY));
+        assert_eq!(
+            found.unwrap(),
+            Value::Matrix(Matrix::new(vec![infinity], 1, 1))
+        );
+        let found = eval_standard_math(
+            "\\begin{pmatrix}1&1\\\\0&1\\end{pmatrix}^{100000}",
+        )
+        .await;
+        let float = |x: f64| Value::from(Number::Float(x));
+        let expected = Matrix::new(
+            vec![float(1.0), float(1e5), float(0.0), float(1.0)],
+            2,
+            2,
+        );
+        assert_eq!(found.unwrap(), Value::Matrix(expected));
+    }
+    #[tokio::test]
+    async fn element_wise_functions_are_opt_in() {
+        let text =
+            "\\lfloor\\begin{pmatrix}2&1\\\\4&3\\end{pmatrix}\\oslash3\\rfloor";
+        let mut context = MathContext::standard_math();
+        let Ast::Expression(expr) = parse(text, &context).await.unwrap() else {
+            panic!("Cannot evaluate statement.");
+        };
+        let found = Approximator::new(context.clone())
+            .eval_expr(expr.clone().simple(&context).unwrap());
+        assert_error_at!(found, EvalError::ArgumentType { .. });
+        context.element_wise = true;
+        let found = Approximator::new(context.clone())
+            .eval_expr(expr.simple(&context).unwrap());
+        let expected = exact_2x2([(0, 1), (0, 1), (1, 1), (1, 1)]);
+        assert_eq!(found.unwrap(), Value::Matrix(expected));
+    }
+    #[tokio::test]
+    async fn errors_are_at_the_innermost_call() {
+        let found = eval_standard_math("1+\\sqrt{\\gcd(1.5, 3)}").await;
+        let span = found.as_ref().err().and_then(EvalError::span);
+        assert_eq!(span, Some(Span::new(8, 20)), "found {found:?}");
+        let found = eval_standard_math("\\sum_{i=1}^{2}\\frac{1}{i-1}").await;
+        let span = found.as_ref().err().and_then(EvalError::span);
+        assert_eq!(span, Some(Span::new(0, 27)), "found {found:?}");
+    }
+    #[tokio::test]
+    async fn errors_know_where_they_are() {
+        for (text, start, end) in [
+            ("1+y", 2, 3),
+            ("\\sqrt{y}", 6, 7),
+            ("x_{5}", 0, 5),
+            ("(1,2)+(1,2,3)", 0, 13),
+            ("2^{(1,2)}", 0, 9),
+            ("\\sqrt{(1,2)}", 0, 12),
+        ] {
+            let found = eval_standard_math(text).await;
+            let span = found.as_ref().err().and_then(EvalError::span);
+            assert_eq!(span, Some(Span::new(start, end)), "{text}: {found:?}");
+        }
+    }
+    #[tokio::test]
+    async fn partial_ast_does_not_evaluate() {
+        let context = MathContext::standard_math();
+        let (ast, errors) =
+            parse_with_recovery("1+\\sqrt{}", &context).await.unwrap();
+        assert_eq!(errors.len(), 1);
+        let Ast::Expression(expr) = ast else {
+            panic!("Cannot evaluate statement.");
+        };
+        let approximator = Approximator::new(context);
+        let found = expr
+            .simple(approximator.context())
+            .and_then(|simple| approximator.eval_expr(simple));
+        let span = found.as_ref().err().and_then(EvalError::span);
+        assert_eq!(span, Some(Span::new(8, 9)), "found {found:?}");
+        assert_error_at!(found, EvalError::InvalidSyntax);
+    }
+    #[tokio::test]
+    async fn gcd_expects_integers() {
+        let found = eval_standard_math("\\gcd(1.5, 3)").await;
+        assert_error_at!(found, EvalError::ExpectedInteger { .. });
+    }
+    #[tokio::test]
+    async fn derivatives_of_elementary_functions() {
+        eval_test(0.2, "\\frac{d}{dx} \\arctan x").await;
+        eval_test(1.0 / (2.0 * 2f64.ln()), "\\frac{d}{dx} \\log_{2} x").await;
+        eval_test(1.0, "\\frac{d}{dx} \\max(x, 1)").await;
+        eval_test(0.0, "\\frac{d}{dx} \\min(x, 1)").await;
+        eval_test(1.0, "\\frac{d}{dx} \\max(1, x)").await;
+        eval_test(3.0, "\\frac{d}{dx} \\max(x^2, 3x)").await;
+        eval_test(4.0, "\\frac{d}{dx} \\min(3x, x^2)").await;
+    }
+    #[tokio::test]
+    async fn lcm_overflow() {
+        let found =
+            eval_standard_math("\\operatorname{lcm}(4294967296, 4294967297)")
+                .await;
+        assert_error_at!(found, EvalError::IntegerOverflow);
+    }
 }
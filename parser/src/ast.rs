@@ -7,21 +7,25 @@ pub mod factorize;
 pub mod helper;
 pub mod into;
 pub mod simplify;
+pub mod substitute;
 pub mod to_latex;
 ///The root of the AST that is non recursive
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Eq, Hash, Debug)]
 pub enum Ast {
     /// A mathematical expression that can be evaluated.
     Expression(MathExpr),
     /// An equation consisting of an equality between a left-hand side and a
     /// right-hand side.
     Equality(MathExpr, MathExpr),
+    /// Several equations that hold at the same time, written in a `cases`
+    /// or `aligned` environment or separated by `;`.
+    System(Vec<(MathExpr, MathExpr)>),
 }
 /// A mathematical expression that consists of one or more terms added
 /// or subtracted.
 ///
 /// See Wikipedia article [Expression (mathematics)](https://en.wikipedia.org/wiki/Expression_(mathematics)).
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub enum MathExpr {
     /// A [Term] containing the rest of the syntax that go before in evaluation
     Term(Term),
@@ -37,9 +41,9 @@ pub enum MathExpr {
     ///     Ast::Expression(
     ///         MathExpr::Add(
     ///             Box::new(
-    ///                 Factor::Constant(2.0).into()
+    ///                 Factor::Constant(2.0.into()).into()
     ///             ),
-    ///             Factor::Constant(2.0).into()
+    ///             Factor::Constant(2.0.into()).into()
     ///         )
     ///     )
     /// );
@@ -57,9 +61,9 @@ pub enum MathExpr {
     ///     Ast::Expression(
     ///         MathExpr::Subtract(
     ///             Box::new(
-    ///                 Factor::Constant(2.0).into()
+    ///                 Factor::Constant(2.0.into()).into()
     ///             ),
-    ///             Factor::Constant(2.0).into()
+    ///             Factor::Constant(2.0.into()).into()
     ///         )
     ///     )
     /// );
@@ -73,7 +77,7 @@ pub enum MathExpr {
 /// For scalar multiplication, the type of multiplication makes no difference,
 /// but in some cases, for example when multiplying vectors, the symbol used
 /// for multiplication makes a difference.
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub enum MulType {
     /// 2 * x
     ///
@@ -94,6 +98,16 @@ pub enum MulType {
     ///
     /// Matrix multiplication when used on matrices.
     Implicit,
+
+    /// 2 \odot x
+    ///
+    /// Element-wise (Hadamard) product when used on matrices.
+    Odot,
+
+    /// 2 \oslash x
+    ///
+    /// Element-wise division, so it divides 2 by x.
+    Oslash,
 }
 
 /// A term consists of a number or variable, or the product or quotient of
@@ -109,7 +123,7 @@ pub enum MulType {
 /// > 1 + 2x + 8yzx
 ///
 /// *1*, *2x*, and *8yzx* are three separate terms.
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub enum Term {
     /// A [Factor] containing the rest of the syntax that go before in
     /// evaluation
@@ -127,9 +141,9 @@ pub enum Term {
     ///         Term::Multiply(
     ///             MulType::Asterisk,
     ///             Box::new(Term::Factor(
-    ///                 Factor::Constant(2.0)
+    ///                 Factor::Constant(2.0.into())
     ///             )),
-    ///             Factor::Constant(2.0)
+    ///             Factor::Constant(2.0.into())
     ///         ).into()
     ///     )
     /// );
@@ -148,9 +162,9 @@ pub enum Term {
     ///     Ast::Expression(
     ///         Term::Divide(
     ///             Box::new(Term::Factor(
-    ///                 Factor::Constant(2.0)
+    ///                 Factor::Constant(2.0.into())
     ///             )),
-    ///             Factor::Constant(2.0)
+    ///             Factor::Constant(2.0.into())
     ///         ).into()
     ///     )
     /// );
@@ -167,29 +181,30 @@ pub enum Term {
 /// Factors also represent most of the mathematical syntax, like roots and
 /// functions. This is because they operate on the same level in terms of
 /// order of operations.
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub enum Factor {
-    /// Normal numbers
+    /// Normal numbers, where decimals are parsed exactly
     /// ## Examples
     ///  ```
     /// # use parser::ast::*;
+    /// # use parser::number::Number;
     /// # use parser::prelude::MathContext;
     /// # use parser::prelude::_private::parse_sync_doc_test as parse;
     /// # let context=MathContext::standard_math();
     /// assert_eq!(
     ///     parse("2", &context),
     ///     Ast::Expression(
-    ///         Factor::Constant(2.0).into()
+    ///         Factor::Constant(2.0.into()).into()
     ///     )
     /// );
     /// assert_eq!(
     ///     parse("1.1", &context),
     ///     Ast::Expression(
-    ///         Factor::Constant(1.1).into()
+    ///         Factor::Constant(Number::fraction(11, 10)).into()
     ///     )
     /// );
     /// ```
-    Constant(f64),
+    Constant(Number),
     /// Parenthesis with a MathExpr
     /// ## Examples
     /// ```
@@ -202,7 +217,7 @@ pub enum Factor {
     /// assert_eq!(
     ///     parse("(1)", &context),
     ///     Ast::Expression(
-    ///         Factor::Parenthesis(Box::new(Factor::Constant(1.0).into())).into()
+    ///         Factor::Parenthesis(Box::new(Factor::Constant(1.0.into()).into())).into()
     ///     )
     /// );
 
@@ -218,7 +233,8 @@ pub enum Factor {
     /// example, if f(x)=2x, then the value of x will vary between calls to
     /// f.
     ///
-    /// Variables are identified using the [MathIdentifier] struct.
+    /// Variables are identified using the [MathIdentifier] struct, and know
+    /// where they are written.
     /// ## Examples
     /// ```
     /// # use parser::ast::*;
@@ -233,11 +249,11 @@ pub enum Factor {
     /// assert_eq!(
     ///     parse("x", &context),
     ///     Ast::Expression(
-    ///         Factor::Variable(MathIdentifier::from_single_ident("x")).into()
+    ///         Factor::from(MathIdentifier::from_single_ident("x")).into()
     ///     )
     /// );
     /// ```
-    Variable(MathIdentifier),
+    Variable(MathIdentifier, NodeSpan),
     /// An expression that represents a function that is being invoked.
     /// ## Examples
     /// ```
@@ -258,7 +274,7 @@ pub enum Factor {
     ///     Ast::Expression(
     ///         Factor::FunctionCall(FunctionCall::new(
     ///             MathIdentifier::from_single_ident("f"),
-    ///             vec![Factor::Variable(MathIdentifier::from_single_ident("x"))
+    ///             vec![Factor::from(MathIdentifier::from_single_ident("x"))
     ///                 .into()],
     ///         ))
     ///         .into()
@@ -281,8 +297,9 @@ pub enum Factor {
     ///     parse("3^2", &context),
     ///     Ast::Expression(
     ///         Factor::Power {
-    ///             base: Box::new(Factor::Constant(3.0)),
-    ///             exponent: Box::new(Factor::Constant(2.0).into())
+    ///             base: Box::new(Factor::Constant(3.0.into())),
+    ///             exponent: Box::new(Factor::Constant(2.0.into()).into()),
+    ///             span: NodeSpan::NONE,
     ///         }
     ///         .into()
     ///     )
@@ -295,6 +312,8 @@ pub enum Factor {
         /// The exponent of the ^ so the 2 in our example above for
         /// Fraction::Power
         exponent: Box<MathExpr>,
+        /// Where the power is written.
+        span: NodeSpan,
     },
     /// The root of a MathExpr
     /// ## Examples
@@ -309,8 +328,9 @@ pub enum Factor {
     ///     parse("\\sqrt[3]{2}", &context),
     ///     Ast::Expression(
     ///         Factor::Root {
-    ///             degree: Some(Box::new(Factor::Constant(3.0).into())),
-    ///             radicand: Box::new(Factor::Constant(2.0).into()),
+    ///             degree: Some(Box::new(Factor::Constant(3.0.into()).into())),
+    ///             radicand: Box::new(Factor::Constant(2.0.into()).into()),
+    ///             span: NodeSpan::NONE,
     ///         }
     ///         .into()
     ///     )
@@ -321,6 +341,8 @@ pub enum Factor {
         degree: Option<Box<MathExpr>>,
         /// The thing to take the Nth Root of
         radicand: Box<MathExpr>,
+        /// Where the root is written.
+        span: NodeSpan,
     },
 
     /// A fraction.
@@ -345,8 +367,8 @@ pub enum Factor {
     ///     parse("\\frac{1}{2}", &context),
     ///     Ast::Expression(
     ///         Factor::Fraction(
-    ///             Box::new(Factor::Constant(1.0).into()),
-    ///             Box::new(Factor::Constant(2.0).into()),
+    ///             Box::new(Factor::Constant(1.0.into()).into()),
+    ///             Box::new(Factor::Constant(2.0.into()).into()),
     ///         )
     ///         .into()
     ///     )
@@ -365,7 +387,7 @@ pub enum Factor {
     /// assert_eq!(
     ///     parse("|3|", &context),
     ///     Ast::Expression(
-    ///         Factor::Abs(Box::new(Factor::Constant(3.0).into())).into()
+    ///         Factor::Abs(Box::new(Factor::Constant(3.0.into()).into())).into()
     ///     )
     /// );
     /// ```
@@ -385,10 +407,10 @@ pub enum Factor {
     /// assert_eq!(
     ///     parse("(1,1)", &context),
     ///     Ast::Expression(
-    ///         Factor::Matrix(Matrix::new(
+    ///         Factor::from(Matrix::new(
     ///             vec![
-    ///                 Factor::Constant(1.0).into(),
-    ///                 Factor::Constant(1.0).into()
+    ///                 Factor::Constant(1.0.into()).into(),
+    ///                 Factor::Constant(1.0.into()).into()
     ///             ],
     ///             1,
     ///             2
@@ -409,10 +431,10 @@ pub enum Factor {
     /// assert_eq!(
     ///     parse("\\begin{bmatrix}1&1\\end{bmatrix}", &context),
     ///     Ast::Expression(
-    ///         Factor::Matrix(Matrix::new(
+    ///         Factor::from(Matrix::new(
     ///             vec![
-    ///                 Factor::Constant(1.0).into(),
-    ///                 Factor::Constant(1.0).into()
+    ///                 Factor::Constant(1.0.into()).into(),
+    ///                 Factor::Constant(1.0.into()).into()
     ///             ],
     ///             1,
     ///             2
@@ -435,10 +457,10 @@ pub enum Factor {
     ///     parse("\\begin{Vmatrix}1&1\\end{Vmatrix}", &context),
     ///     Ast::Expression(
     ///         Factor::Abs(Box::new(
-    ///             Factor::Matrix(Matrix::new(
+    ///             Factor::from(Matrix::new(
     ///                 vec![
-    ///                     Factor::Constant(1.0).into(),
-    ///                     Factor::Constant(1.0).into()
+    ///                     Factor::Constant(1.0.into()).into(),
+    ///                     Factor::Constant(1.0.into()).into()
     ///                 ],
     ///                 1,
     ///                 2
@@ -449,14 +471,251 @@ pub enum Factor {
     ///     )
     /// );
     /// ```
-    Matrix(Matrix<MathExpr>),
+    Matrix(Matrix<MathExpr>, NodeSpan),
+    /// A sum over an integer range of an index variable.
+    /// ## Examples
+    /// ```
+    /// # use parser::ast::*;
+    /// # use parser::identifier::MathIdentifier;
+    /// # use parser::prelude::MathContext;
+    /// # use parser::prelude::_private::parse_sync_doc_test as parse;
+    /// # let mut context=MathContext::standard_math();
+    /// // parsing \sum_{i=1}^{3} i
+    /// assert_eq!(
+    ///     parse("\\sum_{i=1}^{3} i", &context),
+    ///     Ast::Expression(
+    ///         Factor::Sum(IndexedOperation::new(
+    ///             MathIdentifier::from_single_ident("i"),
+    ///             Box::new(Factor::Constant(1.0.into()).into()),
+    ///             Box::new(Factor::Constant(3.0.into()).into()),
+    ///             Box::new(
+    ///                 Factor::from(MathIdentifier::from_single_ident("i"))
+    ///                     .into()
+    ///             ),
+    ///         ))
+    ///         .into()
+    ///     )
+    /// );
+    /// ```
+    Sum(IndexedOperation),
+    /// A product over an integer range of an index variable, for example
+    /// `\prod_{k=1}^{5} k`.
+    ///
+    /// See [Factor::Sum] for how the bounds and body are parsed.
+    Product(IndexedOperation),
+    /// A definite integral over an interval of a variable.
+    /// ## Examples
+    /// ```
+    /// # use parser::ast::*;
+    /// # use parser::identifier::MathIdentifier;
+    /// # use parser::prelude::MathContext;
+    /// # use parser::prelude::_private::parse_sync_doc_test as parse;
+    /// # let mut context=MathContext::standard_math();
+    /// // parsing \int_{0}^{1} x \, dx
+    /// assert_eq!(
+    ///     parse("\\int_{0}^{1} x \\, dx", &context),
+    ///     Ast::Expression(
+    ///         Factor::Integral(Integral::new(
+    ///             MathIdentifier::from_single_ident("x"),
+    ///             Box::new(Factor::Constant(0.0.into()).into()),
+    ///             Box::new(Factor::Constant(1.0.into()).into()),
+    ///             Box::new(
+    ///                 Factor::from(MathIdentifier::from_single_ident("x"))
+    ///                     .into()
+    ///             ),
+    ///         ))
+    ///         .into()
+    ///     )
+    /// );
+    /// ```
+    Integral(Integral),
+    /// The limit of an expression as a variable approaches a target.
+    /// ## Examples
+    /// ```
+    /// # use parser::ast::*;
+    /// # use parser::identifier::MathIdentifier;
+    /// # use parser::prelude::MathContext;
+    /// # use parser::prelude::_private::parse_sync_doc_test as parse;
+    /// # let mut context=MathContext::standard_math();
+    /// // parsing \lim_{x \to 0^+} x
+    /// assert_eq!(
+    ///     parse("\\lim_{x \\to 0^+} x", &context),
+    ///     Ast::Expression(
+    ///         Factor::Limit(Limit::new(
+    ///             MathIdentifier::from_single_ident("x"),
+    ///             Box::new(Factor::Constant(0.0.into()).into()),
+    ///             LimitDirection::Above,
+    ///             Box::new(
+    ///                 Factor::from(MathIdentifier::from_single_ident("x"))
+    ///                     .into()
+    ///             ),
+    ///         ))
+    ///         .into()
+    ///     )
+    /// );
+    /// ```
+    Limit(Limit),
+    /// A derivative of an expression, written in Leibniz notation like
+    /// `\frac{d}{dx}`, as a partial derivative like `\frac{\partial f}{\partial
+    /// y}` or in prime notation like `f'(x)`.
+    /// ## Examples
+    /// ```
+    /// # use parser::ast::*;
+    /// # use parser::identifier::MathIdentifier;
+    /// # use parser::prelude::MathContext;
+    /// # use parser::prelude::_private::parse_sync_doc_test as parse;
+    /// # let mut context=MathContext::standard_math();
+    /// // parsing \frac{d^2}{dx^2} x
+    /// assert_eq!(
+    ///     parse("\\frac{d^2}{dx^2} x", &context),
+    ///     Ast::Expression(
+    ///         Factor::Derivative(Derivative::new(
+    ///             Box::new(
+    ///                 Factor::from(MathIdentifier::from_single_ident("x"))
+    ///                     .into()
+    ///             ),
+    ///             vec![
+    ///                 MathIdentifier::from_single_ident("x"),
+    ///                 MathIdentifier::from_single_ident("x"),
+    ///             ],
+    ///             DerivativeNotation::Leibniz,
+    ///         ))
+    ///         .into()
+    ///     )
+    /// );
+    /// ```
+    Derivative(Derivative),
+    /// A part that could not be parsed, written at the span. Only found in
+    /// the partial AST from [crate::prelude::parse_with_recovery].
+    Error(Span),
+}
+
+/// The bound index, limits and body of an indexed operation such as
+/// [Factor::Sum] or [Factor::Product].
+///
+/// Both limits are inclusive, so `\sum_{i=1}^{3}` evaluates the body for
+/// `i` being 1, 2 and 3.
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct IndexedOperation {
+    /// The index variable that is bound while evaluating the body.
+    pub index: MathIdentifier,
+    /// The first value of the index.
+    pub lower: Box<MathExpr>,
+    /// The last value of the index.
+    pub upper: Box<MathExpr>,
+    /// The expression that is evaluated for every value of the index.
+    pub body: Box<MathExpr>,
+    /// Where the operation is written.
+    pub span: NodeSpan,
+}
+
+/// The variable, bounds and integrand of a definite integral, see
+/// [Factor::Integral].
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Integral {
+    /// The variable of integration, for example the `x` in `dx`.
+    pub variable: MathIdentifier,
+    /// The lower bound of the interval.
+    pub lower: Box<MathExpr>,
+    /// The upper bound of the interval.
+    pub upper: Box<MathExpr>,
+    /// The integrand.
+    pub body: Box<MathExpr>,
+    /// Where the integral is written.
+    pub span: NodeSpan,
+}
+
+/// The variable, target and body of a limit, see [Factor::Limit].
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Limit {
+    /// The variable that approaches the target.
+    pub variable: MathIdentifier,
+    /// The value the variable approaches, which may be infinite.
+    pub target: Box<MathExpr>,
+    /// The side the variable approaches the target from.
+    pub direction: LimitDirection,
+    /// The expression to find the limit of.
+    pub body: Box<MathExpr>,
+    /// Where the limit is written.
+    pub span: NodeSpan,
+}
+
+/// The side a [Limit] approaches its target from.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum LimitDirection {
+    /// From both sides, for example `x \to 0`.
+    Both,
+    /// From above, for example `x \to 0^+`.
+    Above,
+    /// From below, for example `x \to 0^-`.
+    Below,
+}
+
+/// The body and variables of a derivative, see [Factor::Derivative].
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Derivative {
+    /// The expression to differentiate.
+    pub body: Box<MathExpr>,
+    /// The variables to differentiate with respect to, in order. A variable
+    /// is repeated for higher order derivatives, so the second derivative
+    /// `\frac{d^2}{dx^2}` has the variables `x` and `x`.
+    pub variables: Vec<MathIdentifier>,
+    /// How the derivative was written.
+    pub notation: DerivativeNotation,
+    /// The values the variables are evaluated at after differentiating, for
+    /// example the `a` in `f'(a)`. Empty if the derivative is a function of
+    /// the variables.
+    pub point: Vec<(MathIdentifier, MathExpr)>,
+}
+
+/// How a [Derivative] is written.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum DerivativeNotation {
+    /// For example `\frac{d}{dx}`.
+    Leibniz,
+    /// For example `\frac{\partial}{\partial x}`.
+    Partial,
+    /// For example `f'(x)`, where the body is the function called with its
+    /// parameters.
+    Prime,
 }
 
 /// an identified function
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub struct FunctionCall {
     ///The name for the function called
     pub function_name: MathIdentifier,
     ///The input to the function
     pub arguments: Vec<MathExpr>,
+    ///Where the call is written
+    pub span: NodeSpan,
+}
+
+/// Where a node is written in the LaTeX, if it was parsed.
+///
+/// It is ignored when comparing nodes, so the same expression is equal
+/// wherever it is written. The span is boxed to keep the nodes small, since
+/// they are on the stack while evaluating recursive functions.
+#[derive(Debug, Clone, Default)]
+pub struct NodeSpan(Option<Box<Span>>);
+impl NodeSpan {
+    /// A node that was not parsed, so it is not written anywhere.
+    pub const NONE: Self = Self(None);
+    /// A node written at the span.
+    pub fn new(span: Span) -> Self {
+        Self(Some(Box::new(span)))
+    }
+    /// Where the node is written, if it was parsed.
+    pub fn get(&self) -> Option<Span> {
+        self.0.as_deref().copied()
+    }
+}
+impl PartialEq for NodeSpan {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+impl Eq for NodeSpan {}
+impl std::hash::Hash for NodeSpan {
+    fn hash<H: std::hash::Hasher>(&self, _: &mut H) {}
 }
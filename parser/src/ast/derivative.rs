@@ -1,17 +1,36 @@
 //! the derive implementations
-use crate::{identifier::OtherSymbol, prelude::*};
+use crate::{
+    ast::{helper::NumberCompare, simplify::Simplify},
+    identifier::OtherSymbol,
+    prelude::*,
+};
 impl Ast {
     ///doing derivation for the AST
     pub fn derivative(
         &self,
         dependent: &MathIdentifier,
+        cont: &MathContext,
     ) -> Result<Ast, EvalError> {
         Ok(match self {
-            Ast::Expression(m) => Ast::Expression(m.derivative(dependent)?),
+            Ast::Expression(m) => {
+                Ast::Expression(m.derivative(dependent, cont)?)
+            }
             Ast::Equality(lhs, rhs) => Ast::Equality(
-                lhs.derivative(dependent)?,
-                rhs.derivative(dependent)?,
+                lhs.derivative(dependent, cont)?,
+                rhs.derivative(dependent, cont)?,
             ),
+            Ast::System(equations) => {
+                let equations: Result<Vec<_>, EvalError> = equations
+                    .iter()
+                    .map(|(lhs, rhs)| {
+                        Ok((
+                            lhs.derivative(dependent, cont)?,
+                            rhs.derivative(dependent, cont)?,
+                        ))
+                    })
+                    .collect();
+                Ast::System(equations?)
+            }
         })
     }
 }
@@ -21,33 +40,135 @@ impl MathExpr {
     pub fn derivative(
         &self,
         dependent: &MathIdentifier,
+        cont: &MathContext,
     ) -> Result<MathExpr, EvalError> {
         Ok(match self {
-            MathExpr::Term(t) => t.derivative(dependent)?,
+            MathExpr::Term(t) => t.derivative(dependent, cont)?,
             MathExpr::Add(lhs, rhs) => MathExpr::Add(
-                lhs.derivative(dependent)?.boxed(),
-                rhs.derivative(dependent)?.get_term_or_wrap().clone(),
+                lhs.derivative(dependent, cont)?.boxed(),
+                rhs.derivative(dependent, cont)?.get_term_or_wrap().clone(),
             ),
             MathExpr::Subtract(lhs, rhs) => MathExpr::Subtract(
-                lhs.derivative(dependent)?.boxed(),
-                rhs.derivative(dependent)?.get_term_or_wrap().clone(),
+                lhs.derivative(dependent, cont)?.boxed(),
+                rhs.derivative(dependent, cont)?.get_term_or_wrap().clone(),
             ),
         })
     }
+
+    /// The gradient, a column vector of the partial derivatives with respect
+    /// to each of the variables, for example `\nabla f`.
+    ///
+    /// The partial derivatives are [Factor::Derivative]s, so they stay
+    /// symbolic until they are simplified or evaluated.
+    pub fn gradient(&self, variables: &[MathIdentifier]) -> Factor {
+        let values = variables
+            .iter()
+            .map(|variable| self.partial(vec![variable.clone()]))
+            .collect();
+        Factor::from(Matrix::new(values, variables.len(), 1))
+    }
+
+    /// The Jacobian matrix, where each row holds the partial derivatives of
+    /// one component with respect to each of the variables.
+    ///
+    /// The components of a matrix are taken row by row, and any other
+    /// expression is a single component.
+    pub fn jacobian(&self, variables: &[MathIdentifier]) -> Factor {
+        let components = match self.factor() {
+            Some(Factor::Matrix(m, _)) => (0..m.row_count())
+                .flat_map(|row| {
+                    (0..m.column_count())
+                        .map(|column| m.get(row, column).clone())
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+            Some(Factor::Parenthesis(inner)) => {
+                return inner.jacobian(variables)
+            }
+            _ => vec![self.clone()],
+        };
+        let values = components
+            .iter()
+            .flat_map(|component| {
+                variables
+                    .iter()
+                    .map(|variable| component.partial(vec![variable.clone()]))
+            })
+            .collect();
+        Factor::from(Matrix::new(values, components.len(), variables.len()))
+    }
+
+    /// The Hessian matrix, where the element at row `i` and column `j` is the
+    /// second partial derivative with respect to the `i`th and `j`th
+    /// variable.
+    pub fn hessian(&self, variables: &[MathIdentifier]) -> Factor {
+        let values = variables
+            .iter()
+            .flat_map(|row| {
+                variables.iter().map(|column| {
+                    self.partial(vec![row.clone(), column.clone()])
+                })
+            })
+            .collect();
+        Factor::from(Matrix::new(values, variables.len(), variables.len()))
+    }
+
+    /// The partial derivative with respect to the variables, in order.
+    fn partial(&self, variables: Vec<MathIdentifier>) -> MathExpr {
+        Factor::Derivative(Derivative::new(
+            self.clone().boxed(),
+            variables,
+            DerivativeNotation::Partial,
+        ))
+        .into()
+    }
 }
 impl Term {
     ///doing derivation for the Term
     pub fn derivative(
         &self,
         dependent: &MathIdentifier,
+        cont: &MathContext,
     ) -> Result<MathExpr, EvalError> {
         Ok(match self {
-            Term::Factor(f) => f.derivative(dependent)?,
+            Term::Factor(f) => f.derivative(dependent, cont)?,
+            Term::Multiply(MulType::Oslash, f, g) => {
+                // The quotient rule with element-wise operations
+                let top = MathExpr::Subtract(
+                    Term::Multiply(
+                        MulType::Odot,
+                        f.derivative(dependent, cont)?
+                            .get_term_or_wrap()
+                            .boxed(),
+                        g.clone(),
+                    )
+                    .into(),
+                    Term::Multiply(
+                        MulType::Odot,
+                        f.clone(),
+                        g.clone()
+                            .derivative(dependent, cont)?
+                            .get_factor_or_wrap(),
+                    ),
+                );
+                let bottom: MathExpr = Term::Multiply(
+                    MulType::Odot,
+                    Term::Factor(g.clone()).boxed(),
+                    g.clone(),
+                )
+                .into();
+                Term::Multiply(
+                    MulType::Oslash,
+                    Term::Factor(Factor::Parenthesis(top.boxed())).boxed(),
+                    Factor::Parenthesis(bottom.boxed()),
+                )
+                .into()
+            }
             Term::Multiply(mul, rhs, lhs) => MathExpr::Add(
                 Box::new(
                     Term::Multiply(
                         mul.clone(),
-                        rhs.derivative(dependent)?
+                        rhs.derivative(dependent, cont)?
                             .get_term_or_wrap()
                             .clone()
                             .boxed(),
@@ -58,13 +179,15 @@ impl Term {
                 Term::Multiply(
                     mul.clone(),
                     rhs.clone(),
-                    lhs.derivative(dependent)?.get_factor_or_wrap().clone(),
+                    lhs.derivative(dependent, cont)?
+                        .get_factor_or_wrap()
+                        .clone(),
                 ),
             ),
             Term::Divide(f, g) => {
                 let f = (*f.clone()).into();
                 let g = g.clone().into();
-                quotient_rule(&f, &g, dependent)?
+                quotient_rule(&f, &g, dependent, cont)?
             }
         })
     }
@@ -77,11 +200,12 @@ fn quotient_rule(
     f: &MathExpr,
     g: &MathExpr,
     dependent: &MathIdentifier,
+    cont: &MathContext,
 ) -> Result<MathExpr, EvalError> {
     // f'(x)g(x)
     let a = Term::Multiply(
         MulType::Implicit,
-        f.derivative(dependent)?.get_term_or_wrap().boxed(),
+        f.derivative(dependent, cont)?.get_term_or_wrap().boxed(),
         g.get_factor_or_wrap(),
     );
 
@@ -89,7 +213,7 @@ fn quotient_rule(
     let b = Term::Multiply(
         MulType::Implicit,
         f.get_term_or_wrap().boxed(),
-        g.derivative(dependent)?.get_factor_or_wrap(),
+        g.derivative(dependent, cont)?.get_factor_or_wrap(),
     );
 
     // f'(x)g(x) - f(x)g'(x) = a - b;
@@ -99,6 +223,7 @@ fn quotient_rule(
     let bottom: MathExpr = Factor::Power {
         base: g.get_factor_or_wrap().boxed(),
         exponent: 2f64.into(),
+        span: NodeSpan::NONE,
     }
     .into();
 
@@ -110,16 +235,24 @@ impl Factor {
     pub fn derivative(
         &self,
         dependent: &MathIdentifier,
+        cont: &MathContext,
     ) -> Result<MathExpr, EvalError> {
         Ok(match self {
-            Factor::Constant(_) => Factor::Constant(0.0).into(),
-            Factor::Parenthesis(e) => e.derivative(dependent)?,
-            Factor::Variable(v) => match v == dependent {
-                true => Factor::Constant(1.0).into(),
-                false => Factor::Constant(0.0).into(),
+            Factor::Constant(_) => Factor::Constant(0.0.into()).into(),
+            Factor::Error(span) => {
+                return Err(EvalError::InvalidSyntax.at(Some(*span)))
+            }
+            Factor::Parenthesis(e) => e.derivative(dependent, cont)?,
+            Factor::Variable(v, _) => match v == dependent {
+                true => Factor::Constant(1.0.into()).into(),
+                false => Factor::Constant(0.0.into()).into(),
             },
-            Factor::FunctionCall(_) => todo!("function call"),
-            Factor::Power { base, exponent } => MathExpr::Add(
+            Factor::FunctionCall(call) => cont
+                .functions
+                .get(&call.function_name)
+                .ok_or(EvalError::NotDefined)?
+                .derivate(&call.arguments, dependent, cont)?,
+            Factor::Power { base, exponent, .. } => MathExpr::Add(
                 Term::Multiply(
                     MulType::Implicit,
                     Term::Multiply(
@@ -130,13 +263,16 @@ impl Factor {
                             base: base.clone(),
                             exponent: MathExpr::Subtract(
                                 exponent.clone(),
-                                Factor::Constant(1.0).into(),
+                                Factor::Constant(1.0.into()).into(),
                             )
                             .boxed(),
+                            span: NodeSpan::NONE,
                         },
                     )
                     .into(),
-                    base.derivative(dependent)?.get_factor_or_wrap().clone(),
+                    base.derivative(dependent, cont)?
+                        .get_factor_or_wrap()
+                        .clone(),
                 )
                 .into(),
                 Term::Multiply(
@@ -145,26 +281,30 @@ impl Factor {
                         MulType::Implicit,
                         Factor::FunctionCall(FunctionCall::new(
                             MathIdentifier::from_single_symbol(OtherSymbol::Ln),
-                            vec![*exponent.clone()],
+                            vec![(**base).clone().into()],
                         ))
                         .into(),
                         Factor::Power {
                             base: base.clone(),
                             exponent: exponent.clone(),
+                            span: NodeSpan::NONE,
                         },
                     )
                     .into(),
                     exponent
-                        .derivative(dependent)?
+                        .derivative(dependent, cont)?
                         .get_factor_or_wrap()
                         .clone(),
                 ),
             ),
 
-            Factor::Root { degree, radicand } => {
+            Factor::Root {
+                degree, radicand, ..
+            } => {
                 let root = Factor::Root {
                     degree: degree.clone(),
                     radicand: radicand.clone(),
+                    span: NodeSpan::NONE,
                 };
 
                 // Derivative of radicand
@@ -184,7 +324,7 @@ impl Factor {
                         .into(),
                     )
                     .into(),
-                    radicand.derivative(dependent)?.get_factor_or_wrap(),
+                    radicand.derivative(dependent, cont)?.get_factor_or_wrap(),
                 );
 
                 // Derivative of degree, for exmaple in \sqrt[x]{2}.
@@ -198,20 +338,172 @@ impl Factor {
                                 OtherSymbol::Ln,
                             ),
                             arguments: vec![*radicand.clone()],
+                            span: NodeSpan::NONE,
                         }),
                     )
                     .boxed(),
                     degree
                         .clone()
                         .unwrap_or(2_f64.into())
-                        .derivative(dependent)?
+                        .derivative(dependent, cont)?
                         .get_factor_or_wrap(),
                 );
                 MathExpr::Add(radicand_deriv.into(), degree_deriv)
             }
-            Factor::Fraction(f, g) => quotient_rule(f, g, dependent)?,
-            Factor::Abs(_math) => todo!("ABS"),
-            Factor::Matrix(_) => todo!("matrix derivation not defined"),
+            Factor::Fraction(f, g) => quotient_rule(f, g, dependent, cont)?,
+            // |u|' = u' sgn(u), where sgn(u) is written as u/|u|
+            Factor::Abs(expr) => Term::Multiply(
+                MulType::Implicit,
+                expr.derivative(dependent, cont)?.get_term_or_wrap().boxed(),
+                Factor::Fraction(
+                    expr.clone(),
+                    Factor::Abs(expr.clone()).into(),
+                ),
+            )
+            .into(),
+            Factor::Matrix(m, _) => {
+                Factor::from(m.map(|expr| expr.derivative(dependent, cont))?)
+                    .into()
+            }
+            // The index is bound by the operation, so nothing depends on it
+            // outside of the body.
+            Factor::Sum(sum) | Factor::Product(sum)
+                if &sum.index == dependent =>
+            {
+                Factor::Constant(0.0.into()).into()
+            }
+            // The derivative of a sum is the sum of the derivatives.
+            Factor::Sum(sum) => Factor::Sum(IndexedOperation {
+                body: sum.body.derivative(dependent, cont)?.boxed(),
+                ..sum.clone()
+            })
+            .into(),
+            // The product rule, (f_a ... f_b)' is the sum over j of
+            // (f_a ... f_{j-1}) f_j' (f_{j+1} ... f_b). The index of the sum
+            // is reused as j, since the bounds of the inner products are
+            // outside of their own index.
+            Factor::Product(product) => {
+                let j: MathExpr = MathExpr::from(product.index.clone());
+                let before = Factor::Product(IndexedOperation {
+                    upper: MathExpr::Subtract(j.clone().boxed(), 1.0.into())
+                        .boxed(),
+                    ..product.clone()
+                });
+                let after = Factor::Product(IndexedOperation {
+                    lower: MathExpr::Add(j.boxed(), 1.0.into()).boxed(),
+                    ..product.clone()
+                });
+                let derivative = Factor::Parenthesis(
+                    product.body.derivative(dependent, cont)?.boxed(),
+                );
+                Factor::Sum(IndexedOperation {
+                    body: MathExpr::Term(Term::Multiply(
+                        MulType::Implicit,
+                        Term::Multiply(
+                            MulType::Implicit,
+                            Term::Factor(before).boxed(),
+                            derivative,
+                        )
+                        .boxed(),
+                        after,
+                    ))
+                    .boxed(),
+                    ..product.clone()
+                })
+                .into()
+            }
+            Factor::Integral(integral) if &integral.variable == dependent => {
+                Factor::Constant(0.0.into()).into()
+            }
+            // Leibniz integral rule
+            // d/dx \int_{a}^{b} f dt = \int_{a}^{b} f' dt + f(b)b' - f(a)a'
+            Factor::Integral(integral) => {
+                let boundary = |bound: &MathExpr| -> Result<Term, EvalError> {
+                    Ok(Term::Multiply(
+                        MulType::Implicit,
+                        integral
+                            .body
+                            .substitute(&integral.variable, bound)
+                            .get_term_or_wrap()
+                            .boxed(),
+                        bound.derivative(dependent, cont)?.get_factor_or_wrap(),
+                    ))
+                };
+                let inner: MathExpr = Factor::Integral(Integral {
+                    body: integral.body.derivative(dependent, cont)?.boxed(),
+                    ..integral.clone()
+                })
+                .into();
+                MathExpr::Subtract(
+                    MathExpr::Add(inner.boxed(), boundary(&integral.upper)?)
+                        .boxed(),
+                    boundary(&integral.lower)?,
+                )
+            }
+            Factor::Limit(limit) if &limit.variable == dependent => {
+                Factor::Constant(0.0.into()).into()
+            }
+            // The limit of the derivative, which requires that the target
+            // does not depend on the variable.
+            Factor::Limit(limit) => {
+                let target = limit
+                    .target
+                    .derivative(dependent, cont)?
+                    .simple(&cont.unbind(dependent))?;
+                if !matches!(target.factor(), Some(Factor::Constant(c)) if c.is_zero())
+                {
+                    return Err(DeriveError::All {
+                        message: "can not derive a limit with a target that depends on the variable".to_owned(),
+                    }
+                    .into());
+                }
+                Factor::Limit(Limit {
+                    body: limit.body.derivative(dependent, cont)?.boxed(),
+                    ..limit.clone()
+                })
+                .into()
+            }
+            // Differentiate once more with respect to the dependent, and use
+            // the chain rule for the values of the point.
+            Factor::Derivative(derivative) => {
+                let bound_by_point = derivative
+                    .point
+                    .iter()
+                    .any(|(ident, _)| ident == dependent);
+                let mut result: MathExpr = match bound_by_point {
+                    true => Factor::Constant(0.0.into()).into(),
+                    false => Factor::Derivative(Derivative {
+                        variables: [
+                            derivative.variables.clone(),
+                            vec![dependent.clone()],
+                        ]
+                        .concat(),
+                        ..derivative.clone()
+                    })
+                    .into(),
+                };
+                for (ident, value) in &derivative.point {
+                    let inner = Factor::Derivative(Derivative {
+                        variables: [
+                            derivative.variables.clone(),
+                            vec![ident.clone()],
+                        ]
+                        .concat(),
+                        ..derivative.clone()
+                    });
+                    result = MathExpr::Add(
+                        result.boxed(),
+                        Term::Multiply(
+                            MulType::Implicit,
+                            Term::Factor(inner).boxed(),
+                            value
+                                .derivative(dependent, cont)?
+                                .get_factor_or_wrap(),
+                        ),
+                    );
+                }
+                result
+            }
         })
     }
 }
@@ -229,7 +521,7 @@ mod test {
         let found_ast = parse(text, &context)
             .await
             .expect("failed to parse AST")
-            .derivative(dependent)
+            .derivative(dependent, &context)
             .expect("Failed ");
 
         let found_simple = found_ast.simplify(&context).unwrap();
@@ -261,6 +553,15 @@ mod test {
         .await;
     }
     #[tokio::test]
+    async fn sum_term_wise() {
+        ast_test_derive(
+            "\\sum_{i=1}^{3} i x^2",
+            &MathIdentifier::from_single_ident("x"),
+            "\\sum_{i=1}^{3} i(2x)",
+        )
+        .await;
+    }
+    #[tokio::test]
     async fn test() {
         ast_test_derive(
             "(3x^2 + 2x)",
@@ -269,4 +570,22 @@ mod test {
         )
         .await;
     }
+    #[tokio::test]
+    async fn natural_logarithm() {
+        ast_test_derive(
+            "\\ln(x)",
+            &MathIdentifier::from_single_ident("x"),
+            "\\frac{1}{x}",
+        )
+        .await;
+    }
+    #[tokio::test]
+    async fn matrix_element_wise() {
+        ast_test_derive(
+            "\\begin{bmatrix}x^2 & 3x\\end{bmatrix}",
+            &MathIdentifier::from_single_ident("x"),
+            "\\begin{bmatrix}2x & 3\\end{bmatrix}",
+        )
+        .await;
+    }
 }
@@ -49,44 +49,49 @@ impl PrivateMathEquality for Simple {
 }
 
 impl PrivateMathEquality for MathExpr {
+    #[rustfmt::skip]
     fn equals(&self, other: &MathExpr, cont: &MathContext) -> bool {
         match (self,other){
-            (MathExpr::Term(_), MathExpr::Term(_)) => unreachable!("The Simple structs implementations should ensure that this is working"),
+            (MathExpr::Term(t_1), MathExpr::Term(t_2)) => t_1.equals(t_2,cont),
             (MathExpr::Add(lhs_1, rhs_1), MathExpr::Add(lhs_2, rhs_2)) =>
             (lhs_1.equals(lhs_2,cont)&&rhs_1.equals(rhs_2,cont))||
-            (lhs_1.term().map_or(false,|f|f.equals(rhs_2,cont))&&
-            lhs_2.term().map_or(false, |f|f.equals(rhs_1,cont))),
+            (lhs_1.term().is_some_and(|f|f.equals(rhs_2,cont))&&
+            lhs_2.term().is_some_and(|f|f.equals(rhs_1,cont))),
             (MathExpr::Subtract(lhs_1, rhs_1), MathExpr::Subtract(lhs_2, rhs_2)) =>
             (lhs_1.equals(lhs_2,cont)&&rhs_1.equals(rhs_2,cont))||
-            (lhs_1.term().map_or(false,|f|f.equals(rhs_2,cont))&&
-            lhs_2.term().map_or(false, |f|f.equals(rhs_1,cont))),
+            (lhs_1.term().is_some_and(|f|f.equals(rhs_2,cont))&&
+            lhs_2.term().is_some_and(|f|f.equals(rhs_1,cont))),
             _=>false
         }
     }
 }
 impl PrivateMathEquality for Term {
+    #[rustfmt::skip]
     fn equals(&self, other: &Term, cont: &MathContext) -> bool {
         match (self,other){
-            (Term::Factor(_), Term::Factor(_)) => unreachable!("The Simple structs implementations should ensure that this is working"),
-            (Term::Multiply(_, lhs_1, rhs_1), Term::Multiply(_, lhs_2, rhs_2)) => {
+            (Term::Factor(f_1), Term::Factor(f_2)) => f_1.equals(f_2,cont),
+            (Term::Multiply(mul_1, lhs_1, rhs_1), Term::Multiply(mul_2, lhs_2, rhs_2)) => {
             if let(Term::Factor(lhs_1),Term::Factor(lhs_2))=(lhs_1.as_ref(),lhs_2.as_ref()){
-                #[allow(unused_variables)]//remove this when we do matrix equality needs to be checked her because MulType
+                // Products of matrices do not commute, so the factors have to
+                // be in the same order and multiplied the same way
+                let in_order=||mul_1==mul_2&&lhs_1.equals(lhs_2,cont)&&rhs_1.equals(rhs_2,cont);
                 return match (lhs_1,rhs_1,lhs_2,rhs_2){
-                    (Factor::Matrix(lhs1),Factor::Matrix(rhs1),Factor::Matrix(lhs2),Factor::Matrix(rhs2))=>todo!(),
-                    (Factor::Matrix(lhs1),rhs1,Factor::Matrix(lhs2),_)=>todo!(),
-                    (Factor::Matrix(lhs1),rhs1,lhs2,Factor::Matrix(rhs2))=>todo!(),
-                    (_,Factor::Matrix(rhs1),Factor::Matrix(lhs2),_)=>todo!(),
-                    (_,Factor::Matrix(rhs1),_,Factor::Matrix(rhs2))=>todo!(),
+                    (Factor::Matrix(..),Factor::Matrix(..),Factor::Matrix(..),Factor::Matrix(..))=>in_order(),
+                    (Factor::Matrix(..),_,Factor::Matrix(..),_)=>in_order(),
+                    (Factor::Matrix(..),_,_,Factor::Matrix(..))=>in_order(),
+                    (_,Factor::Matrix(..),Factor::Matrix(..),_)=>in_order(),
+                    (_,Factor::Matrix(..),_,Factor::Matrix(..))=>in_order(),
                     _=>(lhs_1.equals(lhs_2,cont)&&rhs_1.equals(rhs_2,cont))||
             (lhs_1.equals(rhs_2,cont)&&
             lhs_2.equals(rhs_1,cont))
                 }
             }
             (lhs_1.equals(lhs_2,cont)&&rhs_1.equals(rhs_2,cont))||
-            (lhs_1.factor().map_or(false,|f|f.equals(rhs_2,cont))&&
-            lhs_2.factor().map_or(false, |f|f.equals(rhs_1,cont)))
+            (lhs_1.factor().is_some_and(|f|f.equals(rhs_2,cont))&&
+            lhs_2.factor().is_some_and(|f|f.equals(rhs_1,cont)))
         }
-            (Term::Divide(_, _), Term::Divide(_, _)) => todo!(),
+            (Term::Divide(lhs_1, rhs_1), Term::Divide(lhs_2, rhs_2)) =>
+            lhs_1.equals(lhs_2,cont)&&rhs_1.equals(rhs_2,cont),
             _=>false
         }
     }
@@ -98,7 +103,7 @@ impl PrivateMathEquality for Factor {
             (Factor::Parenthesis(p_1), Factor::Parenthesis(p_2)) => {
                 p_1.equals(p_2, cont)
             }
-            (Factor::Variable(v_1), Factor::Variable(v_2)) => v_1 == v_2,
+            (Factor::Variable(v_1, _), Factor::Variable(v_2, _)) => v_1 == v_2,
             (Factor::FunctionCall(f_1), Factor::FunctionCall(f_2)) => {
                 f_1 == f_2
             }
@@ -106,20 +111,24 @@ impl PrivateMathEquality for Factor {
                 Factor::Power {
                     base: b_1,
                     exponent: e_1,
+                    ..
                 },
                 Factor::Power {
                     base: b_2,
                     exponent: e_2,
+                    ..
                 },
             ) => b_1.equals(b_2, cont) && e_1.equals(e_2, cont),
             (
                 Factor::Root {
                     degree: d_1,
                     radicand: r_1,
+                    ..
                 },
                 Factor::Root {
                     degree: d_2,
                     radicand: r_2,
+                    ..
                 },
             ) => {
                 let res = match (d_1, d_2) {
@@ -146,7 +155,21 @@ impl PrivateMathEquality for Factor {
                 t_1.equals(t_2, cont) && n_1.equals(n_2, cont)
             }
             (Factor::Abs(a_1), Factor::Abs(a_2)) => a_1.equals(a_2, cont),
-            (Factor::Matrix(_), Factor::Matrix(_)) => todo!(),
+            (Factor::Matrix(m_1, _), Factor::Matrix(m_2, _)) => {
+                m_1.row_count() == m_2.row_count()
+                    && m_1.column_count() == m_2.column_count()
+                    && (0..m_1.row_count()).all(|row| {
+                        (0..m_1.column_count()).all(|column| {
+                            m_1.get(row, column)
+                                .equals(m_2.get(row, column), cont)
+                        })
+                    })
+            }
+            (Factor::Sum(s_1), Factor::Sum(s_2)) => s_1 == s_2,
+            (Factor::Product(p_1), Factor::Product(p_2)) => p_1 == p_2,
+            (Factor::Integral(i_1), Factor::Integral(i_2)) => i_1 == i_2,
+            (Factor::Limit(l_1), Factor::Limit(l_2)) => l_1 == l_2,
+            (Factor::Derivative(d_1), Factor::Derivative(d_2)) => d_1 == d_2,
             _ => false,
         }
     }
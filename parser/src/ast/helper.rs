@@ -2,10 +2,20 @@
 
 use std::ops::Deref;
 
+use num_traits::{One, Zero};
+
 use crate::prelude::*;
 
 use super::{equality::MathEquality, simplify::Simplify};
 
+/// The span covering both spans, or the one that is known.
+fn joined(a: Option<Span>, b: Option<Span>) -> Option<Span> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.to(b)),
+        _ => a.or(b),
+    }
+}
+
 impl MathExpr {
     ///makes a new MathExpr where the term part is wrapped if needed
     fn add_wrapped(a: MathExpr, b: MathExpr) -> Self {
@@ -29,6 +39,26 @@ impl MathExpr {
             _ => Factor::Parenthesis(self.clone().boxed()),
         }
     }
+    /// Where the expression is written, from its first to its last factor
+    /// that knows where it is written.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            MathExpr::Term(t) => t.span(),
+            MathExpr::Add(lhs, rhs) | MathExpr::Subtract(lhs, rhs) => {
+                joined(lhs.span(), rhs.span())
+            }
+        }
+    }
+    /// Whether the expression may evaluate to a matrix, see
+    /// [Term::may_be_matrix].
+    pub fn may_be_matrix(&self) -> bool {
+        match self {
+            MathExpr::Term(t) => t.may_be_matrix(),
+            MathExpr::Add(lhs, rhs) | MathExpr::Subtract(lhs, rhs) => {
+                lhs.may_be_matrix() || rhs.may_be_matrix()
+            }
+        }
+    }
     ///Gets a factor if it is a factor otherwise None
     pub const fn factor(&self) -> Option<&Factor> {
         match self {
@@ -50,11 +80,67 @@ impl MathExpr {
     }
 }
 
+impl MulType {
+    /// Whether this is an ordinary multiplication, which simplification may
+    /// merge with other factors, unlike for example the dot product.
+    pub const fn is_plain(&self) -> bool {
+        matches!(self, MulType::Asterisk | MulType::Implicit)
+    }
+}
+
 impl Term {
     ///Boxes self
     pub fn boxed(self) -> Box<Self> {
         Box::new(self)
     }
+    /// Whether the term, including terms in parentheses, contains a product
+    /// that is not plain, see [MulType::is_plain], between two operands that
+    /// may both be matrices, or an element-wise quotient. Other products of
+    /// scalars are ordinary multiplication.
+    pub fn has_special_product(&self) -> bool {
+        let in_factor = |factor: &Factor| match factor {
+            Factor::Parenthesis(expr) => {
+                expr.term().is_some_and(Term::has_special_product)
+            }
+            _ => false,
+        };
+        match self {
+            Term::Factor(factor) => in_factor(factor),
+            Term::Multiply(mul_type, lhs, rhs) => {
+                *mul_type == MulType::Oslash
+                    || (!mul_type.is_plain()
+                        && lhs.may_be_matrix()
+                        && rhs.may_be_matrix())
+                    || lhs.has_special_product()
+                    || in_factor(rhs)
+            }
+            Term::Divide(num, den) => {
+                num.has_special_product() || in_factor(den)
+            }
+        }
+    }
+    /// Where the term is written, see [MathExpr::span].
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Term::Factor(factor) => factor.span(),
+            Term::Multiply(_, lhs, rhs) | Term::Divide(lhs, rhs) => {
+                joined(lhs.span(), rhs.span())
+            }
+        }
+    }
+    /// Whether the term may evaluate to a matrix, which is only ruled out
+    /// when every factor is known to be a scalar.
+    pub fn may_be_matrix(&self) -> bool {
+        match self {
+            Term::Factor(factor) => factor.may_be_matrix(),
+            Term::Multiply(_, lhs, rhs) => {
+                lhs.may_be_matrix() || rhs.may_be_matrix()
+            }
+            Term::Divide(num, den) => {
+                num.may_be_matrix() || den.may_be_matrix()
+            }
+        }
+    }
     ///does multiplication but wraps if need be
     fn mul_wrapped(mul: MulType, a: MathExpr, b: MathExpr) -> Self {
         Self::Multiply(
@@ -81,6 +167,57 @@ impl Factor {
     pub fn boxed(self) -> Box<Self> {
         Box::new(self)
     }
+    /// Whether the factor may evaluate to a matrix. Variables, function
+    /// calls and the like are unknown, so they may.
+    pub fn may_be_matrix(&self) -> bool {
+        match self {
+            Factor::Constant(_) | Factor::Abs(_) | Factor::Error(_) => false,
+            Factor::Parenthesis(expr) => expr.may_be_matrix(),
+            Factor::Power { base, .. } => base.may_be_matrix(),
+            Factor::Root { radicand, .. } => radicand.may_be_matrix(),
+            Factor::Fraction(num, den) => {
+                num.may_be_matrix() || den.may_be_matrix()
+            }
+            _ => true,
+        }
+    }
+    /// Where the factor is written, if it was parsed. Constants and
+    /// derivatives do not know where they are written.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Factor::Variable(_, node)
+            | Factor::Power { span: node, .. }
+            | Factor::Root { span: node, .. }
+            | Factor::Matrix(_, node)
+            | Factor::FunctionCall(FunctionCall { span: node, .. })
+            | Factor::Sum(IndexedOperation { span: node, .. })
+            | Factor::Product(IndexedOperation { span: node, .. })
+            | Factor::Integral(Integral { span: node, .. })
+            | Factor::Limit(Limit { span: node, .. }) => node.get(),
+            Factor::Error(span) => Some(*span),
+            Factor::Parenthesis(expr) | Factor::Abs(expr) => expr.span(),
+            Factor::Fraction(num, den) => joined(num.span(), den.span()),
+            Factor::Constant(_) | Factor::Derivative(_) => None,
+        }
+    }
+    ///Sets where the factor is written, if it is a node with a span
+    pub fn with_span(mut self, span: Span) -> Self {
+        match &mut self {
+            Factor::Variable(_, node)
+            | Factor::Power { span: node, .. }
+            | Factor::Root { span: node, .. }
+            | Factor::Matrix(_, node)
+            | Factor::FunctionCall(FunctionCall { span: node, .. })
+            | Factor::Sum(IndexedOperation { span: node, .. })
+            | Factor::Product(IndexedOperation { span: node, .. })
+            | Factor::Integral(Integral { span: node, .. })
+            | Factor::Limit(Limit { span: node, .. }) => {
+                *node = NodeSpan::new(span)
+            }
+            _ => {}
+        }
+        self
+    }
 }
 
 impl FunctionCall {
@@ -92,6 +229,73 @@ impl FunctionCall {
         Self {
             function_name,
             arguments,
+            span: NodeSpan::NONE,
+        }
+    }
+}
+impl Integral {
+    ///a helper method
+    pub const fn new(
+        variable: MathIdentifier,
+        lower: Box<MathExpr>,
+        upper: Box<MathExpr>,
+        body: Box<MathExpr>,
+    ) -> Self {
+        Self {
+            variable,
+            lower,
+            upper,
+            body,
+            span: NodeSpan::NONE,
+        }
+    }
+}
+impl Limit {
+    ///a helper method
+    pub const fn new(
+        variable: MathIdentifier,
+        target: Box<MathExpr>,
+        direction: LimitDirection,
+        body: Box<MathExpr>,
+    ) -> Self {
+        Self {
+            variable,
+            target,
+            direction,
+            body,
+            span: NodeSpan::NONE,
+        }
+    }
+}
+impl Derivative {
+    ///a helper method for a derivative that is not evaluated at a point
+    pub const fn new(
+        body: Box<MathExpr>,
+        variables: Vec<MathIdentifier>,
+        notation: DerivativeNotation,
+    ) -> Self {
+        Self {
+            body,
+            variables,
+            notation,
+            point: Vec::new(),
+        }
+    }
+}
+impl IndexedOperation {
+    ///a helper method
+    pub const fn new(
+        index: MathIdentifier,
+        lower: Box<MathExpr>,
+        upper: Box<MathExpr>,
+        body: Box<MathExpr>,
+    ) -> Self {
+        Self {
+            index,
+            lower,
+            upper,
+            body,
+            span: NodeSpan::NONE,
         }
     }
 }
@@ -118,6 +322,30 @@ impl NumberCompare for f64 {
         (self - other).abs() < f64::EPSILON
     }
 }
+impl NumberCompare for Number {
+    fn is_zero(&self) -> bool {
+        match self {
+            Number::Rational(r) => r.is_zero(),
+            Number::Float(f) => NumberCompare::is_zero(f),
+            Number::Big(b) => b.repr().is_zero(),
+        }
+    }
+
+    fn is_one(&self) -> bool {
+        match self {
+            Number::Rational(r) => r.is_one(),
+            Number::Float(f) => NumberCompare::is_one(f),
+            Number::Big(b) => b.repr().is_one(),
+        }
+    }
+
+    fn equals(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Number::Rational(a), Number::Rational(b)) => a == b,
+            _ => self.to_f64().equals(&other.to_f64()),
+        }
+    }
+}
 ///This type if for comparing Simples and returning Simples, this makes sure
 /// that only correct simples can be constructed
 pub trait SimpleCompare {
@@ -131,8 +359,8 @@ pub trait SimpleCompare {
     fn mul_wrapped(self, m: MulType) -> Simple;
     ///divides them and produces a Simple
     fn div_wrapped(self) -> Simple;
-    ///pows them and produces a Simple
-    fn pow_wrapped(self) -> Simple;
+    ///pows them and produces a Simple, written at the span
+    fn pow_wrapped(self, span: NodeSpan) -> Simple;
     ///Gives as Ast::equals
     fn ast_equals(self) -> Ast;
     ///checks if the contained are  the same
@@ -158,10 +386,11 @@ impl SimpleCompare for (Simple, Simple) {
         Simple(MathExpr::Term(Term::div_wrapped(self.0 .0, self.1 .0)))
     }
 
-    fn pow_wrapped(self) -> Simple {
+    fn pow_wrapped(self, span: NodeSpan) -> Simple {
         Simple(MathExpr::Term(Term::Factor(Factor::Power {
             base: self.0 .0.get_factor_or_wrap().boxed(),
             exponent: self.1 .0.boxed(),
+            span,
         })))
     }
 
@@ -203,42 +432,44 @@ impl Simple {
     pub fn expr(self) -> MathExpr {
         self.0
     }
-    ///adds 2 f64s and makes a Simple Constant
-    pub fn add(lhs: f64, rhs: f64) -> Self {
+    ///adds 2 numbers and makes a Simple Constant
+    pub fn add(lhs: Number, rhs: Number) -> Self {
         Simple(Factor::Constant(lhs + rhs).into())
     }
-    ///subtracts 2 f64s and makes a Simple Constant
-    pub fn sub(lhs: f64, rhs: f64) -> Self {
+    ///subtracts 2 numbers and makes a Simple Constant
+    pub fn sub(lhs: Number, rhs: Number) -> Self {
         Simple(Factor::Constant(lhs - rhs).into())
     }
-    ///multiplies 2 f64s and makes a Simple Constant
-    pub fn mul(lhs: f64, rhs: f64) -> Self {
+    ///multiplies 2 numbers and makes a Simple Constant
+    pub fn mul(lhs: Number, rhs: Number) -> Self {
         Simple(Factor::Constant(lhs * rhs).into())
     }
-    ///divide 2 f64s and makes a Simple Constant
-    pub fn divide(numerator: f64, denominator: f64) -> Self {
+    ///divide 2 numbers and makes a Simple Constant
+    pub fn divide(numerator: Number, denominator: Number) -> Self {
         Simple(Factor::Constant(numerator / denominator).into())
     }
     ///makes a Factor::Constant() containing the given constant
-    pub const fn constant(constant: f64) -> Simple {
-        Simple(MathExpr::Term(Term::Factor(Factor::Constant(constant))))
+    pub fn constant(constant: impl Into<Number>) -> Simple {
+        Simple(MathExpr::Term(Term::Factor(Factor::Constant(
+            constant.into(),
+        ))))
     }
-    ///Puts a Variable into a Simple
-    pub const fn variable(m: MathIdentifier) -> Simple {
-        Simple(MathExpr::Term(Term::Factor(Factor::Variable(m))))
+    ///Puts a Variable written at the span into a Simple
+    pub const fn variable(m: MathIdentifier, span: NodeSpan) -> Simple {
+        Simple(MathExpr::Term(Term::Factor(Factor::Variable(m, span))))
     }
     ///Puts a functionCall into a Simple
     pub const fn function(f: FunctionCall) -> Simple {
         Simple(MathExpr::Term(Term::Factor(Factor::FunctionCall(f))))
     }
-    ///simplifies a matrix
+    ///simplifies a matrix written at the span
     pub fn matrix(
         m: Matrix<MathExpr>,
+        span: NodeSpan,
         cont: &MathContext,
     ) -> Result<Simple, EvalError> {
-        Ok(Simple(
-            Factor::Matrix(m.map_owned(|v| Ok(v.simple(cont)?.expr()))?).into(),
-        ))
+        let m = m.map_owned(|v| Ok(v.simple(cont)?.expr()))?;
+        Ok(Simple(Factor::Matrix(m, span).into()))
     }
 }
 
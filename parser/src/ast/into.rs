@@ -21,6 +21,37 @@ impl From<f64> for Box<MathExpr> {
         Box::new(value.into())
     }
 }
+impl From<Value> for MathExpr {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Scalar(c) => Factor::Constant(c).into(),
+            // re + im \mathrm{i}
+            Value::Complex(c) => MathExpr::Add(
+                MathExpr::from(c.re).boxed(),
+                Term::Multiply(
+                    MulType::Implicit,
+                    Term::from(c.im).boxed(),
+                    Factor::from(MathIdentifier::imaginary_unit()),
+                ),
+            ),
+            Value::Matrix(m) => {
+                let values = (0..m.row_count())
+                    .flat_map(|row| {
+                        (0..m.column_count())
+                            .map(|column| m.get(row, column).clone().into())
+                            .collect::<Vec<_>>()
+                    })
+                    .collect();
+                Factor::from(Matrix::new(
+                    values,
+                    m.row_count(),
+                    m.column_count(),
+                ))
+                .into()
+            }
+        }
+    }
+}
 impl From<FunctionCall> for MathExpr {
     fn from(value: FunctionCall) -> Self {
         MathExpr::Term(Term::from(value))
@@ -49,12 +80,12 @@ impl From<Factor> for Term {
 }
 impl From<f64> for Term {
     fn from(value: f64) -> Self {
-        Term::Factor(Factor::Constant(value))
+        Term::Factor(Factor::Constant(value.into()))
     }
 }
 impl From<MathIdentifier> for Term {
     fn from(value: MathIdentifier) -> Self {
-        Term::Factor(Factor::Variable(value))
+        Term::Factor(Factor::from(value))
     }
 }
 impl From<FunctionCall> for Term {
@@ -69,12 +100,22 @@ impl From<Factor> for Box<Term> {
 }
 impl From<f64> for Factor {
     fn from(value: f64) -> Self {
+        Factor::Constant(value.into())
+    }
+}
+impl From<Number> for Factor {
+    fn from(value: Number) -> Self {
         Factor::Constant(value)
     }
 }
 impl From<MathIdentifier> for Factor {
     fn from(value: MathIdentifier) -> Self {
-        Factor::Variable(value)
+        Factor::Variable(value, NodeSpan::NONE)
+    }
+}
+impl From<Matrix<MathExpr>> for Factor {
+    fn from(value: Matrix<MathExpr>) -> Self {
+        Factor::Matrix(value, NodeSpan::NONE)
     }
 }
 impl From<FunctionCall> for Factor {
@@ -27,6 +27,15 @@ impl Ast {
             Ast::Equality(a, b) => {
                 (a.simple(cont)?, b.simple(cont)?).ast_equals()
             }
+            Ast::System(equations) => {
+                let equations: Result<Vec<_>, EvalError> = equations
+                    .into_iter()
+                    .map(|(a, b)| {
+                        Ok((a.simple(cont)?.expr(), b.simple(cont)?.expr()))
+                    })
+                    .collect();
+                Ast::System(equations?)
+            }
         })
     }
 }
@@ -42,9 +51,8 @@ impl Simplify for MathExpr {
                     (
                         MathExpr::Term(Term::Factor(Factor::Constant(a))),
                         MathExpr::Term(Term::Factor(Factor::Constant(b))),
-                    ) => Simple::add(*a, *b),
+                    ) => Simple::add(a.clone(), b.clone()),
                     (MathExpr::Term(Term::Factor(Factor::Constant(a))), _) => {
-                        println!("first");
                         if a.is_zero() {
                             simple.1
                         } else {
@@ -52,7 +60,6 @@ impl Simplify for MathExpr {
                         }
                     }
                     (_, MathExpr::Term(Term::Factor(Factor::Constant(b)))) => {
-                        println!("second");
                         if b.is_zero() {
                             simple.0
                         } else {
@@ -71,17 +78,10 @@ impl Simplify for MathExpr {
                     (
                         MathExpr::Term(Term::Factor(Factor::Constant(a))),
                         MathExpr::Term(Term::Factor(Factor::Constant(b))),
-                    ) => Simple::sub(*a, *b),
-                    (MathExpr::Term(Term::Factor(Factor::Constant(a))), _) => {
-                        if a < &f64::EPSILON {
-                            simple.0
-                        } else {
-                            simple.sub_wrapped()
-                        }
-                    }
+                    ) => Simple::sub(a.clone(), b.clone()),
                     (_, MathExpr::Term(Term::Factor(Factor::Constant(b)))) => {
-                        if b < &f64::EPSILON {
-                            simple.1
+                        if b.is_zero() {
+                            simple.0
                         } else {
                             simple.sub_wrapped()
                         }
@@ -127,7 +127,7 @@ impl FactorVec {
         }
         trace!("simple, before: {:?}", self.vec);
         let mut result = Vec::with_capacity(self.vec.len());
-        let mut constant_term = 1.0;
+        let mut constant_term = Number::from(1);
         for factor in self.vec {
             if let Factor::Constant(c) = factor {
                 if c.is_one() {
@@ -137,11 +137,11 @@ impl FactorVec {
                 } else if c.is_zero() {
                     // If we have a zero anywhere, then all of the factors will
                     // be zero.
-                    result = vec![Factor::Constant(0.0)];
+                    result = vec![Factor::from(0.0)];
                     break;
                 }
                 // Collect all constant terms into one term.
-                constant_term *= c;
+                constant_term = constant_term * c;
                 continue;
             }
             // Push the rest of the factors.
@@ -172,7 +172,13 @@ impl Term {
 
 impl Simplify for Term {
     fn simple(self, cont: &MathContext) -> Result<Simple, EvalError> {
-        let factors = self.simple_inner(cont)?.get_term_or_wrap().factorize();
+        let term = self.simple_inner(cont)?.get_term_or_wrap();
+        // Factorizing would turn dot products and the like into ordinary
+        // multiplication
+        if term.has_special_product() {
+            return Ok(Simple::new_unchecked(MathExpr::Term(term)));
+        }
+        let factors = term.factorize();
 
         let factors_num = factors.factors_num.simplify_factors(cont)?.simple();
 
@@ -182,7 +188,7 @@ impl Simplify for Term {
 
         let mut factors_den = factors.factors_den;
         if factors_den.vec.len() == 1 {
-            if let Factor::Constant(c) = factors_den.vec[0] {
+            if let Factor::Constant(c) = &factors_den.vec[0] {
                 if c.is_one() {
                     // The denominator is 1, we don't need to express this as a
                     // fraction. Remove the denominator (make vec size 0).
@@ -200,10 +206,10 @@ impl Simplify for Term {
 
         let factors_den = factors_den.simplify_factors(cont)?.simple();
 
-        if let Term::Factor(Factor::Constant(c)) = numerator {
+        if let Term::Factor(Factor::Constant(c)) = &numerator {
             if c.is_zero() {
                 // The numerator is 0, everything is zero.
-                return Factor::Constant(0.0).simple(cont);
+                return Factor::from(0.0).simple(cont);
             }
         }
 
@@ -227,48 +233,56 @@ impl Simplify for Factor {
     fn simple(self, cont: &MathContext) -> Result<Simple, EvalError> {
         Ok(match self {
             Factor::Constant(c) => Simple::constant(c),
+            Factor::Error(span) => {
+                return Err(EvalError::InvalidSyntax.at(Some(span)))
+            }
             Factor::Parenthesis(p) => p.simple(cont)?,
-            Factor::Variable(m) => {
+            Factor::Variable(m, span) => {
                 return cont
                     .variables
                     .get(&m)
                     .map(|v| v.clone().simple(cont))
-                    .unwrap_or(Ok(Simple::variable(m)))
+                    .unwrap_or(Ok(Simple::variable(m, span)))
             }
             Factor::FunctionCall(func_call) => {
+                let span = func_call.span.get();
                 let func = cont
                     .functions
                     .get(&func_call.function_name)
-                    .ok_or(EvalError::NotDefined)?;
+                    .ok_or(EvalError::NotDefined.at(span))?;
                 match func {
                     MathFunction::Native(_) => {
                         Simple::function(func_call.clone())
                     }
+                    // The call is kept, since the function may be
+                    // recursive. It is evaluated by the Approximator.
                     MathFunction::Foreign(f) => {
-                        match func_call.arguments.len() == f.input.len() {
-                            false => Err(EvalError::ArgumentLengthMismatch {
+                        if func_call.arguments.len() != f.input.len() {
+                            Err(EvalError::ArgumentLengthMismatch {
                                 expected: vec![f.input.len()],
                                 found: func_call.arguments.len(),
-                            })?,
-                            true => {
-                                debug_assert!(
-                                    func_call.arguments.len() == f.input.len()
-                                );
-                                let mut new_cont:MathContext=
-                                        f.input.iter().zip(func_call.arguments.iter())
-                                        .try_fold(MathContext::new(),
-                                        |mut context:MathContext,(ident,expr)|
-                                        Ok::<MathContext,EvalError>({
-                                            context.variables.insert(ident.clone(), expr.clone());
-                                            context}))?;
-                                new_cont.merge(cont);
-                                return f.expr.clone().simple(&new_cont);
                             }
+                            .at(span))?
                         }
+                        let arguments = func_call
+                            .arguments
+                            .into_iter()
+                            .map(|arg| Ok(arg.simple(cont)?.expr()))
+                            .collect::<Result<_, EvalError>>()
+                            .map_err(|e| e.at(span))?;
+                        Simple::function(FunctionCall {
+                            function_name: func_call.function_name,
+                            arguments,
+                            span: func_call.span,
+                        })
                     }
                 }
             }
-            Factor::Power { base, exponent } => {
+            Factor::Power {
+                base,
+                exponent,
+                span,
+            } => {
                 let simple = (base.simple(cont)?, exponent.simple(cont)?);
                 match simple.to_math_expr() {
                     (
@@ -276,7 +290,21 @@ impl Simplify for Factor {
                         MathExpr::Term(Term::Factor(Factor::Constant(
                             exponent,
                         ))),
-                    ) => Simple::constant(base.powf(*exponent)),
+                    ) => match base.pow(exponent) {
+                        // Left for the approximator, which gives a complex
+                        // number
+                        power if power.is_nan() => simple.pow_wrapped(span),
+                        // Irrational powers of exact numbers are left for the
+                        // approximator, which knows the precision
+                        power
+                            if !power.is_exact()
+                                && base.is_exact()
+                                && exponent.is_exact() =>
+                        {
+                            simple.pow_wrapped(span)
+                        }
+                        power => Simple::constant(power),
+                    },
                     (
                         _,
                         MathExpr::Term(Term::Factor(Factor::Constant(
@@ -285,19 +313,23 @@ impl Simplify for Factor {
                     ) => {
                         if exponent.is_one() {
                             simple.0
-                        } else if exponent.is_zero() {
+                        } else if exponent.is_zero()
+                            // The power 0 of a matrix is the identity matrix
+                            && simple.0.factor().is_none_or(|base| {
+                                !matches!(base, Factor::Matrix(..))
+                            })
+                        {
                             Simple::constant(1.0)
                         } else {
-                            simple.pow_wrapped()
+                            simple.pow_wrapped(span)
                         }
                     }
-                    _ => simple.pow_wrapped(),
+                    _ => simple.pow_wrapped(span),
                 }
             }
-            Factor::Root {
-                degree: _,
-                radicand: _,
-            } => Simple::new_unchecked(MathExpr::Term(Term::Factor(self))), /* TODO */
+            Factor::Root { .. } => {
+                Simple::new_unchecked(MathExpr::Term(Term::Factor(self)))
+            } /* TODO */
             Factor::Fraction(numerator, denominator) => {
                 simplify_fraction_or_div(
                     numerator.simple(cont)?,
@@ -305,18 +337,106 @@ impl Simplify for Factor {
                     cont,
                 )
             }
-            Factor::Abs(_) => {
-                Simple::new_unchecked(MathExpr::Term(Term::Factor(self)))
-            } // TODO
-            Factor::Matrix(m) => Simple::matrix(m, cont)?,
+            Factor::Abs(expr) => {
+                let simple = expr.simple(cont)?;
+                match simple.factor() {
+                    Some(Factor::Constant(c)) => Simple::constant(c.abs()),
+                    _ => Simple::new_unchecked(
+                        Factor::Abs(simple.expr().boxed()).into(),
+                    ),
+                }
+            }
+            Factor::Matrix(m, span) => Simple::matrix(m, span, cont)?,
+            Factor::Sum(sum) => Simple::new_unchecked(
+                Factor::Sum(sum.simple_parts(cont)?).into(),
+            ),
+            Factor::Product(product) => Simple::new_unchecked(
+                Factor::Product(product.simple_parts(cont)?).into(),
+            ),
+            Factor::Integral(integral) => Simple::new_unchecked(
+                Factor::Integral(integral.simple_parts(cont)?).into(),
+            ),
+            Factor::Limit(limit) => Simple::new_unchecked(
+                Factor::Limit(limit.simple_parts(cont)?).into(),
+            ),
+            Factor::Derivative(derivative) => {
+                derivative.simple_derivative(cont)?
+            }
+        })
+    }
+}
+impl IndexedOperation {
+    /// Simplify the bounds and the body of the operation.
+    ///
+    /// The body is simplified without any outer definition of the index,
+    /// since the index is only bound when the operation is evaluated.
+    fn simple_parts(
+        self,
+        cont: &MathContext,
+    ) -> Result<IndexedOperation, EvalError> {
+        let scope = cont.unbind(&self.index);
+        Ok(IndexedOperation {
+            lower: self.lower.simple(cont)?.expr().boxed(),
+            upper: self.upper.simple(cont)?.expr().boxed(),
+            body: self.body.simple(&scope)?.expr().boxed(),
+            index: self.index,
+            span: self.span,
         })
     }
 }
+impl Integral {
+    /// Simplify the bounds and the integrand, where the integrand is
+    /// simplified without any outer definition of the variable of
+    /// integration.
+    fn simple_parts(self, cont: &MathContext) -> Result<Integral, EvalError> {
+        let scope = cont.unbind(&self.variable);
+        Ok(Integral {
+            lower: self.lower.simple(cont)?.expr().boxed(),
+            upper: self.upper.simple(cont)?.expr().boxed(),
+            body: self.body.simple(&scope)?.expr().boxed(),
+            variable: self.variable,
+            span: self.span,
+        })
+    }
+}
+impl Limit {
+    /// Simplify the target and the body, where the body is simplified
+    /// without any outer definition of the variable.
+    fn simple_parts(self, cont: &MathContext) -> Result<Limit, EvalError> {
+        let scope = cont.unbind(&self.variable);
+        Ok(Limit {
+            target: self.target.simple(cont)?.expr().boxed(),
+            body: self.body.simple(&scope)?.expr().boxed(),
+            ..self
+        })
+    }
+}
+impl Derivative {
+    /// Differentiate the body symbolically and then substitute the point, if
+    /// there is one.
+    ///
+    /// The body is simplified without any outer definition of the variables.
+    fn simple_derivative(
+        self,
+        cont: &MathContext,
+    ) -> Result<Simple, EvalError> {
+        let scope = self
+            .variables
+            .iter()
+            .chain(self.point.iter().map(|(ident, _)| ident))
+            .fold(cont.clone(), |scope, ident| scope.unbind(ident));
+        let mut expr = self.body.simple(&scope)?.expr();
+        for variable in &self.variables {
+            expr = expr.derivative(variable, &scope)?.simple(&scope)?.expr();
+        }
+        expr.substitute_all(&self.point).simple(cont)
+    }
+}
+
 ///Managing simplification off division and fraction
 ///
 /// Because the division and factorization both use Simple objects it can be
 /// ensured that
-
 fn simplify_fraction_or_div(
     numerator: Simple,
     denominator: Simple,
@@ -325,25 +445,23 @@ fn simplify_fraction_or_div(
     // TODO simplify fraction, aka factor a and b and cancel common
     // factors, remove fraction of b==1, etc.
     let simple = (numerator, denominator);
-    if simple.equivalent(cont) {
-        Simple::constant(1.0)
-    } else {
-        match simple.to_math_expr() {
-            (
-                MathExpr::Term(Term::Factor(Factor::Constant(num))),
-                MathExpr::Term(Term::Factor(Factor::Constant(den))),
-            ) => Simple::divide(*num, *den),
-            (_, MathExpr::Term(Term::Factor(Factor::Constant(c)))) => {
-                if c.is_one() {
-                    simple.0
-                } else if c.is_zero() {
-                    Simple::constant(f64::NAN)
-                } else {
-                    simple.div_wrapped()
-                }
+    match simple.to_math_expr() {
+        // Constants are divided first, so 0/0 is not simplified to 1.
+        (
+            MathExpr::Term(Term::Factor(Factor::Constant(num))),
+            MathExpr::Term(Term::Factor(Factor::Constant(den))),
+        ) => Simple::divide(num.clone(), den.clone()),
+        _ if simple.equivalent(cont) => Simple::constant(1.0),
+        (_, MathExpr::Term(Term::Factor(Factor::Constant(c)))) => {
+            if c.is_one() {
+                simple.0
+            } else if c.is_zero() {
+                Simple::constant(f64::NAN)
+            } else {
+                simple.div_wrapped()
             }
-            _ => simple.div_wrapped(),
         }
+        _ => simple.div_wrapped(),
     }
 }
 
@@ -404,4 +522,25 @@ mod test {
     async fn multiply_remove_parenthesis_2() {
         ast_test_simplify("3(2x)+2", "6x+2").await;
     }
+    #[tokio::test]
+    async fn matrix_product_minus_itself() {
+        ast_test_simplify(
+            "\\begin{pmatrix}1&2\\end{pmatrix} y - \\begin{pmatrix}1&2\\end{pmatrix} y",
+            "0",
+        )
+        .await;
+    }
+    #[tokio::test]
+    async fn quotient_minus_itself() {
+        ast_test_simplify("y/z - y/z", "0").await;
+    }
+    #[tokio::test]
+    async fn dot_product_of_scalars() {
+        ast_test_simplify("2\\cdot 3", "6").await;
+        ast_test_simplify("2\\cdot x\\cdot 3", "6x").await;
+    }
+    #[tokio::test]
+    async fn dot_product_of_vectors_is_kept() {
+        ast_test_simplify("x\\cdot y\\cdot 2", "x\\cdot y\\cdot 2").await;
+    }
 }
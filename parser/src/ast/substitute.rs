@@ -0,0 +1,457 @@
+//! Substituting a variable for an expression in the AST
+use crate::prelude::*;
+
+/// Variables and the values they are replaced with, see
+/// [MathExpr::substitute_all].
+pub type Bindings = [(MathIdentifier, MathExpr)];
+
+/// The bindings without `ident`, for the body of an operation that binds
+/// `ident` itself.
+fn without(
+    bindings: &Bindings,
+    ident: &MathIdentifier,
+) -> Vec<(MathIdentifier, MathExpr)> {
+    bindings
+        .iter()
+        .filter(|(bound, _)| bound != ident)
+        .cloned()
+        .collect()
+}
+
+/// The first of `ident`, `ident_{1}`, `ident_{2}`, ... that is not `used`.
+pub(crate) fn fresh_identifier(
+    ident: &MathIdentifier,
+    used: impl Fn(&MathIdentifier) -> bool,
+) -> MathIdentifier {
+    std::iter::once(ident.clone())
+        .chain((1..).map(|n| MathIdentifier::Index {
+            name: ident.clone().into(),
+            index: MathExpr::from(f64::from(n)).boxed(),
+        }))
+        .find(|candidate| !used(candidate))
+        .expect("there are more names than variables")
+}
+
+/// Substitutes the bindings into the body of an operation that binds
+/// `ident`, and returns the identifier that the operation binds afterwards.
+///
+/// When a value would be captured by the operation, like `t` in
+/// `\int_{0}^{1} xt \, dt` when substituting `t` for `x`, the bound variable
+/// is renamed to an unused `ident_{n}` first.
+fn substitute_bound(
+    body: &MathExpr,
+    ident: &MathIdentifier,
+    bindings: &Bindings,
+) -> (MathIdentifier, MathExpr) {
+    let bindings = without(bindings, ident);
+    let free = body.free_variables();
+    let captured = bindings.iter().any(|(bound, value)| {
+        free.contains(bound) && value.free_variables().contains(ident)
+    });
+    if !captured {
+        return (ident.clone(), body.substitute_all(&bindings));
+    }
+    let used = |candidate: &MathIdentifier| {
+        free.contains(candidate)
+            || bindings.iter().any(|(bound, value)| {
+                bound == candidate || value.free_variables().contains(candidate)
+            })
+    };
+    let fresh = fresh_identifier(ident, used);
+    let mut renamed = bindings;
+    renamed.push((ident.clone(), fresh.clone().into()));
+    let body = body.substitute_all(&renamed);
+    (fresh, body)
+}
+
+impl MathExpr {
+    /// Replaces every free occurrence of the variable `ident` with `value`.
+    ///
+    /// Occurrences that are bound by an operation, for example the `i` in
+    /// `\sum_{i=1}^{n} i`, are left as is.
+    pub fn substitute(&self, ident: &MathIdentifier, value: &MathExpr) -> Self {
+        self.substitute_all(&[(ident.clone(), value.clone())])
+    }
+
+    /// Whether the variable `ident` occurs freely in the expression.
+    pub fn depends_on(&self, ident: &MathIdentifier) -> bool {
+        // Only an occurrence can be replaced by something else
+        self.substitute(ident, &Factor::Constant(0.into()).into()) != *self
+    }
+
+    /// Replaces every free occurrence of the variables at the same time, so
+    /// a value is never substituted into again. This means that swapping
+    /// `x` and `y` works as expected.
+    pub fn substitute_all(&self, bindings: &Bindings) -> Self {
+        match self {
+            MathExpr::Term(t) => MathExpr::Term(t.substitute_all(bindings)),
+            MathExpr::Add(lhs, rhs) => MathExpr::Add(
+                lhs.substitute_all(bindings).boxed(),
+                rhs.substitute_all(bindings),
+            ),
+            MathExpr::Subtract(lhs, rhs) => MathExpr::Subtract(
+                lhs.substitute_all(bindings).boxed(),
+                rhs.substitute_all(bindings),
+            ),
+        }
+    }
+}
+
+impl Term {
+    /// Replaces every free occurrence of the variable `ident` with `value`.
+    pub fn substitute(&self, ident: &MathIdentifier, value: &MathExpr) -> Self {
+        self.substitute_all(&[(ident.clone(), value.clone())])
+    }
+
+    /// Replaces every free occurrence of the variables at the same time.
+    pub fn substitute_all(&self, bindings: &Bindings) -> Self {
+        match self {
+            Term::Factor(f) => Term::Factor(f.substitute_all(bindings)),
+            Term::Multiply(mul, lhs, rhs) => Term::Multiply(
+                mul.clone(),
+                lhs.substitute_all(bindings).boxed(),
+                rhs.substitute_all(bindings),
+            ),
+            Term::Divide(lhs, rhs) => Term::Divide(
+                lhs.substitute_all(bindings).boxed(),
+                rhs.substitute_all(bindings),
+            ),
+        }
+    }
+}
+
+impl Factor {
+    /// Replaces every free occurrence of the variable `ident` with `value`.
+    pub fn substitute(&self, ident: &MathIdentifier, value: &MathExpr) -> Self {
+        self.substitute_all(&[(ident.clone(), value.clone())])
+    }
+
+    /// Replaces every free occurrence of the variables at the same time.
+    pub fn substitute_all(&self, bindings: &Bindings) -> Self {
+        let sub = |expr: &MathExpr| expr.substitute_all(bindings).boxed();
+        match self {
+            Factor::Constant(_) | Factor::Error(_) => self.clone(),
+            Factor::Parenthesis(expr) => Factor::Parenthesis(sub(expr)),
+            Factor::Variable(v, _) => {
+                bindings.iter().find(|(bound, _)| bound == v).map_or_else(
+                    || self.clone(),
+                    |(_, value)| value.get_factor_or_wrap(),
+                )
+            }
+            Factor::FunctionCall(call) => Factor::FunctionCall(FunctionCall {
+                function_name: call.function_name.clone(),
+                arguments: call
+                    .arguments
+                    .iter()
+                    .map(|arg| arg.substitute_all(bindings))
+                    .collect(),
+                span: call.span.clone(),
+            }),
+            Factor::Power {
+                base,
+                exponent,
+                span,
+            } => Factor::Power {
+                base: base.substitute_all(bindings).boxed(),
+                exponent: sub(exponent),
+                span: span.clone(),
+            },
+            Factor::Root {
+                degree,
+                radicand,
+                span,
+            } => Factor::Root {
+                degree: degree.as_deref().map(sub),
+                radicand: sub(radicand),
+                span: span.clone(),
+            },
+            Factor::Fraction(numerator, denominator) => {
+                Factor::Fraction(sub(numerator), sub(denominator))
+            }
+            Factor::Abs(expr) => Factor::Abs(sub(expr)),
+            Factor::Matrix(m, span) => {
+                let values = (0..m.row_count())
+                    .flat_map(|row| {
+                        (0..m.column_count()).map(move |column| {
+                            m.get(row, column).substitute_all(bindings)
+                        })
+                    })
+                    .collect();
+                Factor::Matrix(
+                    Matrix::new(values, m.row_count(), m.column_count()),
+                    span.clone(),
+                )
+            }
+            Factor::Sum(op) => Factor::Sum(op.substitute_all(bindings)),
+            Factor::Product(op) => Factor::Product(op.substitute_all(bindings)),
+            Factor::Integral(integral) => {
+                let (variable, body) = substitute_bound(
+                    &integral.body,
+                    &integral.variable,
+                    bindings,
+                );
+                Factor::Integral(Integral {
+                    lower: sub(&integral.lower),
+                    upper: sub(&integral.upper),
+                    body: body.boxed(),
+                    variable,
+                    span: integral.span.clone(),
+                })
+            }
+            Factor::Limit(limit) => {
+                let (variable, body) =
+                    substitute_bound(&limit.body, &limit.variable, bindings);
+                Factor::Limit(Limit {
+                    target: sub(&limit.target),
+                    body: body.boxed(),
+                    variable,
+                    ..limit.clone()
+                })
+            }
+            Factor::Derivative(derivative) => {
+                let mut point: Vec<_> = derivative
+                    .point
+                    .iter()
+                    .map(|(bound, value)| {
+                        (bound.clone(), value.substitute_all(bindings))
+                    })
+                    .collect();
+                let mut body_bindings = Vec::new();
+                for (ident, value) in bindings {
+                    if derivative.point.iter().any(|(bound, _)| bound == ident)
+                    {
+                        continue;
+                    }
+                    match derivative.variables.contains(ident) {
+                        // Evaluate the derivative at the value instead
+                        true => point.push((ident.clone(), value.clone())),
+                        false => {
+                            body_bindings.push((ident.clone(), value.clone()))
+                        }
+                    }
+                }
+                Factor::Derivative(Derivative {
+                    body: derivative
+                        .body
+                        .substitute_all(&body_bindings)
+                        .boxed(),
+                    point,
+                    ..derivative.clone()
+                })
+            }
+        }
+    }
+}
+
+impl IndexedOperation {
+    /// Replaces every free occurrence of the variable `ident` with `value`,
+    /// leaving the body as is if `ident` is the index.
+    pub fn substitute(&self, ident: &MathIdentifier, value: &MathExpr) -> Self {
+        self.substitute_all(&[(ident.clone(), value.clone())])
+    }
+
+    /// Replaces every free occurrence of the variables at the same time,
+    /// except for the index in the body. The index is renamed if a value
+    /// would be captured by it.
+    pub fn substitute_all(&self, bindings: &Bindings) -> Self {
+        let (index, body) = substitute_bound(&self.body, &self.index, bindings);
+        IndexedOperation {
+            index,
+            lower: self.lower.substitute_all(bindings).boxed(),
+            upper: self.upper.substitute_all(bindings).boxed(),
+            body: body.boxed(),
+            span: self.span.clone(),
+        }
+    }
+}
+
+/// Adds the variables that are not already found.
+fn extend(found: &mut Vec<MathIdentifier>, variables: Vec<MathIdentifier>) {
+    for variable in variables {
+        if !found.contains(&variable) {
+            found.push(variable);
+        }
+    }
+}
+
+/// The free variables of the body of an operation that binds `ident`.
+fn free_in_body(
+    body: &MathExpr,
+    ident: &MathIdentifier,
+) -> Vec<MathIdentifier> {
+    let mut variables = body.free_variables();
+    variables.retain(|variable| variable != ident);
+    variables
+}
+
+impl MathExpr {
+    /// The variables that occur freely, in order of their first occurrence.
+    pub fn free_variables(&self) -> Vec<MathIdentifier> {
+        let mut found = Vec::new();
+        match self {
+            MathExpr::Term(t) => extend(&mut found, t.free_variables()),
+            MathExpr::Add(lhs, rhs) | MathExpr::Subtract(lhs, rhs) => {
+                extend(&mut found, lhs.free_variables());
+                extend(&mut found, rhs.free_variables());
+            }
+        }
+        found
+    }
+}
+
+impl Term {
+    /// The variables that occur freely, in order of their first occurrence.
+    pub fn free_variables(&self) -> Vec<MathIdentifier> {
+        let mut found = Vec::new();
+        match self {
+            Term::Factor(f) => extend(&mut found, f.free_variables()),
+            Term::Multiply(_, lhs, rhs) | Term::Divide(lhs, rhs) => {
+                extend(&mut found, lhs.free_variables());
+                extend(&mut found, rhs.free_variables());
+            }
+        }
+        found
+    }
+}
+
+impl Factor {
+    /// The variables that occur freely, in order of their first occurrence.
+    pub fn free_variables(&self) -> Vec<MathIdentifier> {
+        let mut found = Vec::new();
+        match self {
+            Factor::Constant(_) | Factor::Error(_) => {}
+            Factor::Variable(v, _) => found.push(v.clone()),
+            Factor::Parenthesis(expr) | Factor::Abs(expr) => {
+                extend(&mut found, expr.free_variables())
+            }
+            Factor::FunctionCall(call) => {
+                for argument in &call.arguments {
+                    extend(&mut found, argument.free_variables());
+                }
+            }
+            Factor::Power { base, exponent, .. } => {
+                extend(&mut found, base.free_variables());
+                extend(&mut found, exponent.free_variables());
+            }
+            Factor::Root {
+                degree, radicand, ..
+            } => {
+                if let Some(degree) = degree {
+                    extend(&mut found, degree.free_variables());
+                }
+                extend(&mut found, radicand.free_variables());
+            }
+            Factor::Fraction(numerator, denominator) => {
+                extend(&mut found, numerator.free_variables());
+                extend(&mut found, denominator.free_variables());
+            }
+            Factor::Matrix(m, _) => {
+                for row in 0..m.row_count() {
+                    for column in 0..m.column_count() {
+                        extend(&mut found, m.get(row, column).free_variables());
+                    }
+                }
+            }
+            Factor::Sum(op) | Factor::Product(op) => {
+                extend(&mut found, op.lower.free_variables());
+                extend(&mut found, op.upper.free_variables());
+                extend(&mut found, free_in_body(&op.body, &op.index));
+            }
+            Factor::Integral(integral) => {
+                extend(&mut found, integral.lower.free_variables());
+                extend(&mut found, integral.upper.free_variables());
+                extend(
+                    &mut found,
+                    free_in_body(&integral.body, &integral.variable),
+                );
+            }
+            Factor::Limit(limit) => {
+                extend(&mut found, limit.target.free_variables());
+                extend(&mut found, free_in_body(&limit.body, &limit.variable));
+            }
+            Factor::Derivative(derivative) => {
+                let mut body = derivative.body.free_variables();
+                body.retain(|v| {
+                    !derivative.point.iter().any(|(bound, _)| bound == v)
+                });
+                extend(&mut found, body);
+                for (_, value) in &derivative.point {
+                    extend(&mut found, value.free_variables());
+                }
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ast::to_latex::ToLaTeX, prelude::*};
+    use pretty_assertions::assert_eq;
+
+    async fn substitute_test(text: &str, value: &str, expected: &str) {
+        let context = MathContext::standard_math();
+        let Ast::Expression(expr) = parse(text, &context).await.unwrap() else {
+            panic!("Expected an expression");
+        };
+        let Ast::Expression(value) = parse(value, &context).await.unwrap()
+        else {
+            panic!("Expected an expression");
+        };
+        let found =
+            expr.substitute(&MathIdentifier::from_single_ident("x"), &value);
+        assert_eq!(found.to_latex(), expected);
+    }
+
+    #[tokio::test]
+    async fn wraps_sums_in_parenthesis() {
+        substitute_test("2x^2", "y+1", "2\\cdot \\left(y+1\\right)^{2}").await;
+    }
+
+    #[tokio::test]
+    async fn substitutes_at_the_same_time() {
+        let context = MathContext::standard_math();
+        let Ast::Expression(expr) = parse("x-y", &context).await.unwrap()
+        else {
+            panic!("Expected an expression");
+        };
+        let x = MathIdentifier::from_single_ident("x");
+        let y = MathIdentifier::from_single_ident("y");
+        let found = expr.substitute_all(&[
+            (x.clone(), Factor::from(y.clone()).into()),
+            (y, Factor::from(x).into()),
+        ]);
+        assert_eq!(found.to_latex(), "y-x");
+    }
+
+    #[tokio::test]
+    async fn free_variables_skip_bound_ones() {
+        let context = MathContext::standard_math();
+        let Ast::Expression(expr) =
+            parse("y + \\sum_{i=1}^{n} i x", &context).await.unwrap()
+        else {
+            panic!("Expected an expression");
+        };
+        let found: Vec<String> = expr
+            .free_variables()
+            .iter()
+            .map(ToLaTeX::to_latex)
+            .collect();
+        assert_eq!(found, ["y", "n", "x"]);
+    }
+
+    #[tokio::test]
+    async fn bound_variable_is_kept() {
+        substitute_test("\\sum_{x=1}^{x} x", "3", "\\sum_{x=1}^{3}x").await;
+    }
+
+    #[tokio::test]
+    async fn bound_variable_does_not_capture() {
+        substitute_test(
+            "\\int_{0}^{1} x t \\, dt",
+            "t",
+            "\\int_{0}^{1}t\\cdot t_{1}\\,dt_{1}",
+        )
+        .await;
+    }
+}
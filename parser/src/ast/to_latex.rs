@@ -1,7 +1,7 @@
 //! convert the ast to latex
 
 use crate::{
-    identifier::{MathLetter, MathString},
+    identifier::{MathLetter, MathString, OtherSymbol},
     prelude::*,
 };
 
@@ -16,6 +16,13 @@ impl ToLaTeX for Ast {
         match self {
             Ast::Expression(e) => e.to_latex(),
             Ast::Equality(a, b) => format!("{}={}", a.to_latex(), b.to_latex()),
+            Ast::System(equations) => {
+                let rows: Vec<String> = equations
+                    .iter()
+                    .map(|(a, b)| format!("{}={}", a.to_latex(), b.to_latex()))
+                    .collect();
+                format!("\\begin{{cases}}{}\\end{{cases}}", rows.join("\\\\"))
+            }
         }
     }
 }
@@ -48,6 +55,8 @@ impl ToLaTeX for Term {
                     MulType::Asterisk => "*",
                     MulType::Cdot => "\\cdot ",
                     MulType::Times => "\\times ",
+                    MulType::Odot => "\\odot ",
+                    MulType::Oslash => "\\oslash ",
                     MulType::Implicit => "",
                 };
                 format!("{}{}{}", a.to_latex(), mul_token, b.to_latex())
@@ -66,19 +75,14 @@ impl ToLaTeX for Factor {
             Factor::Parenthesis(expr) => {
                 format!("\\left({}\\right)", expr.to_latex())
             }
-            Factor::Variable(var) => var.to_latex(),
-            Factor::FunctionCall(call) => {
-                let args = call
-                    .arguments
-                    .iter()
-                    .map(|arg| arg.to_latex())
-                    .collect::<String>();
-                format!("{}({})", call.function_name.to_latex(), args)
-            }
-            Factor::Power { base, exponent } => {
+            Factor::Variable(var, _) => var.to_latex(),
+            Factor::FunctionCall(call) => call.to_latex(),
+            Factor::Power { base, exponent, .. } => {
                 format!("{}^{{{}}}", base.to_latex(), exponent.to_latex())
             }
-            Factor::Root { degree, radicand } => {
+            Factor::Root {
+                degree, radicand, ..
+            } => {
                 if let Some(degree) = degree {
                     format!(
                         "\\sqrt[{}]{{{}}}",
@@ -93,7 +97,7 @@ impl ToLaTeX for Factor {
                 format!("\\frac{{{}}}{{{}}}", a.to_latex(), b.to_latex())
             }
             Factor::Abs(val) => format!("|{}|", val.to_latex()),
-            Factor::Matrix(m) => {
+            Factor::Matrix(m, _) => {
                 let mut output = String::new();
                 output.push_str("\\begin{bmatrix}");
 
@@ -111,6 +115,178 @@ impl ToLaTeX for Factor {
                 output.push_str("\\end{bmatrix}");
                 output
             }
+            Factor::Sum(sum) => format!("\\sum{}", sum.to_latex()),
+            Factor::Product(product) => {
+                format!("\\prod{}", product.to_latex())
+            }
+            Factor::Integral(integral) => integral.to_latex(),
+            Factor::Limit(limit) => limit.to_latex(),
+            Factor::Derivative(derivative) => derivative.to_latex(),
+            Factor::Error(_) => "\\square".to_owned(),
+        }
+    }
+}
+
+impl ToLaTeX for IndexedOperation {
+    fn to_latex(&self) -> String {
+        format!(
+            "_{{{}={}}}^{{{}}}{}",
+            self.index.to_latex(),
+            self.lower.to_latex(),
+            self.upper.to_latex(),
+            self.body.get_term_or_wrap().to_latex()
+        )
+    }
+}
+
+impl ToLaTeX for Integral {
+    fn to_latex(&self) -> String {
+        format!(
+            "\\int_{{{}}}^{{{}}}{}\\,d{}",
+            self.lower.to_latex(),
+            self.upper.to_latex(),
+            self.body.to_latex(),
+            self.variable.to_latex()
+        )
+    }
+}
+
+impl ToLaTeX for Limit {
+    fn to_latex(&self) -> String {
+        let direction = match self.direction {
+            LimitDirection::Both => "",
+            LimitDirection::Above => "^{+}",
+            LimitDirection::Below => "^{-}",
+        };
+        format!(
+            "\\lim_{{{}\\to {}{}}}{}",
+            self.variable.to_latex(),
+            self.target.to_latex(),
+            direction,
+            self.body.get_term_or_wrap().to_latex()
+        )
+    }
+}
+
+impl ToLaTeX for Derivative {
+    fn to_latex(&self) -> String {
+        if let (
+            DerivativeNotation::Prime,
+            Term::Factor(Factor::FunctionCall(call)),
+        ) = (self.notation, self.body.get_term_or_wrap())
+        {
+            // The point replaces the arguments, for example f'(a)
+            let arguments = call
+                .arguments
+                .iter()
+                .map(|argument| match argument {
+                    MathExpr::Term(Term::Factor(Factor::Variable(
+                        ident,
+                        _,
+                    ))) => self
+                        .point
+                        .iter()
+                        .find(|(bound, _)| bound == ident)
+                        .map_or(argument, |(_, value)| value),
+                    _ => argument,
+                })
+                .map(|argument| argument.to_latex())
+                .collect::<Vec<_>>()
+                .join(",");
+            return format!(
+                "{}{}({})",
+                call.function_name.to_latex(),
+                "'".repeat(self.variables.len()),
+                arguments
+            );
+        }
+        let symbol = match self.notation {
+            DerivativeNotation::Partial => "\\partial ",
+            _ => "d",
+        };
+        let order = |count: usize| match count {
+            1 => String::new(),
+            _ => format!("^{{{}}}", count),
+        };
+        // Group repeated variables, for example dx^{2}dy
+        let mut denominator = String::new();
+        let mut variables = self.variables.iter().peekable();
+        while let Some(variable) = variables.next() {
+            let mut count = 1;
+            while variables.next_if(|next| *next == variable).is_some() {
+                count += 1;
+            }
+            denominator.push_str(&format!(
+                "{}{}{}",
+                symbol,
+                variable.to_latex(),
+                order(count)
+            ));
+        }
+        let mut output = format!(
+            "\\frac{{{}{}}}{{{}}}{}",
+            symbol.trim_end(),
+            order(self.variables.len()),
+            denominator,
+            self.body.get_term_or_wrap().to_latex()
+        );
+        if !self.point.is_empty() {
+            let point = self
+                .point
+                .iter()
+                .map(|(ident, value)| {
+                    format!("{}={}", ident.to_latex(), value.to_latex())
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            output.push_str(&format!("|_{{{}}}", point));
+        }
+        output
+    }
+}
+
+impl ToLaTeX for FunctionCall {
+    fn to_latex(&self) -> String {
+        let args = self
+            .arguments
+            .iter()
+            .map(|arg| arg.to_latex())
+            .collect::<Vec<_>>();
+        let name = &self.function_name;
+        match &args[..] {
+            [arg] if *name == MathIdentifier::from_operator_name("floor") => {
+                format!("\\lfloor {}\\rfloor ", arg)
+            }
+            [arg] if *name == MathIdentifier::from_operator_name("ceil") => {
+                format!("\\lceil {}\\rceil ", arg)
+            }
+            [size] if *name == MathIdentifier::from_operator_name("zeros") => {
+                format!("0_{{{}}}", size)
+            }
+            [rows, columns]
+                if *name == MathIdentifier::from_operator_name("zeros") =>
+            {
+                format!("0_{{{}\\times {}}}", rows, columns)
+            }
+            [.., last]
+                if *name == MathIdentifier::from_operator_name("range") =>
+            {
+                let start = args[..args.len() - 1].join(",");
+                format!("\\left({},\\ldots,{}\\right)", start, last)
+            }
+            [arg] if *name == MathIdentifier::from_operator_name("norm") => {
+                format!("\\|{}\\|", arg)
+            }
+            [arg, p] if *name == MathIdentifier::from_operator_name("norm") => {
+                format!("\\|{}\\|_{{{}}}", arg, p)
+            }
+            [arg, base]
+                if *name
+                    == MathIdentifier::from_single_symbol(OtherSymbol::Log) =>
+            {
+                format!("\\log_{{{}}}({})", base, arg)
+            }
+            _ => format!("{}({})", name.to_latex(), args.join(",")),
         }
     }
 }
@@ -119,15 +295,17 @@ impl ToLaTeX for MathIdentifier {
     fn to_latex(&self) -> String {
         match self {
             MathIdentifier::Name(ms) => ms.to_latex(),
-            MathIdentifier::Index { name, index } => {
-                format!("{}_{{{}}}", name.to_latex(), index.to_latex())
+            MathIdentifier::Index { name, .. } => {
+                let entries = self.index_entries().unwrap_or_default();
+                let entries: Vec<String> =
+                    entries.iter().map(|entry| entry.to_latex()).collect();
+                format!("{}_{{{}}}", name.to_latex(), entries.join(","))
             }
             MathIdentifier::Modifier(modifier_type, inner) => {
-                format!(
-                    "\\{}{{{}}}",
-                    modifier_type.latex_code(),
-                    inner.to_latex()
-                )
+                // Print operator names as written, not as a product
+                let inner =
+                    self.operator_name().unwrap_or_else(|| inner.to_latex());
+                format!("\\{}{{{}}}", modifier_type.latex_code(), inner)
             }
         }
     }
@@ -0,0 +1,406 @@
+//! # Compiled expressions
+//! Evaluating an expression at many points, for example for a plot or a
+//! table, without simplifying it again at every point.
+//!
+//! The expression is simplified once and turned into a tree of closures over
+//! the values of its variables. Parts that do not depend on the variables are
+//! evaluated once while compiling, and parts that can not be compiled, like a
+//! sum with a variable bound, are evaluated by the [Approximator] at every
+//! point.
+//!
+//! Since the expression is simplified before the variables have values, it
+//! may be defined at points where the [Approximator] is not. For example
+//! `\frac{x}{x}` is simplified to 1, which it is even at x = 0.
+use std::sync::Arc;
+
+use crate::{ast::simplify::Simplify, prelude::*};
+
+/// A compiled part of an expression, evaluated at the values of the
+/// variables.
+type Node = Box<dyn Fn(&[f64]) -> Result<f64, EvalError> + Send + Sync>;
+
+/// An expression compiled to be evaluated at many points, see
+/// [Approximator::compile].
+pub struct CompiledExpr {
+    /// The variables, in the order of their values at a point.
+    variables: Vec<MathIdentifier>,
+    /// The compiled expression.
+    root: Node,
+}
+
+impl CompiledExpr {
+    /// Compiles the expression, where the variables are left undefined
+    /// while simplifying so they can have a different value at each point.
+    pub(crate) fn new(
+        approximator: &Approximator,
+        expr: &MathExpr,
+        variables: &[MathIdentifier],
+    ) -> Result<Self, EvalError> {
+        let scope = variables
+            .iter()
+            .fold(approximator.clone(), |scope, variable| {
+                scope.unbind(variable)
+            });
+        let compiler = Compiler {
+            variables: variables.to_vec(),
+            approximator: Arc::new(scope),
+        };
+        let expr = expr.clone().simple(compiler.approximator.context())?;
+        Ok(Self {
+            variables: compiler.variables.clone(),
+            root: compiler.expr(&expr.expr())?,
+        })
+    }
+
+    /// The variables, in the order of their values at a point.
+    pub fn variables(&self) -> &[MathIdentifier] {
+        &self.variables
+    }
+
+    /// Evaluates the expression at a point, where `point[i]` is the value
+    /// of the i-th variable.
+    ///
+    /// # Errors
+    /// [EvalError::ArgumentLengthMismatch] if there is not one value for
+    /// every variable, or another [EvalError] if the expression can not be
+    /// evaluated at the point, like [EvalError::ExpectedReal] if it is
+    /// complex there.
+    pub fn eval(&self, point: &[f64]) -> Result<f64, EvalError> {
+        if point.len() != self.variables.len() {
+            return Err(EvalError::ArgumentLengthMismatch {
+                expected: vec![self.variables.len()],
+                found: point.len(),
+            });
+        }
+        (self.root)(point)
+    }
+
+    /// Evaluates the expression at many points, where `columns[i]` holds
+    /// the values of the i-th variable, so the j-th point is made of the
+    /// j-th value of every column.
+    ///
+    /// Every point has its own result, so a point where the expression can
+    /// not be evaluated does not stop the others.
+    ///
+    /// # Errors
+    /// [EvalError::ArgumentLengthMismatch] if there is not one column for
+    /// every variable, or if the columns are not equally long.
+    pub fn eval_columns(
+        &self,
+        columns: &[&[f64]],
+    ) -> Result<Vec<Result<f64, EvalError>>, EvalError> {
+        if columns.len() != self.variables.len() {
+            return Err(EvalError::ArgumentLengthMismatch {
+                expected: vec![self.variables.len()],
+                found: columns.len(),
+            });
+        }
+        let len = columns.first().map_or(0, |column| column.len());
+        if let Some(column) = columns.iter().find(|c| c.len() != len) {
+            return Err(EvalError::ArgumentLengthMismatch {
+                expected: vec![len],
+                found: column.len(),
+            });
+        }
+        let mut point = vec![0.0; columns.len()];
+        Ok((0..len)
+            .map(|row| {
+                for (value, column) in point.iter_mut().zip(columns) {
+                    *value = column[row];
+                }
+                (self.root)(&point)
+            })
+            .collect())
+    }
+}
+
+/// Compiles the parts of an expression into [Node]s.
+struct Compiler {
+    /// The variables, in the order of their values at a point.
+    variables: Vec<MathIdentifier>,
+    /// Evaluates the parts that do not depend on the variables, and the
+    /// parts that can not be compiled.
+    approximator: Arc<Approximator>,
+}
+
+impl Compiler {
+    /// Whether any of the free variables of a part are the variables.
+    fn depends_on_variables(&self, free_variables: &[MathIdentifier]) -> bool {
+        free_variables
+            .iter()
+            .any(|variable| self.variables.contains(variable))
+    }
+
+    /// Evaluates a part that does not depend on the variables once.
+    fn constant(&self, expr: MathExpr) -> Result<Node, EvalError> {
+        let value = self.exact(expr)?.scalar()?;
+        Ok(Box::new(move |_| Ok(value)))
+    }
+
+    /// Evaluates a part that does not depend on the variables without
+    /// rounding it to a real number.
+    fn exact(&self, expr: MathExpr) -> Result<Value, EvalError> {
+        let context = self.approximator.context();
+        self.approximator.eval_expr(expr.simple(context)?)
+    }
+
+    /// Compiles an expression.
+    fn expr(&self, expr: &MathExpr) -> Result<Node, EvalError> {
+        if !self.depends_on_variables(&expr.free_variables()) {
+            return self.constant(expr.clone());
+        }
+        Ok(match expr {
+            MathExpr::Term(term) => self.term(term)?,
+            MathExpr::Add(a, b) => {
+                let (a, b) = (self.expr(a)?, self.term(b)?);
+                Box::new(move |point| Ok(a(point)? + b(point)?))
+            }
+            MathExpr::Subtract(a, b) => {
+                let (a, b) = (self.expr(a)?, self.term(b)?);
+                Box::new(move |point| Ok(a(point)? - b(point)?))
+            }
+        })
+    }
+
+    /// Compiles a term, where every kind of product of real numbers is their
+    /// product, except for [MulType::Oslash] which divides them.
+    fn term(&self, term: &Term) -> Result<Node, EvalError> {
+        if !self.depends_on_variables(&term.free_variables()) {
+            return self.constant(MathExpr::Term(term.clone()));
+        }
+        Ok(match term {
+            Term::Factor(factor) => self.factor(factor)?,
+            Term::Multiply(MulType::Oslash, a, b) => {
+                let (a, b) = (self.term(a)?, self.factor(b)?);
+                Box::new(move |point| Ok(a(point)? / b(point)?))
+            }
+            Term::Multiply(_, a, b) => {
+                let (a, b) = (self.term(a)?, self.factor(b)?);
+                Box::new(move |point| Ok(a(point)? * b(point)?))
+            }
+            Term::Divide(a, b) => {
+                let (a, b) = (self.term(a)?, self.factor(b)?);
+                Box::new(move |point| Ok(a(point)? / b(point)?))
+            }
+        })
+    }
+
+    /// Compiles a factor, where powers and roots that are not real are
+    /// evaluated like the [Approximator] does to report them.
+    fn factor(&self, factor: &Factor) -> Result<Node, EvalError> {
+        if !self.depends_on_variables(&factor.free_variables()) {
+            return self.constant(factor.clone().into());
+        }
+        let precision = self.approximator.precision();
+        Ok(match factor {
+            Factor::Variable(variable, _) => {
+                match self.variables.iter().position(|v| v == variable) {
+                    Some(i) => Box::new(move |point| Ok(point[i])),
+                    None => self.fallback(factor),
+                }
+            }
+            Factor::Parenthesis(expr) => self.expr(expr)?,
+            Factor::Fraction(a, b) => {
+                let (a, b) = (self.expr(a)?, self.expr(b)?);
+                Box::new(move |point| Ok(a(point)? / b(point)?))
+            }
+            Factor::Abs(expr) => {
+                let expr = self.expr(expr)?;
+                Box::new(move |point| Ok(expr(point)?.abs()))
+            }
+            Factor::Power { base, exponent, .. } => {
+                // A constant exponent is kept exact, since for example the
+                // power of a negative base to 1/3 is only real if it is
+                let constant =
+                    !self.depends_on_variables(&exponent.free_variables());
+                let exact = constant
+                    .then(|| self.exact((**exponent).clone()))
+                    .transpose()?;
+                let (base, exponent) =
+                    (self.factor(base)?, self.expr(exponent)?);
+                let span = factor.span();
+                Box::new(move |point| {
+                    let (base, exponent) = (base(point)?, exponent(point)?);
+                    let power = base.powf(exponent);
+                    if !power.is_nan() || base.is_nan() || exponent.is_nan() {
+                        return Ok(power);
+                    }
+                    let exponent =
+                        exact.clone().unwrap_or_else(|| Value::from(exponent));
+                    Value::from(base)
+                        .pow_with(&exponent, precision)
+                        .and_then(|value| value.scalar())
+                        .map_err(|e| e.at(span))
+                })
+            }
+            Factor::Root {
+                degree, radicand, ..
+            } => {
+                let radicand = self.expr(radicand)?;
+                let degree = degree
+                    .as_ref()
+                    .map(|degree| self.expr(degree))
+                    .transpose()?;
+                let span = factor.span();
+                Box::new(move |point| {
+                    let radicand = radicand(point)?;
+                    let degree = match &degree {
+                        Some(degree) => degree(point)?,
+                        None => 2.0,
+                    };
+                    if degree == 2.0 && radicand >= 0.0 {
+                        return Ok(radicand.sqrt());
+                    }
+                    Value::from(radicand)
+                        .root_with(&Value::from(degree), precision)
+                        .and_then(|value| value.scalar())
+                        .map_err(|e| e.at(span))
+                })
+            }
+            Factor::FunctionCall(call) => {
+                let functions = &self.approximator.context().functions;
+                let Some(MathFunction::Native(function)) =
+                    functions.get(&call.function_name)
+                else {
+                    return Ok(self.fallback(factor));
+                };
+                let function = function.clone();
+                let arguments = call
+                    .arguments
+                    .iter()
+                    .map(|argument| self.expr(argument))
+                    .collect::<Result<Vec<Node>, EvalError>>()?;
+                let span = call.span.get();
+                Box::new(move |point| {
+                    let arguments = arguments
+                        .iter()
+                        .map(|argument| argument(point).map(Value::from))
+                        .collect::<Result<Vec<Value>, EvalError>>()?;
+                    function
+                        .run(arguments)
+                        .and_then(|value| value.scalar())
+                        .map_err(|e| e.at(span))
+                })
+            }
+            _ => self.fallback(factor),
+        })
+    }
+
+    /// A part that can not be compiled, which is evaluated by the
+    /// [Approximator] with the variables defined as their values.
+    fn fallback(&self, factor: &Factor) -> Node {
+        let approximator = Arc::clone(&self.approximator);
+        let variables = self.variables.clone();
+        let expr = MathExpr::from(factor.clone());
+        Box::new(move |point| {
+            let mut scope = (*approximator).clone();
+            for (variable, value) in variables.iter().zip(point) {
+                scope = scope.bind(variable.clone(), MathExpr::from(*value));
+            }
+            scope
+                .eval_expr(expr.clone().simple(scope.context())?)?
+                .scalar()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    async fn compile(text: &str, variables: &[&str]) -> CompiledExpr {
+        let context = MathContext::standard_math();
+        let Ast::Expression(expr) = parse(text, &context).await.unwrap() else {
+            panic!("Cannot compile statement.");
+        };
+        let variables: Vec<MathIdentifier> = variables
+            .iter()
+            .map(|v| MathIdentifier::from_single_ident(v))
+            .collect();
+        Approximator::new(context)
+            .compile(&expr, &variables)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn columns_are_evaluated_per_point() {
+        let compiled =
+            compile("x^2+\\sin(y)\\cdot\\sqrt{x}-\\frac{1}{2}", &["x", "y"])
+                .await;
+        let xs = [0.0, 1.0, 4.0];
+        let ys = [0.0, 0.5, -1.0];
+        let found = compiled.eval_columns(&[&xs, &ys]).unwrap();
+        for ((x, y), found) in xs.iter().zip(ys).zip(found) {
+            let expected = x * x + y.sin() * x.sqrt() - 0.5;
+            let found = found.unwrap();
+            assert!((found - expected).abs() < 1e-12, "found {found} at {x}");
+        }
+    }
+
+    #[tokio::test]
+    async fn matches_the_approximator() {
+        let context = MathContext::standard_math();
+        for text in [
+            "\\frac{d}{dx}x^3",
+            "\\sum_{i=1}^{3} i\\cdot x",
+            "\\int_0^x t\\,dt",
+            "\\sqrt[3]{x-8}",
+            "\\left|x-3\\right|\\cdot\\pi",
+            "x^{\\frac{1}{3}}",
+            "\\frac{x}{x}",
+            "x\\oslash 2+3\\oslash x",
+        ] {
+            let compiled = compile(text, &["x"]).await;
+            for x in [-8.0, -2.0, 0.5, 3.0] {
+                let Ast::Expression(expr) =
+                    parse(text, &context).await.unwrap()
+                else {
+                    panic!("Cannot evaluate statement.");
+                };
+                let x_ident = MathIdentifier::from_single_ident("x");
+                let approximator =
+                    Approximator::new(context.bind(x_ident, x.into()));
+                let expected = approximator
+                    .eval_expr(expr.simple(approximator.context()).unwrap())
+                    .and_then(|value| value.scalar());
+                // Both fail where it is not real, like x^{1/3} at x = -2
+                match (compiled.eval(&[x]), expected) {
+                    (Ok(found), Ok(expected)) => assert!(
+                        (found - expected).abs() < 1e-9,
+                        "{text} at {x}: found {found} expected {expected}"
+                    ),
+                    (found, expected) => assert!(
+                        found.is_err() && expected.is_err(),
+                        "{text} at {x}: found {found:?} expected {expected:?}"
+                    ),
+                }
+            }
+        }
+        // Simplified before x has a value, unlike for the approximator
+        let compiled = compile("\\frac{x}{x}", &["x"]).await;
+        assert_eq!(compiled.eval(&[0.0]).ok(), Some(1.0));
+    }
+
+    #[tokio::test]
+    async fn points_fail_on_their_own() {
+        let compiled = compile("\\sqrt{x}", &["x"]).await;
+        let found = compiled.eval_columns(&[&[4.0, -1.0]]).unwrap();
+        assert_eq!(found[0].as_ref().ok(), Some(&2.0));
+        assert!(
+            matches!(
+                &found[1],
+                Err(EvalError::At { source, span })
+                    if matches!(**source, EvalError::ExpectedReal { .. })
+                        && *span == Span::new(0, 8)
+            ),
+            "found {found:?}"
+        );
+        let found = compiled.eval_columns(&[&[1.0], &[2.0]]);
+        assert!(
+            matches!(found, Err(EvalError::ArgumentLengthMismatch { .. })),
+            "found {:?}",
+            found.err()
+        );
+    }
+}
@@ -1,11 +1,17 @@
 //! # Context
 //! this module is for helping with keeping track of variables and functions
 //! for that it uses MathContext where you can add any function or variable  
-use std::collections::HashMap;
+use std::{cmp::Ordering, collections::HashMap, sync::Arc};
+
+use num_complex::Complex64;
 
 use crate::{
+    decomposition::{self, Eigen, Lu, Qr, Svd},
+    functions::real_or_complex,
     identifier::{GreekLetter, OtherSymbol},
+    matrix,
     prelude::*,
+    solver::{self, LinearSolution},
 };
 
 ///The MathContext, holding all the functions and variables
@@ -15,6 +21,10 @@ pub struct MathContext {
     pub variables: HashMap<MathIdentifier, MathExpr>,
     /// The functions defined in this math context
     pub functions: HashMap<MathIdentifier, MathFunction>,
+    /// Whether functions of a scalar, like `\sin`, are applied to every
+    /// element when called with a matrix. Off by default, where `\sin(A)` is
+    /// an error.
+    pub element_wise: bool,
 }
 
 impl Default for MathContext {
@@ -29,6 +39,7 @@ impl MathContext {
         Self {
             variables: HashMap::new(),
             functions: HashMap::new(),
+            element_wise: false,
         }
     }
 
@@ -45,6 +56,22 @@ impl MathContext {
         });
     }
 
+    /// Creates a child scope of this context where `ident` is bound to
+    /// `value`, shadowing any outer definition of it.
+    pub fn bind(&self, ident: MathIdentifier, value: MathExpr) -> MathContext {
+        let mut scope = self.clone();
+        scope.variables.insert(ident, value);
+        scope
+    }
+
+    /// Creates a child scope of this context where `ident` is not defined,
+    /// used for variables that are bound by an operation like an integral.
+    pub fn unbind(&self, ident: &MathIdentifier) -> MathContext {
+        let mut scope = self.clone();
+        scope.variables.remove(ident);
+        scope
+    }
+
     ///if the function is contained
     pub fn is_defined_function(&self, ident: &MathIdentifier) -> bool {
         // println!("is_function({:?}) = {}", ident, res); TODO tracing
@@ -56,6 +83,11 @@ impl MathContext {
         self.variables
             .insert(MathIdentifier::from_single_greek(letter), value);
     }
+    /// Adding a variable that is a single symbol.
+    fn add_symbol_var(&mut self, symbol: OtherSymbol, value: MathExpr) {
+        self.variables
+            .insert(MathIdentifier::from_single_symbol(symbol), value);
+    }
     /// Adding a string as a single ident
     fn add_ascii_var(&mut self, s: &str, value: MathExpr) {
         self.variables
@@ -95,43 +127,662 @@ impl MathContext {
         self.functions.insert(ident, func.into_math_function());
     }
 
+    /// Sets whether `i` is the imaginary unit, which it is in
+    /// [MathContext::standard_math]. Turn it off when `i` is a variable or an
+    /// index instead, `\mathrm{i}` is always the imaginary unit.
+    pub fn set_imaginary_i(&mut self, enabled: bool) {
+        let i = MathIdentifier::from_single_ident("i");
+        match enabled {
+            true => {
+                let unit = Factor::from(MathIdentifier::imaginary_unit());
+                self.variables.insert(i, unit.into());
+            }
+            false => {
+                self.variables.remove(&i);
+            }
+        }
+    }
+
     ///The standard math
     /// Variables:
     /// * pi
     /// * e
+    /// * infty
+    /// * i - the imaginary unit, see [MathContext::set_imaginary_i]
     ///
     /// Functions:
-    /// * sin
-    /// * cos
-    /// * tan
-    /// * ln - natural log
+    /// * sin, cos, tan, sec, csc, cot
+    /// * arcsin, arccos, arctan
+    /// * sinh, cosh, tanh
+    /// * exp, ln - natural log
+    /// * log - log base 10, or any base with `\log_{b}`
+    /// * lg - log base 10
+    /// * max, min, gcd, lcm - of any amount of arguments
+    /// * floor, ceil - written as `\lfloor x \rfloor` and `\lceil x \rceil`
+    /// * sgn - the sign
+    /// * solve - the solution of `Ax = b`, written `\operatorname{solve}(A, b)`
+    /// * norm - the length of a vector, written `\|v\|`, or the `p`-norm
+    ///   written `\|v\|_p`
+    /// * angle, proj - the angle between two vectors, written `\angle(u, v)`,
+    ///   and the projection of `v` onto `u`, written
+    ///   `\operatorname{proj}_u v`, where `\hat{v}` is the unit vector
+    /// * det, tr, rank, rref, inv - of a matrix, where the inverse and the
+    ///   transpose can also be written `A^{-1}` and `A^T` or `A^\top`
+    /// * zeros, diag, rot, range - constructed matrices, written
+    ///   `0_{m\times n}`, `\operatorname{diag}(1,2,3)`,
+    ///   `\operatorname{rot}(\theta)` or `\operatorname{rot}(\theta, axis)`
+    ///   and `(1,\ldots,n)`, where `I_n` and `\mathbb{1}_n` are the identity
+    ///   unless `I` is defined
+    /// * lu, qr, chol, svd, eig - decompositions of a matrix, where the
+    ///   factors are returned as a row of matrices, for example `(P, L, U)`
+    ///   with `PA = LU`, and eig gives the eigenvalues and the eigenvectors
     pub fn standard_math() -> MathContext {
         let mut context = MathContext::new();
 
-        // Constants
+        // Constants, as calls so they are evaluated with the precision of
+        // the approximator
         context.add_greek_var(
             GreekLetter::LowercasePi,
-            Factor::Constant(std::f64::consts::PI).into(),
+            call(OtherSymbol::Arccos, MathExpr::from(-1.0)).into(),
         );
+        context.add_ascii_var("e", call(OtherSymbol::Exp, 1.0.into()).into());
+        context.add_symbol_var(
+            OtherSymbol::Infty,
+            Factor::Constant(f64::INFINITY.into()).into(),
+        );
+
+        context.set_imaginary_i(true);
+
+        context.add_trigonometric_functions();
+        context.add_exponential_functions();
+        context.add_discrete_functions();
+        context.add_linear_algebra_functions();
+
         context
-            .add_ascii_var("e", Factor::Constant(std::f64::consts::E).into());
+    }
+
+    /// Adding the trigonometric and hyperbolic functions and their
+    /// inverses.
+    fn add_trigonometric_functions(&mut self) {
+        self.add_symbol_function(
+            OtherSymbol::Sin,
+            (Number::sin, Complex64::sin, |u| {
+                Ok(call(OtherSymbol::Cos, u).into())
+            }),
+        );
+        self.add_symbol_function(
+            OtherSymbol::Cos,
+            (Number::cos, Complex64::cos, |u| {
+                Ok(negate(call(OtherSymbol::Sin, u)))
+            }),
+        );
+        self.add_symbol_function(
+            OtherSymbol::Tan,
+            (Number::tan, Complex64::tan, |u| {
+                Ok(reciprocal(squared(call(OtherSymbol::Cos, u))))
+            }),
+        );
+        self.add_symbol_function(
+            OtherSymbol::Sec,
+            (
+                |x: &Number| Number::from(1) / x.cos(),
+                |z: Complex64| z.cos().inv(),
+                |u: MathExpr| {
+                    Ok(Term::Multiply(
+                        MulType::Implicit,
+                        Term::from(call(OtherSymbol::Sec, u.clone())).boxed(),
+                        call(OtherSymbol::Tan, u),
+                    )
+                    .into())
+                },
+            ),
+        );
+        self.add_symbol_function(
+            OtherSymbol::Csc,
+            (
+                |x: &Number| Number::from(1) / x.sin(),
+                |z: Complex64| z.sin().inv(),
+                |u: MathExpr| {
+                    Ok(negate(Factor::Parenthesis(
+                        MathExpr::from(Term::Multiply(
+                            MulType::Implicit,
+                            Term::from(call(OtherSymbol::Csc, u.clone()))
+                                .boxed(),
+                            call(OtherSymbol::Cot, u),
+                        ))
+                        .boxed(),
+                    )))
+                },
+            ),
+        );
+        self.add_symbol_function(
+            OtherSymbol::Cot,
+            (
+                |x: &Number| Number::from(1) / x.tan(),
+                |z: Complex64| z.tan().inv(),
+                |u| {
+                    let sin_squared = squared(call(OtherSymbol::Sin, u));
+                    Ok(negate(reciprocal(sin_squared).get_factor_or_wrap()))
+                },
+            ),
+        );
+
+        // Inverse functions
+        self.add_symbol_function(
+            OtherSymbol::Arcsin,
+            (Number::asin, Complex64::asin, |u| {
+                Ok(reciprocal(one_minus_squared(u)))
+            }),
+        );
+        self.add_symbol_function(
+            OtherSymbol::Arccos,
+            (Number::acos, Complex64::acos, |u| {
+                Ok(negate(
+                    reciprocal(one_minus_squared(u)).get_factor_or_wrap(),
+                ))
+            }),
+        );
+        self.add_symbol_function(
+            OtherSymbol::Arctan,
+            (Number::atan, Complex64::atan, |u: MathExpr| {
+                let one_plus_squared = MathExpr::Add(
+                    MathExpr::from(1.0).boxed(),
+                    squared(u.get_factor_or_wrap()).into(),
+                );
+                Ok(reciprocal(Factor::Parenthesis(one_plus_squared.boxed())))
+            }),
+        );
 
-        // TODO add proper functions system so we can define the definition
-        //  and value sets to validate the amount of arguments, the types of
-        // arguments  (scalar or matrix).
+        // Hyperbolic functions
+        self.add_symbol_function(
+            OtherSymbol::Sinh,
+            (Number::sinh, Complex64::sinh, |u| {
+                Ok(call(OtherSymbol::Cosh, u).into())
+            }),
+        );
+        self.add_symbol_function(
+            OtherSymbol::Cosh,
+            (Number::cosh, Complex64::cosh, |u| {
+                Ok(call(OtherSymbol::Sinh, u).into())
+            }),
+        );
+        self.add_symbol_function(
+            OtherSymbol::Tanh,
+            (Number::tanh, Complex64::tanh, |u| {
+                Ok(reciprocal(squared(call(OtherSymbol::Cosh, u))))
+            }),
+        );
+    }
 
-        // Trigonometric functions
-        context.add_symbol_function(OtherSymbol::Sin, f64::sin);
-        context.add_symbol_function(OtherSymbol::Cos, f64::cos);
-        context.add_symbol_function(OtherSymbol::Tan, f64::tan);
+    /// Adding the exponential function and the logarithms.
+    fn add_exponential_functions(&mut self) {
+        self.add_symbol_function(
+            OtherSymbol::Exp,
+            (Number::exp, Complex64::exp, |u| {
+                Ok(call(OtherSymbol::Exp, u).into())
+            }),
+        );
+        self.add_symbol_function(
+            OtherSymbol::Ln,
+            (Number::ln, Complex64::ln, |u: MathExpr| {
+                Ok(reciprocal(u.get_factor_or_wrap()))
+            }),
+        );
+        self.add_symbol_function(
+            OtherSymbol::Lg,
+            (Number::log10, Complex64::log10, |u| {
+                Ok(logarithm_derivative(u, 10.0.into()))
+            }),
+        );
+        // \log x is base 10 and \log_{b} x is parsed as \log(x, b)
+        self.add_symbol_function(
+            OtherSymbol::Log,
+            NativeFunction::new(complexes(1), |v| {
+                let z = v[0].complex()?;
+                Ok(real_or_complex(
+                    v[0].number().ok().map(Number::log10),
+                    || z.log10(),
+                ))
+            })
+            .overload(complexes(2), |v| {
+                let (z, base) = (v[0].complex()?, v[1].complex()?);
+                let real = match (v[0].number(), v[1].number()) {
+                    (Ok(x), Ok(base)) => Some(x.ln() / base.ln()),
+                    _ => None,
+                };
+                Ok(real_or_complex(real, || z.ln() / base.ln()))
+            })
+            .with_derivative(Arc::new(|mut v: Vec<MathExpr>| {
+                let base = match v.len() {
+                    2 => v.remove(1),
+                    _ => 10.0.into(),
+                };
+                Ok(logarithm_derivative(v.remove(0), base))
+            })),
+        );
+    }
 
-        // Logarithm
-        context.add_symbol_function(OtherSymbol::Ln, f64::ln);
+    /// Adding the functions that round, compare or take divisors, which are
+    /// constant wherever they can be derived.
+    fn add_discrete_functions(&mut self) {
+        self.add_symbol_function(
+            OtherSymbol::Max,
+            variadic(|values| extremum(values, Ordering::Greater))
+                .with_derivative(Arc::new(|v| extremum_derivative(v, 1.0)))
+                .symmetric(),
+        );
+        self.add_symbol_function(
+            OtherSymbol::Min,
+            variadic(|values| extremum(values, Ordering::Less))
+                .with_derivative(Arc::new(|v| extremum_derivative(v, -1.0)))
+                .symmetric(),
+        );
+        let integers = || {
+            Signature::variadic(
+                vec![ParameterKind::Scalar],
+                ParameterKind::Scalar,
+            )
+        };
+        self.add_symbol_function(
+            OtherSymbol::Gcd,
+            NativeFunction::new(integers(), |v| {
+                let mut result = 0;
+                for value in v {
+                    result = gcd(result, value.integer()?.unsigned_abs());
+                }
+                Ok(Value::from(Number::from(to_i64(result)?)))
+            })
+            .with_derivative(Arc::new(|_| Ok(0.0.into()))),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("lcm"),
+            NativeFunction::new(integers(), |v| {
+                let mut result: u64 = 1;
+                for value in v {
+                    let value = value.integer()?.unsigned_abs();
+                    result = match gcd(result, value) {
+                        0 => 0,
+                        divisor => (result / divisor)
+                            .checked_mul(value)
+                            .ok_or(EvalError::IntegerOverflow)?,
+                    };
+                }
+                Ok(Value::from(Number::from(to_i64(result)?)))
+            })
+            .with_derivative(Arc::new(|_| Ok(0.0.into()))),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("floor"),
+            piecewise_constant(Number::floor),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("ceil"),
+            piecewise_constant(Number::ceil),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("sgn"),
+            piecewise_constant(Number::signum),
+        );
+    }
 
-        context
+    /// Adding the functions of matrices.
+    fn add_linear_algebra_functions(&mut self) {
+        /// The function of a single matrix argument.
+        fn of_matrix(
+            func: fn(&Matrix<Value>) -> Result<Value, EvalError>,
+        ) -> NativeFunction {
+            NativeFunction::new(
+                Signature::new(vec![ParameterKind::Matrix]),
+                move |v| {
+                    let Value::Matrix(m) = &v[0] else {
+                        unreachable!("the signature only accepts matrices")
+                    };
+                    func(m)
+                },
+            )
+        }
+        /// The function of two vectors, which are checked by `func`.
+        fn of_vectors(
+            func: fn(
+                &Matrix<Value>,
+                &Matrix<Value>,
+            ) -> Result<Value, EvalError>,
+        ) -> NativeFunction {
+            NativeFunction::new(
+                Signature::repeat(ParameterKind::Matrix, 2),
+                move |v| {
+                    let (Value::Matrix(a), Value::Matrix(b)) = (&v[0], &v[1])
+                    else {
+                        unreachable!("the signature only accepts matrices")
+                    };
+                    func(a, b)
+                },
+            )
+        }
+        self.add_function(
+            MathIdentifier::from_single_symbol(OtherSymbol::Det),
+            of_matrix(Matrix::determinant),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("tr"),
+            of_matrix(Matrix::trace),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("rank"),
+            of_matrix(|m| Ok(Value::from(Number::from(m.rank()? as i64)))),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("rref"),
+            of_matrix(|m| Ok(Value::Matrix(m.rref()?))),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("inv"),
+            of_matrix(|m| Ok(Value::Matrix(m.inverse()?))),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("lu"),
+            of_matrix(|m| {
+                let Lu { p, l, u } = decomposition::lu(m)?;
+                Ok(factors(vec![p, l, u]))
+            }),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("qr"),
+            of_matrix(|m| {
+                let Qr { q, r } = decomposition::qr(m)?;
+                Ok(factors(vec![q, r]))
+            }),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("chol"),
+            of_matrix(|m| Ok(Value::Matrix(decomposition::cholesky(m)?))),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("svd"),
+            of_matrix(|m| {
+                let Svd { u, sigma, v } = decomposition::svd(m)?;
+                Ok(factors(vec![u, sigma, v]))
+            }),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("eig"),
+            of_matrix(|m| {
+                let Eigen { values, vectors } = decomposition::eig(m)?;
+                Ok(factors(vec![values, vectors]))
+            }),
+        );
+        // The norm of a matrix, by default the 2-norm
+        let norm = |v: Vec<Value>| match &v[..] {
+            [Value::Matrix(m)] => m.norm(&Value::from(Number::from(2))),
+            [Value::Matrix(m), p] => m.norm(p),
+            _ => unreachable!("the signatures only accept matrices"),
+        };
+        self.add_function(
+            MathIdentifier::from_operator_name("norm"),
+            NativeFunction::new(
+                Signature::new(vec![ParameterKind::Complex]),
+                |v| v[0].abs(),
+            )
+            .overload(Signature::new(vec![ParameterKind::Matrix]), norm)
+            .overload(
+                Signature::new(vec![
+                    ParameterKind::Matrix,
+                    ParameterKind::Scalar,
+                ]),
+                norm,
+            ),
+        );
+        self.add_symbol_function(OtherSymbol::Angle, of_vectors(Matrix::angle));
+        self.add_function(
+            MathIdentifier::from_operator_name("proj"),
+            of_vectors(|v, u| Ok(Value::Matrix(v.projection(u)?))),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("zeros"),
+            NativeFunction::new(
+                Signature::new(vec![ParameterKind::Scalar]),
+                |v| zeros(&v[0], &v[0]),
+            )
+            .overload(Signature::repeat(ParameterKind::Scalar, 2), |v| {
+                zeros(&v[0], &v[1])
+            }),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("diag"),
+            NativeFunction::new(
+                Signature::variadic(
+                    vec![ParameterKind::Complex],
+                    ParameterKind::Complex,
+                ),
+                |v| Ok(Value::Matrix(Matrix::diagonal(v))),
+            )
+            .overload(
+                Signature::new(vec![ParameterKind::Matrix]),
+                |v| {
+                    let Value::Matrix(m) = &v[0] else {
+                        unreachable!("the signature only accepts matrices")
+                    };
+                    let vector = m.as_vector()?;
+                    let values =
+                        (0..vector.get_size()).map(|i| vector.get(i).clone());
+                    Ok(Value::Matrix(Matrix::diagonal(values.collect())))
+                },
+            ),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("rot"),
+            NativeFunction::new(
+                Signature::new(vec![ParameterKind::Scalar]),
+                |v| Ok(Value::Matrix(Matrix::rotation(&v[0])?)),
+            )
+            .overload(
+                Signature::new(vec![
+                    ParameterKind::Scalar,
+                    ParameterKind::Vector(3),
+                ]),
+                |v| {
+                    let Value::Matrix(axis) = &v[1] else {
+                        unreachable!("the signature only accepts vectors")
+                    };
+                    Ok(Value::Matrix(Matrix::rotation_about(&v[0], axis)?))
+                },
+            ),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("range"),
+            NativeFunction::new(
+                Signature::repeat(ParameterKind::Scalar, 2),
+                |v| Ok(Value::Matrix(Matrix::range(&v[0], None, &v[1])?)),
+            )
+            .overload(
+                Signature::repeat(ParameterKind::Scalar, 3),
+                |v| {
+                    Ok(Value::Matrix(Matrix::range(&v[0], Some(&v[1]), &v[2])?))
+                },
+            ),
+        );
+        self.add_function(
+            MathIdentifier::from_operator_name("solve"),
+            NativeFunction::new(
+                Signature::repeat(ParameterKind::Matrix, 2),
+                |v| {
+                    let (Value::Matrix(a), Value::Matrix(b)) = (&v[0], &v[1])
+                    else {
+                        unreachable!("the signature only accepts matrices")
+                    };
+                    match solver::solve_linear(a, b)? {
+                        LinearSolution::Unique(x) => Ok(Value::Matrix(x)),
+                        LinearSolution::Infinite { .. } => {
+                            Err(EvalError::InfinitelyManySolutions)
+                        }
+                        LinearSolution::None => Err(EvalError::NoSolution),
+                    }
+                },
+            ),
+        );
+    }
+}
+
+/// The matrix of exact zeros with the given amount of rows and columns.
+fn zeros(rows: &Value, columns: &Value) -> Result<Value, EvalError> {
+    let (rows, columns) = matrix::shape(rows, columns)?;
+    let zero = Value::from(Number::from(0));
+    Ok(Value::Matrix(Matrix::new_default(rows, columns, zero)))
+}
+
+/// The results of a function that has several, as a row of matrices.
+fn factors(matrices: Vec<Matrix<Value>>) -> Value {
+    let count = matrices.len();
+    let values = matrices.into_iter().map(Value::Matrix).collect();
+    Value::Matrix(Matrix::new(values, 1, count))
+}
+
+/// Calls the function identified by `symbol` with a single argument.
+fn call(symbol: OtherSymbol, argument: MathExpr) -> Factor {
+    Factor::FunctionCall(FunctionCall::new(
+        MathIdentifier::from_single_symbol(symbol),
+        vec![argument],
+    ))
+}
+
+/// `-1` times the factor.
+fn negate(factor: Factor) -> MathExpr {
+    Term::Multiply(MulType::Implicit, Term::from(-1.0).boxed(), factor).into()
+}
+
+/// The factor to the power of two.
+fn squared(factor: Factor) -> Factor {
+    Factor::Power {
+        base: factor.boxed(),
+        exponent: 2.0.into(),
+        span: NodeSpan::NONE,
     }
 }
 
+/// One divided by the factor.
+fn reciprocal(denominator: Factor) -> MathExpr {
+    Factor::Fraction(1.0.into(), denominator.into()).into()
+}
+
+/// `\sqrt{1-u^2}`, the denominator of the derivatives of arcsin and arccos.
+fn one_minus_squared(u: MathExpr) -> Factor {
+    Factor::Root {
+        degree: None,
+        radicand: MathExpr::Subtract(
+            MathExpr::from(1.0).boxed(),
+            squared(u.get_factor_or_wrap()).into(),
+        )
+        .boxed(),
+        span: NodeSpan::NONE,
+    }
+}
+
+/// The derivative of the logarithm of `u` in `base`, `\frac{1}{u \ln b}`.
+fn logarithm_derivative(u: MathExpr, base: MathExpr) -> MathExpr {
+    reciprocal(Factor::Parenthesis(
+        MathExpr::from(Term::Multiply(
+            MulType::Implicit,
+            Term::from(u.get_factor_or_wrap()).boxed(),
+            call(OtherSymbol::Ln, base),
+        ))
+        .boxed(),
+    ))
+}
+
+/// A native function of one or more scalars.
+fn variadic<F>(func: F) -> NativeFunction
+where
+    F: Fn(Vec<Number>) -> Number + Send + Sync + 'static,
+{
+    NativeFunction::new(
+        Signature::variadic(vec![ParameterKind::Scalar], ParameterKind::Scalar),
+        move |v| {
+            let values: Result<Vec<Number>, EvalError> =
+                v.iter().map(|value| value.number().cloned()).collect();
+            Ok(Value::from(func(values?)))
+        },
+    )
+}
+
+/// The largest of the numbers when `keep` is [Ordering::Greater], or the
+/// smallest when it is [Ordering::Less], ignoring numbers that are not a
+/// number.
+fn extremum(values: Vec<Number>, keep: Ordering) -> Number {
+    values
+        .into_iter()
+        .reduce(|extremum, value| match value.compare(&extremum) {
+            Some(ordering) if ordering == keep => value,
+            None if extremum.is_nan() => value,
+            _ => extremum,
+        })
+        .unwrap_or(Number::Float(f64::NAN))
+}
+
+/// A function of a single real number that is constant where it is
+/// differentiable, like `\lfloor x\rfloor`.
+fn piecewise_constant(func: fn(&Number) -> Number) -> NativeFunction {
+    NativeFunction::new(scalars(1), move |v| {
+        Ok(Value::from(func(v[0].number()?)))
+    })
+    .with_derivative(Arc::new(|_| Ok(0.0.into())))
+}
+
+/// A signature of `count` real numbers.
+fn scalars(count: usize) -> Signature {
+    Signature::repeat(ParameterKind::Scalar, count)
+}
+
+/// A signature of `count` real or complex numbers.
+fn complexes(count: usize) -> Signature {
+    Signature::repeat(ParameterKind::Complex, count)
+}
+
+/// The derivative of the maximum, when `direction` is 1, or the minimum, when
+/// it is -1, with respect to the first argument.
+///
+/// This is one when the first argument is the extremum and zero otherwise,
+/// written as `\frac{1 + d \operatorname{sgn}(u - m)}{2}` where `m` is the
+/// extremum of the other arguments.
+fn extremum_derivative(
+    mut arguments: Vec<MathExpr>,
+    direction: f64,
+) -> Result<MathExpr, EvalError> {
+    let u = arguments.remove(0);
+    if arguments.is_empty() {
+        return Ok(1.0.into());
+    }
+    let symbol = match direction > 0.0 {
+        true => OtherSymbol::Max,
+        false => OtherSymbol::Min,
+    };
+    let rest = Factor::FunctionCall(FunctionCall::new(
+        MathIdentifier::from_single_symbol(symbol),
+        arguments,
+    ));
+    let sign = Factor::FunctionCall(FunctionCall::new(
+        MathIdentifier::from_operator_name("sgn"),
+        vec![MathExpr::Subtract(u.boxed(), rest.into())],
+    ));
+    let numerator = MathExpr::Add(
+        MathExpr::from(1.0).boxed(),
+        Term::Multiply(MulType::Implicit, Term::from(direction).boxed(), sign),
+    );
+    Ok(Factor::Fraction(numerator.boxed(), 2.0.into()).into())
+}
+
+/// The greatest common divisor of two integers.
+fn gcd(a: u64, b: u64) -> u64 {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b),
+    }
+}
+
+/// The result of an integer function like `\gcd` as an [i64].
+fn to_i64(value: u64) -> Result<i64, EvalError> {
+    i64::try_from(value).map_err(|_| EvalError::IntegerOverflow)
+}
+
 #[cfg(test)]
 mod test {
     use snafu::whatever;
@@ -157,13 +808,19 @@ mod test {
         let mut c1 = MathContext::new();
         c1.add_symbol_function(
             OtherSymbol::Sin,
-            (|_| whatever!("testing"), 1, None),
+            (
+                |_| {
+                    whatever!("testing");
+                },
+                1,
+                None,
+            ),
         );
         c1.merge(&c2);
         let f = &c1.functions
             [&MathIdentifier::from_single_symbol(OtherSymbol::Sin)];
         assert!(match f {
-            MathFunction::Native(n) => n.run(vec![Value::Scalar(1.1)]).is_err(),
+            MathFunction::Native(n) => n.run(vec![Value::from(1.1)]).is_err(),
             MathFunction::Foreign(_) => false,
         });
     }
@@ -0,0 +1,844 @@
+//! # Decomposition
+//! Factoring a matrix into a product of simpler matrices, and finding its
+//! eigenvalues and eigenvectors.
+//!
+//! The LU and Cholesky decompositions only add, multiply, divide and take
+//! square roots, so they are exact for exact matrices. The others are
+//! iterative and approximate the matrix with floats:
+//! - QR uses Householder reflections.
+//! - SVD uses the one-sided Jacobi method, which rotates pairs of columns
+//!   until they are orthogonal.
+//! - The eigenvalues of a symmetric matrix are found with the Jacobi
+//!   eigenvalue method, which gives orthogonal eigenvectors. Other matrices
+//!   use the shifted QR algorithm in complex numbers, since a real matrix
+//!   can have complex eigenvalues, and inverse iteration for the
+//!   eigenvectors.
+use num_complex::Complex64;
+
+use crate::{prelude::*, solver};
+
+/// The maximum amount of sweeps of the Jacobi methods, and of QR steps per
+/// eigenvalue.
+pub const MAX_ITERATIONS: usize = 100;
+
+/// Entries that are this small relative to the size of the matrix are
+/// treated as zero by the iterative methods.
+const TOLERANCE: f64 = 1e-14;
+
+/// How much two mirrored entries of a symmetric matrix may differ, relative
+/// to their size.
+const SYMMETRY_TOLERANCE: f64 = 1e-12;
+
+/// How far from an eigenvalue inverse iteration shifts the matrix, relative
+/// to the size of the eigenvalue, so it is not exactly singular.
+const EIGENVECTOR_SHIFT: f64 = 1e-10;
+
+/// The LU decomposition `PA = LU` of a square matrix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lu {
+    /// The permutation matrix of the row swaps.
+    pub p: Matrix<Value>,
+    /// A lower triangular matrix with ones on the diagonal.
+    pub l: Matrix<Value>,
+    /// An upper triangular matrix.
+    pub u: Matrix<Value>,
+}
+
+/// The QR decomposition `A = QR` of a matrix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Qr {
+    /// An orthogonal matrix.
+    pub q: Matrix<Value>,
+    /// An upper triangular matrix with a nonnegative diagonal.
+    pub r: Matrix<Value>,
+}
+
+/// The singular value decomposition `A = U \Sigma V^T` of a matrix, where
+/// the inner size is the smallest of the row and column count.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Svd {
+    /// A matrix with orthonormal columns, the left singular vectors.
+    pub u: Matrix<Value>,
+    /// A diagonal matrix of the singular values in descending order.
+    pub sigma: Matrix<Value>,
+    /// A matrix with orthonormal columns, the right singular vectors.
+    pub v: Matrix<Value>,
+}
+
+/// The eigenvalues and eigenvectors of a square matrix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Eigen {
+    /// A column vector of the eigenvalues, in ascending order of their real
+    /// part and then their imaginary part.
+    pub values: Matrix<Value>,
+    /// The eigenvectors of unit length as columns, in the same order as the
+    /// eigenvalues.
+    pub vectors: Matrix<Value>,
+}
+
+/// Calculates the LU decomposition with partial pivoting.
+///
+/// # Errors
+/// [IncompatibleMatrixSizes::Square] if the matrix is not square, or an
+/// error if the elements can not be combined.
+pub fn lu(a: &Matrix<Value>) -> Result<Lu, EvalError> {
+    let size = a.square_size()?;
+    let mut u = a.rows();
+    let mut l = Matrix::identity(size).rows();
+    let mut order: Vec<usize> = (0..size).collect();
+    for k in 0..size {
+        let mut best = k;
+        let mut best_size = u[k][k].abs()?.scalar()?;
+        for (row, values) in u.iter().enumerate().skip(k + 1) {
+            let size = values[k].abs()?.scalar()?;
+            if size > best_size {
+                (best, best_size) = (row, size);
+            }
+        }
+        // The column is already eliminated
+        if solver::is_negligible(&u[best][k])? {
+            continue;
+        }
+        if best != k {
+            u.swap(k, best);
+            order.swap(k, best);
+            // Only the multipliers of the previous columns follow the rows
+            let (top, bottom) = l.split_at_mut(best);
+            top[k][..k].swap_with_slice(&mut bottom[0][..k]);
+        }
+        let pivot_row = u[k].clone();
+        for (l_row, u_row) in l.iter_mut().zip(u.iter_mut()).skip(k + 1) {
+            if solver::is_zero(&u_row[k]) {
+                continue;
+            }
+            let factor = (u_row[k].clone() / pivot_row[k].clone())?;
+            for (value, pivot) in u_row.iter_mut().zip(&pivot_row).skip(k) {
+                let subtracted = factor.mul(&MulType::Implicit, pivot)?;
+                *value = (value.clone() - subtracted)?;
+            }
+            u_row[k] = Value::from(Number::from(0));
+            l_row[k] = factor;
+        }
+    }
+    let mut p = Matrix::new_default(size, size, Value::from(Number::from(0)));
+    for (row, &column) in order.iter().enumerate() {
+        p.set(row, column, Value::from(Number::from(1)));
+    }
+    Ok(Lu {
+        p,
+        l: Matrix::from_rows(l),
+        u: Matrix::from_rows(u),
+    })
+}
+
+/// Calculates the Cholesky decomposition `A = LL^T` of a symmetric positive
+/// definite matrix, returning the lower triangular `L`.
+///
+/// # Errors
+/// [IncompatibleMatrixSizes::Square] if the matrix is not square,
+/// [EvalError::NotPositiveDefinite] if it is not symmetric and positive
+/// definite, [EvalError::ExpectedReal] if it is complex, or
+/// [EvalError::ExpectedFinite] if an entry is infinite or not a number.
+pub fn cholesky(a: &Matrix<Value>) -> Result<Matrix<Value>, EvalError> {
+    let size = a.square_size()?;
+    if !is_symmetric(&real_rows(a)?) {
+        return Err(EvalError::NotPositiveDefinite);
+    }
+    let mut l = vec![vec![Value::from(Number::from(0)); size]; size];
+    for j in 0..size {
+        // The entry minus the dot product of the finished parts of the rows
+        let reduce = |l: &[Vec<Value>], i: usize| {
+            l[i][..j]
+                .iter()
+                .zip(&l[j][..j])
+                .try_fold(a.get(i, j).clone(), |sum, (x, y)| {
+                    sum - x.mul(&MulType::Implicit, y)?
+                })
+        };
+        let diagonal = reduce(&l, j)?;
+        if diagonal.scalar()? <= 0.0 {
+            return Err(EvalError::NotPositiveDefinite);
+        }
+        let root = diagonal.root(&Value::from(Number::from(2)))?;
+        for i in j + 1..size {
+            l[i][j] = (reduce(&l, i)? / root.clone())?;
+        }
+        l[j][j] = root;
+    }
+    Ok(Matrix::from_rows(l))
+}
+
+/// Calculates the QR decomposition with Householder reflections, where `Q`
+/// is square.
+///
+/// # Errors
+/// [EvalError::ExpectedReal] if the matrix is complex.
+pub fn qr(a: &Matrix<Value>) -> Result<Qr, EvalError> {
+    let (m, n) = (a.row_count(), a.column_count());
+    let mut r = real_rows(a)?;
+    let mut q = identity(m);
+    for k in 0..n.min(m - 1) {
+        // Reflects the column below the diagonal onto the diagonal
+        let norm = r[k..].iter().map(|row| row[k].powi(2)).sum::<f64>().sqrt();
+        if norm == 0.0 {
+            continue;
+        }
+        let mut v: Vec<f64> = r[k..].iter().map(|row| row[k]).collect();
+        v[0] += norm.copysign(v[0]);
+        let length: f64 = v.iter().map(|x| x * x).sum();
+        for j in 0..n {
+            let dot: f64 =
+                v.iter().zip(&r[k..]).map(|(x, row)| x * row[j]).sum();
+            for (x, row) in v.iter().zip(&mut r[k..]) {
+                row[j] -= 2.0 * dot / length * x;
+            }
+        }
+        for row in &mut q {
+            let dot: f64 = v.iter().zip(&row[k..]).map(|(x, y)| x * y).sum();
+            for (x, y) in v.iter().zip(&mut row[k..]) {
+                *y -= 2.0 * dot / length * x;
+            }
+        }
+        for row in &mut r[k + 1..] {
+            row[k] = 0.0;
+        }
+    }
+    // A nonnegative diagonal makes the decomposition unique
+    for k in 0..n.min(m) {
+        if r[k][k] < 0.0 {
+            r[k].iter_mut().for_each(|x| *x = -*x);
+            q.iter_mut().for_each(|row| row[k] = -row[k]);
+        }
+    }
+    Ok(Qr {
+        q: from_real_rows(q),
+        r: from_real_rows(r),
+    })
+}
+
+/// Calculates the thin singular value decomposition with the one-sided
+/// Jacobi method.
+///
+/// # Errors
+/// [EvalError::ExpectedReal] if the matrix is complex, or
+/// [EvalError::NoConvergence] if the columns do not become orthogonal.
+pub fn svd(a: &Matrix<Value>) -> Result<Svd, EvalError> {
+    if a.row_count() < a.column_count() {
+        // A^T = U \Sigma V^T, so A = V \Sigma U^T
+        let Svd { u, sigma, v } = svd(&a.transpose())?;
+        return Ok(Svd { u: v, sigma, v: u });
+    }
+    let (m, n) = (a.row_count(), a.column_count());
+    let mut u = real_rows(a)?;
+    let mut v = identity(n);
+    let mut converged = false;
+    for _ in 0..MAX_ITERATIONS {
+        converged = true;
+        for p in 0..n {
+            for q in p + 1..n {
+                let (mut alpha, mut beta, mut gamma) = (0.0, 0.0, 0.0);
+                for row in &u {
+                    alpha += row[p] * row[p];
+                    beta += row[q] * row[q];
+                    gamma += row[p] * row[q];
+                }
+                if gamma.abs() <= TOLERANCE * (alpha * beta).sqrt() {
+                    continue;
+                }
+                converged = false;
+                let (c, s) = jacobi_rotation(alpha, beta, gamma);
+                rotate(&mut u, p, q, c, s);
+                rotate(&mut v, p, q, c, s);
+            }
+        }
+        if converged {
+            break;
+        }
+    }
+    if !converged {
+        return Err(EvalError::NoConvergence);
+    }
+    // The columns are orthogonal, and their lengths are the singular values
+    let columns: Vec<Vec<f64>> = (0..n)
+        .map(|j| u.iter().map(|row| row[j]).collect())
+        .collect();
+    let lengths: Vec<f64> = columns.iter().map(|c| norm(c)).collect();
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| lengths[j].total_cmp(&lengths[i]));
+    let largest = lengths[order[0]];
+    let mut left: Vec<Vec<f64>> = Vec::with_capacity(n);
+    for &j in &order {
+        if lengths[j] > TOLERANCE * largest {
+            left.push(columns[j].iter().map(|x| x / lengths[j]).collect());
+        }
+    }
+    // Singular values of zero have no column to normalize
+    while left.len() < n {
+        left.push(orthogonal_complement(&left, m));
+    }
+    let mut sigma = vec![vec![0.0; n]; n];
+    for (i, &j) in order.iter().enumerate() {
+        sigma[i][i] = lengths[j];
+    }
+    Ok(Svd {
+        u: from_real_rows(transpose(&left)),
+        sigma: from_real_rows(sigma),
+        v: from_real_rows(
+            v.iter()
+                .map(|row| order.iter().map(|&j| row[j]).collect())
+                .collect(),
+        ),
+    })
+}
+
+/// Calculates the eigenvalues and eigenvectors of a real square matrix,
+/// which are complex when needed.
+///
+/// # Errors
+/// [IncompatibleMatrixSizes::Square] if the matrix is not square,
+/// [EvalError::ExpectedReal] if it is complex, [EvalError::ExpectedFinite]
+/// if an entry is infinite or not a number, or [EvalError::NoConvergence] if
+/// the iteration does not converge.
+pub fn eig(a: &Matrix<Value>) -> Result<Eigen, EvalError> {
+    a.square_size()?;
+    let rows = real_rows(a)?;
+    let (values, vectors) = match is_symmetric(&rows) {
+        true => symmetric_eigen(rows)?,
+        false => general_eigen(rows)?,
+    };
+    let size = values.len();
+    Ok(Eigen {
+        values: Matrix::new(
+            values.into_iter().map(Value::from).collect(),
+            size,
+            1,
+        ),
+        vectors: Matrix::from_rows(
+            transpose(&vectors)
+                .into_iter()
+                .map(|row| row.into_iter().map(Value::from).collect())
+                .collect(),
+        ),
+    })
+}
+
+/// The eigenvalues and eigenvectors of a symmetric matrix with the Jacobi
+/// eigenvalue method, which rotates away one off-diagonal entry at a time.
+fn symmetric_eigen(
+    mut a: Vec<Vec<f64>>,
+) -> Result<(Vec<Complex64>, Vec<Vec<Complex64>>), EvalError> {
+    let size = a.len();
+    let scale = frobenius_norm(&a);
+    let mut v = identity(size);
+    let mut converged = false;
+    for _ in 0..MAX_ITERATIONS {
+        converged = true;
+        for p in 0..size {
+            for q in p + 1..size {
+                if a[p][q].abs() <= TOLERANCE * scale {
+                    continue;
+                }
+                converged = false;
+                let (c, s) = jacobi_rotation(a[p][p], a[q][q], a[p][q]);
+                rotate(&mut a, p, q, c, s);
+                rotate(&mut v, p, q, c, s);
+                // The same rotation of the rows, which is a rotation of the
+                // columns since the matrix stays symmetric
+                let (row_p, row_q) = (a[p].clone(), a[q].clone());
+                for (k, (x, y)) in row_p.iter().zip(&row_q).enumerate() {
+                    a[p][k] = c * x - s * y;
+                    a[q][k] = s * x + c * y;
+                }
+            }
+        }
+        if converged {
+            break;
+        }
+    }
+    if !converged {
+        return Err(EvalError::NoConvergence);
+    }
+    let mut order: Vec<usize> = (0..size).collect();
+    order.sort_by(|&i, &j| a[i][i].total_cmp(&a[j][j]));
+    Ok((
+        order.iter().map(|&i| Complex64::from(a[i][i])).collect(),
+        order
+            .iter()
+            .map(|&i| v.iter().map(|row| Complex64::from(row[i])).collect())
+            .collect(),
+    ))
+}
+
+/// The eigenvalues and eigenvectors of any real matrix, where the
+/// eigenvectors are found by inverse iteration.
+fn general_eigen(
+    a: Vec<Vec<f64>>,
+) -> Result<(Vec<Complex64>, Vec<Vec<Complex64>>), EvalError> {
+    let scale = frobenius_norm(&a);
+    let a: Vec<Vec<Complex64>> = a
+        .iter()
+        .map(|row| row.iter().map(|&x| Complex64::from(x)).collect())
+        .collect();
+    let mut values = eigenvalues(a.clone(), scale)?;
+    // The imaginary parts of real eigenvalues are rounding errors
+    for value in &mut values {
+        if value.im.abs() <= SYMMETRY_TOLERANCE * scale {
+            value.im = 0.0;
+        }
+    }
+    values.sort_by(|x, y| x.re.total_cmp(&y.re).then(x.im.total_cmp(&y.im)));
+    let vectors = values
+        .iter()
+        .map(|&value| eigenvector(&a, value, scale))
+        .collect();
+    Ok((values, vectors))
+}
+
+/// The eigenvalues of a complex matrix with the shifted QR algorithm.
+///
+/// The QR steps converge to an upper triangular matrix, so every time the
+/// off-diagonal part of the last row is small enough its diagonal entry is
+/// an eigenvalue, and the rest of the matrix is searched.
+fn eigenvalues(
+    mut h: Vec<Vec<Complex64>>,
+    scale: f64,
+) -> Result<Vec<Complex64>, EvalError> {
+    let mut values = Vec::with_capacity(h.len());
+    let mut size = h.len();
+    let mut iterations = 0;
+    while size > 1 {
+        let last = size - 1;
+        let off_diagonal: f64 = h[last][..last].iter().map(|x| x.norm()).sum();
+        if off_diagonal <= TOLERANCE * scale {
+            values.push(h[last][last]);
+            size -= 1;
+            iterations = 0;
+            continue;
+        }
+        iterations += 1;
+        if iterations > MAX_ITERATIONS {
+            return Err(EvalError::NoConvergence);
+        }
+        // An unusual shift now and then breaks cycles
+        let shift = match iterations % 11 {
+            0 => h[last][last] + off_diagonal,
+            _ => wilkinson_shift(
+                h[last - 1][last - 1],
+                h[last - 1][last],
+                h[last][last - 1],
+                h[last][last],
+            ),
+        };
+        qr_step(&mut h, size, shift);
+    }
+    values.push(h[0][0]);
+    Ok(values)
+}
+
+/// The eigenvalue of the 2x2 matrix `[[a, b], [c, d]]` closest to `d`.
+fn wilkinson_shift(
+    a: Complex64,
+    b: Complex64,
+    c: Complex64,
+    d: Complex64,
+) -> Complex64 {
+    let half = (a - d) / 2.0;
+    let root = (half * half + b * c).sqrt();
+    let mean = (a + d) / 2.0;
+    match ((mean + root) - d).norm() < ((mean - root) - d).norm() {
+        true => mean + root,
+        false => mean - root,
+    }
+}
+
+/// Replaces the leading `size` rows and columns of `h` by `RQ + \mu I`
+/// where `QR = H - \mu I`, which has the same eigenvalues. The QR
+/// decomposition is made with Givens rotations.
+fn qr_step(h: &mut [Vec<Complex64>], size: usize, shift: Complex64) {
+    for (i, row) in h.iter_mut().enumerate().take(size) {
+        row[i] -= shift;
+    }
+    let mut rotations = Vec::new();
+    for column in 0..size - 1 {
+        for row in column + 1..size {
+            let (a, b) = (h[column][column], h[row][column]);
+            if b.norm() == 0.0 {
+                continue;
+            }
+            let length = (a.norm_sqr() + b.norm_sqr()).sqrt();
+            let (c, s) = (a / length, b / length);
+            let (top, bottom) = h.split_at_mut(row);
+            let pairs = top[column][column..size]
+                .iter_mut()
+                .zip(&mut bottom[0][column..size]);
+            for (x, y) in pairs {
+                (*x, *y) = (c.conj() * *x + s.conj() * *y, -s * *x + c * *y);
+            }
+            rotations.push((column, row, c, s));
+        }
+    }
+    for (i, k, c, s) in rotations {
+        for row in h.iter_mut().take(size) {
+            let (x, y) = (row[i], row[k]);
+            row[i] = x * c + y * s;
+            row[k] = -x * s.conj() + y * c.conj();
+        }
+    }
+    for (i, row) in h.iter_mut().enumerate().take(size) {
+        row[i] += shift;
+    }
+}
+
+/// An eigenvector of unit length by inverse iteration, which solves
+/// `(A - \lambda I)x = b` for a shift close to the eigenvalue, so the
+/// solution is dominated by the eigenvector.
+fn eigenvector(
+    a: &[Vec<Complex64>],
+    value: Complex64,
+    scale: f64,
+) -> Vec<Complex64> {
+    let shift = value + EIGENVECTOR_SHIFT * (value.norm() + scale);
+    let shifted: Vec<Vec<Complex64>> = a
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut row = row.clone();
+            row[i] -= shift;
+            row
+        })
+        .collect();
+    let size = a.len();
+    let mut x: Vec<Complex64> = (0..size)
+        .map(|i| Complex64::from(1.0 + i as f64 / size as f64))
+        .collect();
+    for _ in 0..2 {
+        x = solve_complex(&shifted, x, scale);
+        let length = x.iter().map(|x| x.norm_sqr()).sum::<f64>().sqrt();
+        x.iter_mut().for_each(|x| *x /= length);
+    }
+    // Rotated so the largest component is positive
+    let largest = x
+        .iter()
+        .copied()
+        .max_by(|a, b| a.norm().total_cmp(&b.norm()))
+        .expect("a matrix is not empty");
+    let phase = largest.conj() / largest.norm();
+    x.into_iter().map(|x| x * phase).collect()
+}
+
+/// Solves `Ax = b` by Gaussian elimination with partial pivoting, where
+/// pivots of zero are replaced by tiny numbers since `A` is meant to be
+/// almost singular.
+fn solve_complex(
+    a: &[Vec<Complex64>],
+    b: Vec<Complex64>,
+    scale: f64,
+) -> Vec<Complex64> {
+    let size = a.len();
+    let mut rows: Vec<Vec<Complex64>> = a
+        .iter()
+        .zip(b)
+        .map(|(row, b)| {
+            let mut row = row.clone();
+            row.push(b);
+            row
+        })
+        .collect();
+    for k in 0..size {
+        let best = (k..size)
+            .max_by(|&i, &j| rows[i][k].norm().total_cmp(&rows[j][k].norm()))
+            .expect("the range is not empty");
+        rows.swap(k, best);
+        if rows[k][k].norm() == 0.0 {
+            rows[k][k] = Complex64::from(f64::EPSILON * scale);
+        }
+        let pivot_row = rows[k].clone();
+        for row in &mut rows[k + 1..] {
+            let factor = row[k] / pivot_row[k];
+            for (x, pivot) in row.iter_mut().zip(&pivot_row).skip(k) {
+                *x -= factor * pivot;
+            }
+        }
+    }
+    let mut x = vec![Complex64::from(0.0); size];
+    for k in (0..size).rev() {
+        let sum: Complex64 =
+            (k + 1..size).map(|j| rows[k][j] * x[j]).sum::<Complex64>();
+        x[k] = (rows[k][size] - sum) / rows[k][k];
+    }
+    x
+}
+
+/// The cosine and sine of the Jacobi rotation of the columns `p` and `q`
+/// that makes them orthogonal, where `alpha` and `beta` are their squared
+/// lengths and `gamma` their dot product.
+fn jacobi_rotation(alpha: f64, beta: f64, gamma: f64) -> (f64, f64) {
+    let zeta = (beta - alpha) / (2.0 * gamma);
+    let t = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+    let c = 1.0 / (1.0 + t * t).sqrt();
+    (c, c * t)
+}
+
+/// Rotates the columns `p` and `q` of the rows.
+fn rotate(rows: &mut [Vec<f64>], p: usize, q: usize, c: f64, s: f64) {
+    for row in rows {
+        let (x, y) = (row[p], row[q]);
+        row[p] = c * x - s * y;
+        row[q] = s * x + c * y;
+    }
+}
+
+/// A unit vector of the given size that is orthogonal to the orthonormal
+/// vectors, of which there are fewer than the size.
+fn orthogonal_complement(vectors: &[Vec<f64>], size: usize) -> Vec<f64> {
+    // The standard basis vector with the largest part outside the vectors
+    let candidate = (0..size)
+        .map(|i| {
+            let mut candidate = vec![0.0; size];
+            candidate[i] = 1.0;
+            for vector in vectors {
+                let dot = vector[i];
+                for (x, y) in candidate.iter_mut().zip(vector) {
+                    *x -= dot * y;
+                }
+            }
+            candidate
+        })
+        .max_by(|a, b| norm(a).total_cmp(&norm(b)))
+        .expect("the size is not zero");
+    let length = norm(&candidate);
+    candidate.into_iter().map(|x| x / length).collect()
+}
+
+/// Whether the matrix equals its transpose, up to rounding errors.
+fn is_symmetric(rows: &[Vec<f64>]) -> bool {
+    rows.iter().enumerate().all(|(i, row)| {
+        row.iter().enumerate().skip(i + 1).all(|(j, &x)| {
+            let y = rows[j][i];
+            (x - y).abs() <= SYMMETRY_TOLERANCE * x.abs().max(y.abs())
+        })
+    })
+}
+
+/// The entries of a real matrix, row by row.
+///
+/// # Errors
+/// [EvalError::ExpectedReal] if an entry is complex, or
+/// [EvalError::ExpectedFinite] if it is infinite or not a number, since the
+/// iterations would never converge.
+fn real_rows(a: &Matrix<Value>) -> Result<Vec<Vec<f64>>, EvalError> {
+    let finite = |value: &Value| match value.scalar()? {
+        found if !found.is_finite() => Err(EvalError::ExpectedFinite { found }),
+        found => Ok(found),
+    };
+    a.rows()
+        .iter()
+        .map(|row| row.iter().map(finite).collect())
+        .collect()
+}
+
+/// A matrix of the approximated entries, row by row.
+fn from_real_rows(rows: Vec<Vec<f64>>) -> Matrix<Value> {
+    Matrix::from_rows(
+        rows.into_iter()
+            .map(|row| row.into_iter().map(Value::from).collect())
+            .collect(),
+    )
+}
+
+/// The identity matrix, row by row.
+fn identity(size: usize) -> Vec<Vec<f64>> {
+    (0..size)
+        .map(|i| (0..size).map(|j| f64::from(u8::from(i == j))).collect())
+        .collect()
+}
+
+/// The rows as columns.
+fn transpose<T: Copy>(rows: &[Vec<T>]) -> Vec<Vec<T>> {
+    (0..rows[0].len())
+        .map(|j| rows.iter().map(|row| row[j]).collect())
+        .collect()
+}
+
+/// The length of a vector.
+fn norm(vector: &[f64]) -> f64 {
+    vector.iter().map(|x| x * x).sum::<f64>().sqrt()
+}
+
+/// The square root of the sum of the squares of the entries, which is at
+/// least as large as every eigenvalue.
+fn frobenius_norm(rows: &[Vec<f64>]) -> f64 {
+    rows.iter().map(|row| norm(row).powi(2)).sum::<f64>().sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cholesky, eig, lu, qr, svd, Eigen, Lu, Qr, Svd};
+    use crate::prelude::*;
+    use num_complex::Complex64;
+    use pretty_assertions::assert_eq;
+
+    /// A matrix of exact integers, row by row.
+    fn matrix(values: &[i64], row_count: usize) -> Matrix<Value> {
+        let values: Vec<Value> = values
+            .iter()
+            .map(|&v| Value::from(Number::from(v)))
+            .collect();
+        let column_count = values.len() / row_count;
+        Matrix::new(values, row_count, column_count)
+    }
+
+    /// The product of the matrices.
+    fn product(matrices: &[&Matrix<Value>]) -> Matrix<Value> {
+        matrices[1..]
+            .iter()
+            .fold(matrices[0].clone(), |product, m| {
+                product.matrix_mul(m).unwrap()
+            })
+    }
+
+    /// Asserts that the matrices have the same size and entries that differ
+    /// by less than `1e-10`.
+    fn assert_close(found: &Matrix<Value>, expected: &Matrix<Value>) {
+        assert_eq!(found.row_count(), expected.row_count());
+        assert_eq!(found.column_count(), expected.column_count());
+        for row in 0..found.row_count() {
+            for column in 0..found.column_count() {
+                let x = found.get(row, column).complex().unwrap();
+                let y = expected.get(row, column).complex().unwrap();
+                assert!(
+                    (x - y).norm() < 1e-10,
+                    "found {found:?}, expected {expected:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn lu_is_exact() {
+        let a = matrix(&[1, 2, 4, 3, 8, 14, 2, 6, 13], 3);
+        let Lu { p, .. } = lu(&a).unwrap();
+        assert_eq!(p, matrix(&[0, 1, 0, 1, 0, 0, 0, 0, 1], 3));
+        // The second one swaps rows in the second column too
+        for a in [a, matrix(&[1, 2, 3, 2, 1, 1, 4, 1, 9], 3)] {
+            let Lu { p, l, u } = lu(&a).unwrap();
+            assert_eq!(product(&[&p, &a]), product(&[&l, &u]));
+            for row in 0..3 {
+                assert_eq!(l.get(row, row), &Value::from(Number::from(1)));
+                for column in 0..row {
+                    let zero = Value::from(Number::from(0));
+                    assert_eq!(u.get(row, column), &zero);
+                    assert_eq!(l.get(column, row), &zero);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cholesky_is_exact() {
+        let a = matrix(&[4, 2, -2, 2, 10, 2, -2, 2, 11], 3);
+        let l = cholesky(&a).unwrap();
+        assert_eq!(l, matrix(&[2, 0, 0, 1, 3, 0, -1, 1, 3], 3));
+        assert_eq!(product(&[&l, &l.transpose()]), a);
+        let not_definite = matrix(&[1, 2, 2, 1], 2);
+        assert!(matches!(
+            cholesky(&not_definite),
+            Err(EvalError::NotPositiveDefinite)
+        ));
+    }
+
+    #[test]
+    fn qr_is_orthogonal_times_triangular() {
+        let a = matrix(&[12, -51, 4, 6, 167, -68, -4, 24, -41], 3);
+        let Qr { q, r } = qr(&a).unwrap();
+        assert_close(&product(&[&q, &r]), &a);
+        assert_close(&product(&[&q.transpose(), &q]), &Matrix::identity(3));
+        assert_close(&r, &matrix(&[14, 21, -14, 0, 175, -70, 0, 0, 35], 3));
+    }
+
+    #[test]
+    fn svd_of_wide_and_singular_matrices() {
+        for a in [
+            matrix(&[3, 0, 4, 5], 2),
+            matrix(&[1, 2, 3, 2, 4, 6], 2),
+            matrix(&[1, 0, 1, 1, 0, 1], 3),
+        ] {
+            let Svd { u, sigma, v } = svd(&a).unwrap();
+            assert_close(&product(&[&u, &sigma, &v.transpose()]), &a);
+            let inner = sigma.row_count();
+            let identity = Matrix::identity(inner);
+            assert_close(&product(&[&u.transpose(), &u]), &identity);
+            assert_close(&product(&[&v.transpose(), &v]), &identity);
+        }
+        let Svd { sigma, .. } = svd(&matrix(&[3, 0, 4, 5], 2)).unwrap();
+        let expected = [45f64.sqrt(), 0.0, 0.0, 5f64.sqrt()];
+        let expected = expected.into_iter().map(Value::from).collect();
+        assert_close(&sigma, &Matrix::new(expected, 2, 2));
+    }
+
+    /// Asserts that the columns of the vectors are eigenvectors of unit
+    /// length for the eigenvalues.
+    fn assert_eigen(a: &Matrix<Value>, eigen: &Eigen) {
+        let size = a.row_count();
+        for column in 0..size {
+            let entries = (0..size)
+                .map(|row| eigen.vectors.get(row, column).clone())
+                .collect();
+            let vector = Matrix::new(entries, size, 1);
+            let value = eigen.values.get(column, 0);
+            let scaled =
+                vector.map(|x| value.mul(&MulType::Implicit, x)).unwrap();
+            assert_close(&a.matrix_mul(&vector).unwrap(), &scaled);
+            let length: f64 = (0..size)
+                .map(|row| vector.get(row, 0).complex().unwrap().norm_sqr())
+                .sum();
+            assert!((length - 1.0).abs() < 1e-10, "found length {length}");
+        }
+    }
+
+    #[test]
+    fn eigenvalues_of_symmetric_matrix() {
+        let a = matrix(&[2, 1, 0, 1, 2, 0, 0, 0, 5], 3);
+        let eigen = eig(&a).unwrap();
+        assert_close(&eigen.values, &matrix(&[1, 3, 5], 3));
+        assert_eigen(&a, &eigen);
+    }
+
+    #[test]
+    fn non_finite_entries() {
+        for value in [f64::INFINITY, f64::NAN] {
+            let a = Matrix::new(
+                vec![Value::from(value), 0.0.into(), 0.0.into(), 1.0.into()],
+                2,
+                2,
+            );
+            assert!(matches!(eig(&a), Err(EvalError::ExpectedFinite { .. })));
+            assert!(matches!(
+                cholesky(&a),
+                Err(EvalError::ExpectedFinite { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn eigenvalues_of_general_matrix() {
+        let a = matrix(&[4, 1, 2, 3], 2);
+        let eigen = eig(&a).unwrap();
+        assert_close(&eigen.values, &matrix(&[2, 5], 2));
+        assert_eigen(&a, &eigen);
+    }
+
+    #[test]
+    fn rotation_has_complex_eigenvalues() {
+        let a = matrix(&[0, -1, 1, 0], 2);
+        let eigen = eig(&a).unwrap();
+        let i = Complex64::i();
+        let expected = vec![Value::from(-i), Value::from(i)];
+        assert_close(&eigen.values, &Matrix::new(expected, 2, 1));
+        assert_eigen(&a, &eigen);
+        let state_space = matrix(&[0, 1, 0, 0, 0, 1, -6, -11, -6], 3);
+        let eigen = eig(&state_space).unwrap();
+        assert_close(&eigen.values, &matrix(&[-3, -2, -1], 3));
+        assert_eigen(&state_space, &eigen);
+    }
+}
@@ -0,0 +1,270 @@
+//! Presenting errors to the user, with where they are in the LaTeX and hints
+//! on how to fix them.
+use std::fmt::Write;
+
+use crate::{
+    identifier::{GreekLetter, ModifierType, OtherSymbol},
+    normalizer::Normalizer,
+    parsing::{Command, VectorOperator},
+    prelude::*,
+};
+
+/// An error prepared for a front-end, see [Diagnostic::render].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    /// What went wrong.
+    pub message: String,
+    /// Where it went wrong in the LaTeX, if it is known.
+    pub span: Option<Span>,
+    /// A suggestion on how to fix it, for example "did you mean `\sin`?".
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    /// A diagnostic for an error while parsing. The context is used to
+    /// suggest functions with similar names.
+    pub fn from_ast_error(error: &AstError, context: &MathContext) -> Self {
+        let AstError::ParseError { source, span } = error else {
+            return Self {
+                message: error.to_string(),
+                span: None,
+                hint: None,
+            };
+        };
+        Self {
+            message: source.to_string(),
+            span: Some(*span),
+            hint: parse_hint(source, context),
+        }
+    }
+
+    /// A diagnostic for an error while evaluating.
+    pub fn from_eval_error(error: &EvalError) -> Self {
+        let span = error.span();
+        let error = match error {
+            EvalError::At { source, .. } => source,
+            error => error,
+        };
+        Self {
+            message: error.to_string(),
+            span,
+            hint: eval_hint(error),
+        }
+    }
+
+    /// Renders the diagnostic like a compiler error, with the input
+    /// underlined where the error is.
+    ///
+    /// ```text
+    /// error: Got unexpected Token:"EOF", expected one of Tokens:"[}]"
+    ///   | \sqrt{2
+    ///   |        ^
+    ///   = hint: missing `}`
+    /// ```
+    pub fn render(&self, input: &str) -> String {
+        let mut text = format!("error: {}", self.message);
+        if let Some(span) = self.span {
+            let start = span.start.min(input.chars().count());
+            let width = (span.end.saturating_sub(start)).max(1);
+            let _ = write!(
+                text,
+                "\n  | {input}\n  | {}{}",
+                " ".repeat(start),
+                "^".repeat(width)
+            );
+        }
+        if let Some(hint) = &self.hint {
+            let _ = write!(text, "\n  = hint: {hint}");
+        }
+        text
+    }
+}
+
+/// A hint on how to fix a parse error.
+fn parse_hint(error: &ParseError, context: &MathContext) -> Option<String> {
+    match error {
+        ParseError::UnexpectedToken { expected, found } => {
+            match (&expected[..], found) {
+                ([expected], Token::EndOfContent) => {
+                    Some(format!("missing `{expected}`"))
+                }
+                ([expected], _) => {
+                    Some(format!("expected `{expected}` before this"))
+                }
+                _ => None,
+            }
+        }
+        ParseError::Trailing { token } => match token {
+            Token::RightCurlyBracket
+            | Token::RightParenthesis
+            | Token::RightBracket => {
+                Some(format!("there is no opening bracket for this `{token}`"))
+            }
+            _ => None,
+        },
+        ParseError::InvalidIdentifierCommmand { command } => {
+            let commands = Command::LATEX_CODES
+                .iter()
+                .chain(Normalizer::REMOVED_COMMANDS)
+                .chain(GreekLetter::LATEX_CODES)
+                .chain(OtherSymbol::LATEX_CODES)
+                .chain(ModifierType::LATEX_CODES)
+                .copied();
+            closest(command, commands)
+                .map(|command| format!("did you mean `\\{command}`?"))
+        }
+        ParseError::UnknownOperator { name } => {
+            let mut names: Vec<String> = context
+                .functions
+                .keys()
+                .filter_map(MathIdentifier::operator_name)
+                .collect();
+            names.sort();
+            names.splice(
+                0..0,
+                VectorOperator::LATEX_CODES.iter().map(|&n| n.to_owned()),
+            );
+            let hint = match closest(name, names.iter().map(String::as_str)) {
+                Some(name) => {
+                    format!("did you mean `\\operatorname{{{name}}}`?")
+                }
+                None => format!("the known operators are {}", names.join(", ")),
+            };
+            Some(hint)
+        }
+        ParseError::InvalidBegin { .. } => Some(
+            "matrices are written like `\\begin{pmatrix}1&2\\end{pmatrix}`"
+                .to_owned(),
+        ),
+        _ => None,
+    }
+}
+
+/// A hint on how to fix an error while evaluating.
+fn eval_hint(error: &EvalError) -> Option<String> {
+    Some(
+        match error {
+            EvalError::AmbiguousMulType { .. } => {
+                "use `\\cdot` for the dot product or `\\times` for the cross product"
+            }
+            EvalError::NotDefined => "define it first, for example `x=2`",
+            _ => return None,
+        }
+        .to_owned(),
+    )
+}
+
+/// The candidate that is the most similar to `word`, if any is similar
+/// enough to be a typo of it without being it.
+fn closest<'a>(
+    word: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max_distance = (word.chars().count() / 3).max(1);
+    candidates
+        .map(|candidate| (distance(word, candidate), candidate))
+        .filter(|(distance, _)| (1..=max_distance).contains(distance))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The amount of characters that have to be inserted, removed, replaced or
+/// swapped with the next one to turn `a` into `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // distances[i][j] is the distance between the first i characters of a
+    // and the first j characters of b.
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = best;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    async fn parse_diagnostic(text: &str) -> Diagnostic {
+        let context = MathContext::standard_math();
+        let error = parse(text, &context).await.unwrap_err();
+        Diagnostic::from_ast_error(&error, &context)
+    }
+
+    #[tokio::test]
+    async fn misspelled_commands() {
+        let found = parse_diagnostic("\\sni(x)").await;
+        assert_eq!(found.hint.as_deref(), Some("did you mean `\\sin`?"));
+        let found = parse_diagnostic("\\operatorname{dag}(1,2)").await;
+        assert_eq!(
+            found.hint.as_deref(),
+            Some("did you mean `\\operatorname{diag}`?")
+        );
+        let found = parse_diagnostic("\\operatorname{xyzzy}(1)").await;
+        let hint = found.hint.unwrap();
+        assert!(hint.starts_with("the known operators are J, H, "));
+        assert!(hint.contains("lcm"));
+        let found = parse_diagnostic("\\xyzzy").await;
+        assert_eq!(found.hint, None);
+        let found = parse_diagnostic("\\frac{\\parital f}{\\partial x}").await;
+        assert_eq!(found.hint.as_deref(), Some("did you mean `\\partial`?"));
+        let found = parse_diagnostic("\\lfet(x\\right)").await;
+        assert_eq!(found.hint.as_deref(), Some("did you mean `\\left`?"));
+        // The command itself is not a typo of it
+        let found = parse_diagnostic("\\partial").await;
+        assert_eq!(found.hint, None);
+    }
+
+    #[tokio::test]
+    async fn missing_brace_is_underlined() {
+        let found = parse_diagnostic("\\sqrt{2").await;
+        assert_eq!(found.hint.as_deref(), Some("missing `}`"));
+        assert_eq!(
+            found.render("\\sqrt{2"),
+            format!(
+                "error: {}\n  | \\sqrt{{2\n  |        ^\n  = hint: missing `}}`",
+                found.message
+            )
+        );
+    }
+
+    #[test]
+    fn ambiguous_products() {
+        let error = EvalError::AmbiguousMulType {
+            r#type: MulType::Asterisk,
+        }
+        .at(Some(Span::new(3, 4)));
+        let found = Diagnostic::from_eval_error(&error);
+        assert_eq!(found.span, Some(Span::new(3, 4)));
+        assert_eq!(
+            found.hint.as_deref(),
+            Some("use `\\cdot` for the dot product or `\\times` for the cross product")
+        );
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(distance("sni", "sin"), 1);
+        assert_eq!(distance("sqrt", "sqrt"), 0);
+        assert_eq!(distance("frc", "frac"), 1);
+        assert_eq!(distance("alpha", "lapha"), 1);
+    }
+}
@@ -1,8 +1,7 @@
 //!The errors that can happen when evaluating a latex string
-use crate::prelude::{MulType, Token};
+use crate::prelude::{MulType, ParameterKind, Span, Token};
 use slicedisplay::SliceDisplay;
 use snafu::Snafu;
-use tokio::task::JoinError;
 ///The errors that can happen while parsing latex
 #[derive(Debug, Snafu)]
 pub enum ParseError {
@@ -53,6 +52,9 @@ pub enum ParseError {
         ///The string of what was to "begin"
         beginning: String,
     },
+    /// An environment like `\begin{cases}` without any equations.
+    #[snafu(display("Expected at least one equation"))]
+    EmptySystem,
     ///Column size is wrong
     #[snafu(display("Expected it to have the same amount of columns, but previous had:{prev} instead got:{current}"))]
     MismatchedMatrixColumnSize {
@@ -64,16 +66,107 @@ pub enum ParseError {
     #[snafu(display("A matrix cannot be empty."))]
     ///The matrix was an empty matrix
     EmptyMatrix,
+    /// When an operation like \sum expects a variable to bind but got
+    /// something else.
+    #[snafu(display("Expected a variable to bind, like the i in \\sum_{{i=1}}, but found {found}"))]
+    InvalidBoundVariable {
+        /// The LaTeX of what was found instead of a variable.
+        found: String,
+    },
+    /// When an integral is missing one of its bounds, since only definite
+    /// integrals can be evaluated.
+    #[snafu(display(
+        "Expected an integral with both bounds, like \\int_{{0}}^{{1}}"
+    ))]
+    IndefiniteIntegral,
+    /// When the order of a derivative, for example the 2 in `d^2`, is not a
+    /// positive integer of at most [crate::parsing::MAX_DERIVATIVE_ORDER].
+    #[snafu(display(
+        "Expected the order of the derivative to be a positive integer of at most {}, but found {found}",
+        crate::parsing::MAX_DERIVATIVE_ORDER
+    ))]
+    InvalidDerivativeOrder {
+        /// The LaTeX of the order that was found.
+        found: String,
+    },
+    /// When the order in the numerator of a derivative does not match the
+    /// amount of variables in the denominator, for example a second derivative
+    /// with respect to one variable.
+    #[snafu(display(
+        "The derivative has the order {numerator} but differentiates with respect to {denominator} variables"
+    ))]
+    DerivativeOrderMismatch {
+        /// The order in the numerator.
+        numerator: usize,
+        /// The amount of variables in the denominator.
+        denominator: usize,
+    },
+    /// When a function in prime notation, for example `f'(x)`, is not called
+    /// with exactly one argument.
+    #[snafu(display(
+        "Prime notation is only supported for one argument, but found {found}"
+    ))]
+    PrimeArguments {
+        /// The amount of arguments that was found.
+        found: usize,
+    },
+    /// When an operator like `\nabla` expects a user defined function but
+    /// got something else.
+    #[snafu(display(
+        "Expected a user defined function with named parameters, but found {found}"
+    ))]
+    ExpectedFunction {
+        /// The LaTeX of what was found instead of a function.
+        found: String,
+    },
+    /// When an operator like `\nabla` is followed by neither a function nor
+    /// a subscript with the variables.
+    #[snafu(display(
+        "Expected a function like \\nabla f(x,y) or variables like \\nabla_{{x,y}} f after the operator, but found {found}"
+    ))]
+    InvalidVectorDerivativeOperand {
+        /// The LaTeX of what was found instead.
+        found: String,
+    },
+    /// When a user defined function is called with the wrong amount of
+    /// arguments.
+    #[snafu(display("Expected {expected} arguments, but found {found}"))]
+    ArgumentCount {
+        /// The amount of parameters of the function.
+        expected: usize,
+        /// The amount of arguments that was found.
+        found: usize,
+    },
+    /// When `\operatorname` is used with an unknown operator.
+    #[snafu(display(
+        "Unknown operator {name}, expected J for the Jacobian, H for the Hessian or the name of a function"
+    ))]
+    UnknownOperator {
+        /// The name of the operator.
+        name: String,
+    },
+    /// When the `\ldots` of a range is not the second to last element.
+    #[snafu(display(
+        "Expected \\ldots before the last element of a range, like (1,\\ldots,n) or (1,3,\\ldots,n)"
+    ))]
+    MisplacedEllipsis,
+}
+impl ParseError {
+    /// The token that could not be parsed, if the error is about one.
+    pub const fn token(&self) -> Option<&Token> {
+        match self {
+            Self::UnexpectedToken { found: token, .. }
+            | Self::Invalid { token }
+            | Self::Trailing { token }
+            | Self::InvalidFactor { token }
+            | Self::InvalidIdentifierToken { token } => Some(token),
+            _ => None,
+        }
+    }
 }
 ///The errors that can happen when generating the AST
 #[derive(Debug, Snafu)]
 pub enum AstError {
-    ///could not join the threads
-    #[snafu(transparent)]
-    Join {
-        ///the source of what crashed
-        source: JoinError,
-    },
     ///Thread panicked
     #[snafu(whatever)]
     Panic {
@@ -81,12 +174,23 @@ pub enum AstError {
         message: String,
     },
     ///Got a parse error
-    #[snafu(transparent)]
+    #[snafu(display("{source} at {span}"))]
     ParseError {
         ///The ParseError
         source: ParseError,
+        ///Where the error is in the LaTeX
+        span: Span,
     },
 }
+impl AstError {
+    /// Where the error is in the LaTeX, if it is a parse error.
+    pub const fn span(&self) -> Option<Span> {
+        match self {
+            Self::ParseError { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+}
 /// The errors that can happen when evaluating a AST
 #[derive(Debug, Snafu)]
 pub enum EvalError {
@@ -95,6 +199,24 @@ pub enum EvalError {
         "A matrix was found when it was expected to be a scalar"
     ))]
     ExpectedScalar,
+    /// A complex number was found where a real number is needed, for
+    /// example the bounds of an integral.
+    #[snafu(display("Expected a real number but found {found}"))]
+    ExpectedReal {
+        /// The complex number that was found, written as LaTeX.
+        found: String,
+    },
+    /// A value is not a number, for example a bound of an integral that is
+    /// NaN.
+    #[snafu(display("Expected a number but found NaN"))]
+    NotANumber,
+    /// An infinite value or NaN was found where a finite number is needed,
+    /// for example in a matrix that is decomposed.
+    #[snafu(display("Expected a finite number but found {found}"))]
+    ExpectedFinite {
+        /// The value that was found.
+        found: f64,
+    },
     ///Incompatible types
     #[snafu(whatever, display("The types are not compatible: {message}"))]
     IncompatibleTypes {
@@ -129,6 +251,30 @@ pub enum EvalError {
         ///the found amount of arguments
         found: usize,
     },
+    /// When a function that takes a variable amount of arguments is called
+    /// with too few of them.
+    #[snafu(display(
+        "Expected at least {minimum} arguments, but found {found}"
+    ))]
+    TooFewArguments {
+        /// The least amount of arguments the function takes.
+        minimum: usize,
+        /// The amount of arguments that was found.
+        found: usize,
+    },
+    /// An argument of a function has the wrong kind, for example a matrix
+    /// where a scalar was expected.
+    #[snafu(display(
+        "Argument {position} should be {expected}, but found {found}"
+    ))]
+    ArgumentType {
+        /// The position of the argument, starting at 1.
+        position: usize,
+        /// The kind that was expected.
+        expected: ParameterKind,
+        /// A description of the value that was found.
+        found: String,
+    },
     #[snafu(transparent)]
     ///Cant derive this expression
     DeriveError {
@@ -138,6 +284,121 @@ pub enum EvalError {
     /// Division by zero.
     #[snafu(display("Cannot divide by zero"))]
     DivideByZero,
+    /// A value that has to be an integer, for example the bounds of a sum,
+    /// was not.
+    #[snafu(display("Expected an integer but found {found}"))]
+    ExpectedInteger {
+        /// The value that was found.
+        found: f64,
+    },
+    /// The limit does not exist, for example because the expression
+    /// oscillates or approaches different values from each side.
+    #[snafu(display("The limit does not exist"))]
+    LimitDoesNotExist,
+    /// Every value of the unknown solves the equation, for example `x = x`,
+    /// or a system of equations has infinitely many solutions.
+    #[snafu(display("There are infinitely many solutions"))]
+    InfinitelyManySolutions,
+    /// The equations contradict each other, so there is no solution.
+    #[snafu(display("There is no solution"))]
+    NoSolution,
+    /// A system of equations where a single equation was expected.
+    #[snafu(display("Expected a single equation but found {found}"))]
+    ExpectedSingleEquation {
+        /// The amount of equations that was found.
+        found: usize,
+    },
+    /// An equation has several unknowns, or none, where a single one was
+    /// expected.
+    #[snafu(display("Expected a single unknown but found {found}"))]
+    ExpectedSingleUnknown {
+        /// The amount of unknowns that was found.
+        found: usize,
+    },
+    /// An equation of a linear system is not linear in the unknowns, for
+    /// example `xy = 1`.
+    #[snafu(display("The equations are not linear in the unknowns"))]
+    NotLinear,
+    /// The matrix has no inverse because its determinant is zero.
+    #[snafu(display("The matrix is singular, so it has no inverse"))]
+    SingularMatrix,
+    /// The matrix is not symmetric and positive definite, so it has no
+    /// Cholesky decomposition.
+    #[snafu(display("The matrix is not symmetric and positive definite"))]
+    NotPositiveDefinite,
+    /// An iterative method, like the one for eigenvalues, did not converge.
+    #[snafu(display("The iteration did not converge"))]
+    NoConvergence,
+    /// User defined functions called each other, or themselves, too many
+    /// times deep, for example because the recursion never ends.
+    #[snafu(display(
+        "Functions were called more than {depth} times deep, is there a recursion that never ends?"
+    ))]
+    RecursionLimit {
+        /// The maximum depth.
+        depth: usize,
+    },
+    /// A sum or product has more terms than can be evaluated one by one.
+    #[snafu(display("Expected at most {max} terms, but found {found}"))]
+    TooManyTerms {
+        /// The amount of terms.
+        found: u64,
+        /// The maximum amount of terms.
+        max: u64,
+    },
+    /// An integer result, for example of `\operatorname{lcm}`, does not fit
+    /// in 64 bits.
+    #[snafu(display("The integer result is too large"))]
+    IntegerOverflow,
+    /// A precision without any significant digits, see
+    /// [crate::precision::Precision::Digits].
+    #[snafu(display("The precision needs at least one significant digit"))]
+    InvalidPrecision,
+    /// A range like `(1,\ldots,n)` is empty or never reaches its end,
+    /// because the step is zero or goes the other way.
+    #[snafu(display("The range is empty or never ends"))]
+    InvalidRange,
+    /// A part of the expression could not be parsed, see
+    /// [crate::prelude::parse_with_recovery].
+    #[snafu(display("This part could not be parsed"))]
+    InvalidSyntax,
+    /// An error while evaluating a part of the expression, like a function
+    /// call or a sum, that was written at the span.
+    #[snafu(display("{source}"))]
+    At {
+        /// The error.
+        source: Box<EvalError>,
+        /// Where the part is in the LaTeX.
+        span: Span,
+    },
+}
+impl EvalError {
+    /// Where the innermost part of the expression with a known position that
+    /// failed is in the LaTeX.
+    pub const fn span(&self) -> Option<Span> {
+        match self {
+            Self::At { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+    /// The error without its position.
+    pub fn without_span(self) -> Self {
+        match self {
+            Self::At { source, .. } => *source,
+            error => error,
+        }
+    }
+    /// Adds the position of the part of the expression that failed, unless
+    /// a part inside of it already added its own.
+    pub fn at(self, span: Option<Span>) -> Self {
+        match (self, span) {
+            (error @ Self::At { .. }, _) | (error, None) => error,
+            (error, Some(span)) => Self::At {
+                source: Box::new(error),
+                span,
+            },
+        }
+    }
 }
 /// The error for when it required another size of the matrix
 #[derive(Debug, Snafu)]
@@ -187,6 +448,39 @@ pub enum IncompatibleMatrixSizes {
         ///Second Vector dimensions
         b: usize,
     },
+    ///The operation is only defined for square matrices, like the
+    /// determinant and the inverse
+    #[snafu(display(
+        "Expected a square matrix but got a {rows:?}x{columns:?} matrix."
+    ))]
+    Square {
+        ///The rows of the matrix
+        rows: usize,
+        ///The columns of the matrix
+        columns: usize,
+    },
+    ///An index is outside of the matrix, where indices start at 1
+    #[snafu(display("Index {index:?} is outside of 1 to {size:?}"))]
+    Index {
+        ///The index that was found
+        index: i64,
+        ///The amount of rows, columns or elements that can be indexed
+        size: usize,
+    },
+    ///A constructed matrix like `I_n` needs a positive size
+    #[snafu(display("A matrix cannot have {size:?} rows or columns"))]
+    Size {
+        ///The size that was found
+        size: i64,
+    },
+    ///A constructed matrix like `I_n` or a range has too many elements
+    #[snafu(display("Expected at most {max:?} elements but found {found:?}"))]
+    TooLarge {
+        ///The amount of elements that was found
+        found: usize,
+        ///The maximum amount of elements
+        max: usize,
+    },
 }
 #[derive(Debug, Snafu)]
 ///All the ways we cant derive
@@ -0,0 +1,196 @@
+//! # Extrapolation
+//! Numerically estimating the limit of a function as its argument approaches
+//! zero from above.
+//!
+//! The function is sampled at steps that are halved every time, and the
+//! samples are combined using Richardson extrapolation, which removes the
+//! error terms that are proportional to powers of the step.
+use crate::prelude::*;
+
+/// The amount of times the function is sampled.
+pub const SAMPLES: usize = 10;
+
+/// The first and largest step the function is sampled at.
+const FIRST_STEP: f64 = 0.1;
+
+/// The relative tolerance for the extrapolated value to be accepted.
+const TOLERANCE: f64 = 1e-6;
+
+/// How much closer every sample has to be to a value than the one before for
+/// the function to approach it, see [approaches].
+const CONVERGENCE_RATIO: f64 = 0.99;
+
+/// The result of numerically estimating a limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Extrapolation {
+    /// The function approaches a finite value.
+    Finite(f64),
+    /// The function grows without bound, where the value is either positive
+    /// or negative infinity.
+    Infinite(f64),
+    /// The function does not approach any value, for example because it
+    /// oscillates.
+    NoLimit,
+}
+
+/// Estimates the limit of `func(h)` as `h` approaches zero from above.
+///
+/// # Errors
+/// Returns the first error returned by `func`.
+pub fn limit_at_zero<F>(func: F) -> Result<Extrapolation, EvalError>
+where
+    F: Fn(f64) -> Result<f64, EvalError>,
+{
+    let mut samples = Vec::with_capacity(SAMPLES);
+    let mut step = FIRST_STEP;
+    for _ in 0..SAMPLES {
+        samples.push(func(step)?);
+        step /= 2.0;
+    }
+    if samples.iter().any(|value| value.is_nan()) {
+        return Ok(Extrapolation::NoLimit);
+    }
+    if let Some(value) = richardson(&samples) {
+        return Ok(Extrapolation::Finite(value));
+    }
+    if grows_without_bound(&samples) {
+        let sign = samples[SAMPLES - 1].signum();
+        return Ok(Extrapolation::Infinite(sign * f64::INFINITY));
+    }
+    Ok(Extrapolation::NoLimit)
+}
+
+/// Whether `func(h)` approaches `value` as `h` approaches zero from above.
+///
+/// This is the case if the last sample is within the tolerance of `value`,
+/// or if the last samples keep getting closer to it, like `\sqrt{h}` does
+/// to 0. It is not the case at a jump, like `\lfloor 1-h\rfloor` which
+/// stays 0 away from 1.
+///
+/// # Errors
+/// Returns the first error returned by `func`.
+pub fn approaches<F>(func: F, value: f64) -> Result<bool, EvalError>
+where
+    F: Fn(f64) -> Result<f64, EvalError>,
+{
+    let mut distances = Vec::with_capacity(SAMPLES);
+    let mut step = FIRST_STEP;
+    for _ in 0..SAMPLES {
+        distances.push((func(step)? - value).abs());
+        step /= 2.0;
+    }
+    if distances.iter().any(|distance| distance.is_nan()) {
+        return Ok(false);
+    }
+    if distances[SAMPLES - 1] <= TOLERANCE * value.abs().max(1.0) {
+        return Ok(true);
+    }
+    Ok(distances[SAMPLES - 4..]
+        .windows(2)
+        .all(|pair| pair[1] <= CONVERGENCE_RATIO * pair[0]))
+}
+
+/// Combines the samples using Richardson extrapolation and returns the
+/// estimate that is closest to the previous estimate, if it is within the
+/// tolerance.
+fn richardson(samples: &[f64]) -> Option<f64> {
+    let mut previous_row: Vec<f64> = Vec::new();
+    let mut best: Option<(f64, f64)> = None;
+    for (i, sample) in samples.iter().enumerate() {
+        let mut row = Vec::with_capacity(i + 1);
+        row.push(*sample);
+        for j in 1..=i {
+            let factor = 2f64.powi(j as i32) - 1.0;
+            row.push(row[j - 1] + (row[j - 1] - previous_row[j - 1]) / factor);
+        }
+        if let Some(previous) = previous_row.last() {
+            let estimate = row[i];
+            let error = (estimate - previous).abs();
+            if best.is_none_or(|(_, best_error)| error < best_error) {
+                best = Some((estimate, error));
+            }
+        }
+        previous_row = row;
+    }
+    let (estimate, error) = best?;
+    if !estimate.is_finite() || error > TOLERANCE * estimate.abs().max(1.0) {
+        return None;
+    }
+    Some(estimate)
+}
+
+/// Whether the last samples keep the same sign and grow in magnitude by
+/// steps that do not shrink, like `1/h` or `\ln h`.
+fn grows_without_bound(samples: &[f64]) -> bool {
+    let last = &samples[samples.len() - 4..];
+    let same_sign = last.iter().all(|v| v.signum() == last[0].signum());
+    let differences: Vec<f64> = last
+        .windows(2)
+        .map(|pair| pair[1].abs() - pair[0].abs())
+        .collect();
+    same_sign
+        && differences.iter().all(|d| *d > 0.0)
+        && differences.windows(2).all(|pair| pair[1] >= 0.9 * pair[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{approaches, limit_at_zero, Extrapolation};
+
+    fn finite_test(expected: f64, func: fn(f64) -> f64) {
+        match limit_at_zero(|h| Ok(func(h))).unwrap() {
+            Extrapolation::Finite(found) if (found - expected).abs() < 1e-6 => {
+            }
+            found => panic!("Found {:?} expected {}", found, expected),
+        }
+    }
+
+    #[test]
+    fn sine_over_argument() {
+        finite_test(1.0, |h| h.sin() / h);
+    }
+
+    #[test]
+    fn one_minus_cosine() {
+        finite_test(0.5, |h| (1.0 - h.cos()) / (h * h));
+    }
+
+    #[test]
+    fn compound_interest() {
+        finite_test(std::f64::consts::E, |h| (1.0 + h).powf(1.0 / h));
+    }
+
+    #[test]
+    fn reciprocal_is_infinite() {
+        assert_eq!(
+            limit_at_zero(|h| Ok(-1.0 / h)).unwrap(),
+            Extrapolation::Infinite(f64::NEG_INFINITY)
+        );
+    }
+
+    #[test]
+    fn logarithm_is_infinite() {
+        assert_eq!(
+            limit_at_zero(|h| Ok(h.ln())).unwrap(),
+            Extrapolation::Infinite(f64::NEG_INFINITY)
+        );
+    }
+
+    #[test]
+    fn approaching_values() {
+        assert!(approaches(|h| Ok(1.0 + h * h), 1.0).unwrap());
+        assert!(approaches(|h| Ok(h.sqrt()), 0.0).unwrap());
+        // A jump
+        assert!(!approaches(|h| Ok((1.0 - h).floor()), 1.0).unwrap());
+        // Getting closer to 1 instead of 2
+        assert!(!approaches(|h| Ok(1.0 + h), 2.0).unwrap());
+    }
+
+    #[test]
+    fn oscillating_has_no_limit() {
+        assert_eq!(
+            limit_at_zero(|h| Ok((1.0 / h).sin())).unwrap(),
+            Extrapolation::NoLimit
+        );
+    }
+}
@@ -7,58 +7,275 @@ pub type InnerMathFunction =
 ///The inner type alias for the derivative function
 pub type InnerDeriveFunction =
     Arc<dyn Fn(Vec<MathExpr>) -> Result<MathExpr, EvalError> + Send + Sync>;
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    fmt::{Debug, Display},
+    sync::Arc,
+};
+
+use num_complex::Complex64;
 
 use crate::{
-    ast::{helper::Simple, simplify::Simplify},
+    ast::{helper::NumberCompare, simplify::Simplify},
     prelude::*,
 };
 
+/// The kind of value a parameter of a native function accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParameterKind {
+    /// A real number, or a 1x1 matrix of one.
+    Scalar,
+    /// A real or complex number, or a 1x1 matrix of one.
+    Complex,
+    /// A row or column vector with this many elements.
+    Vector(usize),
+    /// A matrix of any size.
+    Matrix,
+    /// Any value.
+    Any,
+}
+impl ParameterKind {
+    /// Whether a value of this kind can be passed as the argument.
+    pub fn accepts(&self, value: &Value) -> bool {
+        match (self, value) {
+            (ParameterKind::Any, _) => true,
+            (ParameterKind::Scalar, _) => value.number().is_ok(),
+            (ParameterKind::Complex, _) => value.complex().is_ok(),
+            (ParameterKind::Vector(size), Value::Matrix(m)) => {
+                m.is_vector() && m.row_count() * m.column_count() == *size
+            }
+            (ParameterKind::Matrix, Value::Matrix(_)) => true,
+            (_, Value::Scalar(_) | Value::Complex(_)) => false,
+        }
+    }
+}
+impl Display for ParameterKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParameterKind::Scalar => write!(f, "a real number"),
+            ParameterKind::Complex => write!(f, "a real or complex number"),
+            ParameterKind::Vector(size) => {
+                write!(f, "a vector of size {}", size)
+            }
+            ParameterKind::Matrix => write!(f, "a matrix"),
+            ParameterKind::Any => write!(f, "any value"),
+        }
+    }
+}
+
+/// The parameters of one overload of a native function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    /// The kinds of the parameters, in order.
+    pub parameters: Vec<ParameterKind>,
+    /// The kind of any extra arguments after the parameters, if the function
+    /// takes a variable amount of arguments.
+    pub variadic: Option<ParameterKind>,
+}
+impl Signature {
+    /// A signature with a fixed amount of parameters.
+    pub const fn new(parameters: Vec<ParameterKind>) -> Self {
+        Self {
+            parameters,
+            variadic: None,
+        }
+    }
+    /// A signature that takes any amount of extra arguments of the kind
+    /// `rest` after the parameters, for example `max(a, b, ...)`.
+    pub const fn variadic(
+        parameters: Vec<ParameterKind>,
+        rest: ParameterKind,
+    ) -> Self {
+        Self {
+            parameters,
+            variadic: Some(rest),
+        }
+    }
+    /// A signature with `count` parameters of the same kind.
+    pub fn repeat(kind: ParameterKind, count: usize) -> Self {
+        Self::new(vec![kind; count])
+    }
+    /// Whether the function can be called with `count` arguments.
+    pub const fn accepts_count(&self, count: usize) -> bool {
+        match self.variadic {
+            Some(_) => count >= self.parameters.len(),
+            None => count == self.parameters.len(),
+        }
+    }
+    /// The kind of the argument at `index`.
+    fn kind(&self, index: usize) -> Option<ParameterKind> {
+        self.parameters.get(index).copied().or(self.variadic)
+    }
+    /// Checks the kind of every argument.
+    ///
+    /// # Errors
+    /// [EvalError::ArgumentType] naming the first argument that has the
+    /// wrong kind.
+    pub fn check(&self, arguments: &[Value]) -> Result<(), EvalError> {
+        for (index, argument) in arguments.iter().enumerate() {
+            let Some(kind) = self.kind(index) else {
+                continue;
+            };
+            if !kind.accepts(argument) {
+                return Err(EvalError::ArgumentType {
+                    position: index + 1,
+                    expected: kind,
+                    found: describe(argument),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describes the kind of a value for error messages, for example "a 2x3
+/// matrix".
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Scalar(_) => "a scalar".to_owned(),
+        Value::Complex(_) => "a complex number".to_owned(),
+        Value::Matrix(m) => {
+            format!("a {}x{} matrix", m.row_count(), m.column_count())
+        }
+    }
+}
+
+/// One overload of a native function.
+#[derive(Clone)]
+struct Overload {
+    /// The parameters it accepts.
+    signature: Signature,
+    /// The function to run.
+    approximate: InnerMathFunction,
+}
+
 #[derive(Clone)]
 /// A native function that is implemented in rust
+///
+/// A function may have several overloads, which are tried in the order they
+/// were added until one accepts the arguments.
 pub struct NativeFunction {
-    ///The function to run
-    approximate: InnerMathFunction,
-    ///the amount of acceptable arguments
-    arguments: usize,
+    ///The overloads of the function
+    overloads: Vec<Overload>,
     ///the derivation
     derivative: InnerDeriveFunction,
+    ///whether the order of the arguments does not matter, see
+    /// [NativeFunction::symmetric]
+    symmetric: bool,
 }
 impl Debug for NativeFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "{{NativeFunction}}, args:{}", self.arguments)
+        let signatures: Vec<_> = self.signatures().collect();
+        writeln!(f, "{{NativeFunction}}, signatures:{:?}", signatures)
     }
 }
 impl NativeFunction {
-    ///New native function
-    fn new(
-        approximate: InnerMathFunction,
-        arguments: usize,
-        derivative: InnerDeriveFunction,
-    ) -> Self {
+    ///New native function with a single overload that can not be derived
+    pub fn new<F>(signature: Signature, approximate: F) -> Self
+    where
+        F: Fn(Vec<Value>) -> Result<Value, EvalError> + Send + Sync + 'static,
+    {
         Self {
-            approximate,
-            arguments,
-            derivative,
+            overloads: vec![Overload {
+                signature,
+                approximate: Arc::new(approximate),
+            }],
+            derivative: Arc::new(|_| {
+                Err(DeriveError::All {
+                    message: "can not derive this function".to_owned(),
+                }
+                .into())
+            }),
+            symmetric: false,
         }
     }
-    ///Running the function
+    /// Adds an overload, which is tried after the previous ones.
+    pub fn overload<F>(mut self, signature: Signature, approximate: F) -> Self
+    where
+        F: Fn(Vec<Value>) -> Result<Value, EvalError> + Send + Sync + 'static,
+    {
+        self.overloads.push(Overload {
+            signature,
+            approximate: Arc::new(approximate),
+        });
+        self
+    }
+    /// Sets the derivative, which takes the arguments and returns the
+    /// derivative with respect to the first argument.
+    pub fn with_derivative(mut self, derivative: InnerDeriveFunction) -> Self {
+        self.derivative = derivative;
+        self
+    }
+    /// Marks the function as symmetric, like `\max`, so the order of the
+    /// arguments does not matter. It can then be derived with respect to
+    /// every argument, by moving that argument to the front.
+    pub const fn symmetric(mut self) -> Self {
+        self.symmetric = true;
+        self
+    }
+    /// The signatures of the overloads, in order.
+    pub fn signatures(&self) -> impl Iterator<Item = &Signature> {
+        self.overloads.iter().map(|overload| &overload.signature)
+    }
+    /// Whether an overload accepts the arguments.
+    pub fn accepts(&self, args: &[Value]) -> bool {
+        self.signatures().any(|signature| {
+            signature.accepts_count(args.len()) && signature.check(args).is_ok()
+        })
+    }
+    /// The error for when no overload takes `found` arguments.
+    ///
+    /// A variadic overload takes any amount of arguments from its amount of
+    /// parameters on, so only the fixed amounts below that are listed.
+    fn length_mismatch(&self, found: usize) -> EvalError {
+        let minimum = self
+            .signatures()
+            .filter(|signature| signature.variadic.is_some())
+            .map(|signature| signature.parameters.len())
+            .min();
+        let mut expected: Vec<usize> = self
+            .signatures()
+            .map(|signature| signature.parameters.len())
+            .filter(|&count| minimum.is_none_or(|minimum| count < minimum))
+            .collect();
+        expected.sort_unstable();
+        expected.dedup();
+        match minimum {
+            Some(minimum) if expected.is_empty() => {
+                EvalError::TooFewArguments { minimum, found }
+            }
+            Some(minimum) => {
+                expected.push(minimum);
+                EvalError::ArgumentLengthMismatch { expected, found }
+            }
+            None => EvalError::ArgumentLengthMismatch { expected, found },
+        }
+    }
+    ///Running the function, using the first overload that accepts the
+    /// arguments
+    ///
+    /// # Errors
+    /// [EvalError::ArgumentLengthMismatch] or [EvalError::TooFewArguments]
+    /// if no overload takes this amount of arguments, [EvalError::ArgumentType] if an argument has the wrong
+    /// kind for every overload that does, or the error of the function.
     pub fn run(&self, args: Vec<Value>) -> Result<Value, EvalError> {
-        if args.len() != self.arguments {
-            return Err(EvalError::ArgumentLengthMismatch {
-                expected: vec![1],
-                found: args.len(),
-            });
+        let mut type_error = None;
+        for overload in &self.overloads {
+            if !overload.signature.accepts_count(args.len()) {
+                continue;
+            }
+            match overload.signature.check(&args) {
+                Ok(()) => return (overload.approximate)(args),
+                Err(err) => {
+                    type_error.get_or_insert(err);
+                }
+            }
         }
-        (self.approximate)(args)
+        Err(type_error.unwrap_or_else(|| self.length_mismatch(args.len())))
     }
     ///takes the derivative of the function
     fn derivate(&self, v: Vec<MathExpr>) -> Result<MathExpr, EvalError> {
-        if v.len() != self.arguments {
-            return Err(EvalError::ArgumentLengthMismatch {
-                expected: vec![1],
-                found: v.len(),
-            });
+        if !self.signatures().any(|s| s.accepts_count(v.len())) {
+            return Err(self.length_mismatch(v.len()));
         }
         (self.derivative)(v)
     }
@@ -84,19 +301,14 @@ impl MathFunction {
     ///Helper new for native functions
     fn new_native(
         func: InnerMathFunction,
-        arguments: usize,
+        signature: Signature,
         derivative: Option<InnerDeriveFunction>,
     ) -> Self {
-        Self::Native(NativeFunction::new(
-            func,
-            arguments,
-            derivative.unwrap_or(Arc::new(|_| {
-                Err(DeriveError::All {
-                    message: "can not derive this function".to_owned(),
-                }
-                .into())
-            })),
-        ))
+        let native = NativeFunction::new(signature, move |v| func(v));
+        Self::Native(match derivative {
+            Some(derivative) => native.with_derivative(derivative),
+            None => native,
+        })
     }
     /// Helper new for foreign functions
     pub const fn new_foreign(
@@ -111,7 +323,11 @@ impl MathFunction {
         arguments: usize,
         derivative: Option<InnerDeriveFunction>,
     ) -> Self {
-        Self::new_native(Arc::new(func), arguments, derivative)
+        Self::new_native(
+            Arc::new(func),
+            Signature::repeat(ParameterKind::Any, arguments),
+            derivative,
+        )
     }
     ///Helper new for fn pointers with f64s
     pub fn from_fn_pointer_expecting_scalars<
@@ -125,9 +341,9 @@ impl MathFunction {
             Arc::new(move |v: Vec<Value>| {
                 let v_new: Result<Vec<f64>, EvalError> =
                     v.into_iter().map(|val| val.scalar()).collect();
-                Ok(Value::Scalar(func(v_new?)))
+                Ok(Value::from(func(v_new?)))
             }),
-            arguments,
+            Signature::repeat(ParameterKind::Scalar, arguments),
             derivative,
         )
     }
@@ -143,9 +359,35 @@ impl MathFunction {
         Self::new_native(
             Arc::new(move |v: Vec<Value>| {
                 let s = v[0].scalar()?;
-                Ok(Value::Scalar(func(s)))
+                Ok(Value::from(func(s)))
             }),
-            arguments,
+            Signature::repeat(ParameterKind::Scalar, arguments),
+            Some(single_var_derivation_function(deriv)),
+        )
+    }
+    ///Helper function for creating a function of a single real or complex
+    /// number, where `real` is used when the argument and the result are
+    /// real and `complex` otherwise, for example for `\ln(-1)`.
+    ///
+    /// `real` gets the number as is, so it can be approximated with the
+    /// precision of the number.
+    pub fn from_fn_pointer_expecting_single_complex<
+        F: Fn(&Number) -> Number + Send + Sync + 'static,
+        C: Fn(Complex64) -> Complex64 + Send + Sync + 'static,
+        D: Fn(MathExpr) -> Result<MathExpr, EvalError> + Send + Sync + 'static,
+    >(
+        real: F,
+        complex: C,
+        deriv: D,
+    ) -> Self {
+        Self::new_native(
+            Arc::new(move |v: Vec<Value>| {
+                let z = v[0].complex()?;
+                Ok(real_or_complex(v[0].number().ok().map(&real), || {
+                    complex(z)
+                }))
+            }),
+            Signature::repeat(ParameterKind::Complex, 1),
             Some(single_var_derivation_function(deriv)),
         )
     }
@@ -160,27 +402,98 @@ impl MathFunction {
         Self::new_native(
             Arc::new(move |v: Vec<Value>| {
                 let s = v[0].scalar()?;
-                Ok(Value::Scalar(func(s)))
+                Ok(Value::from(func(s)))
             }),
-            arguments,
+            Signature::repeat(ParameterKind::Scalar, arguments),
             None,
         )
     }
-    ///take the derivative of the function
+    /// Takes the derivative of the function called with `arguments` with
+    /// respect to `dependent`.
+    ///
+    /// Native functions use the chain rule with their stored derivative,
+    /// which is with respect to the first argument, so the other arguments
+    /// may not depend on `dependent`. Foreign functions are inlined and their
+    /// expression is differentiated.
     pub fn derivate(
         &self,
-        val: Vec<MathExpr>,
+        arguments: &[MathExpr],
+        dependent: &MathIdentifier,
         cont: &MathContext,
-        _dependant: MathIdentifier,
-    ) -> Result<Simple, EvalError> {
+    ) -> Result<MathExpr, EvalError> {
         match self {
             MathFunction::Native(n) => {
-                n.derivate(val).map(|v| v.simple(cont))?
+                let [_, rest @ ..] = arguments else {
+                    return Err(DeriveError::All {
+                        message: "can not derive a function without arguments"
+                            .to_owned(),
+                    }
+                    .into());
+                };
+                // The chain rule for the argument at the front
+                let chain = |arguments: Vec<MathExpr>| {
+                    let outer = n.derivate(arguments.clone())?;
+                    Ok::<_, EvalError>(Term::Multiply(
+                        MulType::Implicit,
+                        outer.get_term_or_wrap().boxed(),
+                        arguments[0]
+                            .derivative(dependent, cont)?
+                            .get_factor_or_wrap(),
+                    ))
+                };
+                if n.symmetric {
+                    let mut sum = MathExpr::Term(chain(arguments.to_vec())?);
+                    for i in 1..arguments.len() {
+                        let mut moved = arguments.to_vec();
+                        let argument = moved.remove(i);
+                        moved.insert(0, argument);
+                        sum = MathExpr::Add(sum.boxed(), chain(moved)?);
+                    }
+                    return Ok(sum);
+                }
+                for other in rest {
+                    let derivative = other
+                        .derivative(dependent, cont)?
+                        .simple(&cont.unbind(dependent))?;
+                    if !matches!(derivative.factor(), Some(Factor::Constant(c)) if c.is_zero())
+                    {
+                        return Err(DeriveError::All {
+                            message: "can only derive native functions with respect to their first argument".to_owned(),
+                        }
+                        .into());
+                    }
+                }
+                Ok(chain(arguments.to_vec())?.into())
+            }
+            MathFunction::Foreign(f) => {
+                if arguments.len() != f.input.len() {
+                    return Err(EvalError::ArgumentLengthMismatch {
+                        expected: vec![f.input.len()],
+                        found: arguments.len(),
+                    });
+                }
+                let bindings: Vec<_> = f
+                    .input
+                    .iter()
+                    .cloned()
+                    .zip(arguments.iter().cloned())
+                    .collect();
+                f.expr.substitute_all(&bindings).derivative(dependent, cont)
             }
-            MathFunction::Foreign(_f) => todo!(),
         }
     }
 }
+/// The real result if there is one, and otherwise the complex result, for
+/// example of the square root of a negative number.
+pub(crate) fn real_or_complex(
+    real: Option<Number>,
+    complex: impl FnOnce() -> Complex64,
+) -> Value {
+    match real {
+        Some(result) if !result.is_nan() => Value::Scalar(result),
+        _ => Value::from(complex()),
+    }
+}
 ///helper function to be able to ensure the function only takes 1 argument
 fn single_var_derivation_function<
     D: Fn(MathExpr) -> Result<MathExpr, EvalError> + Send + Sync + 'static,
@@ -213,7 +526,16 @@ where
     F: Fn(Vec<Value>) -> Result<Value, EvalError> + Send + Sync + 'static,
 {
     fn into_math_function(self) -> MathFunction {
-        MathFunction::new_native(Arc::new(self.0), self.1, self.2)
+        MathFunction::new_native(
+            Arc::new(self.0),
+            Signature::repeat(ParameterKind::Any, self.1),
+            self.2,
+        )
+    }
+}
+impl IntoMathFunction for NativeFunction {
+    fn into_math_function(self) -> MathFunction {
+        MathFunction::Native(self)
     }
 }
 impl IntoMathFunction for MathFunction {
@@ -231,6 +553,18 @@ where
         MathFunction::from_fn_pointer_expecting_single_scalar(self.0, 1, self.1)
     }
 }
+impl<F, C, D> IntoMathFunction for (F, C, D)
+where
+    F: Fn(&Number) -> Number + Send + Sync + 'static,
+    C: Fn(Complex64) -> Complex64 + Send + Sync + 'static,
+    D: Fn(MathExpr) -> Result<MathExpr, EvalError> + Send + Sync + 'static,
+{
+    fn into_math_function(self) -> MathFunction {
+        MathFunction::from_fn_pointer_expecting_single_complex(
+            self.0, self.1, self.2,
+        )
+    }
+}
 impl<F: Fn(f64) -> f64> IntoMathFunction for F
 where
     F: Send + Sync + 'static,
@@ -241,3 +575,100 @@ where
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use num_complex::Complex64;
+
+    use crate::prelude::*;
+
+    fn sum(args: Vec<Value>) -> Result<Value, EvalError> {
+        let mut total = 0.0;
+        for arg in args {
+            total += arg.scalar()?;
+        }
+        Ok(Value::from(total))
+    }
+
+    fn vector() -> Value {
+        Value::Matrix(Matrix::new(
+            vec![Value::from(1.0), Value::from(2.0)],
+            2,
+            1,
+        ))
+    }
+
+    #[test]
+    fn argument_count_lists_every_overload() {
+        let f = NativeFunction::new(
+            Signature::repeat(ParameterKind::Scalar, 1),
+            sum,
+        )
+        .overload(Signature::repeat(ParameterKind::Scalar, 3), sum);
+        assert!(matches!(
+            f.run(vec![]),
+            Err(EvalError::ArgumentLengthMismatch { expected, found: 0 })
+                if expected == vec![1, 3]
+        ));
+    }
+
+    #[test]
+    fn type_error_names_the_argument() {
+        let f = NativeFunction::new(
+            Signature::repeat(ParameterKind::Scalar, 2),
+            sum,
+        );
+        let err = f.run(vec![Value::from(1.0), vector()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Argument 2 should be a real number, but found a 2x1 matrix"
+        );
+        let i = Value::from(Complex64::new(0.0, 1.0));
+        let err = f.run(vec![Value::from(1.0), i.clone()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Argument 2 should be a real number, but found a complex number"
+        );
+        let g = NativeFunction::new(
+            Signature::repeat(ParameterKind::Complex, 1),
+            |v| Ok(v[0].clone()),
+        );
+        assert_eq!(g.run(vec![i.clone()]).unwrap(), i);
+    }
+
+    #[test]
+    fn overload_is_chosen_by_kind() {
+        let f = NativeFunction::new(
+            Signature::new(vec![ParameterKind::Vector(2)]),
+            |_| Ok(Value::from(2.0)),
+        )
+        .overload(Signature::new(vec![ParameterKind::Scalar]), sum);
+        assert_eq!(f.run(vec![vector()]).unwrap(), Value::from(2.0));
+        assert_eq!(f.run(vec![Value::from(5.0)]).unwrap(), Value::from(5.0));
+    }
+
+    #[test]
+    fn variadic_takes_extra_arguments() {
+        let f = NativeFunction::new(
+            Signature::variadic(
+                vec![ParameterKind::Scalar],
+                ParameterKind::Scalar,
+            ),
+            sum,
+        );
+        let args = vec![Value::from(1.0); 4];
+        assert_eq!(f.run(args).unwrap(), Value::from(4.0));
+        let err = f.run(vec![]).unwrap_err();
+        assert!(matches!(
+            err,
+            EvalError::TooFewArguments {
+                minimum: 1,
+                found: 0
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Expected at least 1 arguments, but found 0"
+        );
+    }
+}
@@ -1,14 +1,13 @@
 //! A module describing mathematical identifiers used in variable and function
 //! names.
 
-use crate::ast::to_latex::ToLaTeX;
 use crate::prelude::*;
 use std::hash::Hash;
 
 /// A mathematical identifier, for example variable or function names.
 ///
 /// Examples of valid math identifiers: "x", "x_1", "F_g", "\overline{v}".
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub enum MathIdentifier {
     /// Identified by a name, for example "x".
     Name(MathString),
@@ -31,26 +30,6 @@ pub enum MathIdentifier {
     Modifier(ModifierType, Box<MathExpr>),
 }
 
-// TODO this is a bit of a hack since MathExpr doesn't implement Hash or Eq.
-// We might want to move away from using f64 for Factor::Constant so that
-// all AST structs can implement Eq and Hash.
-impl Eq for MathIdentifier {}
-impl Hash for MathIdentifier {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        match self {
-            MathIdentifier::Name(s) => s.hash(state),
-            MathIdentifier::Index { name, index } => {
-                name.hash(state);
-                index.to_latex().hash(state);
-            }
-            MathIdentifier::Modifier(m, i) => {
-                m.hash(state);
-                i.to_latex().hash(state);
-            }
-        }
-    }
-}
-
 impl MathIdentifier {
     /// Create a MathIdentifier from a single string. For example "x".
     ///
@@ -76,6 +55,81 @@ impl MathIdentifier {
     pub fn from_single_symbol(symbol: OtherSymbol) -> Self {
         Self::Name(MathString::from_letters(vec![MathLetter::Other(symbol)]))
     }
+
+    /// Create a MathIdentifier for an operator written with
+    /// `\operatorname`. For example "lcm" for `\operatorname{lcm}`.
+    ///
+    /// The name is stored the same way the parser reads the inner
+    /// expression of a modifier, as its letters multiplied together.
+    pub fn from_operator_name(name: &str) -> Self {
+        let mut letters = name
+            .chars()
+            .map(|c| Factor::from(Self::from_single_ident(&c.to_string())));
+        let first = Term::Factor(letters.next().expect("empty operator name"));
+        let term = letters.fold(first, |term, letter| {
+            Term::Multiply(MulType::Implicit, term.boxed(), letter)
+        });
+        Self::Modifier(ModifierType::Operatorname, MathExpr::Term(term).boxed())
+    }
+
+    /// The imaginary unit `\mathrm{i}`, see [crate::value::Value::Complex].
+    pub fn imaginary_unit() -> Self {
+        Self::Modifier(
+            ModifierType::Mathrm,
+            MathExpr::from(Self::from_single_ident("i")).boxed(),
+        )
+    }
+
+    /// The entries of the index of an indexed identifier, where `A_{2,3}`
+    /// has the entries `2` and `3`, or None for other identifiers.
+    pub fn index_entries(&self) -> Option<Vec<&MathExpr>> {
+        let Self::Index { index, .. } = self else {
+            return None;
+        };
+        Some(match index.as_ref() {
+            MathExpr::Term(Term::Factor(Factor::Matrix(m, _)))
+                if m.is_row_vector() =>
+            {
+                (0..m.column_count())
+                    .map(|column| m.get(0, column))
+                    .collect()
+            }
+            index => vec![index],
+        })
+    }
+
+    /// The name of an operator created by
+    /// [MathIdentifier::from_operator_name], or None for other identifiers.
+    pub fn operator_name(&self) -> Option<String> {
+        /// Collects the letters of the product from left to right.
+        fn letters(term: &Term, name: &mut String) -> Option<()> {
+            let letter = match term {
+                Term::Factor(letter) => letter,
+                Term::Multiply(MulType::Implicit, lhs, letter) => {
+                    letters(lhs, name)?;
+                    letter
+                }
+                _ => return None,
+            };
+            let Factor::Variable(MathIdentifier::Name(s), _) = letter else {
+                return None;
+            };
+            let [MathLetter::Ascii(c)] = s.letters()[..] else {
+                return None;
+            };
+            name.push(c as char);
+            Some(())
+        }
+        let Self::Modifier(ModifierType::Operatorname, inner) = self else {
+            return None;
+        };
+        let MathExpr::Term(term) = inner.as_ref() else {
+            return None;
+        };
+        let mut name = String::new();
+        letters(term, &mut name)?;
+        Some(name)
+    }
 }
 
 /// A macro for creating an enum with LaTeX.
@@ -104,6 +158,9 @@ macro_rules! enum_with_latex {
     }
 
         impl $name {
+            /// The LaTeX code of every variant.
+            pub const LATEX_CODES: &'static [&'static str] = &[$($latex),*];
+
             /// Get the LaTeX code for this enum variant.
             pub const fn latex_code(&self) -> &'static str {
                 match self {
@@ -136,12 +193,15 @@ enum_with_latex!(ModifierType {
     Text => "text",
     Mathbb => "mathbb",
     Mathcal => "mathcal",
+    Mathrm => "mathrm",
+    Operatorname => "operatorname",
 });
 
 /// A string of mathematical letters that may consist of greek letters and other
 /// math symbols.
 #[derive(Eq, PartialEq, Debug, Hash, Clone)]
 pub struct MathString {
+    /// The letters that make up the string.
     vec: Vec<MathLetter>,
 }
 
@@ -244,7 +304,26 @@ enum_with_latex!(OtherSymbol {
     Sin => "sin",
     Cos => "cos",
     Tan => "tan",
+    Sec => "sec",
+    Csc => "csc",
+    Cot => "cot",
+    Arcsin => "arcsin",
+    Arccos => "arccos",
+    Arctan => "arctan",
+    Sinh => "sinh",
+    Cosh => "cosh",
+    Tanh => "tanh",
+    Exp => "exp",
     Ln => "ln",
+    Log => "log",
+    Lg => "lg",
+    Max => "max",
+    Min => "min",
+    Gcd => "gcd",
+    Det => "det",
+    Top => "top",
+    Angle => "angle",
+    Infty => "infty",
 });
 
 #[cfg(test)]
@@ -4,38 +4,62 @@ use std::mem::take;
 use tracing::{debug, trace, trace_span};
 ///The lexer creating tokens from a string
 pub struct Lexer {
-    ///where it sends the tokens
-    channel: TokenSender,
+    ///where it writes the tokens
+    output: Vec<SpannedToken>,
 }
 
 impl Lexer {
-    ///creating a new Lexer
-    pub fn new(channel: TokenSender) -> Self {
-        trace!("created Lexer");
-        Self { channel }
+    ///tokenizing all of the string, ending with `Token::EndOfContent`
+    pub fn tokenize(s: &str) -> Vec<SpannedToken> {
+        // Every character is at most one token, and then the end of content
+        let mut lexer = Self {
+            output: Vec::with_capacity(s.len() + 1),
+        };
+        lexer.tokenize_into_output(s);
+        lexer.output
     }
-    ///for sending or crashing when the pipe is broken
-    async fn send_or_crash(&self, token: Token) {
-        trace!("send_or_crash token={token}");
-        self.channel.send(token).await.expect("Broken Pipe")
+    ///for writing a token to the output
+    fn send(&mut self, token: Token, span: Span) {
+        trace!("send token={token} span={span}");
+        self.output.push(SpannedToken::new(token, span))
     }
-    ///The main function of the Lexer, will create tokens and send them away
-    pub async fn tokenize(self, s: &str) {
+    ///sends the identifier or number being read, if any, ending at `end`
+    fn send_pending(
+        &mut self,
+        pending: &mut String,
+        end: usize,
+        token: fn(String) -> Token,
+    ) {
+        if pending.is_empty() {
+            return;
+        }
+        let start = end - pending.chars().count();
+        self.send(token(take(pending)), Span::new(start, end));
+    }
+    ///sends the number and then the identifier being read, if any
+    fn send_all_pending(
+        &mut self,
+        temp_ident: &mut String,
+        temp_number: &mut String,
+        end: usize,
+    ) {
+        self.send_pending(temp_number, end, number);
+        self.send_pending(temp_ident, end, Token::Identifier);
+    }
+    ///creating the tokens and writing them to the output
+    fn tokenize_into_output(&mut self, s: &str) {
         let span = trace_span!("lexer::tokenize");
         let _enter = span.enter();
         debug!("tokenizing: {s:?}");
         let mut temp_ident = String::new();
         let mut temp_number = String::new();
-        for c in s.chars() {
+        let mut len = 0;
+        for (i, c) in s.chars().enumerate() {
             trace!("char = {c:?}");
+            len = i + 1;
             let t = match c {
                 '0'..='9' | '.' => {
-                    if !temp_ident.is_empty() {
-                        self.send_or_crash(Token::Identifier(take(
-                            &mut temp_ident,
-                        )))
-                        .await;
-                    }
+                    self.send_pending(&mut temp_ident, i, Token::Identifier);
                     trace!("temp_number::push char={c:?}");
                     temp_number.push(c);
                     continue;
@@ -55,89 +79,56 @@ impl Lexer {
                 '/' => Token::Slash,
                 ',' => Token::Comma,
                 '&' => Token::Ampersand,
+                ';' => Token::Semicolon,
                 '=' => Token::Equals,
                 '(' => Token::LeftParenthesis,
                 ')' => Token::RightParenthesis,
                 ' ' => {
-                    if !temp_number.is_empty() {
-                        let num = Token::NumberLiteral(temp_number.into());
-                        temp_number = String::new();
-                        self.send_or_crash(num).await;
-                    }
-                    if !temp_ident.is_empty() {
-                        self.send_or_crash(Token::Identifier(take(
-                            &mut temp_ident,
-                        )))
-                        .await;
-                    }
+                    self.send_all_pending(&mut temp_ident, &mut temp_number, i);
                     continue;
                 }
                 _ => {
-                    if !temp_number.is_empty() {
-                        let num = Token::NumberLiteral(temp_number.into());
-                        temp_number = String::new();
-                        self.send_or_crash(num).await;
-                    }
-
+                    self.send_pending(&mut temp_number, i, number);
                     trace!("temp_ident::push char={c:?}");
                     temp_ident.push(c);
                     continue;
                 }
             };
-            if !temp_number.is_empty() {
-                let num = Token::NumberLiteral(temp_number.into());
-                temp_number = String::new();
-                self.send_or_crash(num).await;
-            }
-            if !temp_ident.is_empty() {
-                self.send_or_crash(Token::Identifier(take(&mut temp_ident)))
-                    .await;
-            }
-
-            self.send_or_crash(t).await;
-        }
-        if !temp_number.is_empty() {
-            let num = Token::NumberLiteral(
-                temp_number
-                    .parse()
-                    .expect("THIS NEEDS FIXING IT FAILED TO PARSE NUMBER"),
-            );
-
-            self.send_or_crash(num).await;
-        }
-        if !temp_ident.is_empty() {
-            self.send_or_crash(Token::Identifier(take(&mut temp_ident)))
-                .await;
+            self.send_all_pending(&mut temp_ident, &mut temp_number, i);
+            self.send(t, Span::new(i, i + 1));
         }
-        self.send_or_crash(Token::EndOfContent).await;
+        self.send_all_pending(&mut temp_ident, &mut temp_number, len);
+        self.send(Token::EndOfContent, Span::new(len, len));
     }
 }
 
+///a number literal token
+fn number(raw: String) -> Token {
+    Token::NumberLiteral(raw.into())
+}
+
 #[cfg(test)]
 mod tests {
 
     use crate::prelude::*;
     use pretty_assertions::assert_eq;
-    async fn tokenize(text: &str) -> Vec<Token> {
-        let (tx, mut rx): (TokenSender, TokenReceiver) = mpsc::channel(32);
-        let lexer = Lexer::new(tx);
-
-        lexer.tokenize(text).await;
-
-        let mut vec = Vec::new();
-        while let Some(t) = rx.recv().await {
-            if t == Token::EndOfContent {
-                break;
-            }
-            vec.push(t);
-        }
-        vec
+    fn tokenize(text: &str) -> Vec<Token> {
+        tokenize_spanned(text)
+            .into_iter()
+            .map(|t| t.token)
+            .collect()
+    }
+    fn tokenize_spanned(text: &str) -> Vec<SpannedToken> {
+        let mut tokens = Lexer::tokenize(text);
+        let end = tokens.pop().map(|t| t.token);
+        assert_eq!(end, Some(Token::EndOfContent));
+        tokens
     }
 
-    #[tokio::test]
-    async fn test_simple_sqrt() {
+    #[test]
+    fn test_simple_sqrt() {
         assert_eq!(
-            tokenize("\\sqrt{1+2x}").await,
+            tokenize("\\sqrt{1+2x}"),
             vec![
                 Token::Backslash,
                 Token::Identifier("sqrt".to_string()),
@@ -150,18 +141,18 @@ mod tests {
             ]
         );
     }
-    #[tokio::test]
-    async fn test_all_simple_operations() {
+    #[test]
+    fn test_all_simple_operations() {
         assert_eq!(
-            tokenize("-+*/").await,
+            tokenize("-+*/"),
             vec![Token::Minus, Token::Plus, Token::Asterisk, Token::Slash]
         );
     }
 
-    #[tokio::test]
-    async fn test_single_character_tokens() {
+    #[test]
+    fn test_single_character_tokens() {
         assert_eq!(
-            tokenize("()[]{}^'|").await,
+            tokenize("()[]{}^'|"),
             vec![
                 Token::LeftParenthesis,
                 Token::RightParenthesis,
@@ -175,20 +166,20 @@ mod tests {
             ]
         );
     }
-    #[tokio::test]
-    async fn test_number_literals() {
+    #[test]
+    fn test_number_literals() {
         assert_eq!(
-            tokenize("3.14 42").await,
+            tokenize("3.14 42"),
             vec![
                 Token::NumberLiteral("3.14".to_owned().into()),
                 Token::NumberLiteral(42.into()),
             ]
         );
     }
-    #[tokio::test]
-    async fn test_identifiers_and_commands() {
+    #[test]
+    fn test_identifiers_and_commands() {
         assert_eq!(
-            tokenize("\\pi R").await,
+            tokenize("\\pi R"),
             vec![
                 Token::Backslash,
                 Token::Identifier("pi".to_string()),
@@ -196,10 +187,10 @@ mod tests {
             ]
         );
     }
-    #[tokio::test]
-    async fn test_complex_expressions() {
+    #[test]
+    fn test_complex_expressions() {
         assert_eq!(
-            tokenize("{3.14*R^2}").await,
+            tokenize("{3.14*R^2}"),
             vec![
                 Token::LeftCurlyBracket,
                 Token::NumberLiteral("3.14".to_owned().into()),
@@ -211,10 +202,10 @@ mod tests {
             ]
         );
     }
-    #[tokio::test]
-    async fn test_number_followed_by_identifier() {
+    #[test]
+    fn test_number_followed_by_identifier() {
         assert_eq!(
-            tokenize("42x + 3.14y").await,
+            tokenize("42x + 3.14y"),
             vec![
                 Token::NumberLiteral(42.into()),
                 Token::Identifier("x".to_string()),
@@ -224,10 +215,10 @@ mod tests {
             ]
         );
     }
-    #[tokio::test]
-    async fn test_number_followed_by_command() {
+    #[test]
+    fn test_number_followed_by_command() {
         assert_eq!(
-            tokenize("3.14\\piR").await,
+            tokenize("3.14\\piR"),
             vec![
                 Token::NumberLiteral("3.14".to_owned().into()),
                 Token::Backslash,
@@ -235,10 +226,10 @@ mod tests {
             ]
         );
     }
-    #[tokio::test]
-    async fn test_mixed_number_and_text_sequences() {
+    #[test]
+    fn test_mixed_number_and_text_sequences() {
         assert_eq!(
-            tokenize("2a + 4b - 5\\sqrt{c}").await,
+            tokenize("2a + 4b - 5\\sqrt{c}"),
             vec![
                 Token::NumberLiteral(2.into()),
                 Token::Identifier("a".to_string()),
@@ -255,4 +246,12 @@ mod tests {
             ]
         );
     }
+    #[test]
+    fn spans_are_character_offsets() {
+        let spans: Vec<_> = tokenize_spanned("\\alpha+3.5 λx")
+            .into_iter()
+            .map(|t| (t.span.start, t.span.end))
+            .collect();
+        assert_eq!(spans, vec![(0, 1), (1, 6), (6, 7), (7, 10), (11, 13)]);
+    }
 }
@@ -7,17 +7,27 @@
 
 pub mod approximator;
 pub mod ast;
+pub mod compiled;
 pub mod context;
+pub mod decomposition;
+pub mod diagnostic;
 
 pub mod error;
+pub mod extrapolation;
 pub mod functions;
 pub mod identifier;
 pub mod lexer;
 pub mod matrix;
 pub mod normalizer;
+pub mod number;
 pub mod parsing;
+pub mod precision;
 pub mod prelude;
+pub mod quadrature;
+pub mod solver;
 pub mod token;
 pub mod token_reader;
 pub mod value;
-pub use prelude::parse;
+pub use prelude::{
+    parse, parse_sync, parse_with_recovery, parse_with_recovery_sync,
+};
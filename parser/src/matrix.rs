@@ -1,12 +1,12 @@
 //! # Matrix
 //!
 //! implementing all the matrix multiplication scalar or otherwise
-use std::ops::{Add, AddAssign, Mul, Sub};
+use std::ops::{Add, Mul, Sub};
 
-use crate::prelude::*;
+use crate::{number::MAX_EXACT_BITS, prelude::*, solver};
 
 ///The matrix struct representing a Matrix with one or more rows and columns
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Eq, Hash, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Matrix<T> {
     /// all the values stored in a Vec stored column per column so first in:
@@ -182,7 +182,7 @@ impl<T> Matrix<T> {
     ///
     /// # Errors
     /// Will error if this matrix is not a row or column vector.
-    pub const fn as_vector(&self) -> Result<Vector<T>, EvalError> {
+    pub const fn as_vector(&self) -> Result<Vector<'_, T>, EvalError> {
         Vector::new(self)
     }
     /// Get all elements of it as a vector
@@ -235,7 +235,315 @@ impl<T: Clone> Matrix<T> {
     }
 }
 
+/// How many elements a constructed matrix may have, since every element is
+/// allocated up front.
+pub const MAX_ELEMENTS: usize = 1_000_000;
+
+/// The amount of rows and columns of a constructed matrix.
+///
+/// # Errors
+/// [EvalError::ExpectedInteger] if either is not an integer,
+/// [IncompatibleMatrixSizes::Size] if either is not positive, or
+/// [IncompatibleMatrixSizes::TooLarge] if the matrix would have more than
+/// [MAX_ELEMENTS] elements.
+pub(crate) fn shape(
+    rows: &Value,
+    columns: &Value,
+) -> Result<(usize, usize), EvalError> {
+    let size = |value: &Value| -> Result<usize, EvalError> {
+        let size = value.integer()?;
+        match usize::try_from(size) {
+            Ok(size @ 1..) => Ok(size),
+            _ => Err(IncompatibleMatrixSizes::Size { size }.into()),
+        }
+    };
+    let (rows, columns) = (size(rows)?, size(columns)?);
+    elements(rows.saturating_mul(columns))?;
+    Ok((rows, columns))
+}
+
+/// Checks that a constructed matrix with `found` elements is not too large.
+const fn elements(found: usize) -> Result<(), IncompatibleMatrixSizes> {
+    match found > MAX_ELEMENTS {
+        true => Err(IncompatibleMatrixSizes::TooLarge {
+            found,
+            max: MAX_ELEMENTS,
+        }),
+        false => Ok(()),
+    }
+}
+
 impl Matrix<Value> {
+    /// The identity matrix of the given size, with exact ones and zeros.
+    ///
+    /// # Panics
+    /// If the size is zero, see [Matrix::new].
+    pub fn identity(size: usize) -> Matrix<Value> {
+        let mut identity =
+            Matrix::new_default(size, size, Value::from(Number::from(0)));
+        for i in 0..size {
+            identity.set(i, i, Value::from(Number::from(1)));
+        }
+        identity
+    }
+
+    /// The square matrix with the values on the diagonal and exact zeros
+    /// elsewhere.
+    ///
+    /// # Panics
+    /// If there are no values, see [Matrix::new].
+    pub fn diagonal(values: Vec<Value>) -> Matrix<Value> {
+        let size = values.len();
+        let mut diagonal =
+            Matrix::new_default(size, size, Value::from(Number::from(0)));
+        for (i, value) in values.into_iter().enumerate() {
+            diagonal.set(i, i, value);
+        }
+        diagonal
+    }
+
+    /// The matrix that rotates the plane counterclockwise by `angle`
+    /// radians.
+    ///
+    /// # Errors
+    /// [EvalError::ExpectedReal] if the angle is not real.
+    pub fn rotation(angle: &Value) -> Result<Matrix<Value>, EvalError> {
+        let angle = angle.number()?;
+        let (cos, sin) = (Value::from(angle.cos()), Value::from(angle.sin()));
+        let minus_sin = (&sin * Number::from(-1))?;
+        Ok(Matrix::new(vec![cos.clone(), minus_sin, sin, cos], 2, 2))
+    }
+
+    /// The matrix that rotates space by `angle` radians about `axis`,
+    /// counterclockwise when the axis points towards the viewer.
+    ///
+    /// # Errors
+    /// [IncompatibleMatrixSizes::SameSizeVectors] if the axis is not a
+    /// vector with 3 components, [EvalError::DivideByZero] if it is zero, or
+    /// [EvalError::ExpectedReal] if a value is not real.
+    pub fn rotation_about(
+        angle: &Value,
+        axis: &Matrix<Value>,
+    ) -> Result<Matrix<Value>, EvalError> {
+        let vector = axis.as_vector()?;
+        if vector.get_size() != 3 {
+            return Err(IncompatibleMatrixSizes::SameSizeVectors {
+                a: 3,
+                b: vector.get_size(),
+            }
+            .into());
+        }
+        let length = axis.norm(&Value::from(Number::from(2)))?.scalar()?;
+        if length == 0.0 {
+            return Err(EvalError::DivideByZero);
+        }
+        let mut k = [0.0; 3];
+        for (i, component) in k.iter_mut().enumerate() {
+            *component = vector.get(i).scalar()? / length;
+        }
+        let angle = angle.scalar()?;
+        let (cos, sin) = (angle.cos(), angle.sin());
+        // Rodrigues' rotation formula, cos(a) I + sin(a) K + (1 - cos(a)) kk^T
+        // where K is the cross product with k
+        let cross =
+            [[0.0, -k[2], k[1]], [k[2], 0.0, -k[0]], [-k[1], k[0], 0.0]];
+        let mut values = Vec::with_capacity(9);
+        for (i, cross_row) in cross.iter().enumerate() {
+            for (j, cross_element) in cross_row.iter().enumerate() {
+                let identity = if i == j { cos } else { 0.0 };
+                let value =
+                    identity + sin * cross_element + (1.0 - cos) * k[i] * k[j];
+                values.push(Value::from(value));
+            }
+        }
+        Ok(Matrix::new(values, 3, 3))
+    }
+
+    /// The row vector `(first, second, \ldots, last)`, which counts from
+    /// `first` in steps of `second - first`, or of one without `second`,
+    /// until it would pass `last`.
+    ///
+    /// # Errors
+    /// [EvalError::InvalidRange] if the step is zero or goes away from
+    /// `last`, or an error if the values can not be combined.
+    pub fn range(
+        first: &Value,
+        second: Option<&Value>,
+        last: &Value,
+    ) -> Result<Matrix<Value>, EvalError> {
+        let step = match second {
+            Some(second) => (second.clone() - first.clone())?,
+            None => Value::from(Number::from(1)),
+        };
+        if solver::is_zero(&step) {
+            return Err(EvalError::InvalidRange);
+        }
+        let steps = ((last.clone() - first.clone())? / step.clone())?;
+        // A rounding error should not drop the last element
+        let steps = (steps.scalar()? + 1e-9).floor();
+        if !steps.is_finite() || steps < 0.0 {
+            return Err(EvalError::InvalidRange);
+        }
+        // The float is saturated to the largest usize
+        elements((steps as usize).saturating_add(1))?;
+        let values = (0..=steps as i64)
+            .map(|k| first.clone() + (&step * Number::from(k))?)
+            .collect::<Result<Vec<Value>, EvalError>>()?;
+        let len = values.len();
+        Ok(Matrix::new(values, 1, len))
+    }
+
+    /// The rows of the matrix, for row operations.
+    pub(crate) fn rows(&self) -> Vec<Vec<Value>> {
+        self.values
+            .chunks(self.column_count)
+            .map(<[Value]>::to_vec)
+            .collect()
+    }
+
+    /// Constructs a matrix from rows that all have the same length.
+    pub(crate) fn from_rows(rows: Vec<Vec<Value>>) -> Matrix<Value> {
+        let (row_count, column_count) = (rows.len(), rows[0].len());
+        Matrix::new(rows.concat(), row_count, column_count)
+    }
+
+    /// The size of a square matrix.
+    ///
+    /// # Errors
+    /// [IncompatibleMatrixSizes::Square] if the matrix is not square.
+    pub(crate) fn square_size(&self) -> Result<usize, EvalError> {
+        if self.row_count != self.column_count {
+            return Err(IncompatibleMatrixSizes::Square {
+                rows: self.row_count,
+                columns: self.column_count,
+            }
+            .into());
+        }
+        Ok(self.row_count)
+    }
+
+    /// Calculates the determinant of a square matrix by Gaussian elimination.
+    ///
+    /// # Errors
+    /// [IncompatibleMatrixSizes::Square] if the matrix is not square, or an
+    /// error if the elements can not be combined.
+    pub fn determinant(&self) -> Result<Value, EvalError> {
+        let size = self.square_size()?;
+        let (pivots, determinant) = solver::row_reduce(&mut self.rows(), size)?;
+        Ok(match pivots.len() == size {
+            true => determinant,
+            false => Value::from(Number::from(0)),
+        })
+    }
+
+    /// Calculates the trace, the sum of the diagonal of a square matrix.
+    ///
+    /// # Errors
+    /// [IncompatibleMatrixSizes::Square] if the matrix is not square.
+    pub fn trace(&self) -> Result<Value, EvalError> {
+        let size = self.square_size()?;
+        (1..size).try_fold(self.get(0, 0).clone(), |sum, i| {
+            sum + self.get(i, i).clone()
+        })
+    }
+
+    /// Calculates the rank, the amount of linearly independent rows.
+    ///
+    /// # Errors
+    /// Returns an `Err` if the elements can not be combined.
+    pub fn rank(&self) -> Result<usize, EvalError> {
+        let (pivots, _) =
+            solver::row_reduce(&mut self.rows(), self.column_count)?;
+        Ok(pivots.len())
+    }
+
+    /// Calculates the reduced row echelon form of the matrix.
+    ///
+    /// # Errors
+    /// Returns an `Err` if the elements can not be combined.
+    pub fn rref(&self) -> Result<Matrix<Value>, EvalError> {
+        let mut rows = self.rows();
+        solver::row_reduce(&mut rows, self.column_count)?;
+        Ok(Matrix::from_rows(rows))
+    }
+
+    /// Calculates the inverse of a square matrix by reducing the matrix next
+    /// to the identity matrix.
+    ///
+    /// # Errors
+    /// [IncompatibleMatrixSizes::Square] if the matrix is not square, or
+    /// [EvalError::SingularMatrix] if it has no inverse.
+    pub fn inverse(&self) -> Result<Matrix<Value>, EvalError> {
+        let size = self.square_size()?;
+        let mut rows: Vec<Vec<Value>> = self
+            .rows()
+            .into_iter()
+            .zip(Matrix::identity(size).rows())
+            .map(|(row, unit)| [row, unit].concat())
+            .collect();
+        let (pivots, _) = solver::row_reduce(&mut rows, size)?;
+        if pivots.len() < size {
+            return Err(EvalError::SingularMatrix);
+        }
+        Ok(Matrix::from_rows(
+            rows.into_iter().map(|row| row[size..].to_vec()).collect(),
+        ))
+    }
+
+    /// Raises a square matrix to an integer power by repeated matrix
+    /// multiplication. The power `0` is the identity matrix and negative
+    /// powers are powers of the inverse. Powers too large to be exact are
+    /// approximated with the given precision.
+    ///
+    /// # Errors
+    /// [IncompatibleMatrixSizes::Square] if the matrix is not square, or
+    /// [EvalError::SingularMatrix] if the power is negative and the matrix
+    /// has no inverse.
+    pub fn power(
+        &self,
+        exponent: i64,
+        precision: Precision,
+    ) -> Result<Matrix<Value>, EvalError> {
+        let size = self.square_size()?;
+        let mut base = match exponent < 0 {
+            true => self.inverse()?,
+            false => self.clone(),
+        };
+        // Each product adds at most the bits of the largest entry and of the
+        // size, like for scalars too large exact powers are approximated
+        let bits = base
+            .values
+            .iter()
+            .filter_map(|value| value.number().ok())
+            .map(Number::exact_bits)
+            .max()
+            .unwrap_or(0)
+            + u64::from(usize::BITS - size.leading_zeros());
+        if bits.saturating_mul(exponent.unsigned_abs()) > MAX_EXACT_BITS {
+            base = base.map_owned(|value| {
+                Ok(match value {
+                    Value::Scalar(number) => {
+                        Value::Scalar(number.inexact(precision))
+                    }
+                    _ => value,
+                })
+            })?;
+        }
+        let mut exponent = exponent.unsigned_abs();
+        let mut result = Matrix::identity(size);
+        // Exponentiation by squaring
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = result.matrix_mul(&base)?;
+            }
+            exponent /= 2;
+            if exponent > 0 {
+                base = base.matrix_mul(&base)?;
+            }
+        }
+        Ok(result)
+    }
+
     /// Calculates the dot product of two matrices (treated as vectors).
     ///
     /// # Errors
@@ -318,6 +626,72 @@ impl Matrix<Value> {
             column_count: 1,
         })
     }
+
+    /// Calculates the `p`-norm, the `p`th root of the sum of the absolute
+    /// values of the elements to the power of `p`. For `p = 2` this is the
+    /// length of a vector or the Frobenius norm of a matrix, and for
+    /// `p = \infty` it is the largest absolute value.
+    ///
+    /// # Errors
+    /// [EvalError::ExpectedReal] if `p` is not real, or an error if the
+    /// elements can not be combined.
+    pub fn norm(&self, p: &Value) -> Result<Value, EvalError> {
+        let absolute = self.map(Value::abs)?.values;
+        let zero = Value::from(Number::from(0));
+        if p.scalar()? == f64::INFINITY {
+            return absolute.into_iter().try_fold(zero, |largest, value| {
+                Ok(match value.scalar()? > largest.scalar()? {
+                    true => value,
+                    false => largest,
+                })
+            });
+        }
+        let sum = absolute
+            .iter()
+            .try_fold(zero, |sum, value| sum + value.pow(p)?)?;
+        sum.root(p)
+    }
+
+    /// Calculates the angle in radians between two vectors, from `0` to
+    /// `\pi`.
+    ///
+    /// # Errors
+    /// Returns an `Err` if:
+    /// - One of the matrices isn't a vector.
+    /// - The vectors do not have the same size.
+    /// - One of the vectors is zero, see [EvalError::DivideByZero].
+    pub fn angle(&self, other: &Matrix<Value>) -> Result<Value, EvalError> {
+        let two = Value::from(Number::from(2));
+        let lengths = self
+            .norm(&two)?
+            .mul(&MulType::Implicit, &other.norm(&two)?)?;
+        if solver::is_zero(&lengths) {
+            return Err(EvalError::DivideByZero);
+        }
+        let cosine = (self.dot_product(other)? / lengths)?.scalar()?;
+        // Rounding errors can put the cosine of parallel vectors just outside
+        // of the domain of arccos
+        Ok(Value::from(cosine.clamp(-1.0, 1.0).acos()))
+    }
+
+    /// Calculates the projection of this vector onto the vector `onto`.
+    ///
+    /// # Errors
+    /// Returns an `Err` if:
+    /// - One of the matrices isn't a vector.
+    /// - The vectors do not have the same size.
+    /// - `onto` is zero, see [EvalError::DivideByZero].
+    pub fn projection(
+        &self,
+        onto: &Matrix<Value>,
+    ) -> Result<Matrix<Value>, EvalError> {
+        let length_squared = onto.dot_product(onto)?;
+        if solver::is_zero(&length_squared) {
+            return Err(EvalError::DivideByZero);
+        }
+        let scale = (self.dot_product(onto)? / length_squared)?;
+        onto.map(|value| scale.mul(&MulType::Implicit, value))
+    }
 }
 
 impl<Lhs> Matrix<Lhs> {
@@ -415,62 +789,6 @@ impl<Lhs> Matrix<Lhs> {
         })
     }
     */
-    /// Calculates the determinant of a square matrix.
-    ///
-    /// # Errors
-    ///
-    /// Returns an `Err` of type `EvalError` if the matrix is not square
-    pub fn determinant<Res>(&self) -> Result<Res, EvalError>
-    where
-        Lhs: Clone + Mul<Res, Output = Result<Res, EvalError>> + Into<Res>,
-        Res: Default + AddAssign,
-    {
-        if self.row_count != self.column_count {
-            return Err(IncompatibleMatrixSizes::Column {
-                expected: self.row_count,
-                found: self.column_count,
-            }
-            .into());
-        }
-        if self.row_count == 1 {
-            return Ok(self.values[0].clone().into());
-        }
-
-        let mut det = Res::default();
-        for col in 0..self.column_count {
-            let submatrix = self.submatrix(0, col);
-            let term =
-                (self.get(0, col).clone() * (submatrix.determinant()?))?;
-            det += term;
-        }
-
-        Ok(det)
-    }
-
-    /// Helper function to create a submatrix by excluding a specified row and
-    /// column.
-    fn submatrix(&self, skip_row: usize, skip_col: usize) -> Matrix<Lhs>
-    where
-        Lhs: Clone,
-    {
-        let mut sub_values = Vec::new();
-        for row in 0..self.row_count {
-            if row == skip_row {
-                continue;
-            }
-            for col in 0..self.column_count {
-                if col == skip_col {
-                    continue;
-                }
-                sub_values.push(self.get(row, col).clone());
-            }
-        }
-        Matrix {
-            values: sub_values,
-            row_count: self.row_count - 1,
-            column_count: self.column_count - 1,
-        }
-    }
 }
 
 impl<T: Clone> Clone for Matrix<T> {
@@ -556,11 +874,13 @@ impl<T> Matrix<T> {
 
 #[cfg(test)]
 impl Matrix<MathExpr> {
+    /// Constructs a new `Matrix` filled with zero constants.
     pub fn zero(rows: usize, cols: usize) -> Matrix<MathExpr> {
-        let values = vec![
-            MathExpr::Term(Term::Factor(Factor::Constant(0.0)));
-            rows * cols
-        ];
+        let values =
+            vec![
+                MathExpr::Term(Term::Factor(Factor::Constant(0.0.into())));
+                rows * cols
+            ];
         Self {
             values,
             row_count: rows,
@@ -586,7 +906,7 @@ impl Matrix<Value> {
         let mut result = Matrix::new_default(
             self.row_count,
             rhs.column_count,
-            Value::Scalar(0.0),
+            Value::from(0.0),
         );
         for i in 0..self.row_count {
             for j in 0..rhs.column_count {
@@ -681,12 +1001,12 @@ impl<Lhs: Clone + Mul<Value, Output = Result<Value, EvalError>>> Mul<Value>
         self.map(|val| val.clone() * rhs.clone())
     }
 }
-impl Mul<f64> for &Matrix<Value> {
+impl Mul<Number> for &Matrix<Value> {
     type Output = Result<Matrix<Value>, EvalError>;
 
-    fn mul(self, rhs: f64) -> Self::Output {
+    fn mul(self, rhs: Number) -> Self::Output {
         // Multiply matrix components by self
-        self.map(|val| val * rhs)
+        self.map(|val| val * rhs.clone())
     }
 }
 
@@ -695,130 +1015,198 @@ mod tests {
     use super::Matrix;
     use crate::prelude::*;
     use pretty_assertions::assert_eq;
+
+    /// A matrix of exact fractions, row by row.
+    fn exact(values: &[(i64, i64)], row_count: usize) -> Matrix<Value> {
+        let values: Vec<Value> = values
+            .iter()
+            .map(|&(n, d)| Value::Scalar(Number::fraction(n, d)))
+            .collect();
+        let column_count = values.len() / row_count;
+        Matrix::new(values, row_count, column_count)
+    }
+
     #[test]
     fn matrix_scalar_value_addition() {
-        let a = Matrix::new_default(2, 3, Value::Scalar(1.0));
-        let b = Matrix::new_default(2, 3, Value::Scalar(2.0));
-        let c = Matrix::new_default(2, 3, Value::Scalar(3.0));
+        let a = Matrix::new_default(2, 3, Value::from(1.0));
+        let b = Matrix::new_default(2, 3, Value::from(2.0));
+        let c = Matrix::new_default(2, 3, Value::from(3.0));
 
         assert_eq!((a + b).unwrap(), c);
     }
 
     #[test]
     fn matrix_scalar_value_subtraction() {
-        let a = Matrix::new_default(2, 3, Value::Scalar(3.0));
-        let b = Matrix::new_default(2, 3, Value::Scalar(1.0));
-        let c = Matrix::new_default(2, 3, Value::Scalar(2.0));
+        let a = Matrix::new_default(2, 3, Value::from(3.0));
+        let b = Matrix::new_default(2, 3, Value::from(1.0));
+        let c = Matrix::new_default(2, 3, Value::from(2.0));
 
         assert_eq!((a - b).unwrap(), c);
     }
 
     #[test]
     fn matrix_2x2_scalar_value_multiplication() {
-        let mut a = Matrix::new_default(2, 2, Value::Scalar(0.0));
-        a.set(0, 0, Value::Scalar(1.0));
-        a.set(0, 1, Value::Scalar(2.0));
-        a.set(1, 0, Value::Scalar(3.0));
-        a.set(1, 1, Value::Scalar(4.0));
-        let mut b = Matrix::new_default(2, 2, Value::Scalar(0.0));
-        b.set(0, 0, Value::Scalar(5.0));
-        b.set(0, 1, Value::Scalar(6.0));
-        b.set(1, 0, Value::Scalar(7.0));
-        b.set(1, 1, Value::Scalar(8.0));
-        let mut c = Matrix::new_default(2, 2, Value::Scalar(0.0));
-        c.set(0, 0, Value::Scalar(19.0));
-        c.set(0, 1, Value::Scalar(22.0));
-        c.set(1, 0, Value::Scalar(43.0));
-        c.set(1, 1, Value::Scalar(50.0));
+        let mut a = Matrix::new_default(2, 2, Value::from(0.0));
+        a.set(0, 0, Value::from(1.0));
+        a.set(0, 1, Value::from(2.0));
+        a.set(1, 0, Value::from(3.0));
+        a.set(1, 1, Value::from(4.0));
+        let mut b = Matrix::new_default(2, 2, Value::from(0.0));
+        b.set(0, 0, Value::from(5.0));
+        b.set(0, 1, Value::from(6.0));
+        b.set(1, 0, Value::from(7.0));
+        b.set(1, 1, Value::from(8.0));
+        let mut c = Matrix::new_default(2, 2, Value::from(0.0));
+        c.set(0, 0, Value::from(19.0));
+        c.set(0, 1, Value::from(22.0));
+        c.set(1, 0, Value::from(43.0));
+        c.set(1, 1, Value::from(50.0));
 
         assert_eq!((a.matrix_mul(&b)).unwrap(), c);
     }
 
     #[test]
     fn matrix_3x2_times_2x1_scalar_value_multiplication() {
-        let mut a = Matrix::new_default(3, 2, Value::Scalar(0.0));
-        a.set(0, 0, Value::Scalar(1.0));
-        a.set(0, 1, Value::Scalar(2.0));
-        a.set(1, 0, Value::Scalar(3.0));
-        a.set(1, 1, Value::Scalar(4.0));
-        a.set(2, 0, Value::Scalar(5.0));
-        a.set(2, 1, Value::Scalar(6.0));
-        let mut b = Matrix::new_default(2, 1, Value::Scalar(0.0));
-        b.set(0, 0, Value::Scalar(7.0));
-        b.set(1, 0, Value::Scalar(8.0));
-        let mut c = Matrix::new_default(3, 1, Value::Scalar(0.0));
-        c.set(0, 0, Value::Scalar(23.0));
-        c.set(1, 0, Value::Scalar(53.0));
-        c.set(2, 0, Value::Scalar(83.0));
+        let mut a = Matrix::new_default(3, 2, Value::from(0.0));
+        a.set(0, 0, Value::from(1.0));
+        a.set(0, 1, Value::from(2.0));
+        a.set(1, 0, Value::from(3.0));
+        a.set(1, 1, Value::from(4.0));
+        a.set(2, 0, Value::from(5.0));
+        a.set(2, 1, Value::from(6.0));
+        let mut b = Matrix::new_default(2, 1, Value::from(0.0));
+        b.set(0, 0, Value::from(7.0));
+        b.set(1, 0, Value::from(8.0));
+        let mut c = Matrix::new_default(3, 1, Value::from(0.0));
+        c.set(0, 0, Value::from(23.0));
+        c.set(1, 0, Value::from(53.0));
+        c.set(2, 0, Value::from(83.0));
 
         assert_eq!((a.matrix_mul(&b)).unwrap(), c);
     }
 
     #[test]
     fn dot_product_row_column_vectors() {
-        let mut a = Matrix::new_default(1, 3, Value::Scalar(0.0));
-        a.set(0, 0, Value::Scalar(1.0));
-        a.set(0, 1, Value::Scalar(2.0));
-        a.set(0, 2, Value::Scalar(3.0));
-        let mut b = Matrix::new_default(3, 1, Value::Scalar(0.0));
-        b.set(0, 0, Value::Scalar(4.0));
-        b.set(1, 0, Value::Scalar(5.0));
-        b.set(2, 0, Value::Scalar(6.0));
-
-        assert_eq!(a.dot_product(&b).unwrap(), Value::Scalar(32.0));
-        assert_eq!(b.dot_product(&a).unwrap(), Value::Scalar(32.0));
+        let mut a = Matrix::new_default(1, 3, Value::from(0.0));
+        a.set(0, 0, Value::from(1.0));
+        a.set(0, 1, Value::from(2.0));
+        a.set(0, 2, Value::from(3.0));
+        let mut b = Matrix::new_default(3, 1, Value::from(0.0));
+        b.set(0, 0, Value::from(4.0));
+        b.set(1, 0, Value::from(5.0));
+        b.set(2, 0, Value::from(6.0));
+
+        assert_eq!(a.dot_product(&b).unwrap(), Value::from(32.0));
+        assert_eq!(b.dot_product(&a).unwrap(), Value::from(32.0));
     }
 
     #[test]
     fn dot_product_row_vectors() {
-        let mut a = Matrix::new_default(1, 3, Value::Scalar(0.0));
-        a.set(0, 0, Value::Scalar(1.0));
-        a.set(0, 1, Value::Scalar(2.0));
-        a.set(0, 2, Value::Scalar(3.0));
-        let mut b = Matrix::new_default(1, 3, Value::Scalar(0.0));
-        b.set(0, 1, Value::Scalar(5.0));
-        b.set(0, 0, Value::Scalar(4.0));
-        b.set(0, 2, Value::Scalar(6.0));
-
-        assert_eq!(a.dot_product(&b).unwrap(), Value::Scalar(32.0));
-        assert_eq!(b.dot_product(&a).unwrap(), Value::Scalar(32.0));
+        let mut a = Matrix::new_default(1, 3, Value::from(0.0));
+        a.set(0, 0, Value::from(1.0));
+        a.set(0, 1, Value::from(2.0));
+        a.set(0, 2, Value::from(3.0));
+        let mut b = Matrix::new_default(1, 3, Value::from(0.0));
+        b.set(0, 1, Value::from(5.0));
+        b.set(0, 0, Value::from(4.0));
+        b.set(0, 2, Value::from(6.0));
+
+        assert_eq!(a.dot_product(&b).unwrap(), Value::from(32.0));
+        assert_eq!(b.dot_product(&a).unwrap(), Value::from(32.0));
     }
 
     #[test]
     fn dot_product_column_vectors() {
-        let mut a = Matrix::new_default(3, 1, Value::Scalar(0.0));
-        a.set(0, 0, Value::Scalar(1.0));
-        a.set(1, 0, Value::Scalar(2.0));
-        a.set(2, 0, Value::Scalar(3.0));
-        let mut b = Matrix::new_default(3, 1, Value::Scalar(0.0));
-        b.set(0, 0, Value::Scalar(4.0));
-        b.set(1, 0, Value::Scalar(5.0));
-        b.set(2, 0, Value::Scalar(6.0));
-
-        assert_eq!(a.dot_product(&b).unwrap(), Value::Scalar(32.0));
-        assert_eq!(b.dot_product(&a).unwrap(), Value::Scalar(32.0));
+        let mut a = Matrix::new_default(3, 1, Value::from(0.0));
+        a.set(0, 0, Value::from(1.0));
+        a.set(1, 0, Value::from(2.0));
+        a.set(2, 0, Value::from(3.0));
+        let mut b = Matrix::new_default(3, 1, Value::from(0.0));
+        b.set(0, 0, Value::from(4.0));
+        b.set(1, 0, Value::from(5.0));
+        b.set(2, 0, Value::from(6.0));
+
+        assert_eq!(a.dot_product(&b).unwrap(), Value::from(32.0));
+        assert_eq!(b.dot_product(&a).unwrap(), Value::from(32.0));
     }
 
     #[test]
     fn cross_product() {
-        let mut x = Matrix::new_default(3, 1, Value::Scalar(0.0));
-        x.set(0, 0, Value::Scalar(1.0));
-        x.set(1, 0, Value::Scalar(0.0));
-        x.set(2, 0, Value::Scalar(0.0));
-        let mut y = Matrix::new_default(3, 1, Value::Scalar(0.0));
-        y.set(0, 0, Value::Scalar(0.0));
-        y.set(1, 0, Value::Scalar(1.0));
-        y.set(2, 0, Value::Scalar(0.0));
-        let mut z = Matrix::new_default(3, 1, Value::Scalar(0.0));
-        z.set(0, 0, Value::Scalar(0.0));
-        z.set(1, 0, Value::Scalar(0.0));
-        z.set(2, 0, Value::Scalar(1.0));
+        let mut x = Matrix::new_default(3, 1, Value::from(0.0));
+        x.set(0, 0, Value::from(1.0));
+        x.set(1, 0, Value::from(0.0));
+        x.set(2, 0, Value::from(0.0));
+        let mut y = Matrix::new_default(3, 1, Value::from(0.0));
+        y.set(0, 0, Value::from(0.0));
+        y.set(1, 0, Value::from(1.0));
+        y.set(2, 0, Value::from(0.0));
+        let mut z = Matrix::new_default(3, 1, Value::from(0.0));
+        z.set(0, 0, Value::from(0.0));
+        z.set(1, 0, Value::from(0.0));
+        z.set(2, 0, Value::from(1.0));
 
         assert_eq!(x.cross_product(&y).unwrap(), z);
-        assert_eq!(y.cross_product(&x).unwrap(), (&z * -1.0).unwrap());
+        assert_eq!(
+            y.cross_product(&x).unwrap(),
+            (&z * Number::from(-1)).unwrap()
+        );
         assert_eq!(z.cross_product(&x).unwrap(), y);
-        assert_eq!(x.cross_product(&z).unwrap(), (&y * -1.0).unwrap());
+        assert_eq!(
+            x.cross_product(&z).unwrap(),
+            (&y * Number::from(-1)).unwrap()
+        );
         assert_eq!(y.cross_product(&z).unwrap(), x);
-        assert_eq!(z.cross_product(&y).unwrap(), (&x * -1.0).unwrap());
+        assert_eq!(
+            z.cross_product(&y).unwrap(),
+            (&x * Number::from(-1)).unwrap()
+        );
+    }
+
+    #[test]
+    fn determinant_with_row_swaps() {
+        let a = exact(&[(0, 1), (2, 1), (1, 1), (1, 1), (1, 1), (1, 1)], 2);
+        assert!(a.determinant().is_err());
+        let a = exact(&[(0, 1), (1, 1), (1, 1), (0, 1)], 2);
+        assert_eq!(a.determinant().unwrap(), Value::from(Number::from(-1)));
+        let a = exact(&[(1, 2), (1, 3), (1, 4), (1, 5)], 2);
+        assert_eq!(
+            a.determinant().unwrap(),
+            Value::from(Number::fraction(1, 60))
+        );
+    }
+
+    #[test]
+    fn inverse_times_matrix_is_identity() {
+        let a = exact(&[(1, 1), (2, 1), (0, 1), (0, 1), (1, 1), (4, 1)], 2);
+        assert!(a.inverse().is_err());
+        let a = exact(
+            &[
+                (0, 1),
+                (1, 1),
+                (2, 1),
+                (1, 1),
+                (0, 1),
+                (3, 1),
+                (4, 1),
+                (-3, 1),
+                (8, 1),
+            ],
+            3,
+        );
+        let inverse = a.inverse().unwrap();
+        assert_eq!(inverse.matrix_mul(&a).unwrap(), Matrix::identity(3));
+        assert_eq!(a.matrix_mul(&inverse).unwrap(), Matrix::identity(3));
+    }
+
+    #[test]
+    fn rref_and_rank() {
+        let a = exact(&[(1, 1), (2, 1), (3, 1), (2, 1), (4, 1), (7, 1)], 2);
+        assert_eq!(a.rank().unwrap(), 2);
+        assert_eq!(
+            a.rref().unwrap(),
+            exact(&[(1, 1), (2, 1), (0, 1), (0, 1), (0, 1), (1, 1)], 2)
+        );
+        let b = exact(&[(1, 1), (2, 1), (2, 1), (4, 1)], 2);
+        assert_eq!(b.rank().unwrap(), 1);
     }
 }
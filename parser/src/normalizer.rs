@@ -8,59 +8,100 @@ pub struct Normalizer {
     ///The input from the lexer
     reader: TokenReader,
     ///The output to the parser
-    output: TokenSender,
+    output: Vec<SpannedToken>,
 }
 impl Normalizer {
-    ///Creates a normalizer
-    pub fn new(input: TokenReceiver, output: TokenSender) -> Self {
-        trace!("created Normalizer");
-
-        Self {
-            reader: TokenReader::new(input),
+    ///Normalizing all the tokens from the lexer, which end with
+    /// `Token::EndOfContent`
+    pub fn normalize(tokens: Vec<SpannedToken>) -> Vec<SpannedToken> {
+        let output = Vec::with_capacity(tokens.len());
+        let mut normalizer = Self {
+            reader: TokenReader::from_tokens(tokens),
             output,
-        }
+        };
+        normalizer.normalize_into_output();
+        normalizer.output
     }
-    ///Starting the normalization process will read until EOF
-    /// for more info read on in the TokenSender
-    pub async fn normalize(mut self) {
+    ///Normalizing the tokens until EOF and writing them to the output
+    fn normalize_into_output(&mut self) {
         let span = trace_span!("normalizer::normalize");
         let _enter = span.enter();
         loop {
-            self.normalize_tokens().await;
+            // Removing tokens may reveal new tokens to normalize, for example
+            // in "\,\,"
+            while self.normalize_tokens() {}
             debug!("successfully normalized tokens");
 
-            let token = self.reader.read().await;
-            trace!("reader::read {token}");
-            let eof = token == Token::EndOfContent;
-            trace!("output::send {token}");
-            self.output.send(token).await.expect("Broken pipe");
+            let token = self.reader.read_spanned();
+            trace!("reader::read {} at {}", token.token, token.span);
+            let eof = token.token == Token::EndOfContent;
+            self.output.push(token);
             if eof {
                 trace!("'end of content' has been hit");
                 break;
             }
         }
     }
+    /// The commands that only style the LaTeX and are removed.
+    pub const REMOVED_COMMANDS: &'static [&'static str] = &[
+        // TODO Remove dot after, for example "\left."
+        // we have no token for lone dots though
+        "left",
+        "middle",
+        "right",
+        "displaystyle",
+        "textstyle",
+        "limits",
+        "nolimits",
+        // Spacing, for example "\quad"
+        "quad",
+        "qquad",
+    ];
     ///Removing unwanted stuff to make the stream easier to handle
-    async fn normalize_tokens(&mut self) {
+    ///
+    /// Returns true if the first token was removed.
+    fn normalize_tokens(&mut self) -> bool {
         let span = trace_span!("normalize_tokens");
         let _enter = span.enter();
         trace!("normalize_tokens");
-        match self.reader.peek_range(0..=1).await[..] {
+        match self.reader.peek_range(0..=1)[..] {
             [Token::Backslash, Token::Identifier(v)] => {
                 trace!("ident = {v}");
                 match v.as_str() {
-                    "left" | "middle" | "right" => {
-                        self.reader.replace(0..=1, vec![]).await;
-                        // TODO Remove dot after, for example "\left."
-                        // we have no token for lone dots though
+                    command if Self::REMOVED_COMMANDS.contains(&command) => {
+                        self.reader.replace(0..=1, vec![]);
+                        return true;
+                    }
+                    // The delimiters of a norm, for example "\lVert v \rVert"
+                    "Vert" | "lVert" | "rVert" => {
+                        let pipe = vec![Token::DoubleVerticalPipe];
+                        self.reader.replace(0..=1, pipe);
                     }
-                    "displaystyle" | "textstyle" => {
-                        self.reader.replace(0..=1, vec![]).await;
+                    // Spacing, for example "\;" or "\!", where the rest of
+                    // the identifier is kept, like the "dx" in "\;dx"
+                    _ if v.starts_with([';', ':', '!']) => {
+                        let rest = v[1..].to_owned();
+                        let tokens = match rest.is_empty() {
+                            true => vec![],
+                            false => vec![Token::Identifier(rest)],
+                        };
+                        self.reader.replace(0..=1, tokens);
+                        return true;
                     }
                     _ => {}
                 }
             }
-            [Token::Caret, Token::NumberLiteral(n)] => {
+            // The delimiter of a norm, "\|"
+            [Token::Backslash, Token::VerticalPipe] => {
+                let pipe = vec![Token::DoubleVerticalPipe];
+                self.reader.replace(0..=1, pipe);
+            }
+            // Spacing, "\," or "\;"
+            [Token::Backslash, Token::Comma | Token::Semicolon] => {
+                self.reader.replace(0..=1, vec![]);
+                return true;
+            }
+            [Token::Caret | Token::Underscore, Token::NumberLiteral(n)] => {
                 trace!("number literal = {n}");
                 if n.raw.is_empty() {
                     panic!("string is weird");
@@ -71,11 +112,12 @@ impl Normalizer {
                     trace!("rest = {:?}", rest);
                     let single = Token::NumberLiteral(s.into());
                     trace!("single = {:?}", single);
-                    self.reader.replace(1..=1, vec![single, rest]).await;
+                    self.reader.replace(1..=1, vec![single, rest]);
                 }
             }
             _ => {}
         }
+        false
     }
 }
 
@@ -85,39 +127,23 @@ mod tests {
 
     use crate::prelude::*;
     use pretty_assertions::assert_eq;
-    async fn normalize(tokens: Vec<Token>) -> Vec<Token> {
-        let (tx1, rx1): (TokenSender, TokenReceiver) = mpsc::channel(32);
-        let (tx2, mut rx2): (TokenSender, TokenReceiver) = mpsc::channel(32);
-        let normalizer = Normalizer::new(rx1, tx2);
-
-        let mut result = Vec::with_capacity(tokens.len());
-
-        for token in tokens {
-            tx1.send(token).await.unwrap();
-        }
-
-        normalizer.normalize().await;
-
-        while let Some(t) = rx2.recv().await {
-            if t == Token::EndOfContent {
-                result.push(Token::EndOfContent);
-                break;
-            }
-            result.push(t);
-        }
-
-        result
+    fn normalize(tokens: Vec<Token>) -> Vec<Token> {
+        let tokens = tokens.into_iter().map(Into::into).collect();
+        Normalizer::normalize(tokens)
+            .into_iter()
+            .map(|t| t.token)
+            .collect()
     }
-    #[tokio::test]
-    async fn direct_eof() {
-        black_box(normalize(vec![Token::EndOfContent]).await);
+    #[test]
+    fn direct_eof() {
+        black_box(normalize(vec![Token::EndOfContent]));
     }
-    #[tokio::test]
-    async fn second_is_eof() {
-        black_box(normalize(vec![Token::Backslash, Token::EndOfContent]).await);
+    #[test]
+    fn second_is_eof() {
+        black_box(normalize(vec![Token::Backslash, Token::EndOfContent]));
     }
-    #[tokio::test]
-    async fn all_tokens_returned() {
+    #[test]
+    fn all_tokens_returned() {
         assert_eq!(
             normalize(vec![
                 Token::Backslash,
@@ -129,8 +155,7 @@ mod tests {
                 Token::Identifier("x".to_string()),
                 Token::RightCurlyBracket,
                 Token::EndOfContent,
-            ])
-            .await,
+            ]),
             vec![
                 Token::Backslash,
                 Token::Identifier("sqrt".to_string()),
@@ -145,16 +170,15 @@ mod tests {
         );
     }
 
-    #[tokio::test]
-    async fn exponent_split() {
+    #[test]
+    fn exponent_split() {
         assert_eq!(
             normalize(vec![
                 Token::NumberLiteral("2".to_owned().into()),
                 Token::Caret,
                 Token::NumberLiteral("025".to_owned().into()),
                 Token::EndOfContent,
-            ])
-            .await,
+            ]),
             vec![
                 Token::NumberLiteral("2".to_owned().into()),
                 Token::Caret,
@@ -165,8 +189,8 @@ mod tests {
         );
     }
 
-    #[tokio::test]
-    async fn remove_left_middle_right() {
+    #[test]
+    fn remove_left_middle_right() {
         assert_eq!(
             normalize(vec![
                 Token::Backslash,
@@ -181,8 +205,7 @@ mod tests {
                 Token::Identifier("right".to_string()),
                 Token::RightParenthesis,
                 Token::EndOfContent,
-            ])
-            .await,
+            ]),
             vec![
                 Token::LeftParenthesis,
                 Token::NumberLiteral("1".to_owned().into()),
@@ -193,4 +216,53 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn double_vertical_pipes() {
+        assert_eq!(
+            normalize(vec![
+                Token::Backslash,
+                Token::VerticalPipe,
+                Token::Identifier("v".to_string()),
+                Token::Backslash,
+                Token::VerticalPipe,
+                Token::Backslash,
+                Token::Identifier("lVert".to_string()),
+                Token::Identifier("u".to_string()),
+                Token::Backslash,
+                Token::Identifier("rVert".to_string()),
+                Token::EndOfContent,
+            ]),
+            vec![
+                Token::DoubleVerticalPipe,
+                Token::Identifier("v".to_string()),
+                Token::DoubleVerticalPipe,
+                Token::DoubleVerticalPipe,
+                Token::Identifier("u".to_string()),
+                Token::DoubleVerticalPipe,
+                Token::EndOfContent,
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_spacing() {
+        assert_eq!(
+            normalize(vec![
+                Token::Identifier("x".to_string()),
+                Token::Backslash,
+                Token::Comma,
+                Token::Backslash,
+                Token::Identifier("quad".to_string()),
+                Token::Backslash,
+                Token::Identifier(";dx".to_string()),
+                Token::EndOfContent,
+            ]),
+            vec![
+                Token::Identifier("x".to_string()),
+                Token::Identifier("dx".to_string()),
+                Token::EndOfContent,
+            ]
+        );
+    }
 }
@@ -0,0 +1,609 @@
+//! # Number
+//! The numbers used in the AST and when evaluating.
+//!
+//! Numbers are exact fractions of big integers as long as possible, so
+//! `\frac{1}{3}+\frac{1}{6}` is exactly `\frac{1}{2}`. Operations that give
+//! irrational results, like most roots and logarithms, fall back to floating
+//! point, or to big floats with the [Precision] of an approximator.
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    hash::Hash,
+    ops::{Add, Div, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+use dashu_float::{round::mode::HalfAway, DBig, FBig};
+use dashu_int::{IBig, Sign, UBig};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Pow, Signed, ToPrimitive, Zero};
+
+use crate::precision::{self, Precision};
+
+/// The largest amount of bits an exact power may have, larger powers are
+/// approximated instead.
+pub(crate) const MAX_EXACT_BITS: u64 = 1 << 16;
+
+/// The largest degree of a root that is tried exactly.
+const MAX_EXACT_DEGREE: u32 = 64;
+
+/// A number that is exact when possible and approximated otherwise.
+///
+/// Note that equality is structural, so `\frac{1}{2}` is not equal to the
+/// float `0.5`. Use [Number::to_f64] to compare the values.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Number {
+    /// An exact fraction, always in lowest terms. It is boxed to keep
+    /// numbers small, since values are moved around a lot when evaluating.
+    Rational(
+        #[cfg_attr(feature = "serde", serde(with = "rational_string"))]
+        Box<BigRational>,
+    ),
+    /// An approximation, for example of an irrational number.
+    Float(f64),
+    /// An approximation with more significant digits than a float, see
+    /// [Precision].
+    Big(#[cfg_attr(feature = "serde", serde(with = "big_string"))] Box<DBig>),
+}
+
+impl Number {
+    /// The exact fraction `numerator / denominator`.
+    ///
+    /// # Panics
+    /// If the denominator is zero.
+    pub fn fraction(numerator: i64, denominator: i64) -> Self {
+        Self::Rational(Box::new(BigRational::new(
+            numerator.into(),
+            denominator.into(),
+        )))
+    }
+    /// The value as a float, which may lose precision.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Rational(r) => r.to_f64().unwrap_or(f64::NAN),
+            Number::Float(f) => *f,
+            Number::Big(b) => big_to_f64(b),
+        }
+    }
+    /// The value as an integer, if it is one.
+    pub fn to_integer(&self) -> Option<i64> {
+        match self {
+            Number::Rational(r) if r.is_integer() => r.numer().to_i64(),
+            Number::Rational(_) => None,
+            Number::Float(f) if f.is_finite() && f.fract() == 0.0 => {
+                Some(*f as i64)
+            }
+            Number::Float(_) => None,
+            Number::Big(b) if b.repr().is_int() => {
+                i64::try_from(b.to_int().value()).ok()
+            }
+            Number::Big(_) => None,
+        }
+    }
+    /// Whether the number is exact.
+    pub const fn is_exact(&self) -> bool {
+        matches!(self, Number::Rational(_))
+    }
+    /// The amount of bits of an exact number, or zero for approximations.
+    pub(crate) fn exact_bits(&self) -> u64 {
+        match self {
+            Number::Rational(r) => r.numer().bits() + r.denom().bits(),
+            _ => 0,
+        }
+    }
+    /// The number approximated with the given precision, where exact
+    /// numbers become floats for double precision.
+    pub(crate) fn inexact(&self, precision: Precision) -> Self {
+        match (self, precision) {
+            (Number::Rational(_), Precision::Double) => {
+                Number::Float(self.to_f64())
+            }
+            _ => precision.approximate(self),
+        }
+    }
+    /// Whether the number is not a number, for example because it is the
+    /// square root of a negative number.
+    pub const fn is_nan(&self) -> bool {
+        matches!(self, Number::Float(f) if f.is_nan())
+    }
+    /// The absolute value.
+    pub fn abs(&self) -> Self {
+        match self {
+            Number::Rational(r) => Number::Rational(Box::new(r.abs())),
+            Number::Float(f) => Number::Float(f.abs()),
+            Number::Big(b) if b.sign() == Sign::Negative => -self.clone(),
+            Number::Big(_) => self.clone(),
+        }
+    }
+    /// Compares the values, exactly if both numbers are exact and with the
+    /// larger precision of the two otherwise. It is `None` if either is not
+    /// a number.
+    pub fn compare(&self, other: &Number) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Rational(a), Number::Rational(b)) => Some(a.cmp(b)),
+            (Number::Float(_), _) | (_, Number::Float(_)) => {
+                self.to_f64().partial_cmp(&other.to_f64())
+            }
+            _ => {
+                let Precision::Digits(digits) =
+                    self.precision().max(other.precision())
+                else {
+                    unreachable!("one of the numbers is a big float")
+                };
+                Some(self.to_big(digits)?.cmp(&other.to_big(digits)?))
+            }
+        }
+    }
+    /// The largest integer that is at most the number.
+    pub fn floor(&self) -> Self {
+        match self {
+            Number::Rational(r) => Number::Rational(Box::new(r.floor())),
+            Number::Float(f) => Number::from(f.floor()),
+            Number::Big(b) => Number::Big(Box::new(b.floor())),
+        }
+    }
+    /// The smallest integer that is at least the number.
+    pub fn ceil(&self) -> Self {
+        match self {
+            Number::Rational(r) => Number::Rational(Box::new(r.ceil())),
+            Number::Float(f) => Number::from(f.ceil()),
+            Number::Big(b) => Number::Big(Box::new(b.ceil())),
+        }
+    }
+    /// The sign, which is -1, 0 or 1, or not a number for not a number.
+    pub fn signum(&self) -> Self {
+        match self.compare(&Number::from(0)) {
+            Some(Ordering::Less) => Number::from(-1),
+            Some(Ordering::Equal) => Number::from(0),
+            Some(Ordering::Greater) => Number::from(1),
+            None => Number::Float(f64::NAN),
+        }
+    }
+    /// The precision the number is approximated with, which is double
+    /// precision for exact numbers.
+    pub fn precision(&self) -> Precision {
+        match self {
+            Number::Big(b) => Precision::Digits(b.precision()),
+            _ => Precision::Double,
+        }
+    }
+    /// The number as a big float with `digits` significant digits, or None
+    /// if it is not finite.
+    pub fn to_big(&self, digits: usize) -> Option<DBig> {
+        match self {
+            Number::Rational(r) => {
+                let numerator = DBig::from(to_ibig(r.numer()));
+                let denominator = DBig::from(to_ibig(r.denom()));
+                Some(numerator.with_precision(digits).value() / denominator)
+            }
+            Number::Float(f) => {
+                let binary = FBig::<HalfAway, 2>::try_from(*f).ok()?;
+                binary.repr().is_finite().then(|| {
+                    binary.with_base_and_precision::<10>(digits).value()
+                })
+            }
+            Number::Big(b) => Some(precision::with_digits(b, digits)),
+        }
+    }
+    /// Raises the number to the power of `exponent`.
+    ///
+    /// The result is exact for integer exponents, and for fractional
+    /// exponents when the root is exact, like `4^{\frac{1}{2}}`. Other
+    /// powers are approximated with the precision of the numbers.
+    pub fn pow(&self, exponent: &Number) -> Self {
+        self.pow_with(exponent, Precision::Double)
+    }
+    /// Raises the number to the power of `exponent` like [Number::pow], but
+    /// approximates with at least the given precision.
+    pub fn pow_with(&self, exponent: &Number, precision: Precision) -> Self {
+        if let (Number::Rational(base), Number::Rational(exponent)) =
+            (self, exponent)
+        {
+            if let Some(exact) = exact_pow(base, exponent) {
+                return Number::Rational(Box::new(exact));
+            }
+        }
+        let precision =
+            precision.max(self.precision()).max(exponent.precision());
+        if let Precision::Digits(digits) = precision {
+            if let (Some(base), Some(exponent)) =
+                (self.to_big(digits), exponent.to_big(digits))
+            {
+                return precision::pow(&base, &exponent);
+            }
+        }
+        Number::Float(self.to_f64().powf(exponent.to_f64()))
+    }
+    /// The root of the given degree, where odd roots of negative numbers are
+    /// negative.
+    pub fn root(&self, degree: &Number) -> Self {
+        self.root_with(degree, Precision::Double)
+    }
+    /// The root of the given degree like [Number::root], but approximates
+    /// with at least the given precision.
+    pub fn root_with(&self, degree: &Number, precision: Precision) -> Self {
+        if let Number::Rational(degree) = degree {
+            if !degree.is_zero() {
+                let exponent = degree.recip();
+                if let Number::Rational(radicand) = self {
+                    if let Some(exact) = exact_pow(radicand, &exponent) {
+                        return Number::Rational(Box::new(exact));
+                    }
+                }
+            }
+        }
+        let odd = degree.to_integer().is_some_and(|n| n % 2 != 0);
+        if self.to_f64() < 0.0 && odd {
+            return -(-self.clone()).root_with(degree, precision);
+        }
+        if degree.to_integer() == Some(2) {
+            if let Precision::Digits(digits) = precision.max(self.precision()) {
+                if let Some(radicand) = self.to_big(digits) {
+                    return precision::sqrt(&radicand);
+                }
+            }
+        }
+        let exponent = Number::from(1) / degree.clone();
+        self.pow_with(&exponent, precision)
+    }
+}
+
+/// Implements elementary functions that are approximated with big floats
+/// for big floats and with `f64` otherwise.
+macro_rules! impl_elementary {
+    ($($(#[$doc:meta])* $name:ident),*) => {
+        impl Number {
+            $(
+                $(#[$doc])*
+                pub fn $name(&self) -> Number {
+                    match self {
+                        Number::Big(x) => precision::$name(x),
+                        _ => Number::from(self.to_f64().$name()),
+                    }
+                }
+            )*
+        }
+    };
+}
+impl_elementary!(
+    /// The sine.
+    sin,
+    /// The cosine.
+    cos,
+    /// The tangent.
+    tan,
+    /// The arcsine.
+    asin,
+    /// The arccosine.
+    acos,
+    /// The arctangent.
+    atan,
+    /// The hyperbolic sine.
+    sinh,
+    /// The hyperbolic cosine.
+    cosh,
+    /// The hyperbolic tangent.
+    tanh,
+    /// The exponential function.
+    exp,
+    /// The natural logarithm.
+    ln,
+    /// The logarithm in base 10.
+    log10
+);
+
+/// Converts a big integer between the integer libraries.
+fn to_ibig(value: &BigInt) -> IBig {
+    let (sign, bytes) = value.to_bytes_le();
+    let magnitude = IBig::from(UBig::from_le_bytes(&bytes));
+    match sign {
+        num_bigint::Sign::Minus => -magnitude,
+        _ => magnitude,
+    }
+}
+
+/// The exact power if the result is rational and not too large.
+fn exact_pow(
+    base: &BigRational,
+    exponent: &BigRational,
+) -> Option<BigRational> {
+    let power = exponent.numer().to_i32()?;
+    let degree = exponent.denom().to_u32()?;
+    if degree > MAX_EXACT_DEGREE || (base.is_zero() && power < 0) {
+        return None;
+    }
+    let bits = base.numer().bits() + base.denom().bits();
+    if bits.saturating_mul(power.unsigned_abs().into()) > MAX_EXACT_BITS {
+        return None;
+    }
+    let root = match degree {
+        1 => base.clone(),
+        _ => BigRational::new(
+            exact_root(base.numer(), degree)?,
+            exact_root(base.denom(), degree)?,
+        ),
+    };
+    Some(Pow::pow(root, power))
+}
+
+/// The exact root of an integer, if there is one.
+fn exact_root(value: &BigInt, degree: u32) -> Option<BigInt> {
+    if value.is_negative() && degree.is_multiple_of(2) {
+        return None;
+    }
+    let root = value.nth_root(degree);
+    (Pow::pow(&root, degree) == *value).then_some(root)
+}
+
+/// The nearest float to a big float. The decimal digits are parsed, since
+/// converting the digits to binary can fail for large precisions.
+fn big_to_f64(value: &DBig) -> f64 {
+    let repr = value.repr();
+    if repr.is_infinite() {
+        return value.to_f64().value();
+    }
+    format!("{}e{}", repr.significand(), repr.exponent())
+        .parse()
+        .unwrap_or(f64::NAN)
+}
+
+impl From<f64> for Number {
+    /// Integers are stored exactly and other values as floats.
+    fn from(value: f64) -> Self {
+        // Integers this small are exactly representable
+        if value.fract() == 0.0 && value.abs() <= 2f64.powi(53) {
+            return (value as i64).into();
+        }
+        Number::Float(value)
+    }
+}
+
+impl From<i64> for Number {
+    fn from(value: i64) -> Self {
+        Number::Rational(Box::new(BigRational::from_integer(value.into())))
+    }
+}
+
+impl From<i32> for Number {
+    fn from(value: i32) -> Self {
+        i64::from(value).into()
+    }
+}
+
+impl FromStr for Number {
+    type Err = std::num::ParseFloatError;
+
+    /// Parses a decimal like `12.25` exactly.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Check that it is a valid number first
+        let float: f64 = s.parse()?;
+        let (whole, decimals) = s.split_once('.').unwrap_or((s, ""));
+        let digits = format!("{whole}{decimals}");
+        let Ok(numerator) = BigInt::from_str(&digits) else {
+            return Ok(Number::Float(float));
+        };
+        let denominator = Pow::pow(BigInt::from(10), decimals.len());
+        Ok(Number::Rational(Box::new(BigRational::new(
+            numerator,
+            denominator,
+        ))))
+    }
+}
+
+impl Display for Number {
+    /// Writes the number as LaTeX, for example `\frac{1}{2}`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Number::Rational(r) if r.is_integer() => write!(f, "{}", r.numer()),
+            Number::Rational(r) => {
+                let sign = if r.is_negative() { "-" } else { "" };
+                write!(
+                    f,
+                    "{sign}\\frac{{{}}}{{{}}}",
+                    r.numer().abs(),
+                    r.denom()
+                )
+            }
+            Number::Float(value) => write!(f, "{}", value),
+            Number::Big(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Number::Rational(a), Number::Rational(b)) => a == b,
+            (Number::Float(a), Number::Float(b)) => a.to_bits() == b.to_bits(),
+            (Number::Big(a), Number::Big(b)) => {
+                a.repr() == b.repr() && a.precision() == b.precision()
+            }
+            _ => false,
+        }
+    }
+}
+impl Eq for Number {}
+impl Hash for Number {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        match self {
+            Number::Rational(r) => r.hash(state),
+            Number::Float(f) => f.to_bits().hash(state),
+            Number::Big(b) => {
+                b.repr().significand().hash(state);
+                b.repr().exponent().hash(state);
+            }
+        }
+    }
+}
+
+/// Implements an operator that is exact for two rationals and approximated
+/// otherwise, with big floats if either number is one.
+macro_rules! impl_operator {
+    ($trait:ident, $method:ident, $exact:expr, $big:expr) => {
+        impl $trait for Number {
+            type Output = Number;
+
+            fn $method(self, rhs: Self) -> Self::Output {
+                if let (Number::Rational(a), Number::Rational(b)) =
+                    (&self, &rhs)
+                {
+                    if let Some(exact) = $exact(&**a, &**b) {
+                        return Number::Rational(Box::new(exact));
+                    }
+                }
+                let precision = self.precision().max(rhs.precision());
+                if let Precision::Digits(digits) = precision {
+                    if let (Some(a), Some(b)) =
+                        (self.to_big(digits), rhs.to_big(digits))
+                    {
+                        if let Some(big) = $big(a, b) {
+                            return Number::Big(Box::new(big));
+                        }
+                    }
+                }
+                Number::Float(self.to_f64().$method(rhs.to_f64()))
+            }
+        }
+    };
+}
+impl_operator!(
+    Add,
+    add,
+    |a: &BigRational, b: &BigRational| Some(a + b),
+    |a: DBig, b: DBig| Some(a + b)
+);
+impl_operator!(
+    Sub,
+    sub,
+    |a: &BigRational, b: &BigRational| Some(a - b),
+    |a: DBig, b: DBig| Some(a - b)
+);
+impl_operator!(
+    Mul,
+    mul,
+    |a: &BigRational, b: &BigRational| Some(a * b),
+    |a: DBig, b: DBig| Some(a * b)
+);
+// Dividing by zero gives infinity or NaN like floats do
+impl_operator!(
+    Div,
+    div,
+    |a: &BigRational, b: &BigRational| (!b.is_zero()).then(|| a / b),
+    |a: DBig, b: DBig| (!b.repr().is_zero()).then(|| a / b)
+);
+
+impl Neg for Number {
+    type Output = Number;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            Number::Rational(r) => Number::Rational(Box::new(-*r)),
+            Number::Float(f) => Number::Float(-f),
+            Number::Big(b) => Number::Big(Box::new(-*b)),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Serializing rationals as strings like `1/3`, since the numbers may be too
+/// large for JavaScript.
+mod rational_string {
+    use num_rational::BigRational;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    /// Serializes the rational as a string.
+    pub fn serialize<S: Serializer>(
+        value: &BigRational,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    /// Deserializes the rational from a string.
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Box<BigRational>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map(Box::new).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Serializing big floats as decimal strings, which keep all their digits.
+mod big_string {
+    use dashu_float::DBig;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    /// Serializes the big float as a string.
+    pub fn serialize<S: Serializer>(
+        value: &DBig,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    /// Deserializes the big float from a string.
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Box<DBig>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map(Box::new).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Number;
+    use crate::precision::Precision;
+    use pretty_assertions::assert_eq;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn parses_decimals_exactly() {
+        let sum =
+            "0.1".parse::<Number>().unwrap() + "0.2".parse::<Number>().unwrap();
+        assert_eq!(sum, "0.3".parse().unwrap());
+    }
+
+    #[test]
+    fn fractions_in_lowest_terms() {
+        let sum = Number::fraction(1, 3) + Number::fraction(1, 6);
+        assert_eq!(sum.to_string(), "\\frac{1}{2}");
+        assert_eq!((-sum).to_string(), "-\\frac{1}{2}");
+    }
+
+    #[test]
+    fn exact_powers_and_roots() {
+        let base = Number::fraction(4, 9);
+        assert_eq!(base.pow(&Number::fraction(-3, 2)), Number::fraction(27, 8));
+        assert_eq!(Number::from(-8).root(&3.into()), Number::from(-2));
+    }
+
+    #[test]
+    fn irrational_falls_back_to_float() {
+        let root = Number::from(2).root(&2.into());
+        assert_eq!(root, Number::Float(std::f64::consts::SQRT_2));
+    }
+
+    #[test]
+    fn comparing_and_rounding() {
+        let third = Number::fraction(1, 3);
+        let big = Precision::Digits(40).approximate(&third);
+        assert_eq!(
+            third.compare(&Number::fraction(1, 4)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(big.compare(&Number::fraction(1, 2)), Some(Ordering::Less));
+        assert_eq!(Number::Float(f64::NAN).compare(&third), None);
+        assert_eq!(Number::fraction(-7, 2).floor(), Number::from(-4));
+        assert_eq!(Number::fraction(-7, 2).ceil(), Number::from(-3));
+        assert_eq!((-big).signum(), Number::from(-1));
+    }
+
+    #[test]
+    fn dividing_by_zero_is_infinite() {
+        assert_eq!(Number::from(1) / 0.into(), Number::Float(f64::INFINITY));
+    }
+}
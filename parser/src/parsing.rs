@@ -2,63 +2,349 @@
 use tracing::{trace, trace_span};
 
 use crate::{
-    identifier::{MathLetter, MathString, ModifierType},
+    ast::{substitute::fresh_identifier, to_latex::ToLaTeX},
+    identifier::{MathLetter, MathString, ModifierType, OtherSymbol},
     prelude::*,
 };
-use async_recursion::async_recursion;
+
+crate::enum_with_latex!(Command {
+    Sqrt => "sqrt",
+    Frac => "frac",
+    Sum => "sum",
+    Prod => "prod",
+    Int => "int",
+    Lim => "lim",
+    Nabla => "nabla",
+    Partial => "partial",
+    Operatorname => "operatorname",
+    Lfloor => "lfloor",
+    Lceil => "lceil",
+    Log => "log",
+    Begin => "begin",
+});
+
+crate::enum_with_latex!(VectorOperator {
+    Jacobian => "J",
+    Hessian => "H",
+});
+
+impl VectorOperator {
+    /// Apply the operator to `body` with respect to `variables`.
+    fn apply(&self, body: &MathExpr, variables: &[MathIdentifier]) -> Factor {
+        match self {
+            Self::Jacobian => body.jacobian(variables),
+            Self::Hessian => body.hessian(variables),
+        }
+    }
+}
+/// The highest order of a derivative, since the variable is differentiated
+/// with respect to once for every order.
+pub const MAX_DERIVATIVE_ORDER: usize = 100;
+
+/// What a gradient, Jacobian or Hessian is taken of: the expression, the
+/// variables and the point to evaluate it at.
+type VectorDerivativeOperand = (
+    MathExpr,
+    Vec<MathIdentifier>,
+    Vec<(MathIdentifier, MathExpr)>,
+);
+
 ///Parser for parsing the stream when it is done by the normalizer
 pub struct Parser {
     ///token stream from the normalizer
     reader: TokenReader,
     ///the context in witch it operates in
     context: MathContext,
+    ///how many integrals the parser is currently inside the body of, used to
+    /// know if a differential like `dx` ends the term
+    integral_depth: usize,
+    ///how many limit targets like the `0^+` in `x \to 0^+` the parser is
+    /// currently inside, used to know if a caret is the side of the limit
+    limit_depth: usize,
+    ///whether the parser is inside a norm like `\|v\|`, used to know if a
+    /// double vertical pipe closes it or starts a new norm
+    in_norm: bool,
+    ///the errors recovered from so far, see [Parser::parse_with_recovery].
+    /// `None` if the first error stops the parser
+    recovered: Option<Vec<AstError>>,
 }
 
 impl Parser {
-    ///creating a new
-    pub fn new(tokens: TokenReceiver, context: MathContext) -> Self {
+    ///creating a parser over tokens that are already normalized, which end
+    /// with `Token::EndOfContent`
+    pub fn from_tokens(
+        tokens: Vec<SpannedToken>,
+        context: MathContext,
+    ) -> Self {
         trace!("created Parser");
 
         Parser {
-            reader: TokenReader::new(tokens),
+            reader: TokenReader::from_tokens(tokens),
             context,
+            integral_depth: 0,
+            limit_depth: 0,
+            in_norm: false,
+            recovered: None,
         }
     }
     ///Starting the parser
-    pub async fn parse(mut self) -> Result<Ast, ParseError> {
+    ///
+    /// Errors are at the span of the last read token, which is the one that
+    /// could not be parsed.
+    pub fn parse(mut self) -> Result<Ast, AstError> {
         let span = trace_span!("parse");
         let _enter = span.enter();
 
+        self.ast().map_err(|source| AstError::ParseError {
+            source,
+            span: self.reader.last_span(),
+        })
+    }
+    /// Starting the parser, but continuing after errors to find all of them.
+    ///
+    /// A factor that could not be parsed becomes a [Factor::Error] and the
+    /// parser skips ahead to the next `+`, `-`, closing bracket, `&`, `\\`,
+    /// `=` or `;`. A closing bracket without an opening one is skipped. The
+    /// AST is only complete if there are no errors.
+    pub fn parse_with_recovery(mut self) -> (Ast, Vec<AstError>) {
+        let span = trace_span!("parse_with_recovery");
+        let _enter = span.enter();
+
+        self.recovered = Some(Vec::new());
+        let start = self.reader.peek_span();
+        let ast = match self.ast() {
+            Ok(ast) => ast,
+            Err(source) => {
+                let end = self.reader.last_span();
+                self.recover(source, end);
+                Ast::Expression(Factor::Error(start.to(end)).into())
+            }
+        };
+        let mut errors = self.recovered.take().unwrap_or_default();
+        errors.sort_by_key(|error| error.span().map(|s| (s.start, s.end)));
+        (ast, errors)
+    }
+    /// Parse an expression, equality or system of equations.
+    fn ast(&mut self) -> Result<Ast, ParseError> {
+        if let Some(environment) = self.system_environment() {
+            let equations = self.system(&environment)?;
+            let next = self.reader.read();
+            if next != Token::EndOfContent {
+                self.trailing(next)?;
+            }
+            return Ok(Ast::System(equations));
+        }
+
         // Parse expression
-        let root_expr = self.expr().await?;
+        let root_expr = self.outer_expr()?;
         trace!("root_expr = {root_expr:?}");
 
         // Check if we have more to read, if not, that means we have a full
         // expression we can return.
-        let next = self.reader.read().await;
+        let next = self.reader.read();
         if next == Token::EndOfContent {
             return Ok(Ast::Expression(root_expr));
         }
         if next == Token::Equals {
             // An equality. Try parse a right hand side.
-            let rhs = self.expr().await?;
-            let next = self.reader.read().await;
+            let rhs = self.outer_expr()?;
+            let mut next = self.reader.read();
             trace!("trailing = {next}");
+            if next != Token::Semicolon {
+                if next != Token::EndOfContent {
+                    self.trailing(next)?;
+                }
+                return Ok(Ast::Equality(root_expr, rhs));
+            }
+            // Equations separated by semicolons are a system
+            let mut equations = vec![(root_expr, rhs)];
+            while next == Token::Semicolon {
+                if self.reader.peek() == Token::EndOfContent {
+                    next = self.reader.read();
+                    break;
+                }
+                equations.push(self.equation()?);
+                next = self.reader.read();
+            }
             if next != Token::EndOfContent {
-                return Err(ParseError::Trailing { token: next });
+                self.trailing(next)?;
             }
-            return Ok(Ast::Equality(root_expr, rhs));
+            return Ok(Ast::System(equations));
         }
         // It seems we have expected trailing tokens.
         // This means we failed to parse the expression fully.
-        Err(ParseError::Trailing { token: next })
+        self.trailing(next)?;
+        Ok(Ast::Expression(root_expr))
     }
-    ///expect the next token to be of a type
-    pub(crate) async fn expect(
+    /// Handle a token that was read after the end of the input was expected.
+    /// When recovering, the error is recorded and the rest of the input is
+    /// skipped.
+    fn trailing(&mut self, token: Token) -> Result<(), ParseError> {
+        if self.recovered.is_none() {
+            return Err(ParseError::Trailing { token });
+        }
+        let span = self.reader.last_span();
+        self.recover(ParseError::Trailing { token }, span);
+        while self.reader.read() != Token::EndOfContent {}
+        Ok(())
+    }
+    /// Parse an expression that is not inside any brackets.
+    ///
+    /// When recovering, a closing bracket without an opening one is recorded
+    /// and skipped, and the expression continues after it, like in `a+}+b`.
+    fn outer_expr(&mut self) -> Result<MathExpr, ParseError> {
+        let mut expr = self.expr()?;
+        while self.recovered.is_some()
+            && matches!(
+                self.reader.peek(),
+                Token::RightCurlyBracket
+                    | Token::RightParenthesis
+                    | Token::RightBracket
+            )
+        {
+            let token = self.reader.read();
+            let span = self.reader.last_span();
+            self.recover(ParseError::Trailing { token }, span);
+            expr = self.expr_tail(expr)?;
+        }
+        Ok(expr)
+    }
+    /// Record an error at the span to continue parsing after it.
+    ///
+    /// Only the first error at a span is kept, since the others are caused by
+    /// it, like the missing `)` after the missing factor in `(`.
+    fn recover(&mut self, source: ParseError, span: Span) {
+        trace!("recovering from {source} at {span}");
+        let Some(recovered) = &mut self.recovered else {
+            return;
+        };
+        if recovered.last().and_then(AstError::span) != Some(span) {
+            recovered.push(AstError::ParseError { source, span });
+        }
+    }
+    /// Whether the next token ends the part that is being parsed, so parsing
+    /// can continue there after an error.
+    fn next_is_sync_point(&mut self) -> bool {
+        match &self.reader.peek_range(0..=1)[..] {
+            [Token::RightCurlyBracket
+            | Token::RightParenthesis
+            | Token::RightBracket
+            | Token::Ampersand
+            | Token::Equals
+            | Token::Semicolon
+            | Token::EndOfContent, ..] => true,
+            [Token::Backslash, Token::Backslash] => true,
+            [Token::Backslash, Token::Identifier(command)] => {
+                matches!(command.as_str(), "end" | "rfloor" | "rceil")
+            }
+            _ => false,
+        }
+    }
+    /// Skip the tokens up to the next sync point, `+` or `-` that is not
+    /// inside brackets opened while skipping.
+    fn synchronize(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            if depth == 0 && self.next_is_sync_point() {
+                return;
+            }
+            // A failed term ends at the next `+` or `-` outside of brackets,
+            // like the `*` in `1+*+2`
+            if depth == 0
+                && matches!(self.reader.peek(), Token::Plus | Token::Minus)
+            {
+                return;
+            }
+            match self.reader.read() {
+                Token::LeftCurlyBracket
+                | Token::LeftParenthesis
+                | Token::LeftBracket => depth += 1,
+                Token::RightCurlyBracket
+                | Token::RightParenthesis
+                | Token::RightBracket => depth = depth.saturating_sub(1),
+                Token::EndOfContent => return,
+                _ => {}
+            }
+        }
+    }
+    /// Parse an equation, where the equals sign may be aligned with `&`
+    /// like in `x &= 1`.
+    fn equation(&mut self) -> Result<(MathExpr, MathExpr), ParseError> {
+        let lhs = self.outer_expr()?;
+        if self.reader.peek() == Token::Ampersand {
+            self.reader.skip();
+        }
+        self.expect(Token::Equals)?;
+        if self.reader.peek() == Token::Ampersand {
+            self.reader.skip();
+        }
+        let rhs = self.outer_expr()?;
+        Ok((lhs, rhs))
+    }
+
+    /// The name of the environment if the input starts with an environment
+    /// of equations, like `\begin{cases}`, which is then read.
+    fn system_environment(&mut self) -> Option<String> {
+        let start = self.reader.peek_range(0..=3);
+        let [Token::Backslash, Token::Identifier(begin), Token::LeftCurlyBracket, Token::Identifier(name)] =
+            &start[..]
+        else {
+            return None;
+        };
+        if begin != "begin" || !matches!(name.as_str(), "cases" | "aligned") {
+            return None;
+        }
+        let name = name.clone();
+        for _ in 0..4 {
+            self.reader.skip();
+        }
+        Some(name)
+    }
+
+    /// Parse the equations of an environment like `\begin{cases}`, one per
+    /// row, until `\end` and the name of the environment.
+    fn system(
         &mut self,
-        expected: Token,
-    ) -> Result<(), ParseError> {
-        let found = self.reader.read().await;
+        environment: &str,
+    ) -> Result<Vec<(MathExpr, MathExpr)>, ParseError> {
+        self.expect(Token::RightCurlyBracket)?;
+        let mut equations = Vec::new();
+        loop {
+            if self.reader.peek_range(0..=1)[..]
+                == [Token::Backslash, Token::Identifier("end".to_owned())]
+            {
+                break;
+            }
+            equations.push(self.equation()?);
+            match self.reader.peek_range(0..=1)[..] {
+                // Two backslashes end the row
+                [Token::Backslash, Token::Backslash] => {
+                    self.reader.skip();
+                    self.reader.skip();
+                }
+                [Token::Backslash, _] => {}
+                ref found => {
+                    return Err(ParseError::UnexpectedToken {
+                        expected: vec![Token::Backslash],
+                        found: found[0].clone(),
+                    });
+                }
+            }
+        }
+        self.expect(Token::Backslash)?;
+        self.expect(Token::Identifier("end".to_owned()))?;
+        self.expect(Token::LeftCurlyBracket)?;
+        self.expect(Token::Identifier(environment.to_owned()))?;
+        self.expect(Token::RightCurlyBracket)?;
+        if equations.is_empty() {
+            return Err(ParseError::EmptySystem);
+        }
+        Ok(equations)
+    }
+
+    ///expect the next token to be of a type
+    pub(crate) fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        let found = self.reader.read();
         if found == expected {
             return Ok(());
         }
@@ -68,8 +354,8 @@ impl Parser {
         })
     }
     ///reads the token until it is not a char and then adds them up
-    async fn read_identifier(&mut self) -> Result<String, ParseError> {
-        let token = self.reader.read().await;
+    fn read_identifier(&mut self) -> Result<String, ParseError> {
+        let token = self.reader.read();
         match token {
             Token::Identifier(val) => Ok(val),
             found => Err(ParseError::UnexpectedToken {
@@ -81,21 +367,27 @@ impl Parser {
 
     /// Parse a mathematical expression that consists of multiple terms added
     /// and subtracted.
-    #[async_recursion]
-    async fn expr(&mut self) -> Result<MathExpr, ParseError> {
-        let mut expr = MathExpr::Term(self.term().await?);
+    fn expr(&mut self) -> Result<MathExpr, ParseError> {
+        let term = self.term()?;
+        self.expr_tail(MathExpr::Term(term))
+    }
 
+    /// Parse the terms added to and subtracted from `expr`.
+    fn expr_tail(
+        &mut self,
+        mut expr: MathExpr,
+    ) -> Result<MathExpr, ParseError> {
         loop {
-            let next = self.reader.peek().await;
+            let next = self.reader.peek();
             match next {
                 Token::Plus => {
-                    self.reader.skip().await;
-                    let rhs = self.term().await?;
+                    self.reader.skip();
+                    let rhs = self.term()?;
                     expr = MathExpr::Add(Box::new(expr), rhs);
                 }
                 Token::Minus => {
-                    self.reader.skip().await;
-                    let rhs = self.term().await?;
+                    self.reader.skip();
+                    let rhs = self.term()?;
                     expr = MathExpr::Subtract(Box::new(expr), rhs);
                 }
                 _ => break,
@@ -108,44 +400,52 @@ impl Parser {
     /// Parse a term that consists of multiple factors multiplied and divided.
     /// Will handle implicit multiplication and continues to read until the
     /// end of the term.
-    #[async_recursion]
-    async fn term(&mut self) -> Result<Term, ParseError> {
-        let mut term = Term::Factor(self.factor().await?);
+    fn term(&mut self) -> Result<Term, ParseError> {
+        let mut term = Term::Factor(self.factor()?);
 
         loop {
-            let next = self.reader.peek_range(0..=1).await;
+            if self.integral_depth > 0 && self.next_is_differential() {
+                break;
+            }
+            let next = self.reader.peek_range(0..=1);
             match next[..] {
                 [Token::Asterisk, _] => {
-                    self.reader.skip().await;
-                    let rhs = self.factor().await?;
+                    self.reader.skip();
+                    let rhs = self.factor()?;
                     term =
                         Term::Multiply(MulType::Asterisk, Box::new(term), rhs);
                 }
                 [Token::Backslash, Token::Identifier(ident)]
                     if ident == "cdot"
                         || ident == "cdotp"
-                        || ident == "times" =>
+                        || ident == "times"
+                        || ident == "odot"
+                        || ident == "oslash" =>
                 {
                     let mul_type = match ident.as_str() {
                         "cdot" | "cdotp" => MulType::Cdot,
                         "times" => MulType::Times,
+                        "odot" => MulType::Odot,
+                        "oslash" => MulType::Oslash,
                         _ => unreachable!("invalid multype"),
                     };
-                    self.reader.skip().await;
-                    self.reader.skip().await;
-                    let rhs = self.factor().await?;
+                    self.reader.skip();
+                    self.reader.skip();
+                    let rhs = self.factor()?;
                     term = Term::Multiply(mul_type, Box::new(term), rhs);
                 }
                 [Token::Slash, _] => {
-                    self.reader.skip().await;
-                    let rhs = self.factor().await?;
+                    self.reader.skip();
+                    let rhs = self.factor()?;
                     term = Term::Divide(Box::new(term), rhs);
                 }
                 [Token::Backslash, Token::Backslash] => {
                     break;
                 }
                 [Token::Backslash, Token::Identifier(ident)]
-                    if ident == "end" =>
+                    if ident == "end"
+                        || ident == "rfloor"
+                        || ident == "rceil" =>
                 {
                     break;
                 }
@@ -154,7 +454,13 @@ impl Parser {
                 | Token::NumberLiteral(_)
                 | Token::Backslash
                 | Token::LeftParenthesis, _] => {
-                    let rhs = self.factor().await?;
+                    let rhs = self.factor()?;
+                    term =
+                        Term::Multiply(MulType::Implicit, Box::new(term), rhs);
+                }
+                // Implicit multiplication by a norm, like 2\|v\|
+                [Token::DoubleVerticalPipe, _] if !self.in_norm => {
+                    let rhs = self.factor()?;
                     term =
                         Term::Multiply(MulType::Implicit, Box::new(term), rhs);
                 }
@@ -167,79 +473,157 @@ impl Parser {
 
     /// If the next character is an identifier, ensure that it only has length
     /// one by splitting it.
-    async fn split_next_identifier(&mut self) {
-        if let Token::Identifier(text) = self.reader.peek().await {
+    fn split_next_identifier(&mut self) {
+        if let Token::Identifier(text) = self.reader.peek() {
             if text.len() > 1 {
                 let mut tokens = Vec::new();
                 for c in text.chars() {
                     tokens.push(Token::Identifier(c.to_string()));
                 }
-                self.reader.replace(0..=0, tokens).await;
+                self.reader.replace(0..=0, tokens);
             }
         }
     }
 
     /// Parse a factor, and if the factor has an exponent attached to it, parse
     /// the exponent too.
-    #[async_recursion]
-    async fn factor(&mut self) -> Result<Factor, ParseError> {
+    ///
+    /// When recovering, a factor that could not be parsed becomes a
+    /// [Factor::Error] instead.
+    fn factor(&mut self) -> Result<Factor, ParseError> {
+        if self.recovered.is_none() {
+            return self.factor_without_recovery();
+        }
+        let start = self.reader.peek_span();
+        if self.next_is_sync_point() {
+            // Nothing to parse, like in `1+}` or `\frac{}{2}`
+            let token = self.reader.peek().clone();
+            self.recover(ParseError::InvalidFactor { token }, start);
+            return Ok(Factor::Error(start));
+        }
+        match self.factor_without_recovery() {
+            Ok(factor) => Ok(factor),
+            Err(source) => {
+                // The error may be at a token that ends the part, like the
+                // `&` in `(1&2`, where parsing should continue.
+                let at_sync_point = source.token().is_some_and(|token| {
+                    self.reader.last() == Some(token)
+                        && matches!(
+                            token,
+                            Token::RightCurlyBracket
+                                | Token::RightParenthesis
+                                | Token::RightBracket
+                                | Token::Ampersand
+                                | Token::Equals
+                                | Token::Semicolon
+                        )
+                });
+                let end = self.reader.last_span();
+                self.recover(source, end);
+                if at_sync_point {
+                    self.reader.unread();
+                }
+                self.synchronize();
+                Ok(Factor::Error(start.to(self.reader.last_span())))
+            }
+        }
+    }
+
+    /// Parse a factor like [Parser::factor], but stopping at the first error.
+    fn factor_without_recovery(&mut self) -> Result<Factor, ParseError> {
         // Split identifiers into single characters
-        self.split_next_identifier().await;
+        self.split_next_identifier();
         // First read a factor, but then see if we have exponents after it.
         // Exponents need to be baked into the factor since exponents should
         // be evaluated before multiplications.
         //
-        let factor = match self.reader.read().await {
+        let start = self.reader.peek_span();
+        let factor = match self.reader.read() {
+            // 0_{m\times n} is a zero matrix
+            Token::NumberLiteral(val)
+                if val.raw == "0"
+                    && self.reader.peek() == Token::Underscore
+                    && self.context.is_defined_function(
+                        &MathIdentifier::from_operator_name("zeros"),
+                    ) =>
+            {
+                self.zero_matrix()?
+            }
             Token::NumberLiteral(val) => Factor::Constant(val.parsed),
             Token::LeftParenthesis => {
                 // In most cases, this is one value, for example (1+1).
                 // But parse many values since it could be a vector (1,2,3)
-                // if commas are encountered.
+                // if commas are encountered, or a range like (1,\ldots,n).
                 let mut values = Vec::with_capacity(1);
+                let mut ellipses = Vec::new();
                 loop {
-                    let expr = self.expr().await?;
-                    values.push(expr);
-                    let next = self.reader.peek().await;
+                    if self.next_is_ellipsis() {
+                        self.reader.skip();
+                        self.reader.skip();
+                        ellipses.push(values.len());
+                    } else {
+                        values.push(self.expr()?);
+                    }
+                    let next = self.reader.peek();
                     if next != Token::Comma {
                         break;
                     }
-                    self.reader.skip().await;
+                    self.reader.skip();
                 }
-                self.expect(Token::RightParenthesis).await?;
+                self.expect(Token::RightParenthesis)?;
                 let len = values.len();
-                if len == 1 {
-                    Factor::Parenthesis(Box::new(values.remove(0)))
-                } else {
-                    Factor::Matrix(Matrix::new(values, 1, len))
+                match ellipses[..] {
+                    [] if len == 1 => {
+                        Factor::Parenthesis(Box::new(values.remove(0)))
+                    }
+                    [] => Factor::from(Matrix::new(values, 1, len)),
+                    // The ellipsis comes before the last element, after the
+                    // first one or two
+                    [position]
+                        if position + 1 == len && (2..=3).contains(&len) =>
+                    {
+                        Factor::FunctionCall(FunctionCall::new(
+                            MathIdentifier::from_operator_name("range"),
+                            values,
+                        ))
+                    }
+                    _ => return Err(ParseError::MisplacedEllipsis),
                 }
             }
             Token::Backslash => {
-                let command = self.read_identifier().await?;
-                self.factor_command(&command).await?
+                let command = self.read_identifier()?;
+                self.factor_command(&command)?
             }
             Token::VerticalPipe => {
-                let expr = self.expr().await?;
-                self.expect(Token::VerticalPipe).await?;
+                let expr = self.expr()?;
+                self.expect(Token::VerticalPipe)?;
                 Factor::Abs(Box::new(expr))
             }
+            Token::DoubleVerticalPipe => self.norm()?,
             Token::Identifier(ident) => {
                 if ident.chars().count() != 1 {
                     panic!("Identifier was not splitted correctly.")
                 }
                 let math_identifier = MathIdentifier::from_single_ident(&ident);
                 let math_identifier =
-                    self.math_identifier_tail(math_identifier).await?;
-                self.factor_identifier(math_identifier).await?
+                    self.math_identifier_tail(math_identifier)?;
+                self.factor_identifier(math_identifier)?
             }
-            Token::Minus => Factor::Constant(-1.0),
+            Token::Minus => Factor::Constant((-1.0).into()),
             token => return Err(ParseError::InvalidFactor { token }),
         };
+        let factor = factor.with_span(start.to(self.reader.last_span()));
 
-        let next = self.reader.peek().await;
-        if next == Token::Caret {
+        let next = self.reader.peek();
+        // The caret in for example x \to 0^+ is the side of a limit and not
+        // an exponent.
+        if next == Token::Caret
+            && (self.limit_depth == 0 || self.peek_limit_direction().is_none())
+        {
             // This factor is an exponential
-            self.reader.skip().await;
-            return self.factor_exponent(factor).await;
+            self.reader.skip();
+            let power = self.factor_exponent(factor)?;
+            return Ok(power.with_span(start.to(self.reader.last_span())));
         }
 
         Ok(factor)
@@ -248,46 +632,85 @@ impl Parser {
     /// Parse a factor that is a LaTeX command.
     ///
     /// The `command` parameter is the LaTeX command.
-    async fn factor_command(
-        &mut self,
-        command: &str,
-    ) -> Result<Factor, ParseError> {
-        Ok(match command {
-            "sqrt" => {
-                let next = self.reader.peek().await;
+    fn factor_command(&mut self, command: &str) -> Result<Factor, ParseError> {
+        Ok(match Command::from_latex(command) {
+            Some(Command::Sqrt) => {
+                let next = self.reader.peek();
                 let mut degree = None;
                 if next == Token::LeftBracket {
-                    self.reader.skip().await;
-                    degree = Some(Box::new(self.expr().await?));
-                    self.expect(Token::RightBracket).await?;
+                    self.reader.skip();
+                    degree = Some(Box::new(self.expr()?));
+                    self.expect(Token::RightBracket)?;
+                }
+                self.expect(Token::LeftCurlyBracket)?;
+                let radicand = Box::new(self.expr()?);
+                self.expect(Token::RightCurlyBracket)?;
+                Factor::Root {
+                    degree,
+                    radicand,
+                    span: NodeSpan::NONE,
                 }
-                self.expect(Token::LeftCurlyBracket).await?;
-                let radicand = Box::new(self.expr().await?);
-                self.expect(Token::RightCurlyBracket).await?;
-                Factor::Root { degree, radicand }
             }
-            "frac" => {
-                self.expect(Token::LeftCurlyBracket).await?;
-                let numerator = Box::new(self.expr().await?);
-                self.expect(Token::RightCurlyBracket).await?;
-                self.expect(Token::LeftCurlyBracket).await?;
-                let denominator = Box::new(self.expr().await?);
-                self.expect(Token::RightCurlyBracket).await?;
+            Some(Command::Frac) if self.is_derivative_fraction() => {
+                Factor::Derivative(self.derivative_fraction()?)
+            }
+            Some(Command::Frac) => {
+                self.expect(Token::LeftCurlyBracket)?;
+                let numerator = Box::new(self.expr()?);
+                self.expect(Token::RightCurlyBracket)?;
+                self.expect(Token::LeftCurlyBracket)?;
+                let denominator = Box::new(self.expr()?);
+                self.expect(Token::RightCurlyBracket)?;
                 Factor::Fraction(numerator, denominator)
             }
-            "begin" => {
-                self.expect(Token::LeftCurlyBracket).await?;
-                let s = self.read_identifier().await?;
+            Some(Command::Sum) => Factor::Sum(self.indexed_operation()?),
+            Some(Command::Prod) => Factor::Product(self.indexed_operation()?),
+            Some(Command::Int) => Factor::Integral(self.integral()?),
+            Some(Command::Lim) => Factor::Limit(self.limit()?),
+            Some(Command::Nabla) => {
+                let (body, variables, point) =
+                    self.vector_derivative_operand()?;
+                body.gradient(&variables).substitute_all(&point)
+            }
+            Some(Command::Operatorname) => {
+                self.expect(Token::LeftCurlyBracket)?;
+                let name = self.read_identifier()?;
+                self.expect(Token::RightCurlyBracket)?;
+                let Some(operator) = VectorOperator::from_latex(&name) else {
+                    let ident = MathIdentifier::from_operator_name(&name);
+                    if !self.context.is_defined_function(&ident) {
+                        return Err(ParseError::UnknownOperator { name });
+                    }
+                    if self.reader.peek() == Token::Underscore {
+                        return self.subscripted_call(ident);
+                    }
+                    return self.factor_identifier(ident);
+                };
+                let (body, variables, point) =
+                    self.vector_derivative_operand()?;
+                operator.apply(&body, &variables).substitute_all(&point)
+            }
+            Some(Command::Lfloor) => self.delimited_call("floor", "rfloor")?,
+            Some(Command::Lceil) => self.delimited_call("ceil", "rceil")?,
+            // \log_{b} x is the logarithm of x in base b
+            Some(Command::Log) if self.reader.peek() == Token::Underscore => {
+                let ident =
+                    MathIdentifier::from_single_symbol(OtherSymbol::Log);
+                self.subscripted_call(ident)?
+            }
+            Some(Command::Begin) => {
+                self.expect(Token::LeftCurlyBracket)?;
+                let s = self.read_identifier()?;
                 match s.as_str() {
                     "bmatrix" | "pmatrix" | "Bmatrix" => {
-                        self.expect(Token::RightCurlyBracket).await?;
-                        Factor::Matrix(self.matrix(s).await?)
+                        self.expect(Token::RightCurlyBracket)?;
+                        Factor::Matrix(self.matrix(s)?, NodeSpan::NONE)
                     }
                     "vmatrix" | "Vmatrix" => {
-                        self.expect(Token::RightCurlyBracket).await?;
-                        let matrix = self.matrix(s).await?;
+                        self.expect(Token::RightCurlyBracket)?;
+                        let matrix = self.matrix(s)?;
                         Factor::Abs(Box::new(MathExpr::Term(Term::Factor(
-                            Factor::Matrix(matrix),
+                            Factor::Matrix(matrix, NodeSpan::NONE),
                         ))))
                     }
                     _ => {
@@ -298,14 +721,92 @@ impl Parser {
                 }
             }
             _ => {
-                let ident = self.parse_math_identifier_command(command).await?;
-                self.factor_identifier(ident).await?
+                let ident = self.parse_math_identifier_command(command)?;
+                self.factor_identifier(ident)?
             }
         })
     }
 
+    /// Parse an expression between a pair of delimiters like `\lfloor x
+    /// \rfloor` as a call to the operator `name`, where the opening
+    /// delimiter was just read.
+    fn delimited_call(
+        &mut self,
+        name: &str,
+        closing: &str,
+    ) -> Result<Factor, ParseError> {
+        let argument = self.expr()?;
+        self.expect(Token::Backslash)?;
+        self.expect(Token::Identifier(closing.to_owned()))?;
+        Ok(Factor::FunctionCall(FunctionCall::new(
+            MathIdentifier::from_operator_name(name),
+            vec![argument],
+        )))
+    }
+
+    /// Parse a call to a function with a subscript, where the subscript is
+    /// passed as the last argument, so `\log_{b} x` is `\log(x, b)` and
+    /// `\operatorname{proj}_u v` is `\operatorname{proj}(v, u)`.
+    fn subscripted_call(
+        &mut self,
+        ident: MathIdentifier,
+    ) -> Result<Factor, ParseError> {
+        self.expect(Token::Underscore)?;
+        let subscript = self.script_argument()?;
+        let Factor::FunctionCall(mut call) =
+            self.factor_function_call(ident)?
+        else {
+            unreachable!("factor_function_call returns a call")
+        };
+        call.arguments.push(subscript);
+        Ok(Factor::FunctionCall(call))
+    }
+
+    /// Whether the next tokens are an ellipsis like `\ldots`.
+    fn next_is_ellipsis(&mut self) -> bool {
+        matches!(
+            &self.reader.peek_range(0..=1)[..],
+            [Token::Backslash, Token::Identifier(ident)]
+                if ident == "ldots" || ident == "dots" || ident == "cdots"
+        )
+    }
+
+    /// Parse the size of a zero matrix like `0_{m\times n}` or `0_n` as a
+    /// call to the operator `zeros`, where the `0` was just read.
+    fn zero_matrix(&mut self) -> Result<Factor, ParseError> {
+        self.expect(Token::Underscore)?;
+        let arguments = match self.script_argument()? {
+            MathExpr::Term(Term::Multiply(MulType::Times, rows, columns)) => {
+                vec![MathExpr::Term(*rows), columns.into()]
+            }
+            size => vec![size],
+        };
+        Ok(Factor::FunctionCall(FunctionCall::new(
+            MathIdentifier::from_operator_name("zeros"),
+            arguments,
+        )))
+    }
+
+    /// Parse a norm like `\|v\|` or `\|v\|_p` as a call to the operator
+    /// `norm`, where the opening double vertical pipe was just read.
+    fn norm(&mut self) -> Result<Factor, ParseError> {
+        let outer = std::mem::replace(&mut self.in_norm, true);
+        let argument = self.expr();
+        self.in_norm = outer;
+        let mut arguments = vec![argument?];
+        self.expect(Token::DoubleVerticalPipe)?;
+        if self.reader.peek() == Token::Underscore {
+            self.reader.skip();
+            arguments.push(self.script_argument()?);
+        }
+        Ok(Factor::FunctionCall(FunctionCall::new(
+            MathIdentifier::from_operator_name("norm"),
+            arguments,
+        )))
+    }
+
     /// Parse a [MathIdentifier] that starts with a command.
-    async fn parse_math_identifier_command(
+    fn parse_math_identifier_command(
         &mut self,
         command: &str,
     ) -> Result<MathIdentifier, ParseError> {
@@ -315,7 +816,7 @@ impl Parser {
             let math_str = MathString::from_letters(vec![letter]);
             MathIdentifier::Name(math_str)
         } else if let Some(modifier) = modifier {
-            let inner = self.parse_inner_math_identifier().await?;
+            let inner = self.parse_inner_math_identifier()?;
             MathIdentifier::Modifier(modifier, Box::new(inner))
         } else {
             return Err(ParseError::InvalidIdentifierCommmand {
@@ -323,34 +824,73 @@ impl Parser {
             });
         };
         // Parse index if there is one and then return.
-        self.math_identifier_tail(math_identifier).await
+        self.math_identifier_tail(math_identifier)
     }
 
     /// Parse an inner identifier which may be one character long, or if
     /// surrounded by curly brackets may hold an entire inner identifier.
-    async fn parse_inner_math_identifier(
-        &mut self,
-    ) -> Result<MathExpr, ParseError> {
-        if self.reader.peek().await == Token::LeftCurlyBracket {
-            self.reader.skip().await;
-            let inner = self.expr().await?;
-            self.expect(Token::RightCurlyBracket).await?;
-            Ok(inner)
+    ///
+    /// Several comma separated expressions, like the `2,3` in `A_{2,3}`, are
+    /// parsed as a row vector.
+    fn parse_inner_math_identifier(&mut self) -> Result<MathExpr, ParseError> {
+        if self.reader.peek() == Token::LeftCurlyBracket {
+            self.reader.skip();
+            let mut entries = vec![self.expr()?];
+            while self.reader.peek() == Token::Comma {
+                self.reader.skip();
+                entries.push(self.expr()?);
+            }
+            self.expect(Token::RightCurlyBracket)?;
+            let len = entries.len();
+            Ok(match len {
+                1 => entries.remove(0),
+                _ => {
+                    Factor::Matrix(Matrix::new(entries, 1, len), NodeSpan::NONE)
+                        .into()
+                }
+            })
         } else {
-            todo!("handle single character inner. For example \\overline x or x_1.");
+            // A single character, like the 1 in x_1 or the x in \overline x
+            self.split_next_identifier();
+            self.split_next_digit();
+            match self.reader.read() {
+                Token::Identifier(ident) => {
+                    Ok(MathIdentifier::from_single_ident(&ident).into())
+                }
+                Token::NumberLiteral(number) => {
+                    Ok(Factor::Constant(number.parsed).into())
+                }
+                token => Err(ParseError::InvalidIdentifierToken { token }),
+            }
+        }
+    }
+
+    /// If the next token is an integer with several digits, split off the
+    /// first digit, so `x_12` is `x_1` times two.
+    fn split_next_digit(&mut self) {
+        if let Token::NumberLiteral(number) = self.reader.peek() {
+            let raw = &number.raw;
+            if raw.len() > 1 && raw.bytes().all(|byte| byte.is_ascii_digit()) {
+                let (first, rest) = raw.split_at(1);
+                let tokens = vec![
+                    Token::NumberLiteral(first.to_owned().into()),
+                    Token::NumberLiteral(rest.to_owned().into()),
+                ];
+                self.reader.replace(0..=0, tokens);
+            }
         }
     }
 
     /// Parse the index of an identifier, if there is an index. Otherwise return
     /// the identifier as is.
-    async fn math_identifier_tail(
+    fn math_identifier_tail(
         &mut self,
         ident: MathIdentifier,
     ) -> Result<MathIdentifier, ParseError> {
         // Check for index
-        Ok(if self.reader.peek().await == Token::Underscore {
-            self.reader.skip().await;
-            let index = self.parse_inner_math_identifier().await?;
+        Ok(if self.reader.peek() == Token::Underscore {
+            self.reader.skip();
+            let index = self.parse_inner_math_identifier()?;
             MathIdentifier::Index {
                 name: Box::new(ident),
                 index: Box::new(index),
@@ -363,153 +903,657 @@ impl Parser {
 
     /// Parse a factor when an identifier was just read. This may either be a
     /// function or a variable.
-    async fn factor_identifier(
+    fn factor_identifier(
         &mut self,
         identifier: MathIdentifier,
     ) -> Result<Factor, ParseError> {
         // This might be a function.
         if self.context.is_defined_function(&identifier) {
-            Ok(self.factor_function_call(identifier).await?)
+            // Prime notation, for example f'(x)
+            let mut order = 0;
+            while *self.reader.peek() == Token::Apostrophe {
+                self.reader.skip();
+                order += 1;
+            }
+            let call = self.factor_function_call(identifier)?;
+            if order == 0 {
+                return Ok(call);
+            }
+            Ok(Factor::Derivative(self.prime_derivative(call, order)?))
         } else {
-            Ok(Factor::Variable(identifier))
+            Ok(Factor::from(identifier))
         }
     }
 
-    /// Parse the exponent part of a factor.
+    /// Parse the bounds and body of an indexed operation like `\sum` and
+    /// `\prod`, for example `_{i=1}^{n} i^2`.
     ///
-    /// The `factor` parameter is the base, and the tokens to be parsed by this
-    /// function is the exponent.
-    async fn factor_exponent(
-        &mut self,
-        factor: Factor,
-    ) -> Result<Factor, ParseError> {
-        let next = self.reader.peek().await;
-        let exponent = match next {
-            Token::LeftCurlyBracket => {
-                self.reader.skip().await;
-                let expr = self.expr().await?;
-                self.expect(Token::RightCurlyBracket).await?;
-                expr
-            }
-            Token::Backslash => {
-                let factor = self.factor().await?;
-                MathExpr::Term(Term::Factor(factor))
-            }
-            Token::Identifier(ident) => {
-                let ident = ident.clone();
-                self.reader.skip().await;
-                if ident.len() != 1 {
-                    panic!(
-                        "The normalizer did not correctly handle exponent, got ident = {}",
-                        ident
-                    );
+    /// The body is parsed as a term, so `\sum_{i=1}^{n} i + 1` is the sum
+    /// plus one.
+    fn indexed_operation(&mut self) -> Result<IndexedOperation, ParseError> {
+        self.expect(Token::Underscore)?;
+        self.expect(Token::LeftCurlyBracket)?;
+        let index = self.bound_variable()?;
+        self.expect(Token::Equals)?;
+        let lower = self.expr()?;
+        self.expect(Token::RightCurlyBracket)?;
+        self.expect(Token::Caret)?;
+        let upper = self.script_argument()?;
+        let body = self.term()?;
+        Ok(IndexedOperation::new(
+            index,
+            lower.boxed(),
+            upper.boxed(),
+            MathExpr::Term(body).boxed(),
+        ))
+    }
+
+    /// Parse the bounds, integrand and differential of a definite integral,
+    /// for example `_{0}^{1} x^2 \, dx`.
+    ///
+    /// The integrand is parsed as an expression that ends at the differential,
+    /// which may be written as `dx`, `d x` or `\mathrm{d}x`.
+    fn integral(&mut self) -> Result<Integral, ParseError> {
+        let mut lower = None;
+        let mut upper = None;
+        // The bounds may be written in any order, for example \int^{1}_{0}
+        loop {
+            match self.reader.peek() {
+                Token::Underscore if lower.is_none() => {
+                    self.reader.skip();
+                    lower = Some(self.script_argument()?);
                 }
-                MathExpr::Term(Term::Factor(Factor::Variable(
-                    MathIdentifier::Name(MathString::from_letters(vec![
-                        MathLetter::Ascii(ident.bytes().next().unwrap()),
-                    ])),
-                )))
-            }
-            Token::NumberLiteral(num) => {
-                if num.raw.len() != 1 {
-                    panic!(
-                        "The normalizer did not correctly handle exponent, got num = {:?}",
-                        num
-                    );
+                Token::Caret if upper.is_none() => {
+                    self.reader.skip();
+                    upper = Some(self.script_argument()?);
                 }
-                let parsed = num.parsed;
-                self.reader.skip().await;
-                MathExpr::Term(Term::Factor(Factor::Constant(parsed)))
-            }
-            token => {
-                return Err(ParseError::Invalid {
-                    token: token.clone(),
-                })
+                _ => break,
             }
+        }
+        let (Some(lower), Some(upper)) = (lower, upper) else {
+            return Err(ParseError::IndefiniteIntegral);
         };
 
-        Ok(Factor::Power {
-            base: Box::new(factor),
-            exponent: Box::new(exponent),
-        })
+        self.integral_depth += 1;
+        let body = if self.next_is_differential() {
+            // For example \int_{0}^{1} dx
+            Ok(1f64.into())
+        } else {
+            self.expr()
+        };
+        self.integral_depth -= 1;
+        let body = body?;
+        let variable = self.differential()?;
+        Ok(Integral::new(
+            variable,
+            lower.boxed(),
+            upper.boxed(),
+            body.boxed(),
+        ))
     }
-    ///parsing a suspected function
-    async fn factor_function_call(
-        &mut self,
-        function_name: MathIdentifier,
-    ) -> Result<Factor, ParseError> {
-        let mut arguments = Vec::new();
-        // Read arguments in parenthesis, eg. f(1, 2)
-        if self.reader.peek().await == Token::LeftParenthesis {
-            self.reader.skip().await;
-            loop {
-                let next = self.reader.peek().await;
-                match next {
-                    Token::RightParenthesis => break,
-                    Token::Comma => self.reader.skip().await,
-                    _ => {
-                        let expr = self.expr().await?;
-                        arguments.push(expr);
+
+    /// Whether the next tokens are the differential of an integral, for
+    /// example `dx`, `d\theta` or `\mathrm{d}x`.
+    fn next_is_differential(&mut self) -> bool {
+        self.is_differential_at(0)
+    }
+
+    /// Whether a differential together with its variable, like `dx`,
+    /// `d\theta` or `\mathrm{d}x`, starts at the `n`th next token.
+    fn is_differential_at(&mut self, n: usize) -> bool {
+        match &self.reader.peek_range(0..=n + 3)[n..] {
+            [Token::Identifier(ident), next, after, ..] if ident == "d" => {
+                match (next, after) {
+                    (Token::Identifier(_), _) => true,
+                    (Token::Backslash, Token::Identifier(command)) => {
+                        MathLetter::from_latex(command).is_some()
                     }
+                    _ => false,
                 }
             }
-            self.expect(Token::RightParenthesis).await?;
-        } else {
-            // Read one explicit argument, for example \ln 2
-            let arg = self.term().await?;
-            arguments.push(MathExpr::Term(arg));
+            [Token::Identifier(ident), ..] => is_differential_identifier(ident),
+            [Token::Backslash, Token::Identifier(command), Token::LeftCurlyBracket, Token::Identifier(d)] => {
+                command == "mathrm" && d == "d"
+            }
+            _ => false,
         }
+    }
 
-        Ok(Factor::FunctionCall(FunctionCall {
-            function_name,
-            arguments,
-        }))
+    /// Parse the differential at the end of an integral and return the
+    /// variable of integration.
+    fn differential(&mut self) -> Result<MathIdentifier, ParseError> {
+        self.differential_symbol()?;
+        self.differential_variable()
     }
-    ///Parsing a suspected matrix
-    async fn matrix(
+
+    /// Parse the `d`, `\mathrm{d}` or `\partial` that starts a differential,
+    /// leaving the variable, for example the `x` in `dx`.
+    fn differential_symbol(
         &mut self,
-        matrix_type: String,
-    ) -> Result<Matrix<MathExpr>, ParseError> {
-        let mut rows = Vec::new();
-        let mut current_row = Vec::new();
-        let mut column_count = Option::None;
+    ) -> Result<DerivativeNotation, ParseError> {
+        if *self.reader.peek() != Token::Backslash {
+            self.split_next_identifier();
+            self.expect(Token::Identifier("d".to_owned()))?;
+            return Ok(DerivativeNotation::Leibniz);
+        }
+        self.reader.skip();
+        match self.read_identifier()?.as_str() {
+            "partial" => Ok(DerivativeNotation::Partial),
+            "mathrm" => {
+                self.expect(Token::LeftCurlyBracket)?;
+                self.expect(Token::Identifier("d".to_owned()))?;
+                self.expect(Token::RightCurlyBracket)?;
+                Ok(DerivativeNotation::Leibniz)
+            }
+            command => Err(ParseError::UnexpectedToken {
+                expected: vec![
+                    Token::Identifier(Command::Partial.latex_code().to_owned()),
+                    Token::Identifier(
+                        ModifierType::Mathrm.latex_code().to_owned(),
+                    ),
+                ],
+                found: Token::Identifier(command.to_owned()),
+            }),
+        }
+    }
+
+    /// Parse the variable of a differential, without any exponent, for
+    /// example the `x` in `dx^2`.
+    fn differential_variable(&mut self) -> Result<MathIdentifier, ParseError> {
+        self.split_next_identifier();
+        match self.reader.read() {
+            Token::Identifier(ident) => {
+                let ident = MathIdentifier::from_single_ident(&ident);
+                self.math_identifier_tail(ident)
+            }
+            Token::Backslash => {
+                let command = self.read_identifier()?;
+                self.parse_math_identifier_command(&command)
+            }
+            found => Err(ParseError::InvalidBoundVariable {
+                found: found.to_string(),
+            }),
+        }
+    }
+
+    /// Whether the numerator of a derivative in Leibniz notation, like `d`,
+    /// `d^2`, `dy`, `\mathrm{d}` or `\partial f`, starts at the `n`th next
+    /// token.
+    fn is_derivative_numerator_at(&mut self, n: usize) -> bool {
+        match &self.reader.peek_range(0..=n + 3)[n..] {
+            [Token::Identifier(ident), next, ..] if ident == "d" => matches!(
+                next,
+                Token::RightCurlyBracket
+                    | Token::Caret
+                    | Token::Identifier(_)
+                    | Token::Backslash
+            ),
+            [Token::Identifier(ident), ..] => is_differential_identifier(ident),
+            _ => self.is_partial_at(n) || self.is_differential_at(n),
+        }
+    }
+
+    /// Whether a `\partial` is the `n`th next token.
+    fn is_partial_at(&mut self, n: usize) -> bool {
+        matches!(
+            &self.reader.peek_range(0..=n + 1)[n..],
+            [Token::Backslash, Token::Identifier(command)] if command == "partial"
+        )
+    }
 
+    /// Whether the arguments of a `\frac` are a derivative in Leibniz
+    /// notation, meaning that the numerator starts with a differential and
+    /// the denominator with the differential of a variable, for example
+    /// `{d}{dx}` or `{\partial^2 f}{\partial x^2}`.
+    fn is_derivative_fraction(&mut self) -> bool {
+        if *self.reader.peek() != Token::LeftCurlyBracket
+            || !self.is_derivative_numerator_at(1)
+        {
+            return false;
+        }
+        // Find the end of the numerator
+        let mut depth = 0;
+        let mut end = 0;
         loop {
-            let cell = self.expr().await?;
-            current_row.push(cell);
+            match self.reader.peekn(end) {
+                Token::LeftCurlyBracket => depth += 1,
+                Token::RightCurlyBracket if depth == 1 => break,
+                Token::RightCurlyBracket => depth -= 1,
+                Token::EndOfContent => return false,
+                _ => {}
+            }
+            end += 1;
+        }
+        *self.reader.peekn(end + 1) == Token::LeftCurlyBracket
+            && (self.is_differential_at(end + 2) || self.is_partial_at(end + 2))
+    }
 
-            let next = self.reader.peek().await;
-            match next {
-                Token::Ampersand => {
-                    self.reader.skip().await;
-                    continue;
+    /// Parse a derivative in Leibniz notation after `\frac`, for example
+    /// `{d^2 y}{dx^2}` or `{\partial}{\partial x} f`.
+    ///
+    /// If the numerator only holds the differential, the body is the term
+    /// after the fraction, like the body of [Factor::Sum].
+    fn derivative_fraction(&mut self) -> Result<Derivative, ParseError> {
+        self.expect(Token::LeftCurlyBracket)?;
+        let notation = self.differential_symbol()?;
+        let order = match *self.reader.peek() {
+            Token::Caret => {
+                self.reader.skip();
+                self.derivative_order()?
+            }
+            _ => 1,
+        };
+        let body = match *self.reader.peek() {
+            Token::RightCurlyBracket => None,
+            _ => Some(self.derivative_numerator_body()?),
+        };
+        self.expect(Token::RightCurlyBracket)?;
+
+        self.expect(Token::LeftCurlyBracket)?;
+        let mut variables = Vec::new();
+        while *self.reader.peek() != Token::RightCurlyBracket {
+            self.differential_symbol()?;
+            let variable = self.differential_variable()?;
+            let power = match *self.reader.peek() {
+                Token::Caret => {
+                    self.reader.skip();
+                    self.derivative_order()?
                 }
-                Token::Backslash => {
-                    if self.reader.peekn(1).await == Token::Backslash {
-                        // Two backslashes means end of row.
-                        self.reader.skip().await;
-                        self.reader.skip().await;
-                        match column_count {
-                            Some(column_count) => {
-                                if column_count != current_row.len() {
-                                    return Err(ParseError::MismatchedMatrixColumnSize {
-                                        prev: column_count,
-                                        current: current_row.len(),
-                                    });
-                                }
-                            }
-                            None => column_count = Some(current_row.len()),
-                        }
+                _ => 1,
+            };
+            let denominator = variables.len() + power;
+            if denominator > order {
+                return Err(ParseError::DerivativeOrderMismatch {
+                    numerator: order,
+                    denominator,
+                });
+            }
+            variables.extend(std::iter::repeat_n(variable, power));
+        }
+        self.expect(Token::RightCurlyBracket)?;
+        if order != variables.len() {
+            return Err(ParseError::DerivativeOrderMismatch {
+                numerator: order,
+                denominator: variables.len(),
+            });
+        }
+
+        let body = match body {
+            Some(body) => body,
+            None => MathExpr::Term(self.term()?),
+        };
+        Ok(Derivative::new(body.boxed(), variables, notation))
+    }
+
+    /// Parse the order of a derivative, for example the `2` in `d^2`, which
+    /// is at most [MAX_DERIVATIVE_ORDER].
+    fn derivative_order(&mut self) -> Result<usize, ParseError> {
+        let found = self.script_argument()?;
+        if let MathExpr::Term(Term::Factor(Factor::Constant(order))) = &found {
+            let order = order
+                .to_integer()
+                .and_then(|order| usize::try_from(order).ok());
+            if let Some(order @ 1..=MAX_DERIVATIVE_ORDER) = order {
+                return Ok(order);
+            }
+        }
+        Err(ParseError::InvalidDerivativeOrder {
+            found: found.to_latex(),
+        })
+    }
+
+    /// Parse the expression in the numerator of a derivative, for example the
+    /// `f` in `\frac{\partial f}{\partial x}`.
+    ///
+    /// A user defined function without arguments is called with its own
+    /// parameters.
+    fn derivative_numerator_body(&mut self) -> Result<MathExpr, ParseError> {
+        self.split_next_identifier();
+        let name = match self.reader.peek_range(0..=1)[..] {
+            [Token::Identifier(name), Token::RightCurlyBracket] => {
+                Some(MathIdentifier::from_single_ident(name))
+            }
+            _ => None,
+        };
+        if let Some(name) = name {
+            if let Some(MathFunction::Foreign(function)) =
+                self.context.functions.get(&name)
+            {
+                let arguments = function
+                    .input
+                    .iter()
+                    .cloned()
+                    .map(MathExpr::from)
+                    .collect();
+                self.reader.skip();
+                return Ok(FunctionCall::new(name, arguments).into());
+            }
+        }
+        self.expr()
+    }
+
+    /// Parse what the gradient, Jacobian or Hessian is taken of, and return
+    /// the expression, the variables and the point to evaluate it at.
+    ///
+    /// This is either a user defined function, for example `f` or `f(1, 2)`,
+    /// which is differentiated with respect to its parameters, or a list of
+    /// variables followed by a term, for example `_{x,y} x^2 y`.
+    fn vector_derivative_operand(
+        &mut self,
+    ) -> Result<VectorDerivativeOperand, ParseError> {
+        if *self.reader.peek() == Token::Underscore {
+            self.reader.skip();
+            let variables = self.variable_list()?;
+            let body = MathExpr::Term(self.term()?);
+            return Ok((body, variables, Vec::new()));
+        }
+        let name =
+            self.differential_variable().map_err(|error| match error {
+                ParseError::InvalidBoundVariable { found } => {
+                    ParseError::InvalidVectorDerivativeOperand { found }
+                }
+                error => error,
+            })?;
+        let Some(MathFunction::Foreign(function)) =
+            self.context.functions.get(&name).cloned()
+        else {
+            return Err(ParseError::ExpectedFunction {
+                found: name.to_latex(),
+            });
+        };
+        let mut point = Vec::new();
+        if *self.reader.peek() == Token::LeftParenthesis {
+            let Factor::FunctionCall(call) = self.factor_function_call(name)?
+            else {
+                unreachable!(
+                    "factor_function_call always returns a function call"
+                )
+            };
+            if call.arguments.len() != function.input.len() {
+                return Err(ParseError::ArgumentCount {
+                    expected: function.input.len(),
+                    found: call.arguments.len(),
+                });
+            }
+            point =
+                function.input.iter().cloned().zip(call.arguments).collect();
+        }
+        Ok((function.expr, function.input, point))
+    }
+
+    /// Parse a comma separated list of variables in curly brackets, for
+    /// example `{x, y}`, or a single variable, for example `x`.
+    fn variable_list(&mut self) -> Result<Vec<MathIdentifier>, ParseError> {
+        if *self.reader.peek() != Token::LeftCurlyBracket {
+            return Ok(vec![self.differential_variable()?]);
+        }
+        self.reader.skip();
+        let mut variables = vec![self.differential_variable()?];
+        while *self.reader.peek() == Token::Comma {
+            self.reader.skip();
+            variables.push(self.differential_variable()?);
+        }
+        self.expect(Token::RightCurlyBracket)?;
+        Ok(variables)
+    }
+
+    /// Create a derivative from a function call in prime notation, for
+    /// example `f''(a)`, which is the function differentiated `order` times
+    /// with respect to its parameter and then evaluated at the argument.
+    fn prime_derivative(
+        &self,
+        call: Factor,
+        order: usize,
+    ) -> Result<Derivative, ParseError> {
+        let Factor::FunctionCall(call) = call else {
+            unreachable!("factor_function_call always returns a function call")
+        };
+        let [argument] = &call.arguments[..] else {
+            return Err(ParseError::PrimeArguments {
+                found: call.arguments.len(),
+            });
+        };
+        let parameter = match self.context.functions.get(&call.function_name) {
+            Some(MathFunction::Foreign(function)) => function.input.first(),
+            _ => None,
+        }
+        .cloned()
+        .unwrap_or_else(|| {
+            // Native functions have no named parameters, so use a variable
+            // that is neither defined nor in the argument
+            let free = argument.free_variables();
+            fresh_identifier(&MathIdentifier::from_single_ident("x"), |ident| {
+                free.contains(ident)
+                    || self.context.variables.contains_key(ident)
+            })
+        });
+        Ok(Derivative {
+            body: Factor::FunctionCall(FunctionCall::new(
+                call.function_name,
+                vec![parameter.clone().into()],
+            ))
+            .into(),
+            variables: vec![parameter.clone(); order],
+            notation: DerivativeNotation::Prime,
+            point: vec![(parameter, argument.clone())],
+        })
+    }
+
+    /// Parse the variable, target and body of a limit, for example
+    /// `_{x \to 0^+} \frac{1}{x}`.
+    ///
+    /// The body is parsed as a term, like the body of [Factor::Sum].
+    fn limit(&mut self) -> Result<Limit, ParseError> {
+        self.expect(Token::Underscore)?;
+        self.expect(Token::LeftCurlyBracket)?;
+        let variable = self.bound_variable()?;
+        self.expect(Token::Backslash)?;
+        let arrow = self.reader.read();
+        if !matches!(&arrow, Token::Identifier(a) if a == "to" || a == "rightarrow")
+        {
+            return Err(ParseError::UnexpectedToken {
+                expected: vec![
+                    Token::Identifier("to".to_owned()),
+                    Token::Identifier("rightarrow".to_owned()),
+                ],
+                found: arrow,
+            });
+        }
+        self.limit_depth += 1;
+        let target = self.expr();
+        self.limit_depth -= 1;
+        let target = target?;
+        let direction = match self.peek_limit_direction() {
+            Some((direction, length)) => {
+                for _ in 0..length {
+                    self.reader.skip();
+                }
+                direction
+            }
+            None => LimitDirection::Both,
+        };
+        self.expect(Token::RightCurlyBracket)?;
+        let body = self.term()?;
+        Ok(Limit::new(
+            variable,
+            target.boxed(),
+            direction,
+            MathExpr::Term(body).boxed(),
+        ))
+    }
+
+    /// Check if the next tokens are the side of a one-sided limit, for
+    /// example the `^+` in `x \to 0^+`, and if so return the side and how
+    /// many tokens it consists of.
+    fn peek_limit_direction(&mut self) -> Option<(LimitDirection, usize)> {
+        match self.reader.peek_range(0..=3)[..] {
+            [Token::Caret, Token::Plus, Token::RightCurlyBracket, _] => {
+                Some((LimitDirection::Above, 2))
+            }
+            [Token::Caret, Token::Minus, Token::RightCurlyBracket, _] => {
+                Some((LimitDirection::Below, 2))
+            }
+            [Token::Caret, Token::LeftCurlyBracket, Token::Plus, Token::RightCurlyBracket] => {
+                Some((LimitDirection::Above, 4))
+            }
+            [Token::Caret, Token::LeftCurlyBracket, Token::Minus, Token::RightCurlyBracket] => {
+                Some((LimitDirection::Below, 4))
+            }
+            _ => None,
+        }
+    }
+
+    /// Parse a variable that is bound by an operation, for example the `i` in
+    /// `\sum_{i=1}^{n}`.
+    fn bound_variable(&mut self) -> Result<MathIdentifier, ParseError> {
+        match self.factor()? {
+            Factor::Variable(ident, _) => Ok(ident),
+            found => Err(ParseError::InvalidBoundVariable {
+                found: found.to_latex(),
+            }),
+        }
+    }
+
+    /// Parse the argument of a superscript or subscript. This is either an
+    /// expression in curly brackets, for example `{n+1}`, or a single
+    /// character or command, for example `2`, `x` or `\pi`.
+    fn script_argument(&mut self) -> Result<MathExpr, ParseError> {
+        self.split_next_identifier();
+        let next = self.reader.peek();
+        Ok(match next {
+            Token::LeftCurlyBracket => {
+                self.reader.skip();
+                let expr = self.expr()?;
+                self.expect(Token::RightCurlyBracket)?;
+                expr
+            }
+            Token::Backslash => {
+                let factor = self.factor()?;
+                MathExpr::Term(Term::Factor(factor))
+            }
+            Token::Identifier(ident) => {
+                let ident = ident.clone();
+                self.reader.skip();
+                if ident.len() != 1 {
+                    panic!(
+                        "The normalizer did not correctly handle exponent, got ident = {}",
+                        ident
+                    );
+                }
+                MathExpr::from(MathIdentifier::Name(MathString::from_letters(
+                    vec![MathLetter::Ascii(ident.bytes().next().unwrap())],
+                )))
+            }
+            Token::NumberLiteral(num) => {
+                if num.raw.len() != 1 {
+                    panic!(
+                        "The normalizer did not correctly handle exponent, got num = {:?}",
+                        num
+                    );
+                }
+                let parsed = num.parsed.clone();
+                self.reader.skip();
+                MathExpr::Term(Term::Factor(Factor::Constant(parsed)))
+            }
+            token => {
+                return Err(ParseError::Invalid {
+                    token: token.clone(),
+                })
+            }
+        })
+    }
+
+    /// Parse the exponent part of a factor.
+    ///
+    /// The `factor` parameter is the base, and the tokens to be parsed by this
+    /// function is the exponent.
+    fn factor_exponent(
+        &mut self,
+        factor: Factor,
+    ) -> Result<Factor, ParseError> {
+        let exponent = self.script_argument()?;
+        Ok(Factor::Power {
+            base: Box::new(factor),
+            exponent: Box::new(exponent),
+            span: NodeSpan::NONE,
+        })
+    }
+    ///parsing a suspected function
+    fn factor_function_call(
+        &mut self,
+        function_name: MathIdentifier,
+    ) -> Result<Factor, ParseError> {
+        let mut arguments = Vec::new();
+        // Read arguments in parenthesis, eg. f(1, 2)
+        if self.reader.peek() == Token::LeftParenthesis {
+            self.reader.skip();
+            loop {
+                let next = self.reader.peek();
+                match next {
+                    Token::RightParenthesis => break,
+                    Token::Comma => self.reader.skip(),
+                    _ => {
+                        let expr = self.expr()?;
+                        arguments.push(expr);
+                    }
+                }
+            }
+            self.expect(Token::RightParenthesis)?;
+        } else {
+            // Read one explicit argument, for example \ln 2
+            let arg = self.term()?;
+            arguments.push(MathExpr::Term(arg));
+        }
+
+        Ok(Factor::FunctionCall(FunctionCall::new(
+            function_name,
+            arguments,
+        )))
+    }
+    ///Parsing a suspected matrix
+    fn matrix(
+        &mut self,
+        matrix_type: String,
+    ) -> Result<Matrix<MathExpr>, ParseError> {
+        let mut rows = Vec::new();
+        let mut current_row = Vec::new();
+        let mut column_count = Option::None;
+
+        loop {
+            let cell = self.expr()?;
+            current_row.push(cell);
+
+            let next = self.reader.peek();
+            match next {
+                Token::Ampersand => {
+                    self.reader.skip();
+                    continue;
+                }
+                Token::Backslash => {
+                    if self.reader.peekn(1) == Token::Backslash {
+                        // Two backslashes means end of row.
+                        self.reader.skip();
+                        self.reader.skip();
+                        match column_count {
+                            Some(column_count) => {
+                                if column_count != current_row.len() {
+                                    return Err(ParseError::MismatchedMatrixColumnSize {
+                                        prev: column_count,
+                                        current: current_row.len(),
+                                    });
+                                }
+                            }
+                            None => column_count = Some(current_row.len()),
+                        }
                         rows.push(current_row);
                         current_row = Vec::new();
                     } else {
                         // \end{matrix_type}
-                        self.reader.skip().await;
-                        self.expect(Token::Identifier("end".to_owned()))
-                            .await?;
-                        self.expect(Token::LeftCurlyBracket).await?;
-                        self.expect(Token::Identifier(matrix_type)).await?;
-                        self.expect(Token::RightCurlyBracket).await?;
+                        self.reader.skip();
+                        self.expect(Token::Identifier("end".to_owned()))?;
+                        self.expect(Token::LeftCurlyBracket)?;
+                        self.expect(Token::Identifier(matrix_type))?;
+                        self.expect(Token::RightCurlyBracket)?;
                         // TODO de-duplicate code
                         match column_count {
                             Some(column_count) => {
@@ -553,9 +1597,19 @@ impl Parser {
     }
 }
 
+/// Whether an identifier only holds differentials of single letter variables,
+/// like `dx` or `dxdy`, but not `d` on its own or `dog`.
+fn is_differential_identifier(ident: &str) -> bool {
+    let chars: Vec<char> = ident.chars().collect();
+    !chars.is_empty()
+        && chars.len().is_multiple_of(2)
+        && chars.chunks(2).all(|pair| pair[0] == 'd')
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
+        ast::to_latex::ToLaTeX,
         identifier::{GreekLetter, ModifierType, OtherSymbol},
         prelude::*,
     };
@@ -659,6 +1713,31 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn multiplication_element_wise() {
+        parse_test(
+            "a\\odot b\\oslash c",
+            Ast::Expression(
+                Term::Multiply(
+                    MulType::Oslash,
+                    Box::new(Term::Multiply(
+                        MulType::Odot,
+                        Box::new(
+                            Factor::from(MathIdentifier::from_single_ident(
+                                "a",
+                            ))
+                            .into(),
+                        ),
+                        Factor::from(MathIdentifier::from_single_ident("b")),
+                    )),
+                    Factor::from(MathIdentifier::from_single_ident("c")),
+                )
+                .into(),
+            ),
+        )
+        .await;
+    }
+
     #[tokio::test]
     async fn sqrt() {
         parse_test(
@@ -667,6 +1746,7 @@ mod tests {
                 Factor::Root {
                     degree: None,
                     radicand: 9f64.into(),
+                    span: NodeSpan::NONE,
                 }
                 .into(),
             ),
@@ -682,6 +1762,7 @@ mod tests {
                 Factor::Root {
                     degree: Some(3f64.into()),
                     radicand: 27f64.into(),
+                    span: NodeSpan::NONE,
                 }
                 .into(),
             ),
@@ -695,10 +1776,11 @@ mod tests {
             "2^{3}",
             Ast::Expression(
                 Factor::Power {
-                    base: Box::new(Factor::Constant(2.0)),
+                    base: Box::new(Factor::Constant(2.0.into())),
                     exponent: Box::new(MathExpr::Term(Term::Factor(
-                        Factor::Constant(3.0),
+                        Factor::Constant(3.0.into()),
                     ))),
+                    span: NodeSpan::NONE,
                 }
                 .into(),
             ),
@@ -714,11 +1796,12 @@ mod tests {
                 Factor::Power {
                     base: Box::new(2f64.into()),
                     exponent: Box::new(
-                        Factor::Variable(MathIdentifier::from_single_greek(
+                        Factor::from(MathIdentifier::from_single_greek(
                             GreekLetter::LowercasePi,
                         ))
                         .into(),
                     ),
+                    span: NodeSpan::NONE,
                 }
                 .into(),
             ),
@@ -734,13 +1817,14 @@ mod tests {
                 MulType::Implicit,
                 //2^0
                 Box::new(Term::Factor(Factor::Power {
-                    base: Box::new(Factor::Constant(2.0)),
+                    base: Box::new(Factor::Constant(2.0.into())),
                     exponent: Box::new(MathExpr::Term(Term::Factor(
-                        Factor::Constant(0.0),
+                        Factor::Constant(0.0.into()),
                     ))),
+                    span: NodeSpan::NONE,
                 })),
                 // 25
-                Factor::Constant(25.0),
+                Factor::Constant(25.0.into()),
             ))),
         )
         .await;
@@ -758,6 +1842,7 @@ mod tests {
                 Factor::Power {
                     base: Box::new(Factor::Parenthesis(Box::new(3f64.into()))),
                     exponent: Box::new(3f64.into()),
+                    span: NodeSpan::NONE,
                 },
             ))),
         )
@@ -773,15 +1858,16 @@ mod tests {
                 Box::new(MathExpr::Term(Term::Multiply(
                     MulType::Implicit,
                     // 2
-                    Box::new(Term::Factor(Factor::Constant(2.0))),
+                    Box::new(Term::Factor(Factor::Constant(2.0.into()))),
                     // x^{2}
                     Factor::Power {
-                        base: Box::new(Factor::Variable(
+                        base: Box::new(Factor::from(
                             MathIdentifier::from_single_ident("x"),
                         )),
                         exponent: Box::new(MathExpr::Term(Term::Factor(
-                            Factor::Constant(2.0),
+                            Factor::Constant(2.0.into()),
                         ))),
+                        span: NodeSpan::NONE,
                     },
                 ))),
                 // 5xy
@@ -793,12 +1879,10 @@ mod tests {
                         // 5
                         Box::new(5f64.into()),
                         // x
-                        Factor::Variable(MathIdentifier::from_single_ident(
-                            "x",
-                        )),
+                        Factor::from(MathIdentifier::from_single_ident("x")),
                     )),
                     // y
-                    Factor::Variable(MathIdentifier::from_single_ident("y")),
+                    Factor::from(MathIdentifier::from_single_ident("y")),
                 ),
             )),
         )
@@ -817,14 +1901,15 @@ mod tests {
                     // 2
                     Box::new(2f64.into()),
                     // x
-                    Factor::Variable(MathIdentifier::from_single_ident("x")),
+                    Factor::from(MathIdentifier::from_single_ident("x")),
                 )),
                 // y^2
                 Factor::Power {
-                    base: Box::new(Factor::Variable(
+                    base: Box::new(Factor::from(
                         MathIdentifier::from_single_ident("y"),
                     )),
                     exponent: 2f64.into(),
+                    span: NodeSpan::NONE,
                 },
             ))),
         )
@@ -835,7 +1920,7 @@ mod tests {
     async fn pi() {
         parse_test(
             "\\pi",
-            Ast::Expression(MathExpr::Term(Term::Factor(Factor::Variable(
+            Ast::Expression(MathExpr::Term(Term::Factor(Factor::from(
                 MathIdentifier::from_single_greek(GreekLetter::LowercasePi),
             )))),
         )
@@ -852,26 +1937,25 @@ mod tests {
                 Box::new(Term::Multiply(
                     MulType::Implicit,
                     Box::new(
-                        Factor::Variable(MathIdentifier::from_single_greek(
+                        Factor::from(MathIdentifier::from_single_greek(
                             GreekLetter::LowercasePi,
                         ))
                         .into(),
                     ),
                     Factor::Parenthesis(Box::new(
-                        Factor::Variable(MathIdentifier::from_single_ident(
-                            "x",
-                        ))
-                        .into(),
+                        Factor::from(MathIdentifier::from_single_ident("x"))
+                            .into(),
                     )),
                 )),
                 Factor::FunctionCall(FunctionCall {
                     function_name: MathIdentifier::from_single_symbol(
                         OtherSymbol::Ln,
                     ),
-                    arguments: vec![Factor::Variable(
+                    arguments: vec![Factor::from(
                         MathIdentifier::from_single_ident("x"),
                     )
                     .into()],
+                    span: NodeSpan::NONE,
                 }),
             ))),
         )
@@ -892,15 +1976,15 @@ mod tests {
                     // 5/2
                     Box::new(Term::Divide(
                         // 5
-                        Box::new(Term::Factor(Factor::Constant(5.0))),
+                        Box::new(Term::Factor(Factor::Constant(5.0.into()))),
                         // 2
-                        Factor::Constant(2.0),
+                        Factor::Constant(2.0.into()),
                     )),
                     // x
-                    Factor::Variable(MathIdentifier::from_single_ident("x")),
+                    Factor::from(MathIdentifier::from_single_ident("x")),
                 ))),
                 // 3
-                Term::Factor(Factor::Constant(3.0)),
+                Term::Factor(Factor::Constant(3.0.into())),
             )),
         )
         .await;
@@ -936,7 +2020,49 @@ mod tests {
         parse_test(
             "x=2",
             Ast::Equality(
-                Factor::Variable(MathIdentifier::from_single_ident("x")).into(),
+                Factor::from(MathIdentifier::from_single_ident("x")).into(),
+                2f64.into(),
+            ),
+        )
+        .await;
+    }
+
+    /// The system `x = 1`, `y = 2`.
+    fn x_is_one_and_y_is_two() -> Ast {
+        let variable =
+            |name| Factor::from(MathIdentifier::from_single_ident(name)).into();
+        Ast::System(vec![
+            (variable("x"), 1f64.into()),
+            (variable("y"), 2f64.into()),
+        ])
+    }
+
+    #[tokio::test]
+    async fn system_separated_by_semicolons() {
+        parse_test("x=1; y=2", x_is_one_and_y_is_two()).await;
+        parse_test("x=1; y=2;", x_is_one_and_y_is_two()).await;
+    }
+
+    #[tokio::test]
+    async fn system_environments() {
+        parse_test(
+            r#"\begin{cases} x = 1 \\ y = 2 \end{cases}"#,
+            x_is_one_and_y_is_two(),
+        )
+        .await;
+        parse_test(
+            r#"\begin{aligned} x &= 1 \\ y &= 2 \\ \end{aligned}"#,
+            x_is_one_and_y_is_two(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn spacing_semicolon_is_not_a_system() {
+        parse_test(
+            r#"x \; = 2"#,
+            Ast::Equality(
+                Factor::from(MathIdentifier::from_single_ident("x")).into(),
                 2f64.into(),
             ),
         )
@@ -951,7 +2077,7 @@ mod tests {
         matrix.set(2, 0, 3f64.into());
         parse_test(
             r#"\begin{pmatrix} 1 \\ 2 \\ 3 \end{pmatrix}"#,
-            Ast::Expression(Factor::Matrix(matrix).into()),
+            Ast::Expression(Factor::from(matrix).into()),
         )
         .await;
     }
@@ -964,7 +2090,7 @@ mod tests {
         matrix.set(0, 2, 3f64.into());
         parse_test(
             r#"\begin{pmatrix} 1 & 2 & 3 \end{pmatrix}"#,
-            Ast::Expression(Factor::Matrix(matrix).into()),
+            Ast::Expression(Factor::from(matrix).into()),
         )
         .await;
     }
@@ -975,11 +2101,8 @@ mod tests {
         matrix.set(0, 0, 1f64.into());
         matrix.set(0, 1, 2f64.into());
         matrix.set(0, 2, 3f64.into());
-        parse_test(
-            r#"(1,2,3)"#,
-            Ast::Expression(Factor::Matrix(matrix).into()),
-        )
-        .await;
+        parse_test(r#"(1,2,3)"#, Ast::Expression(Factor::from(matrix).into()))
+            .await;
     }
 
     #[tokio::test]
@@ -993,7 +2116,7 @@ mod tests {
         matrix.set(1, 2, 6f64.into());
         parse_test(
             r#"\begin{bmatrix} 1 & 2 & 3 \\ 4 & 5 & 6  \end{bmatrix}"#,
-            Ast::Expression(Factor::Matrix(matrix).into()),
+            Ast::Expression(Factor::from(matrix).into()),
         )
         .await;
     }
@@ -1003,13 +2126,11 @@ mod tests {
         parse_test(
             r#"\overline{x}"#,
             Ast::Expression(
-                Factor::Variable(MathIdentifier::Modifier(
+                Factor::from(MathIdentifier::Modifier(
                     ModifierType::Overline,
                     Box::new(
-                        Factor::Variable(MathIdentifier::from_single_ident(
-                            "x",
-                        ))
-                        .into(),
+                        Factor::from(MathIdentifier::from_single_ident("x"))
+                            .into(),
                     ),
                 ))
                 .into(),
@@ -1023,13 +2144,11 @@ mod tests {
         parse_test(
             "x_{y}",
             Ast::Expression(
-                Factor::Variable(MathIdentifier::Index {
+                Factor::from(MathIdentifier::Index {
                     name: Box::new(MathIdentifier::from_single_ident("x")),
                     index: Box::new(
-                        Factor::Variable(MathIdentifier::from_single_ident(
-                            "y",
-                        ))
-                        .into(),
+                        Factor::from(MathIdentifier::from_single_ident("y"))
+                            .into(),
                     ),
                 })
                 .into(),
@@ -1043,9 +2162,9 @@ mod tests {
         parse_test(
             "x_{1}",
             Ast::Expression(
-                Factor::Variable(MathIdentifier::Index {
+                Factor::from(MathIdentifier::Index {
                     name: Box::new(MathIdentifier::from_single_ident("x")),
-                    index: Box::new(Factor::Constant(1.0).into()),
+                    index: Box::new(Factor::Constant(1.0.into()).into()),
                 })
                 .into(),
             ),
@@ -1053,18 +2172,307 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn sum_with_bounds() {
+        parse_test(
+            "\\sum_{i=1}^{n} i^2",
+            Ast::Expression(
+                Factor::Sum(IndexedOperation::new(
+                    MathIdentifier::from_single_ident("i"),
+                    1f64.into(),
+                    Factor::from(MathIdentifier::from_single_ident("n")).into(),
+                    Factor::Power {
+                        base: Box::new(Factor::from(
+                            MathIdentifier::from_single_ident("i"),
+                        )),
+                        exponent: 2f64.into(),
+                        span: NodeSpan::NONE,
+                    }
+                    .into(),
+                ))
+                .into(),
+            ),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn product_single_token_upper_bound() {
+        parse_test(
+            "\\prod_{k=1}^5 k + 1",
+            Ast::Expression(MathExpr::Add(
+                Box::new(
+                    Factor::Product(IndexedOperation::new(
+                        MathIdentifier::from_single_ident("k"),
+                        1f64.into(),
+                        5f64.into(),
+                        Factor::from(MathIdentifier::from_single_ident("k"))
+                            .into(),
+                    ))
+                    .into(),
+                ),
+                1f64.into(),
+            )),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn integral_bounds_in_any_order_without_integrand() {
+        parse_test(
+            "\\int^1_0 dt",
+            Ast::Expression(
+                Factor::Integral(Integral::new(
+                    MathIdentifier::from_single_ident("t"),
+                    0f64.into(),
+                    1f64.into(),
+                    1f64.into(),
+                ))
+                .into(),
+            ),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn integral_ends_at_differential() {
+        parse_test(
+            "\\int_{0}^{1} 2x \\; d x + 1",
+            Ast::Expression(MathExpr::Add(
+                Box::new(
+                    Factor::Integral(Integral::new(
+                        MathIdentifier::from_single_ident("x"),
+                        0f64.into(),
+                        1f64.into(),
+                        Term::Multiply(
+                            MulType::Implicit,
+                            Box::new(2f64.into()),
+                            Factor::from(MathIdentifier::from_single_ident(
+                                "x",
+                            )),
+                        )
+                        .into(),
+                    ))
+                    .into(),
+                ),
+                1f64.into(),
+            )),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn integral_body_starting_with_d() {
+        let variable =
+            |name| Factor::from(MathIdentifier::from_single_ident(name));
+        parse_test(
+            "\\int_{0}^{1} dog \\, dx",
+            Ast::Expression(
+                Factor::Integral(Integral::new(
+                    MathIdentifier::from_single_ident("x"),
+                    0f64.into(),
+                    1f64.into(),
+                    Term::Multiply(
+                        MulType::Implicit,
+                        Term::Multiply(
+                            MulType::Implicit,
+                            variable("d").into(),
+                            variable("o"),
+                        )
+                        .boxed(),
+                        variable("g"),
+                    )
+                    .into(),
+                ))
+                .into(),
+            ),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn fraction_of_d_is_not_a_derivative() {
+        let d = || {
+            MathExpr::from(Factor::from(MathIdentifier::from_single_ident("d")))
+        };
+        parse_test(
+            "\\frac{d+1}{d-1}",
+            Ast::Expression(
+                Factor::Fraction(
+                    MathExpr::Add(d().boxed(), 1f64.into()).boxed(),
+                    MathExpr::Subtract(d().boxed(), 1f64.into()).boxed(),
+                )
+                .into(),
+            ),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn leibniz_derivative_of_next_term() {
+        parse_test(
+            "\\frac{d}{dx} (x^2)",
+            Ast::Expression(
+                Factor::Derivative(Derivative::new(
+                    Factor::Parenthesis(
+                        Factor::Power {
+                            base: Factor::from(
+                                MathIdentifier::from_single_ident("x"),
+                            )
+                            .into(),
+                            exponent: 2f64.into(),
+                            span: NodeSpan::NONE,
+                        }
+                        .into(),
+                    )
+                    .into(),
+                    vec![MathIdentifier::from_single_ident("x")],
+                    DerivativeNotation::Leibniz,
+                ))
+                .into(),
+            ),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn mixed_partial_derivative() {
+        parse_test(
+            "\\frac{\\partial^2 y}{\\partial x \\partial y}",
+            Ast::Expression(
+                Factor::Derivative(Derivative::new(
+                    Factor::from(MathIdentifier::from_single_ident("y")).into(),
+                    vec![
+                        MathIdentifier::from_single_ident("x"),
+                        MathIdentifier::from_single_ident("y"),
+                    ],
+                    DerivativeNotation::Partial,
+                ))
+                .into(),
+            ),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn derivative_orders_are_checked() {
+        let context = MathContext::standard_math();
+        for text in [
+            "\\frac{d}{dx^{100000000000}} x",
+            "\\frac{d^{100000000000}}{dx^{100000000000}} x",
+            "\\frac{d^{0}}{dx^{0}} x",
+            "\\frac{d^{1.5}}{dx} x",
+        ] {
+            let found = parse(text, &context).await;
+            assert!(
+                matches!(
+                    found,
+                    Err(AstError::ParseError {
+                        source: ParseError::InvalidDerivativeOrder { .. }
+                            | ParseError::DerivativeOrderMismatch { .. },
+                        ..
+                    })
+                ),
+                "{text}: found {found:?}"
+            );
+        }
+        let found = parse("\\frac{d^2}{dx^3} x", &context).await;
+        assert!(
+            matches!(
+                found,
+                Err(AstError::ParseError {
+                    source: ParseError::DerivativeOrderMismatch {
+                        numerator: 2,
+                        denominator: 3
+                    },
+                    ..
+                })
+            ),
+            "found {found:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn gradient_of_an_expression() {
+        let found =
+            parse("\\nabla (x^2 y)", &MathContext::standard_math()).await;
+        assert!(
+            matches!(
+                &found,
+                Err(AstError::ParseError {
+                    source: ParseError::InvalidVectorDerivativeOperand { found },
+                    ..
+                }) if found == "("
+            ),
+            "found {found:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn gradient_with_variables() {
+        let x = MathIdentifier::from_single_ident("x");
+        let partial = |variable: &str| -> MathExpr {
+            Factor::Derivative(Derivative::new(
+                Factor::from(x.clone()).into(),
+                vec![MathIdentifier::from_single_ident(variable)],
+                DerivativeNotation::Partial,
+            ))
+            .into()
+        };
+        parse_test(
+            "\\nabla_{x, y} x",
+            Ast::Expression(
+                Factor::from(Matrix::new(
+                    vec![partial("x"), partial("y")],
+                    2,
+                    1,
+                ))
+                .into(),
+            ),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn limit_from_below_to_infinity() {
+        parse_test(
+            "\\lim\\limits_{n \\rightarrow \\infty^{-}} \\frac{1}{n} + 1",
+            Ast::Expression(MathExpr::Add(
+                Box::new(
+                    Factor::Limit(Limit::new(
+                        MathIdentifier::from_single_ident("n"),
+                        Factor::from(MathIdentifier::from_single_symbol(
+                            OtherSymbol::Infty,
+                        ))
+                        .into(),
+                        LimitDirection::Below,
+                        Factor::Fraction(
+                            1f64.into(),
+                            Factor::from(MathIdentifier::from_single_ident(
+                                "n",
+                            ))
+                            .into(),
+                        )
+                        .into(),
+                    ))
+                    .into(),
+                ),
+                1f64.into(),
+            )),
+        )
+        .await;
+    }
+
     #[tokio::test]
     async fn math_identifier_index_n_plus_one() {
         parse_test(
             "x_{n+1}",
             Ast::Expression(
-                Factor::Variable(MathIdentifier::Index {
+                Factor::from(MathIdentifier::Index {
                     name: Box::new(MathIdentifier::from_single_ident("x")),
                     index: Box::new(MathExpr::Add(
-                        Factor::Variable(MathIdentifier::from_single_ident(
-                            "n",
-                        ))
-                        .into(),
+                        Factor::from(MathIdentifier::from_single_ident("n"))
+                            .into(),
                         1_f64.into(),
                     )),
                 })
@@ -1073,4 +2481,260 @@ mod tests {
         )
         .await;
     }
+
+    #[tokio::test]
+    async fn math_identifier_index_single_character() {
+        let indexed = |index: MathExpr| {
+            Factor::from(MathIdentifier::Index {
+                name: Box::new(MathIdentifier::from_single_ident("x")),
+                index: Box::new(index),
+            })
+        };
+        parse_test("x_1", Ast::Expression(indexed(1f64.into()).into())).await;
+        // Only the first digit is the index
+        parse_test(
+            "x_12",
+            Ast::Expression(
+                Term::Multiply(
+                    MulType::Implicit,
+                    Box::new(Term::Factor(indexed(1f64.into()))),
+                    Factor::Constant(2.0.into()),
+                )
+                .into(),
+            ),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn math_identifier_index_list() {
+        let ident =
+            |name| Factor::from(MathIdentifier::from_single_ident(name));
+        parse_test(
+            "A_{2,:}",
+            Ast::Expression(
+                Factor::from(MathIdentifier::Index {
+                    name: Box::new(MathIdentifier::from_single_ident("A")),
+                    index: Box::new(
+                        Factor::from(Matrix::new(
+                            vec![2f64.into(), ident(":").into()],
+                            1,
+                            2,
+                        ))
+                        .into(),
+                    ),
+                })
+                .into(),
+            ),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn logarithm_with_base() {
+        parse_test(
+            "\\log_{2} 8",
+            Ast::Expression(
+                Factor::FunctionCall(FunctionCall::new(
+                    MathIdentifier::from_single_symbol(OtherSymbol::Log),
+                    vec![8f64.into(), 2f64.into()],
+                ))
+                .into(),
+            ),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn norms() {
+        let v = || MathExpr::from(MathIdentifier::from_single_ident("v"));
+        let norm = |arguments| {
+            Factor::FunctionCall(FunctionCall::new(
+                MathIdentifier::from_operator_name("norm"),
+                arguments,
+            ))
+        };
+        parse_test("\\|v\\|", Ast::Expression(norm(vec![v()]).into())).await;
+        parse_test(
+            "\\lVert v\\rVert_1",
+            Ast::Expression(norm(vec![v(), 1f64.into()]).into()),
+        )
+        .await;
+        parse_test(
+            "2\\|v\\|\\|v\\|",
+            Ast::Expression(
+                Term::Multiply(
+                    MulType::Implicit,
+                    Term::Multiply(
+                        MulType::Implicit,
+                        Term::from(2.0).boxed(),
+                        norm(vec![v()]),
+                    )
+                    .boxed(),
+                    norm(vec![v()]),
+                )
+                .into(),
+            ),
+        )
+        .await;
+        let context = MathContext::standard_math();
+        let text = "\\|v\\|_{\\infty}";
+        let ast = parse(text, &context).await.unwrap();
+        assert_eq!(ast.to_latex(), text);
+    }
+
+    #[tokio::test]
+    async fn projection_with_subscript() {
+        parse_test(
+            "\\operatorname{proj}_u v",
+            Ast::Expression(
+                Factor::FunctionCall(FunctionCall::new(
+                    MathIdentifier::from_operator_name("proj"),
+                    vec![
+                        MathIdentifier::from_single_ident("v").into(),
+                        MathIdentifier::from_single_ident("u").into(),
+                    ],
+                ))
+                .into(),
+            ),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn zero_matrices_and_ranges() {
+        let n = || MathExpr::from(MathIdentifier::from_single_ident("n"));
+        let call = |name, arguments| {
+            Ast::Expression(
+                Factor::FunctionCall(FunctionCall::new(
+                    MathIdentifier::from_operator_name(name),
+                    arguments,
+                ))
+                .into(),
+            )
+        };
+        parse_test("0_{2\\times n}", call("zeros", vec![2f64.into(), n()]))
+            .await;
+        parse_test("0_n", call("zeros", vec![n()])).await;
+        parse_test("(1,\\ldots,n)", call("range", vec![1f64.into(), n()]))
+            .await;
+        parse_test(
+            "(1,3,\\dots,n)",
+            call("range", vec![1f64.into(), 3f64.into(), n()]),
+        )
+        .await;
+        let context = MathContext::standard_math();
+        for text in ["0_{2\\times n}", "\\left(1,3,\\ldots,n\\right)"] {
+            let ast = parse(text, &context).await.unwrap();
+            assert_eq!(ast.to_latex(), text);
+        }
+        for text in ["(\\ldots,n)", "(1,\\ldots,3,n)", "(1,\\ldots,\\ldots,n)"]
+        {
+            let found = parse(text, &context).await;
+            assert!(
+                matches!(
+                    found,
+                    Err(AstError::ParseError {
+                        source: ParseError::MisplacedEllipsis,
+                        ..
+                    })
+                ),
+                "{text}: found {found:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn errors_are_at_the_unexpected_token() {
+        let context = MathContext::standard_math();
+        for (text, span) in [
+            ("1+\\frac{1}{2}}", Span::new(13, 14)),
+            ("\\sqrt{2", Span::new(7, 7)),
+            ("(1,2", Span::new(4, 4)),
+            ("\\left(1+)\\right)", Span::new(8, 9)),
+        ] {
+            let found = parse(text, &context).await;
+            let found_span = found.as_ref().err().and_then(AstError::span);
+            assert_eq!(found_span, Some(span), "{text}: found {found:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn calls_know_where_they_are_written() {
+        let context = MathContext::standard_math();
+        let ast = parse("2\\cdot \\sin(x)", &context).await.unwrap();
+        let Ast::Expression(MathExpr::Term(Term::Multiply(_, _, factor))) = ast
+        else {
+            panic!("Expected a product, found {ast:?}");
+        };
+        let Factor::FunctionCall(call) = factor else {
+            panic!("Expected a call, found {factor:?}");
+        };
+        assert_eq!(call.span.get(), Some(Span::new(7, 14)));
+    }
+
+    #[tokio::test]
+    async fn recovery_finds_every_error() {
+        let context = MathContext::standard_math();
+        for (text, latex, spans) in [
+            (
+                "\\frac{1+}{2}+\\sqrt{*}",
+                "\\frac{1+\\square}{2}+\\sqrt{\\square}",
+                vec![Span::new(8, 9), Span::new(19, 20)],
+            ),
+            (
+                "\\begin{pmatrix}1&+\\\\(&4\\end{pmatrix}",
+                "\\begin{bmatrix}1 & \\square \\\\\\square & 4\\end{bmatrix}",
+                vec![Span::new(17, 18), Span::new(21, 22)],
+            ),
+            ("x=2+", "x=2+\\square", vec![Span::new(4, 4)]),
+            ("1+2}+3", "1+2+3", vec![Span::new(3, 4)]),
+            (
+                "1+*+2+*",
+                "1+\\square+2+\\square",
+                vec![Span::new(2, 3), Span::new(6, 7)],
+            ),
+            (
+                "a+}+b+}",
+                "a+\\square+b+\\square",
+                vec![Span::new(2, 3), Span::new(6, 7)],
+            ),
+            ("x)=2", "x=2", vec![Span::new(1, 2)]),
+            (
+                "\\sum_{",
+                "\\square",
+                vec![Span::new(5, 6), Span::new(6, 6)],
+            ),
+            ("1+2", "1+2", vec![]),
+        ] {
+            let (ast, errors) =
+                parse_with_recovery(text, &context).await.unwrap();
+            let found: Vec<_> =
+                errors.iter().filter_map(AstError::span).collect();
+            assert_eq!(found, spans, "{text}: found {errors:?}");
+            assert_eq!(ast.to_latex(), latex, "{text}");
+        }
+    }
+
+    #[tokio::test]
+    async fn floor_and_operator_name_round_trip() {
+        let context = MathContext::standard_math();
+        let text = "\\lfloor x\\rfloor +\\operatorname{sgn}(x)";
+        let ast = parse(text, &context).await.unwrap();
+        assert_eq!(ast.to_latex(), text);
+        assert_eq!(parse(&ast.to_latex(), &context).await.unwrap(), ast);
+    }
+
+    #[tokio::test]
+    async fn imaginary_unit() {
+        let context = MathContext::standard_math();
+        let ast = parse("\\mathrm{i}", &context).await.unwrap();
+        assert_eq!(
+            ast,
+            Ast::Expression(
+                Factor::from(MathIdentifier::imaginary_unit()).into()
+            )
+        );
+        assert_eq!(ast.to_latex(), "\\mathrm{i}");
+    }
 }
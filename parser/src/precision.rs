@@ -0,0 +1,346 @@
+//! # Precision
+//! The numeric backends an [Approximator] can evaluate with.
+//!
+//! Exact numbers stay exact regardless of the precision, the precision only
+//! decides how results like `\sqrt{2}` or `\sin 1` are approximated: as an
+//! `f64` or as a decimal big float with a chosen amount of significant
+//! digits. Numbers remember their precision, so operations on big floats give
+//! big floats.
+//!
+//! [Approximator]: crate::approximator::Approximator
+use dashu_float::{ops::SquareRoot, DBig};
+use dashu_int::{IBig, Sign};
+
+use crate::number::Number;
+
+/// The extra digits that are used while computing, so the rounding errors
+/// of the steps do not show up in the result.
+const GUARD_DIGITS: usize = 10;
+
+/// How precisely results that can not be exact are approximated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Precision {
+    /// A 64-bit float, which has about 16 significant digits.
+    #[default]
+    Double,
+    /// A decimal big float with this many significant digits.
+    Digits(usize),
+}
+
+impl Precision {
+    /// The most precise of the two, where more digits are more precise.
+    pub fn max(self, other: Precision) -> Precision {
+        match (self, other) {
+            (Precision::Digits(a), Precision::Digits(b)) => {
+                Precision::Digits(a.max(b))
+            }
+            (Precision::Digits(digits), Precision::Double)
+            | (Precision::Double, Precision::Digits(digits)) => {
+                Precision::Digits(digits)
+            }
+            (Precision::Double, Precision::Double) => Precision::Double,
+        }
+    }
+
+    /// The number as a big float of this precision, so the operations on it
+    /// are approximated with this precision. Numbers are kept as is at
+    /// double precision, and when they are not finite.
+    pub fn approximate(self, number: &Number) -> Number {
+        let Precision::Digits(digits) = self else {
+            return number.clone();
+        };
+        match number.to_big(digits) {
+            Some(big) => Number::Big(Box::new(big)),
+            None => number.clone(),
+        }
+    }
+}
+
+/// Rounds a big float that was computed with guard digits to `digits`.
+fn rounded(value: DBig, digits: usize) -> Number {
+    Number::Big(Box::new(value.with_precision(digits).value()))
+}
+
+/// The value with the given amount of significant digits, where lowering
+/// the precision rounds it.
+pub(crate) fn with_digits(value: &DBig, digits: usize) -> DBig {
+    value.clone().with_precision(digits).value()
+}
+
+/// The working precision for a result with `digits` significant digits.
+const fn working(digits: usize) -> usize {
+    digits + GUARD_DIGITS
+}
+
+/// The integer as a big float with the given precision.
+fn integer(value: i64, digits: usize) -> DBig {
+    DBig::from(IBig::from(value)).with_precision(digits).value()
+}
+
+/// The sum of the terms until they no longer change it, where `next`
+/// gives the next term from the previous term and its index, starting at 1.
+fn series(first: DBig, mut next: impl FnMut(&DBig, i64) -> DBig) -> DBig {
+    let mut term = first.clone();
+    let mut sum = first;
+    for n in 1.. {
+        term = next(&term, n);
+        let new_sum = &sum + &term;
+        if new_sum == sum {
+            break;
+        }
+        sum = new_sum;
+    }
+    sum
+}
+
+/// The arctangent by its Taylor series, which converges quickly when `x`
+/// is small.
+fn atan_series(x: &DBig) -> DBig {
+    let squared = x * x;
+    let mut power = x.clone();
+    series(x.clone(), |_, n| {
+        power = -(&power * &squared);
+        &power / integer(2 * n + 1, x.precision())
+    })
+}
+
+/// Pi by Machin's formula `\pi = 16 \arctan\frac{1}{5} - 4
+/// \arctan\frac{1}{239}`.
+fn pi_working(digits: usize) -> DBig {
+    let one = integer(1, digits);
+    let fifth = atan_series(&(&one / integer(5, digits)));
+    let small = atan_series(&(&one / integer(239, digits)));
+    fifth * integer(16, digits) - small * integer(4, digits)
+}
+
+/// Pi with `digits` significant digits.
+pub fn pi(digits: usize) -> Number {
+    rounded(pi_working(working(digits)), digits)
+}
+
+/// `x` reduced to the range from `-\pi` to `\pi` by subtracting a multiple
+/// of `2\pi`.
+fn reduce_angle(x: &DBig, digits: usize) -> DBig {
+    // Large angles lose the digits of the multiple when subtracting
+    let whole_digits = x.repr().digits() as isize + x.repr().exponent();
+    let digits = digits + whole_digits.max(0) as usize;
+    let x = with_digits(x, digits);
+    let two_pi = pi_working(digits) * integer(2, digits);
+    let turns = (&x / &two_pi).round();
+    x - turns * two_pi
+}
+
+/// The sine of `x`.
+pub fn sin(x: &DBig) -> Number {
+    let digits = working(x.precision());
+    let x = reduce_angle(x, digits);
+    let squared = &x * &x;
+    let value = series(x.clone(), |term, n| {
+        -(term * &squared) / integer(2 * n * (2 * n + 1), digits)
+    });
+    rounded(value, digits - GUARD_DIGITS)
+}
+
+/// The cosine of `x`.
+pub fn cos(x: &DBig) -> Number {
+    let digits = working(x.precision());
+    let x = reduce_angle(x, digits);
+    let squared = &x * &x;
+    let value = series(integer(1, digits), |term, n| {
+        -(term * &squared) / integer((2 * n - 1) * (2 * n), digits)
+    });
+    rounded(value, digits - GUARD_DIGITS)
+}
+
+/// The tangent of `x`.
+pub fn tan(x: &DBig) -> Number {
+    sin(x) / cos(x)
+}
+
+/// The arctangent of `x`.
+pub fn atan(x: &DBig) -> Number {
+    let digits = working(x.precision());
+    rounded(atan_working(&with_digits(x, digits)), x.precision())
+}
+
+/// The arctangent, computed with the precision of `x`.
+fn atan_working(x: &DBig) -> DBig {
+    let digits = x.precision();
+    let one = integer(1, digits);
+    if x.repr().is_zero() {
+        return x.clone();
+    }
+    // \arctan x = \pm\frac{\pi}{2} - \arctan\frac{1}{x}
+    if abs(x) > one {
+        let half_pi = pi_working(digits) / integer(2, digits);
+        let rest = atan_working(&(&one / x));
+        return match x.sign() {
+            Sign::Positive => half_pi - rest,
+            Sign::Negative => -half_pi - rest,
+        };
+    }
+    // \arctan x = 2 \arctan\frac{x}{1 + \sqrt{1 + x^2}} makes x smaller
+    let tenth = &one / integer(10, digits);
+    let mut x = x.clone();
+    let mut doublings = 0;
+    while abs(&x) > tenth {
+        x = &x / (&one + (&one + &x * &x).sqrt());
+        doublings += 1;
+    }
+    atan_series(&x) * integer(1 << doublings, digits)
+}
+
+/// The absolute value.
+fn abs(x: &DBig) -> DBig {
+    match x.sign() {
+        Sign::Positive => x.clone(),
+        Sign::Negative => -x.clone(),
+    }
+}
+
+/// The arcsine of `x`, which is not a number outside of -1 to 1.
+pub fn asin(x: &DBig) -> Number {
+    let digits = working(x.precision());
+    let one = integer(1, digits);
+    let x = with_digits(x, digits);
+    let value = match abs(&x).cmp(&one) {
+        std::cmp::Ordering::Greater => return Number::Float(f64::NAN),
+        std::cmp::Ordering::Equal => {
+            let half_pi = pi_working(digits) / integer(2, digits);
+            match x.sign() {
+                Sign::Positive => half_pi,
+                Sign::Negative => -half_pi,
+            }
+        }
+        std::cmp::Ordering::Less => {
+            atan_working(&(&x / (&one - &x * &x).sqrt()))
+        }
+    };
+    rounded(value, digits - GUARD_DIGITS)
+}
+
+/// The arccosine of `x`, which is not a number outside of -1 to 1.
+pub fn acos(x: &DBig) -> Number {
+    let digits = x.precision();
+    let half_pi =
+        rounded(pi_working(working(digits)), digits) / Number::from(2);
+    half_pi - asin(x)
+}
+
+/// The exponential function.
+pub fn exp(x: &DBig) -> Number {
+    let digits = working(x.precision());
+    rounded(with_digits(x, digits).exp(), digits - GUARD_DIGITS)
+}
+
+/// The natural logarithm, which is negative infinity for zero and not a
+/// number for negative numbers.
+pub fn ln(x: &DBig) -> Number {
+    if x.repr().is_zero() {
+        return Number::Float(f64::NEG_INFINITY);
+    }
+    if x.sign() == Sign::Negative {
+        return Number::Float(f64::NAN);
+    }
+    let digits = working(x.precision());
+    rounded(with_digits(x, digits).ln(), digits - GUARD_DIGITS)
+}
+
+/// The logarithm in base 10.
+pub fn log10(x: &DBig) -> Number {
+    ln(x) / ln(&integer(10, x.precision()))
+}
+
+/// The hyperbolic sine.
+pub fn sinh(x: &DBig) -> Number {
+    (exp(x) - exp(&-x.clone())) / Number::from(2)
+}
+
+/// The hyperbolic cosine.
+pub fn cosh(x: &DBig) -> Number {
+    (exp(x) + exp(&-x.clone())) / Number::from(2)
+}
+
+/// The hyperbolic tangent.
+pub fn tanh(x: &DBig) -> Number {
+    sinh(x) / cosh(x)
+}
+
+/// The square root, which is not a number for negative numbers.
+pub fn sqrt(x: &DBig) -> Number {
+    if x.sign() == Sign::Negative && !x.repr().is_zero() {
+        return Number::Float(f64::NAN);
+    }
+    let digits = working(x.precision());
+    rounded(with_digits(x, digits).sqrt(), digits - GUARD_DIGITS)
+}
+
+/// `base` to the power of `exponent`, which is not a number for negative
+/// bases with fractional exponents.
+pub fn pow(base: &DBig, exponent: &DBig) -> Number {
+    let digits = working(base.precision().max(exponent.precision()));
+    let base = with_digits(base, digits);
+    if exponent.repr().is_int() {
+        let exponent = exponent.to_int().value();
+        if base.repr().is_zero() && exponent < IBig::ZERO {
+            return Number::Float(f64::INFINITY);
+        }
+        return rounded(base.powi(exponent), digits - GUARD_DIGITS);
+    }
+    match (base.repr().is_zero(), base.sign()) {
+        (true, _) if exponent.sign() == Sign::Positive => Number::from(0),
+        (true, _) => Number::Float(f64::INFINITY),
+        (false, Sign::Negative) => Number::Float(f64::NAN),
+        (false, Sign::Positive) => rounded(
+            base.powf(&with_digits(exponent, digits)),
+            digits - GUARD_DIGITS,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Precision;
+    use crate::number::Number;
+
+    const PI: &str = "3.14159265358979323846264338327950288419716939937510";
+    const E: &str = "2.71828182845904523536028747135266249775724709369995";
+
+    /// Whether the number is `expected` up to `digits` decimal places.
+    fn assert_digits(found: &Number, expected: &str, digits: i32) {
+        let expected: Number = expected.parse().unwrap();
+        let error = (found.clone() - expected).abs().to_f64();
+        assert!(error < 10f64.powi(-digits), "found {found}");
+    }
+
+    #[test]
+    fn pi_to_fifty_digits() {
+        assert_digits(&super::pi(50), PI, 49);
+    }
+
+    #[test]
+    fn trigonometry_of_large_angles() {
+        let precision = Precision::Digits(40);
+        let angle = precision.approximate(&Number::from(1000));
+        let Number::Big(angle) = angle else {
+            panic!("Expected a big float");
+        };
+        let (sin, cos) = (super::sin(&angle), super::cos(&angle));
+        let one = sin.clone() * sin + cos.clone() * cos - Number::from(1);
+        assert!(one.to_f64().abs() < 1e-38, "found {one}");
+        assert!((super::tan(&angle).to_f64() - 1000f64.tan()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn exponential_and_inverse_functions() {
+        let Number::Big(one) = Precision::Digits(50).approximate(&1.into())
+        else {
+            panic!("Expected a big float");
+        };
+        assert_digits(&super::exp(&one), E, 49);
+        let quarter_pi = super::atan(&one) * Number::from(4);
+        assert_digits(&quarter_pi, PI, 48);
+        let half_pi = super::asin(&one) * Number::from(2);
+        assert_digits(&half_pi, PI, 48);
+    }
+}
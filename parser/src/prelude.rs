@@ -8,109 +8,97 @@
 pub use crate::{
     approximator::Approximator,
     ast::Ast,
+    compiled::CompiledExpr,
     context::MathContext,
     error::{
         AstError, DeriveError, EvalError, IncompatibleMatrixSizes, ParseError,
     },
-    functions::MathFunction,
+    functions::{MathFunction, NativeFunction, ParameterKind, Signature},
+    number::Number,
+    precision::Precision,
+    token::Span,
     value::Value,
 };
-/// An alias for `Receiver<Token>` to receive tokens
-pub(crate) type TokenReceiver = Receiver<Token>;
 use snafu::whatever;
-pub(crate) use tokio::sync::mpsc;
-/// An alias for `Sender<Token>` to send tokens
-pub(crate) type TokenSender = Sender<Token>;
 
 #[allow(unused_imports)]
 pub(crate) use crate::{
-    ast::{Factor, FunctionCall, MathExpr, MulType, Term},
+    ast::{
+        Derivative, DerivativeNotation, Factor, FunctionCall, IndexedOperation,
+        Integral, Limit, LimitDirection, MathExpr, MulType, NodeSpan, Term,
+    },
     functions::IntoMathFunction,
     identifier::MathIdentifier,
     lexer::Lexer,
     matrix::Matrix,
     normalizer::Normalizer,
     parsing::Parser,
-    token::Token,
+    token::{SpannedToken, Token},
     token_reader::TokenReader,
 };
 
-use futures::FutureExt;
-use std::panic::AssertUnwindSafe;
-use tokio::sync::mpsc::{Receiver, Sender};
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use tracing::{debug, error, trace, trace_span};
 ///The parse function central to the parsing functionality, and outputs an AST
 /// that can be evaluated using
+///
+/// This is [parse_sync] for async code, since parsing never waits.
 pub async fn parse(text: &str, context: &MathContext) -> Result<Ast, AstError> {
+    parse_sync(text, context)
+}
+///Parsing that continues after errors, for example for an editor where the
+/// LaTeX is incomplete while typing. It returns every error that was found
+/// with an AST where the parts that could not be parsed are
+/// [Factor::Error], see [Parser::parse_with_recovery].
+///
+/// It only fails if parsing panics.
+pub async fn parse_with_recovery(
+    text: &str,
+    context: &MathContext,
+) -> Result<(Ast, Vec<AstError>), AstError> {
+    parse_with_recovery_sync(text, context)
+}
+///Parsing without a runtime, by running the lexer, normalizer and parser one
+/// after another over all of the tokens
+pub fn parse_sync(text: &str, context: &MathContext) -> Result<Ast, AstError> {
+    run_parser(text, context, Parser::parse)?
+}
+///[parse_with_recovery] without a runtime
+pub fn parse_with_recovery_sync(
+    text: &str,
+    context: &MathContext,
+) -> Result<(Ast, Vec<AstError>), AstError> {
+    run_parser(text, context, Parser::parse_with_recovery)
+}
+///Running the lexer, normalizer and parser over the text, where `parse`
+/// starts the parser, and catching panics as errors
+fn run_parser<T>(
+    text: &str,
+    context: &MathContext,
+    parse: impl FnOnce(Parser) -> T,
+) -> Result<T, AstError> {
     let span = trace_span!("parsing");
     let _enter = span.enter();
     debug!(text);
-    let channel_buffer_size = 32;
-    let (lexer_in, lexer_out): (TokenSender, TokenReceiver) =
-        mpsc::channel(channel_buffer_size);
-    debug!(
-        "successfully created channel for lexer with {} long buffer",
-        channel_buffer_size
-    );
-    let (normalizer_in, normalizer_out): (TokenSender, TokenReceiver) =
-        mpsc::channel(channel_buffer_size);
-    debug!(
-        "successfully created channel for normalizer with {} long buffer",
-        channel_buffer_size
-    );
-
-    let lexer = Lexer::new(lexer_in);
-    let normalizer = Normalizer::new(lexer_out, normalizer_in);
-    let parser = Parser::new(normalizer_out, context.clone());
-    let cloned_text = text.to_owned();
-    trace!("cloned text");
-
-    let lexer_future = async move { lexer.tokenize(&cloned_text).await };
-    let normalizer_future = async move { normalizer.normalize().await };
-    let parser_future = async move { parser.parse().await };
-    trace!("spawning new tokenize async task");
-    let lexer_handle = spawn_logging_task(lexer_future);
-    trace!("spawning new normalizer async task");
-    let normalizer_handle = spawn_logging_task(normalizer_future);
-    trace!("spawning new parser async task");
-    let parser_handle = spawn_logging_task(parser_future);
-
-    let (lexer_result, normalizer_result, parser_result) =
-        tokio::join!(lexer_handle, normalizer_handle, parser_handle);
-
-    match lexer_result {
-        Err(e) => {
-            error!("lexer task failed");
-            return Err(e.into());
-        }
-        Ok(Err(err)) => {
-            error!("lexer task panicked");
-            whatever!("{}", err.to_owned())
-        }
-        _ => Ok::<(), AstError>(()),
-    }?;
-
-    match normalizer_result {
-        Err(e) => {
-            error!("normalizer task failed");
-            return Err(e.into());
+    let parsed = catch_unwind(AssertUnwindSafe(|| {
+        let tokens = Lexer::tokenize(text);
+        trace!("lexed {} tokens", tokens.len());
+        let tokens = Normalizer::normalize(tokens);
+        trace!("normalized to {} tokens", tokens.len());
+        let parser = Parser::from_tokens(tokens, context.clone());
+        parse(parser)
+    }));
+    match parsed {
+        Ok(parsed) => Ok(parsed),
+        Err(err) => {
+            let panic_message = if let Some(s) = err.downcast_ref::<&str>() {
+                *s
+            } else {
+                "panic occurred while parsing"
+            };
+            error!("parsing panicked");
+            whatever!("{}", panic_message);
         }
-        Ok(Err(err)) => {
-            error!("normalizer task failed");
-            whatever!("{}", err.to_owned())
-        }
-        _ => Ok::<(), AstError>(()),
-    }?;
-    match parser_result {
-        Err(e) => {
-            error!("parser task failed");
-            Err(e.into())
-        }
-        Ok(Err(err)) => {
-            error!("parser task failed");
-            whatever!("{}", err.to_owned())
-        }
-        Ok(Ok(ast)) => ast.map_err(|e| e.into()),
     }
 }
 /// functions for doc testing and other things that need to be public only for
@@ -121,45 +109,13 @@ pub mod _private {
 
     ///function for parsing in sync doc tests
     pub fn parse_sync_doc_test(text: &str, context: &MathContext) -> Ast {
-        tokio::runtime::Runtime::new()
-            .unwrap()
-            .block_on(parse(text, context))
-            .unwrap()
+        parse_sync(text, context).unwrap()
     }
 }
-///starting a task that has a certain output and returning the JoinHandle
-/// making sure to catch panics as results
-fn spawn_logging_task<F, T>(
-    future: F,
-) -> tokio::task::JoinHandle<Result<T, &'static str>>
-where
-    F: std::future::Future<Output = T> + Send + 'static,
-    T: Send + 'static,
-{
-    tokio::spawn(async move {
-        match AssertUnwindSafe(future).catch_unwind().await {
-            Ok(result) => Ok(result),
-            Err(err) => {
-                let panic_message = if let Some(s) = err.downcast_ref::<&str>()
-                {
-                    *s
-                } else {
-                    "panic occurred in spawned task"
-                };
-
-                // Log the panic as a tracing event
-                //error!(target: "panic", "Panic in spawned task: {}",
-                // panic_message);
-
-                // Return the error for further handling if necessary
-                Err(panic_message)
-            }
-        }
-    })
-}
 #[cfg(test)]
 mod tests {
     use crate::{
+        ast::to_latex::ToLaTeX,
         identifier::{GreekLetter, OtherSymbol},
         prelude::*,
     };
@@ -171,6 +127,17 @@ mod tests {
         assert_eq!(found_ast, expected_ast)
     }
 
+    #[test]
+    fn parse_without_runtime() {
+        let context = MathContext::standard_math();
+        let ast = parse_sync("\\frac{1}{2}\\cdot x_1^2", &context).unwrap();
+        assert_eq!(ast.to_latex(), "\\frac{1}{2}\\cdot x_{1}^{2}");
+        let error = parse_sync("\\sqrt{2", &context).unwrap_err();
+        assert_eq!(error.span(), Some(Span::new(7, 7)));
+        let (_, errors) = parse_with_recovery_sync("1+}", &context).unwrap();
+        assert_eq!(errors.len(), 1);
+    }
+
     #[tokio::test]
     async fn constant() {
         parse_test("1", Ast::Expression(1f64.into())).await;
@@ -216,6 +183,7 @@ mod tests {
                 Factor::Root {
                     degree: None,
                     radicand: 9f64.into(),
+                    span: NodeSpan::NONE,
                 }
                 .into(),
             ),
@@ -231,6 +199,7 @@ mod tests {
                 Factor::Root {
                     degree: Some(3f64.into()),
                     radicand: 27f64.into(),
+                    span: NodeSpan::NONE,
                 }
                 .into(),
             ),
@@ -244,10 +213,11 @@ mod tests {
             "2^{3}",
             Ast::Expression(
                 Factor::Power {
-                    base: Box::new(Factor::Constant(2.0)),
+                    base: Box::new(Factor::Constant(2.0.into())),
                     exponent: Box::new(MathExpr::Term(Term::Factor(
-                        Factor::Constant(3.0),
+                        Factor::Constant(3.0.into()),
                     ))),
+                    span: NodeSpan::NONE,
                 }
                 .into(),
             ),
@@ -263,11 +233,12 @@ mod tests {
                 Factor::Power {
                     base: Box::new(2f64.into()),
                     exponent: Box::new(
-                        Factor::Variable(MathIdentifier::from_single_greek(
+                        Factor::from(MathIdentifier::from_single_greek(
                             GreekLetter::LowercasePi,
                         ))
                         .into(),
                     ),
+                    span: NodeSpan::NONE,
                 }
                 .into(),
             ),
@@ -283,13 +254,14 @@ mod tests {
                 MulType::Implicit,
                 //2^0
                 Box::new(Term::Factor(Factor::Power {
-                    base: Box::new(Factor::Constant(2.0)),
+                    base: Box::new(Factor::Constant(2.0.into())),
                     exponent: Box::new(MathExpr::Term(Term::Factor(
-                        Factor::Constant(0.0),
+                        Factor::Constant(0.0.into()),
                     ))),
+                    span: NodeSpan::NONE,
                 })),
                 // 25
-                Factor::Constant(25.0),
+                Factor::Constant(25.0.into()),
             ))),
         )
         .await;
@@ -307,6 +279,7 @@ mod tests {
                 Factor::Power {
                     base: Box::new(Factor::Parenthesis(Box::new(3f64.into()))),
                     exponent: Box::new(3f64.into()),
+                    span: NodeSpan::NONE,
                 },
             ))),
         )
@@ -322,15 +295,16 @@ mod tests {
                 Box::new(MathExpr::Term(Term::Multiply(
                     MulType::Implicit,
                     // 2
-                    Box::new(Term::Factor(Factor::Constant(2.0))),
+                    Box::new(Term::Factor(Factor::Constant(2.0.into()))),
                     // x^{2}
                     Factor::Power {
-                        base: Box::new(Factor::Variable(
+                        base: Box::new(Factor::from(
                             MathIdentifier::from_single_ident("x"),
                         )),
                         exponent: Box::new(MathExpr::Term(Term::Factor(
-                            Factor::Constant(2.0),
+                            Factor::Constant(2.0.into()),
                         ))),
+                        span: NodeSpan::NONE,
                     },
                 ))),
                 // 5xy
@@ -342,12 +316,10 @@ mod tests {
                         // 5
                         Box::new(5f64.into()),
                         // x
-                        Factor::Variable(MathIdentifier::from_single_ident(
-                            "x",
-                        )),
+                        Factor::from(MathIdentifier::from_single_ident("x")),
                     )),
                     // y
-                    Factor::Variable(MathIdentifier::from_single_ident("y")),
+                    Factor::from(MathIdentifier::from_single_ident("y")),
                 ),
             )),
         )
@@ -366,14 +338,15 @@ mod tests {
                     // 2
                     Box::new(2f64.into()),
                     // x
-                    Factor::Variable(MathIdentifier::from_single_ident("x")),
+                    Factor::from(MathIdentifier::from_single_ident("x")),
                 )),
                 // y^2
                 Factor::Power {
-                    base: Box::new(Factor::Variable(
+                    base: Box::new(Factor::from(
                         MathIdentifier::from_single_ident("y"),
                     )),
                     exponent: 2f64.into(),
+                    span: NodeSpan::NONE,
                 },
             ))),
         )
@@ -384,7 +357,7 @@ mod tests {
     async fn pi() {
         parse_test(
             "\\pi",
-            Ast::Expression(MathExpr::Term(Term::Factor(Factor::Variable(
+            Ast::Expression(MathExpr::Term(Term::Factor(Factor::from(
                 MathIdentifier::from_single_greek(GreekLetter::LowercasePi),
             )))),
         )
@@ -401,26 +374,25 @@ mod tests {
                 Box::new(Term::Multiply(
                     MulType::Implicit,
                     Box::new(
-                        Factor::Variable(MathIdentifier::from_single_greek(
+                        Factor::from(MathIdentifier::from_single_greek(
                             GreekLetter::LowercasePi,
                         ))
                         .into(),
                     ),
                     Factor::Parenthesis(Box::new(
-                        Factor::Variable(MathIdentifier::from_single_ident(
-                            "x",
-                        ))
-                        .into(),
+                        Factor::from(MathIdentifier::from_single_ident("x"))
+                            .into(),
                     )),
                 )),
                 Factor::FunctionCall(FunctionCall {
                     function_name: MathIdentifier::from_single_symbol(
                         OtherSymbol::Ln,
                     ),
-                    arguments: vec![Factor::Variable(
+                    arguments: vec![Factor::from(
                         MathIdentifier::from_single_ident("x"),
                     )
                     .into()],
+                    span: NodeSpan::NONE,
                 }),
             ))),
         )
@@ -441,15 +413,15 @@ mod tests {
                     // 5/2
                     Box::new(Term::Divide(
                         // 5
-                        Box::new(Term::Factor(Factor::Constant(5.0))),
+                        Box::new(Term::Factor(Factor::Constant(5.0.into()))),
                         // 2
-                        Factor::Constant(2.0),
+                        Factor::Constant(2.0.into()),
                     )),
                     // x
-                    Factor::Variable(MathIdentifier::from_single_ident("x")),
+                    Factor::from(MathIdentifier::from_single_ident("x")),
                 ))),
                 // 3
-                Term::Factor(Factor::Constant(3.0)),
+                Term::Factor(Factor::Constant(3.0.into())),
             )),
         )
         .await;
@@ -485,7 +457,7 @@ mod tests {
         parse_test(
             "x=2",
             Ast::Equality(
-                Factor::Variable(MathIdentifier::from_single_ident("x")).into(),
+                Factor::from(MathIdentifier::from_single_ident("x")).into(),
                 2f64.into(),
             ),
         )
@@ -0,0 +1,248 @@
+//! # Quadrature
+//! Numeric integration using adaptive Gauss–Kronrod quadrature.
+//!
+//! The interval is estimated using a 15 point Kronrod rule and the embedded 7
+//! point Gauss rule, where the difference between the two is used as an
+//! estimate of the error. Intervals where the error is too large are split in
+//! half until the error is small enough or the recursion limit is reached.
+use crate::prelude::*;
+
+/// The tolerance used by [integrate] for the absolute error of the result.
+pub const DEFAULT_TOLERANCE: f64 = 1e-10;
+
+/// The maximum amount of times an interval is split in half.
+pub const MAX_DEPTH: usize = 12;
+
+/// The nodes of the 15 point Kronrod rule on \[-1, 1\]. Only the non-negative
+/// half is stored since the nodes are symmetric. Every odd node is also a
+/// node of the 7 point Gauss rule.
+const KRONROD_NODES: [f64; 8] = [
+    0.991_455_371_120_812_6,
+    0.949_107_912_342_758_5,
+    0.864_864_423_359_769_1,
+    0.741_531_185_599_394_4,
+    0.586_087_235_467_691_1,
+    0.405_845_151_377_397_2,
+    0.207_784_955_007_898_5,
+    0.0,
+];
+
+/// The weights of the 15 point Kronrod rule, matching [KRONROD_NODES].
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022_935_322_010_529_22,
+    0.063_092_092_629_978_55,
+    0.104_790_010_322_250_2,
+    0.140_653_259_715_525_9,
+    0.169_004_726_639_267_9,
+    0.190_350_578_064_785_4,
+    0.204_432_940_075_298_9,
+    0.209_482_141_084_727_8,
+];
+
+/// The weights of the 7 point Gauss rule, matching every odd node in
+/// [KRONROD_NODES].
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.129_484_966_168_869_7,
+    0.279_705_391_489_276_7,
+    0.381_830_050_505_118_9,
+    0.417_959_183_673_469_4,
+];
+
+/// The result of numeric integration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quadrature {
+    /// The estimated value of the integral.
+    pub value: f64,
+    /// The estimated absolute error of [Quadrature::value].
+    pub error: f64,
+}
+
+/// Integrates `func` from `lower` to `upper` using adaptive Gauss–Kronrod
+/// quadrature with [DEFAULT_TOLERANCE] and [MAX_DEPTH].
+///
+/// The bounds may be infinite, in which case the interval is mapped to a
+/// finite one before integrating. If `lower` is larger than `upper` the
+/// result is negated, following the usual convention for definite integrals.
+///
+/// Note that reaching the recursion limit is not an error, instead the
+/// returned [Quadrature::error] will be larger than the tolerance.
+///
+/// # Errors
+/// Returns [EvalError::NotANumber] if a bound is NaN, or the first error
+/// returned by `func`.
+pub fn integrate<F>(
+    func: F,
+    lower: f64,
+    upper: f64,
+) -> Result<Quadrature, EvalError>
+where
+    F: Fn(f64) -> Result<f64, EvalError>,
+{
+    if lower.is_nan() || upper.is_nan() {
+        return Err(EvalError::NotANumber);
+    }
+    if lower == upper {
+        return Ok(Quadrature {
+            value: 0.0,
+            error: 0.0,
+        });
+    }
+    if lower > upper {
+        let result = integrate(func, upper, lower)?;
+        return Ok(Quadrature {
+            value: -result.value,
+            ..result
+        });
+    }
+    match (lower.is_finite(), upper.is_finite()) {
+        (true, true) => adaptive(&func, lower, upper, DEFAULT_TOLERANCE, 0),
+        // x = t / (1 - t^2) maps (-1, 1) onto the real line.
+        (false, false) => adaptive(
+            &|t: f64| {
+                let s = 1.0 - t * t;
+                Ok(func(t / s)? * (1.0 + t * t) / (s * s))
+            },
+            -1.0,
+            1.0,
+            DEFAULT_TOLERANCE,
+            0,
+        ),
+        // x = a + t / (1 - t) maps [0, 1) onto [a, inf).
+        (true, false) => adaptive(
+            &|t: f64| {
+                let s = 1.0 - t;
+                Ok(func(lower + t / s)? / (s * s))
+            },
+            0.0,
+            1.0,
+            DEFAULT_TOLERANCE,
+            0,
+        ),
+        // x = b - (1 - t) / t maps (0, 1] onto (-inf, b].
+        (false, true) => adaptive(
+            &|t: f64| Ok(func(upper - (1.0 - t) / t)? / (t * t)),
+            0.0,
+            1.0,
+            DEFAULT_TOLERANCE,
+            0,
+        ),
+    }
+}
+
+/// Estimates the integral and splits the interval in half if the error is
+/// larger than `tolerance`, giving each half half of the tolerance.
+fn adaptive<F>(
+    func: &F,
+    lower: f64,
+    upper: f64,
+    tolerance: f64,
+    depth: usize,
+) -> Result<Quadrature, EvalError>
+where
+    F: Fn(f64) -> Result<f64, EvalError>,
+{
+    let whole = gauss_kronrod(func, lower, upper)?;
+    if whole.error <= tolerance || depth >= MAX_DEPTH {
+        return Ok(whole);
+    }
+    let middle = (lower + upper) / 2.0;
+    let left = adaptive(func, lower, middle, tolerance / 2.0, depth + 1)?;
+    let right = adaptive(func, middle, upper, tolerance / 2.0, depth + 1)?;
+    Ok(Quadrature {
+        value: left.value + right.value,
+        error: left.error + right.error,
+    })
+}
+
+/// Estimates the integral over a single interval using the 15 point Kronrod
+/// rule, with the difference to the 7 point Gauss rule as the error.
+fn gauss_kronrod<F>(
+    func: &F,
+    lower: f64,
+    upper: f64,
+) -> Result<Quadrature, EvalError>
+where
+    F: Fn(f64) -> Result<f64, EvalError>,
+{
+    let center = (lower + upper) / 2.0;
+    let half_length = (upper - lower) / 2.0;
+
+    let center_value = func(center)?;
+    let mut kronrod = center_value * KRONROD_WEIGHTS[7];
+    let mut gauss = center_value * GAUSS_WEIGHTS[3];
+    for (i, node) in KRONROD_NODES.iter().take(7).enumerate() {
+        let offset = half_length * node;
+        let sum = func(center - offset)? + func(center + offset)?;
+        kronrod += KRONROD_WEIGHTS[i] * sum;
+        if i % 2 == 1 {
+            gauss += GAUSS_WEIGHTS[i / 2] * sum;
+        }
+    }
+    Ok(Quadrature {
+        value: kronrod * half_length,
+        error: ((kronrod - gauss) * half_length).abs(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::integrate;
+    use crate::{ast::helper::NumberCompare, error::EvalError};
+
+    fn integrate_test(
+        expected: f64,
+        func: fn(f64) -> f64,
+        lower: f64,
+        upper: f64,
+    ) {
+        let found = integrate(|x| Ok(func(x)), lower, upper).unwrap();
+        if (found.value - expected).abs() > 1e-9 {
+            panic!("Found {:?} expected {}", found, expected);
+        }
+        assert!(found.error < 1e-9, "error too large {:?}", found);
+    }
+
+    #[test]
+    fn polynomial() {
+        integrate_test(1.0 / 3.0, |x| x * x, 0.0, 1.0);
+    }
+
+    #[test]
+    fn sine_half_period() {
+        integrate_test(2.0, f64::sin, 0.0, std::f64::consts::PI);
+    }
+
+    #[test]
+    fn reversed_bounds() {
+        integrate_test(-0.5, |x| x, 1.0, 0.0);
+    }
+
+    #[test]
+    fn empty_interval() {
+        let found = integrate(Ok, 2.0, 2.0).unwrap();
+        assert!(found.value.is_zero());
+    }
+
+    #[test]
+    fn gaussian_over_real_line() {
+        integrate_test(
+            std::f64::consts::PI.sqrt(),
+            |x| (-x * x).exp(),
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+        );
+    }
+
+    #[test]
+    fn exponential_decay_to_infinity() {
+        integrate_test(1.0, |x| (-x).exp(), 0.0, f64::INFINITY);
+    }
+
+    #[test]
+    fn nan_bound() {
+        assert!(matches!(
+            integrate(Ok, f64::NAN, 1.0),
+            Err(EvalError::NotANumber)
+        ));
+    }
+}
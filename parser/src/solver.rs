@@ -0,0 +1,613 @@
+//! # Solver
+//! Numerically finding the roots of a function of a single real variable.
+//!
+//! The search interval is sampled evenly, and every sign change between two
+//! samples is narrowed down with Brent's method, which combines bisection
+//! with secant steps and inverse quadratic interpolation. Roots that touch
+//! zero without changing sign, like the root of `x^2`, can not be bracketed,
+//! so Newton's method is started from the samples closest to zero instead.
+//!
+//! Polynomials of degree up to two do not need any of this, their roots are
+//! found in closed form by [Polynomial::roots].
+//!
+//! Systems of linear equations are solved by Gaussian elimination with
+//! partial pivoting, see [solve_linear].
+use crate::{
+    ast::helper::NumberCompare, matrix::Matrix, precision::Precision,
+    prelude::*,
+};
+
+/// The absolute tolerance for the position of a root.
+pub const DEFAULT_TOLERANCE: f64 = 1e-12;
+
+/// The maximum amount of iterations of Brent's or Newton's method.
+pub const MAX_ITERATIONS: usize = 200;
+
+/// The amount of samples [find_roots] takes of the search interval.
+pub const SAMPLES: usize = 2000;
+
+/// Entries that are at most this large after elimination are treated as
+/// zero, unless they are exact.
+const PIVOT_TOLERANCE: f64 = 1e-10;
+
+/// The solutions are searched for between minus this and this when they
+/// can not be found in closed form.
+pub const SEARCH_RANGE: f64 = 100.0;
+
+/// How close to zero the function has to be at a root, which filters out
+/// sign changes at poles, like the one of `\frac{1}{x}`.
+const RESIDUAL_TOLERANCE: f64 = 1e-8;
+
+/// Which solutions of an equation are wanted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Domain {
+    /// Only the real solutions.
+    #[default]
+    Real,
+    /// The real and the complex solutions.
+    Complex,
+}
+
+impl Domain {
+    /// Whether the value is in the domain.
+    pub const fn contains(self, value: &Value) -> bool {
+        match self {
+            Domain::Real => !matches!(value, Value::Complex(_)),
+            Domain::Complex => true,
+        }
+    }
+}
+
+/// A polynomial in a single unknown, with the coefficients in order of
+/// increasing degree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polynomial(pub Vec<Value>);
+
+impl Polynomial {
+    /// A polynomial of degree zero.
+    pub fn constant(value: Value) -> Self {
+        Polynomial(vec![value])
+    }
+    /// The unknown itself.
+    pub fn unknown() -> Self {
+        Polynomial(vec![Value::from(Number::from(0)), Number::from(1).into()])
+    }
+    /// The degree, or None if every coefficient is zero.
+    pub fn degree(&self) -> Option<usize> {
+        self.0.iter().rposition(|c| !is_zero(c))
+    }
+    /// The sum of the polynomials.
+    ///
+    /// # Errors
+    /// If the coefficients can not be added, for example a matrix and a
+    /// scalar.
+    pub fn add(&self, other: &Polynomial) -> Result<Polynomial, EvalError> {
+        self.zip(other, |a, b| a + b)
+    }
+    /// The difference of the polynomials.
+    ///
+    /// # Errors
+    /// If the coefficients can not be subtracted.
+    pub fn sub(&self, other: &Polynomial) -> Result<Polynomial, EvalError> {
+        self.zip(other, |a, b| a - b)
+    }
+    /// The product of the polynomials, where the coefficients are multiplied
+    /// with `mul_type`.
+    ///
+    /// # Errors
+    /// If the coefficients can not be multiplied.
+    pub fn mul(
+        &self,
+        mul_type: &MulType,
+        other: &Polynomial,
+    ) -> Result<Polynomial, EvalError> {
+        let length = self.0.len() + other.0.len() - 1;
+        let mut product = vec![Value::from(Number::from(0)); length];
+        for (i, a) in self.0.iter().enumerate() {
+            for (j, b) in other.0.iter().enumerate() {
+                product[i + j] =
+                    (product[i + j].clone() + a.mul(mul_type, b)?)?;
+            }
+        }
+        Ok(Polynomial(product))
+    }
+    /// The polynomial divided by a value.
+    ///
+    /// # Errors
+    /// If the coefficients can not be divided by the value.
+    pub fn div(&self, divisor: &Value) -> Result<Polynomial, EvalError> {
+        let quotient: Result<Vec<Value>, EvalError> =
+            self.0.iter().map(|c| c.clone() / divisor.clone()).collect();
+        Ok(Polynomial(quotient?))
+    }
+    /// The polynomial to the power of `exponent`.
+    ///
+    /// # Errors
+    /// If the coefficients can not be multiplied.
+    pub fn pow(&self, exponent: u32) -> Result<Polynomial, EvalError> {
+        let mut power = Polynomial::constant(Number::from(1).into());
+        for _ in 0..exponent {
+            power = power.mul(&MulType::Implicit, self)?;
+        }
+        Ok(power)
+    }
+    /// The roots of a polynomial of degree one or two in closed form, or
+    /// None for higher degrees. The roots are exact when possible and
+    /// otherwise approximated with `precision`, where complex roots come
+    /// after the real roots, which are in ascending order.
+    ///
+    /// # Errors
+    /// [EvalError::InfinitelyManySolutions] if every coefficient is zero, or
+    /// if the coefficients can not be combined, for example matrices.
+    pub fn roots(
+        &self,
+        precision: Precision,
+    ) -> Result<Option<Vec<Value>>, EvalError> {
+        let coefficient = |degree: usize| self.0[degree].clone();
+        let mut roots = match self.degree() {
+            None => return Err(EvalError::InfinitelyManySolutions),
+            Some(0) => vec![],
+            // ax + b = 0
+            Some(1) => {
+                let minus_b = (&coefficient(0) * Number::from(-1))?;
+                vec![(minus_b / coefficient(1))?]
+            }
+            // ax^2 + bx + c = 0
+            Some(2) => {
+                let (a, b, c) =
+                    (coefficient(2), coefficient(1), coefficient(0));
+                let four_a_c =
+                    (&a * Number::from(4))?.mul(&MulType::Implicit, &c)?;
+                let discriminant = (b.mul(&MulType::Implicit, &b)? - four_a_c)?;
+                let two_a = (&a * Number::from(2))?;
+                let minus_b = (&b * Number::from(-1))?;
+                if is_zero(&discriminant) {
+                    vec![(minus_b / two_a)?]
+                } else {
+                    let root = discriminant
+                        .root_with(&Number::from(2).into(), precision)?;
+                    vec![
+                        ((minus_b.clone() - root.clone())? / two_a.clone())?,
+                        ((minus_b + root)? / two_a)?,
+                    ]
+                }
+            }
+            Some(_) => return Ok(None),
+        };
+        roots.sort_by(|a, b| match (a.number(), b.number()) {
+            (Ok(a), Ok(b)) => a.to_f64().total_cmp(&b.to_f64()),
+            (a, b) => b.is_ok().cmp(&a.is_ok()),
+        });
+        Ok(Some(roots))
+    }
+    /// Combines the coefficients of the same degree.
+    fn zip(
+        &self,
+        other: &Polynomial,
+        combine: impl Fn(Value, Value) -> Result<Value, EvalError>,
+    ) -> Result<Polynomial, EvalError> {
+        let zero = Value::from(Number::from(0));
+        let length = self.0.len().max(other.0.len());
+        let coefficients: Result<Vec<Value>, EvalError> = (0..length)
+            .map(|i| {
+                let a = self.0.get(i).unwrap_or(&zero).clone();
+                let b = other.0.get(i).unwrap_or(&zero).clone();
+                combine(a, b)
+            })
+            .collect();
+        Ok(Polynomial(coefficients?))
+    }
+}
+
+/// Whether the value is zero.
+pub(crate) fn is_zero(value: &Value) -> bool {
+    match value {
+        Value::Scalar(number) => number.is_zero(),
+        _ => false,
+    }
+}
+
+/// The solutions of a system of linear equations `Ax = b`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinearSolution {
+    /// Exactly one solution, as a column vector.
+    Unique(Matrix<Value>),
+    /// Infinitely many solutions, which are `particular` plus any linear
+    /// combination of `directions`. The unknowns at `free` can be chosen
+    /// freely, and each of them has the direction with a one at its position.
+    Infinite {
+        /// A solution, where the free unknowns are zero.
+        particular: Matrix<Value>,
+        /// The positions of the unknowns that can be chosen freely.
+        free: Vec<usize>,
+        /// A basis of the null space of `A`, one per free unknown.
+        directions: Vec<Matrix<Value>>,
+    },
+    /// The equations contradict each other.
+    None,
+}
+
+/// Solves `Ax = b` by Gaussian elimination with partial pivoting, where `b`
+/// is a column vector with a row per row of `A`.
+///
+/// # Errors
+/// [IncompatibleMatrixSizes] if `b` does not fit `A`, or an error if the
+/// entries can not be combined, for example nested matrices.
+pub fn solve_linear(
+    a: &Matrix<Value>,
+    b: &Matrix<Value>,
+) -> Result<LinearSolution, EvalError> {
+    if b.column_count() != 1 {
+        return Err(IncompatibleMatrixSizes::Column {
+            expected: 1,
+            found: b.column_count(),
+        }
+        .into());
+    }
+    if b.row_count() != a.row_count() {
+        return Err(IncompatibleMatrixSizes::Row {
+            expected: a.row_count(),
+            found: b.row_count(),
+        }
+        .into());
+    }
+    let unknowns = a.column_count();
+    let mut rows: Vec<Vec<Value>> = (0..a.row_count())
+        .map(|row| {
+            let mut augmented: Vec<Value> = (0..unknowns)
+                .map(|column| a.get(row, column).clone())
+                .collect();
+            augmented.push(b.get(row, 0).clone());
+            augmented
+        })
+        .collect();
+    let (pivots, _) = row_reduce(&mut rows, unknowns)?;
+    // A row of zeros that equals something else, like 0 = 1
+    for row in &rows[pivots.len()..] {
+        if !is_negligible(&row[unknowns])? {
+            return Ok(LinearSolution::None);
+        }
+    }
+    let zero = || Value::from(Number::from(0));
+    let mut particular = vec![zero(); unknowns];
+    for (row, &column) in pivots.iter().enumerate() {
+        particular[column] = rows[row][unknowns].clone();
+    }
+    let particular = Matrix::new(particular, unknowns, 1);
+    if pivots.len() == unknowns {
+        return Ok(LinearSolution::Unique(particular));
+    }
+    let free: Vec<usize> = (0..unknowns)
+        .filter(|column| !pivots.contains(column))
+        .collect();
+    let directions: Result<Vec<Matrix<Value>>, EvalError> = free
+        .iter()
+        .map(|&free_column| {
+            let mut direction = vec![zero(); unknowns];
+            direction[free_column] = Number::from(1).into();
+            for (row, &column) in pivots.iter().enumerate() {
+                direction[column] =
+                    (&rows[row][free_column] * Number::from(-1))?;
+            }
+            Ok(Matrix::new(direction, unknowns, 1))
+        })
+        .collect();
+    Ok(LinearSolution::Infinite {
+        particular,
+        free,
+        directions: directions?,
+    })
+}
+
+/// Brings the first `columns` columns of the rows to reduced row echelon
+/// form, choosing the largest pivot of every column to keep rounding errors
+/// small. Returns the columns of the pivots, where the pivot of the n:th
+/// column is in the n:th row, and the product of the pivots with the sign of
+/// the row swaps, which is the determinant when every column has a pivot.
+pub(crate) fn row_reduce(
+    rows: &mut [Vec<Value>],
+    columns: usize,
+) -> Result<(Vec<usize>, Value), EvalError> {
+    let mut pivots = Vec::new();
+    let mut determinant = Value::from(Number::from(1));
+    for column in 0..columns {
+        let top = pivots.len();
+        if top == rows.len() {
+            break;
+        }
+        let mut best = top;
+        let mut best_size = rows[top][column].abs()?.scalar()?;
+        for (row, values) in rows.iter().enumerate().skip(top + 1) {
+            let size = values[column].abs()?.scalar()?;
+            if size > best_size {
+                (best, best_size) = (row, size);
+            }
+        }
+        if is_negligible(&rows[best][column])? {
+            continue;
+        }
+        if best != top {
+            rows.swap(top, best);
+            determinant = (&determinant * Number::from(-1))?;
+        }
+        let pivot = rows[top][column].clone();
+        determinant = determinant.mul(&MulType::Implicit, &pivot)?;
+        for value in rows[top].iter_mut() {
+            *value = (value.clone() / pivot.clone())?;
+        }
+        for row in 0..rows.len() {
+            if row == top || is_zero(&rows[row][column]) {
+                continue;
+            }
+            let factor = rows[row][column].clone();
+            for i in column..rows[row].len() {
+                let subtracted =
+                    factor.mul(&MulType::Implicit, &rows[top][i])?;
+                rows[row][i] = (rows[row][i].clone() - subtracted)?;
+            }
+        }
+        pivots.push(column);
+    }
+    Ok((pivots, determinant))
+}
+
+/// Whether the value is zero, or close enough to zero if it is approximated
+/// that it is probably a rounding error.
+pub(crate) fn is_negligible(value: &Value) -> Result<bool, EvalError> {
+    Ok(match value {
+        Value::Scalar(number) if number.is_exact() => number.is_zero(),
+        _ => value.abs()?.scalar()? <= PIVOT_TOLERANCE,
+    })
+}
+
+/// Finds the real roots of `func` between `lower` and `upper`, in ascending
+/// order.
+///
+/// `derivative` is the derivative of `func`, which is used by Newton's
+/// method for roots where `func` does not change sign. Points where either
+/// function is not a number, for example outside of their domain, are
+/// skipped.
+///
+/// # Errors
+/// [EvalError::InfinitelyManySolutions] if `func` is zero at every sample
+/// that is a number, like `(\sin x)^2+(\cos x)^2-1`, or the first error
+/// returned by `func` or `derivative`.
+pub fn find_roots<F, D>(
+    func: F,
+    derivative: D,
+    lower: f64,
+    upper: f64,
+) -> Result<Vec<f64>, EvalError>
+where
+    F: Fn(f64) -> Result<f64, EvalError>,
+    D: Fn(f64) -> Result<f64, EvalError>,
+{
+    let step = (upper - lower) / SAMPLES as f64;
+    let points: Vec<f64> =
+        (0..=SAMPLES).map(|i| lower + step * i as f64).collect();
+    let values: Result<Vec<f64>, EvalError> =
+        points.iter().map(|&x| func(x)).collect();
+    let values = values?;
+    let mut numbers = values.iter().filter(|value| !value.is_nan()).peekable();
+    if numbers.peek().is_some()
+        && numbers.all(|value| value.abs() <= RESIDUAL_TOLERANCE)
+    {
+        return Err(EvalError::InfinitelyManySolutions);
+    }
+
+    let mut roots = Vec::new();
+    for i in 0..SAMPLES {
+        let (a, b) = (points[i], points[i + 1]);
+        let (fa, fb) = (values[i], values[i + 1]);
+        if fa == 0.0 {
+            roots.push(a);
+        } else if fa.signum() == -fb.signum() && fb != 0.0 {
+            if let Some(root) = brent(&func, a, b, fa, fb)? {
+                roots.push(root);
+            }
+        }
+    }
+    if values[SAMPLES] == 0.0 {
+        roots.push(upper);
+    }
+    // Samples that are closer to zero than their neighbours may be close to
+    // a root that touches zero
+    for i in 1..SAMPLES {
+        let magnitude = values[i].abs();
+        if magnitude < values[i - 1].abs() && magnitude < values[i + 1].abs() {
+            if let Some(root) = newton(&func, &derivative, points[i])? {
+                roots.push(root);
+            }
+        }
+    }
+    roots.retain(|root| (lower..=upper).contains(root));
+    roots.sort_by(f64::total_cmp);
+    roots.dedup_by(|a, b| (*a - *b).abs() <= 1e-8 * a.abs().max(1.0));
+    Ok(roots)
+}
+
+/// Narrows down a root between `a` and `b` using Brent's method, where `fa`
+/// and `fb` have different signs. Returns None if the sign change is not a
+/// root, for example at a pole.
+///
+/// # Errors
+/// Returns the first error returned by `func`.
+pub fn brent<F>(
+    func: &F,
+    mut a: f64,
+    mut b: f64,
+    mut fa: f64,
+    mut fb: f64,
+) -> Result<Option<f64>, EvalError>
+where
+    F: Fn(f64) -> Result<f64, EvalError>,
+{
+    if fa.abs() < fb.abs() {
+        (a, b, fa, fb) = (b, a, fb, fa);
+    }
+    let (mut c, mut fc) = (a, fa);
+    // The step before the last one, which decides if interpolating helped
+    let mut d = b - a;
+    let mut bisected = true;
+    for _ in 0..MAX_ITERATIONS {
+        if fb == 0.0 || (b - a).abs() < DEFAULT_TOLERANCE {
+            break;
+        }
+        let mut s = if fa != fc && fb != fc {
+            // Inverse quadratic interpolation
+            a * fb * fc / ((fa - fb) * (fa - fc))
+                + b * fa * fc / ((fb - fa) * (fb - fc))
+                + c * fa * fb / ((fc - fa) * (fc - fb))
+        } else {
+            // The secant method
+            b - fb * (b - a) / (fb - fa)
+        };
+        let between = (s - (3.0 * a + b) / 4.0) * (s - b) < 0.0;
+        let slow = match bisected {
+            true => (s - b).abs() >= (b - c).abs() / 2.0,
+            false => (s - b).abs() >= (c - d).abs() / 2.0,
+        };
+        bisected = !between || slow;
+        if bisected {
+            s = (a + b) / 2.0;
+        }
+        let fs = func(s)?;
+        (d, c, fc) = (c, b, fb);
+        if fa.signum() == fs.signum() {
+            (a, fa) = (s, fs);
+        } else {
+            (b, fb) = (s, fs);
+        }
+        if fa.abs() < fb.abs() {
+            (a, b, fa, fb) = (b, a, fb, fa);
+        }
+    }
+    Ok((fb.abs() <= RESIDUAL_TOLERANCE).then_some(b))
+}
+
+/// Finds a root close to `start` using Newton's method, or None if it does
+/// not converge.
+///
+/// # Errors
+/// Returns the first error returned by `func` or `derivative`.
+pub fn newton<F, D>(
+    func: &F,
+    derivative: &D,
+    start: f64,
+) -> Result<Option<f64>, EvalError>
+where
+    F: Fn(f64) -> Result<f64, EvalError>,
+    D: Fn(f64) -> Result<f64, EvalError>,
+{
+    let mut x = start;
+    for _ in 0..MAX_ITERATIONS {
+        let value = func(x)?;
+        if value == 0.0 {
+            return Ok(Some(x));
+        }
+        let slope = derivative(x)?;
+        if slope == 0.0 || !slope.is_finite() || !value.is_finite() {
+            break;
+        }
+        let step = value / slope;
+        x -= step;
+        if step.abs() < DEFAULT_TOLERANCE * x.abs().max(1.0) {
+            break;
+        }
+    }
+    let value = func(x)?;
+    Ok((x.is_finite() && value.abs() <= RESIDUAL_TOLERANCE).then_some(x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_roots, solve_linear, LinearSolution};
+    use crate::{matrix::Matrix, prelude::*};
+    use pretty_assertions::assert_eq;
+
+    /// A matrix of exact integers, row by row.
+    fn matrix(values: &[i64], row_count: usize) -> Matrix<Value> {
+        let values: Vec<Value> = values
+            .iter()
+            .map(|&v| Value::from(Number::from(v)))
+            .collect();
+        let column_count = values.len() / row_count;
+        Matrix::new(values, row_count, column_count)
+    }
+
+    fn roots_test(
+        expected: &[f64],
+        func: fn(f64) -> f64,
+        derivative: fn(f64) -> f64,
+    ) {
+        let found =
+            find_roots(|x| Ok(func(x)), |x| Ok(derivative(x)), -10.0, 10.0)
+                .unwrap();
+        assert_eq!(found.len(), expected.len(), "found {found:?}");
+        for (found, expected) in found.iter().zip(expected) {
+            assert!((found - expected).abs() < 1e-9, "found {found}");
+        }
+    }
+
+    #[test]
+    fn unique_solution_needs_a_row_swap() {
+        // The first pivot is zero, so the rows have to be swapped
+        let a = matrix(&[0, 2, 1, 1, 1, 1, 2, 1, 0], 3);
+        let b = matrix(&[7, 6, 4], 3);
+        let found = solve_linear(&a, &b).unwrap();
+        assert_eq!(found, LinearSolution::Unique(matrix(&[1, 2, 3], 3)));
+    }
+
+    #[test]
+    fn parametric_solution() {
+        // x + 2y = 3 and 2x + 4y = 6 is the same line twice
+        let a = matrix(&[1, 2, 2, 4], 2);
+        let b = matrix(&[3, 6], 2);
+        let LinearSolution::Infinite {
+            particular,
+            free,
+            directions,
+        } = solve_linear(&a, &b).unwrap()
+        else {
+            panic!("Expected infinitely many solutions");
+        };
+        assert_eq!(particular, matrix(&[3, 0], 2));
+        assert_eq!(free, [1]);
+        assert_eq!(directions, [matrix(&[-2, 1], 2)]);
+    }
+
+    #[test]
+    fn contradiction_has_no_solution() {
+        let a = matrix(&[1, 1, 1, 1], 2);
+        let b = matrix(&[1, 2], 2);
+        assert_eq!(solve_linear(&a, &b).unwrap(), LinearSolution::None);
+    }
+
+    #[test]
+    fn cubic() {
+        roots_test(
+            &[-1.0, 0.5, 3.0],
+            |x| (x + 1.0) * (2.0 * x - 1.0) * (x - 3.0),
+            |x| 6.0 * x * x - 10.0 * x - 4.0,
+        );
+    }
+
+    #[test]
+    fn root_without_sign_change() {
+        roots_test(&[2.0], |x| (x - 2.0).powi(2), |x| 2.0 * (x - 2.0));
+    }
+
+    #[test]
+    fn pole_is_not_a_root() {
+        roots_test(&[], |x| 1.0 / (x - 0.3), |x| -1.0 / (x - 0.3).powi(2));
+    }
+
+    #[test]
+    fn transcendental() {
+        roots_test(
+            &[0.739_085_133_215_160_6],
+            |x| x.cos() - x,
+            |x| -x.sin() - 1.0,
+        );
+    }
+}
@@ -1,5 +1,7 @@
 //!All valid token types in the TokenStream
 use std::{fmt::Display, hash::Hash, num::ParseFloatError, str::FromStr};
+
+use crate::number::Number;
 /// All the accepted tokens
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum Token {
@@ -55,12 +57,19 @@ pub enum Token {
     /// String representation:`|`
     VerticalPipe,
 
+    /// String representation:`\|`, also written `\Vert`, `\lVert` and
+    /// `\rVert`
+    DoubleVerticalPipe,
+
     /// String representation:`,`
     Comma,
 
     /// String representation:`&`
     Ampersand,
 
+    /// String representation:`;`
+    Semicolon,
+
     /// String representation:`=`
     Equals,
 
@@ -93,8 +102,10 @@ impl Display for Token {
                 Token::Underscore => "_",
                 Token::Caret => "^",
                 Token::VerticalPipe => "|",
+                Token::DoubleVerticalPipe => "\\|",
                 Token::Comma => ",",
                 Token::Ampersand => "&",
+                Token::Semicolon => ";",
                 Token::EndOfContent => "EOF",
                 Token::Equals => "=",
             }
@@ -127,17 +138,65 @@ impl PartialEq<&Token> for Token {
         *self == **other
     }
 }
+/// Where something is written in the LaTeX, as the character offsets from
+/// `start` up to but not including `end`.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    /// The offset of the first character.
+    pub start: usize,
+    /// The offset after the last character.
+    pub end: usize,
+}
+impl Span {
+    /// Creates a span from `start` up to but not including `end`.
+    pub const fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+    /// The smallest span covering both spans.
+    pub fn to(self, other: Span) -> Self {
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+/// A token together with where it was written in the LaTeX.
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct SpannedToken {
+    /// The token.
+    pub token: Token,
+    /// Where the token was written.
+    pub span: Span,
+}
+impl SpannedToken {
+    /// Creates a token written at the span.
+    pub const fn new(token: Token, span: Span) -> Self {
+        Self { token, span }
+    }
+}
+impl From<Token> for SpannedToken {
+    /// A token that was not written anywhere, like in tests.
+    fn from(token: Token) -> Self {
+        Self::new(token, Span::default())
+    }
+}
 ///The number representation
 #[derive(Debug, Clone)]
 pub struct NumberLiteral {
     ///the raw string without being parsed as a number
     pub raw: String,
     ///The parsed value
-    pub parsed: f64,
+    pub parsed: Number,
 }
 impl Display for NumberLiteral {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.parsed)
+        write!(f, "{}", self.raw)
     }
 }
 impl PartialEq for NumberLiteral {
@@ -156,10 +215,9 @@ impl FromStr for NumberLiteral {
     type Err = ParseFloatError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let i: f64 = s.parse()?;
         Ok(Self {
             raw: s.to_owned(),
-            parsed: i,
+            parsed: s.parse()?,
         })
     }
 }
@@ -7,40 +7,50 @@ use std::{collections::VecDeque, ops::RangeInclusive};
 #[derive(Debug)]
 pub struct TokenReader {
     ///The actual token stream
-    tokens: TokenReceiver,
+    tokens: std::vec::IntoIter<SpannedToken>,
     ///The cached tokens
-    next: VecDeque<Token>,
-    /// if the stream has reached EOF
-    eof: bool,
+    next: VecDeque<SpannedToken>,
+    /// Where the end of content is written, once the stream has reached EOF
+    eof: Option<Span>,
+    /// Where the last read token was written
+    last_span: Span,
+    /// The last read token, if it has not been put back with
+    /// [TokenReader::unread]
+    last: Option<Token>,
 }
 
 impl TokenReader {
-    /// Creates a new TokenReader
-    pub const fn new(tokens: TokenReceiver) -> Self {
+    /// Creates a TokenReader over tokens that are already made, which ends
+    /// with `Token::EndOfContent`.
+    pub fn from_tokens(tokens: Vec<SpannedToken>) -> Self {
         TokenReader {
-            tokens,
+            tokens: tokens.into_iter(),
             next: VecDeque::new(),
-            eof: false,
+            eof: None,
+            last_span: Span::new(0, 0),
+            last: None,
         }
     }
 
     /// Read the next token from the stream, and disregard the "next" queue.
-
-    async fn read_internal(&mut self) -> Token {
+    fn read_internal(&mut self) -> SpannedToken {
         let span = trace_span!("reading_tokens");
         let _enter = span.enter();
-        if self.eof {
+        if let Some(span) = self.eof {
             debug!("sending: {}", Token::EndOfContent);
-            return Token::EndOfContent;
+            return SpannedToken::new(Token::EndOfContent, span);
         }
-        let token = self.tokens.recv().await.expect("Broken pipe");
+        let token = self
+            .tokens
+            .next()
+            .expect("Expected the tokens to end with Token::EndOfContent");
         // Handle end of file
-        if token == Token::EndOfContent {
-            self.eof = true;
+        if token.token == Token::EndOfContent {
+            self.eof = Some(token.span);
             debug!("sending: {}", Token::EndOfContent);
-            return Token::EndOfContent;
+            return token;
         }
-        debug!("sending: {}", token);
+        debug!("sending: {}", token.token);
         token
     }
 
@@ -50,8 +60,8 @@ impl TokenReader {
     ///
     /// If end of content is reached, `Token::EndOfContent` will be returned for
     /// subsequent reads.
-    pub async fn peek(&mut self) -> &Token {
-        self.peekn(0).await
+    pub fn peek(&mut self) -> &Token {
+        self.peekn(0)
     }
 
     /// Look at the token a few steps away from the cursor.
@@ -63,9 +73,18 @@ impl TokenReader {
     /// If this method is called out of order, for example `peekn(1)`,
     /// `peekn(3)`, this method will panic since that is usually a sign of a
     /// bug.
-    pub async fn peekn(&mut self, n: usize) -> &Token {
+    pub fn peekn(&mut self, n: usize) -> &Token {
+        &self.peekn_spanned(n).token
+    }
+
+    /// Look at the token a few steps away from the cursor, together with
+    /// where it was written.
+    ///
+    /// ## Panics
+    /// Like [TokenReader::peekn], if it is called out of order.
+    pub fn peekn_spanned(&mut self, n: usize) -> &SpannedToken {
         if self.next.len() == n {
-            let token = self.read_internal().await;
+            let token = self.read_internal();
             self.next.push_back(token);
         }
         if self.next.len() < n {
@@ -82,18 +101,15 @@ impl TokenReader {
     }
 
     /// Peek a range of tokens at once.
-    pub async fn peek_range(
-        &mut self,
-        range: RangeInclusive<usize>,
-    ) -> Vec<&Token> {
+    pub fn peek_range(&mut self, range: RangeInclusive<usize>) -> Vec<&Token> {
         // Ensure we have peeked the tokens.
         for n in range.clone() {
-            self.peekn(n).await;
+            self.peekn(n);
         }
 
         let mut vec = Vec::new();
         for n in range {
-            vec.push(&self.next[n]);
+            vec.push(&self.next[n].token);
         }
         vec
     }
@@ -102,23 +118,54 @@ impl TokenReader {
     ///
     /// If end of file is reached, `Token::EOF` will be returned for subsequent
     /// reads.
-    pub async fn read(&mut self) -> Token {
-        // If we already had it peeked, just consume and return that.
-        if let Some(token) = self.next.pop_front() {
-            return token;
+    pub fn read(&mut self) -> Token {
+        self.read_spanned().token
+    }
+
+    /// Read and consume the next token, together with where it was written.
+    pub fn read_spanned(&mut self) -> SpannedToken {
+        // If we already had it peeked, just consume and return that, otherwise
+        // read from the tokens.
+        let token = match self.next.pop_front() {
+            Some(token) => token,
+            None => self.read_internal(),
+        };
+        self.last_span = token.span;
+        self.last = Some(token.token.clone());
+        token
+    }
+
+    /// The last read token, unless it was put back.
+    pub const fn last(&self) -> Option<&Token> {
+        self.last.as_ref()
+    }
+
+    /// Put the last read token back, so it is the next one again.
+    pub fn unread(&mut self) {
+        if let Some(token) = self.last.take() {
+            self.next
+                .push_front(SpannedToken::new(token, self.last_span));
         }
-        // Read from channel
-        self.read_internal().await
+    }
+
+    /// Where the last read token was written.
+    pub const fn last_span(&self) -> Span {
+        self.last_span
+    }
+
+    /// Where the next token is written.
+    pub fn peek_span(&mut self) -> Span {
+        self.peekn_spanned(0).span
     }
 
     /// Consume the next token.
-    pub async fn skip(&mut self) {
+    pub fn skip(&mut self) {
         // Read but ignore value.
-        _ = self.read().await;
+        _ = self.read();
     }
 
     /// Replace a range of tokens that have been peeked with a vector of
-    /// replacements.
+    /// replacements, which are written where the replaced tokens were.
     ///
     /// ## Examples
     /// ```ignore
@@ -134,7 +181,7 @@ impl TokenReader {
     /// ## Panics
     /// You must peek tokens before calling replace. In other words, you need to
     /// know what you are replacing before calling this function.
-    pub async fn replace(
+    pub fn replace(
         &mut self,
         range: RangeInclusive<usize>,
         replacement: Vec<Token>,
@@ -146,12 +193,15 @@ impl TokenReader {
             );
         }
         let start = *range.start();
+        let mut span = self.next[start].span;
         for _ in range.clone() {
             // Always remove start index because it shifts elements down.
-            self.next.remove(start).expect("length already checked");
+            let removed =
+                self.next.remove(start).expect("length already checked");
+            span = span.to(removed.span);
         }
         for token in replacement.into_iter().rev() {
-            self.next.insert(*range.start(), token);
+            self.next.insert(start, SpannedToken::new(token, span));
         }
     }
 }
@@ -160,31 +210,30 @@ impl TokenReader {
 mod tests {
     use crate::prelude::*;
     use pretty_assertions::assert_eq;
-    #[tokio::test]
-    async fn read_test() {
-        let (tx, rx): (TokenSender, TokenReceiver) = mpsc::channel(32);
 
+    fn reader(tokens: &[Token]) -> TokenReader {
+        TokenReader::from_tokens(
+            tokens.iter().cloned().map(Into::into).collect(),
+        )
+    }
+
+    #[test]
+    fn read_test() {
         let tokens = vec![
             Token::LeftCurlyBracket,
             Token::Identifier("5".to_string()),
             Token::RightCurlyBracket,
         ];
 
-        let mut reader = TokenReader::new(rx);
-
-        for token in &tokens {
-            tx.send(token.clone()).await.unwrap();
-        }
+        let mut reader = reader(&tokens);
 
         for token in tokens {
-            assert_eq!(token, reader.read().await);
+            assert_eq!(token, reader.read());
         }
     }
 
-    #[tokio::test]
-    async fn peek_test() {
-        let (tx, rx): (TokenSender, TokenReceiver) = mpsc::channel(32);
-
+    #[test]
+    fn peek_test() {
         let tokens = vec![
             Token::Backslash,
             Token::LeftCurlyBracket,
@@ -193,25 +242,19 @@ mod tests {
             Token::RightBracket,
         ];
 
-        let mut reader = TokenReader::new(rx);
-
-        for token in &tokens {
-            tx.send(token.clone()).await.unwrap();
-        }
+        let mut reader = reader(&tokens);
 
-        assert_eq!(Token::Backslash, reader.peek().await);
-        assert_eq!(Token::Backslash, reader.read().await);
-        assert_eq!(Token::LeftCurlyBracket, reader.peek().await);
-        assert_eq!(Token::LeftCurlyBracket, reader.read().await);
-        assert_eq!(Token::RightCurlyBracket, reader.read().await);
-        assert_eq!(Token::LeftBracket, reader.read().await);
-        assert_eq!(Token::RightBracket, reader.read().await);
+        assert_eq!(Token::Backslash, reader.peek());
+        assert_eq!(Token::Backslash, reader.read());
+        assert_eq!(Token::LeftCurlyBracket, reader.peek());
+        assert_eq!(Token::LeftCurlyBracket, reader.read());
+        assert_eq!(Token::RightCurlyBracket, reader.read());
+        assert_eq!(Token::LeftBracket, reader.read());
+        assert_eq!(Token::RightBracket, reader.read());
     }
 
-    #[tokio::test]
-    async fn peekn_test() {
-        let (tx, rx): (TokenSender, TokenReceiver) = mpsc::channel(32);
-
+    #[test]
+    fn peekn_test() {
         let tokens = vec![
             Token::Backslash,
             Token::LeftCurlyBracket,
@@ -220,50 +263,40 @@ mod tests {
             Token::RightBracket,
         ];
 
-        let mut reader = TokenReader::new(rx);
-
-        for token in &tokens {
-            tx.send(token.clone()).await.unwrap();
-        }
-
-        assert_eq!(Token::Backslash, reader.peek().await);
-        assert_eq!(Token::LeftCurlyBracket, reader.peekn(1).await);
-        assert_eq!(Token::Backslash, reader.read().await);
-        assert_eq!(Token::LeftCurlyBracket, reader.peekn(0).await);
-        assert_eq!(Token::LeftCurlyBracket, reader.read().await);
-        assert_eq!(Token::RightCurlyBracket, reader.peekn(0).await);
-        assert_eq!(Token::RightCurlyBracket, reader.peekn(0).await);
-        assert_eq!(Token::RightCurlyBracket, reader.read().await);
-        assert_eq!(Token::LeftBracket, reader.read().await);
-        assert_eq!(Token::RightBracket, reader.read().await);
+        let mut reader = reader(&tokens);
+
+        assert_eq!(Token::Backslash, reader.peek());
+        assert_eq!(Token::LeftCurlyBracket, reader.peekn(1));
+        assert_eq!(Token::Backslash, reader.read());
+        assert_eq!(Token::LeftCurlyBracket, reader.peekn(0));
+        assert_eq!(Token::LeftCurlyBracket, reader.read());
+        assert_eq!(Token::RightCurlyBracket, reader.peekn(0));
+        assert_eq!(Token::RightCurlyBracket, reader.peekn(0));
+        assert_eq!(Token::RightCurlyBracket, reader.read());
+        assert_eq!(Token::LeftBracket, reader.read());
+        assert_eq!(Token::RightBracket, reader.read());
     }
 
-    #[tokio::test]
-    async fn peek_read_end_of_content() {
-        let (tx, rx): (TokenSender, TokenReceiver) = mpsc::channel(32);
-
-        let mut reader = TokenReader::new(rx);
-        tx.send(Token::Plus).await.unwrap();
-        tx.send(Token::EndOfContent).await.unwrap();
+    #[test]
+    fn peek_read_end_of_content() {
+        let mut reader = reader(&[Token::Plus, Token::EndOfContent]);
 
-        assert_eq!(Token::Plus, reader.read().await);
-        assert_eq!(Token::EndOfContent, reader.read().await);
+        assert_eq!(Token::Plus, reader.read());
+        assert_eq!(Token::EndOfContent, reader.read());
         for _ in 0..5 {
-            assert_eq!(Token::EndOfContent, reader.peek().await);
+            assert_eq!(Token::EndOfContent, reader.peek());
         }
         for _ in 0..10 {
-            assert_eq!(Token::EndOfContent, reader.read().await);
+            assert_eq!(Token::EndOfContent, reader.read());
         }
         for i in 0..10 {
-            assert_eq!(Token::EndOfContent, reader.peekn(i).await);
+            assert_eq!(Token::EndOfContent, reader.peekn(i));
         }
     }
 
     #[should_panic]
-    #[tokio::test]
-    async fn jump_peek_panic() {
-        let (tx, rx): (TokenSender, TokenReceiver) = mpsc::channel(32);
-
+    #[test]
+    fn jump_peek_panic() {
         let tokens = vec![
             Token::Backslash,
             Token::LeftCurlyBracket,
@@ -272,20 +305,14 @@ mod tests {
             Token::RightBracket,
         ];
 
-        let mut reader = TokenReader::new(rx);
+        let mut reader = reader(&tokens);
 
-        for token in &tokens {
-            tx.send(token.clone()).await.unwrap();
-        }
-
-        assert_eq!(Token::Backslash, reader.peekn(0).await);
-        assert_eq!(Token::RightCurlyBracket, reader.peekn(2).await);
+        assert_eq!(Token::Backslash, reader.peekn(0));
+        assert_eq!(Token::RightCurlyBracket, reader.peekn(2));
     }
 
-    #[tokio::test]
-    async fn replace_test() {
-        let (tx, rx): (TokenSender, TokenReceiver) = mpsc::channel(32);
-
+    #[test]
+    fn replace_test() {
         let tokens = vec![
             Token::LeftBracket,
             Token::Backslash,
@@ -296,52 +323,37 @@ mod tests {
             Token::EndOfContent,
         ];
 
-        let mut reader = TokenReader::new(rx);
+        let mut reader = reader(&tokens);
 
-        for token in &tokens {
-            tx.send(token.clone()).await.unwrap();
-        }
+        assert_eq!(Token::LeftBracket, reader.read());
+        assert_eq!(Token::Backslash, reader.peekn(0));
+        assert_eq!(Token::Identifier("test".to_string()), reader.peekn(1));
+        reader.replace(0..=1, vec![Token::Plus, Token::Minus]);
+        assert_eq!(Token::Plus, reader.read());
+        assert_eq!(Token::Minus, reader.read());
 
-        assert_eq!(Token::LeftBracket, reader.read().await);
-        assert_eq!(Token::Backslash, reader.peekn(0).await);
-        assert_eq!(
-            Token::Identifier("test".to_string()),
-            reader.peekn(1).await
-        );
-        reader.replace(0..=1, vec![Token::Plus, Token::Minus]).await;
-        assert_eq!(Token::Plus, reader.read().await);
-        assert_eq!(Token::Minus, reader.read().await);
-
-        assert_eq!(Token::RightCurlyBracket, reader.read().await);
-        assert_eq!(Token::LeftBracket, reader.read().await);
-        assert_eq!(Token::RightBracket, reader.read().await);
-        assert_eq!(Token::EndOfContent, reader.read().await);
+        assert_eq!(Token::RightCurlyBracket, reader.read());
+        assert_eq!(Token::LeftBracket, reader.read());
+        assert_eq!(Token::RightBracket, reader.read());
+        assert_eq!(Token::EndOfContent, reader.read());
     }
 
-    #[tokio::test]
-    async fn replace_one() {
-        let (tx, rx): (TokenSender, TokenReceiver) = mpsc::channel(32);
-
+    #[test]
+    fn replace_one() {
         let tokens = vec![Token::LeftBracket, Token::Plus, Token::RightBracket];
 
-        let mut reader = TokenReader::new(rx);
+        let mut reader = reader(&tokens);
 
-        for token in &tokens {
-            tx.send(token.clone()).await.unwrap();
-        }
-
-        assert_eq!(Token::LeftBracket, reader.read().await);
-        assert_eq!(Token::Plus, reader.peekn(0).await);
-        reader.replace(0..=0, vec![Token::Minus]).await;
-        assert_eq!(Token::Minus, reader.read().await);
-        assert_eq!(Token::RightBracket, reader.read().await);
+        assert_eq!(Token::LeftBracket, reader.read());
+        assert_eq!(Token::Plus, reader.peekn(0));
+        reader.replace(0..=0, vec![Token::Minus]);
+        assert_eq!(Token::Minus, reader.read());
+        assert_eq!(Token::RightBracket, reader.read());
     }
 
     #[should_panic]
-    #[tokio::test]
-    async fn replace_without_peeking_panics() {
-        let (tx, rx): (TokenSender, TokenReceiver) = mpsc::channel(32);
-
+    #[test]
+    fn replace_without_peeking_panics() {
         let tokens = vec![
             Token::LeftBracket,
             Token::Backslash,
@@ -352,13 +364,36 @@ mod tests {
             Token::EndOfContent,
         ];
 
-        let mut reader = TokenReader::new(rx);
+        let mut reader = reader(&tokens);
 
-        for token in &tokens {
-            tx.send(token.clone()).await.unwrap();
-        }
+        assert_eq!(Token::LeftBracket, reader.read());
+        reader.replace(0..=1, vec![Token::Asterisk]);
+    }
+
+    #[test]
+    fn replace_keeps_spans() {
+        let tokens = vec![
+            SpannedToken::new(Token::Backslash, Span::new(0, 1)),
+            SpannedToken::new(
+                Token::Identifier("cdot".into()),
+                Span::new(1, 5),
+            ),
+            SpannedToken::new(Token::Plus, Span::new(6, 7)),
+            SpannedToken::new(Token::EndOfContent, Span::new(7, 7)),
+        ];
 
-        assert_eq!(Token::LeftBracket, reader.read().await);
-        reader.replace(0..=1, vec![Token::Asterisk]).await;
+        let mut reader = TokenReader::from_tokens(tokens);
+
+        reader.peek_range(0..=1);
+        reader.replace(0..=1, vec![Token::Asterisk]);
+        assert_eq!(Span::new(0, 5), reader.peek_span());
+        assert_eq!(Token::Asterisk, reader.read());
+        assert_eq!(Span::new(0, 5), reader.last_span());
+        assert_eq!(Token::Plus, reader.read());
+        assert_eq!(Span::new(6, 7), reader.last_span());
+        for _ in 0..3 {
+            assert_eq!(Token::EndOfContent, reader.read());
+            assert_eq!(Span::new(7, 7), reader.last_span());
+        }
     }
 }
@@ -4,13 +4,20 @@ use std::{
     ops::{Add, Div, Mul, Sub},
 };
 
-use crate::{ast::MulType, error::EvalError, matrix::Matrix};
+use num_complex::Complex64;
+
+use crate::{
+    ast::MulType, error::EvalError, matrix::Matrix, number::Number,
+    precision::Precision,
+};
 ///The different types of values that can exist
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
-    ///A normal number
-    Scalar(f64),
+    ///A normal number, which is exact when possible
+    Scalar(Number),
+    ///A complex number, which always has a nonzero imaginary part
+    Complex(Complex64),
     ///A matrix of Values
     Matrix(Matrix<Value>),
 }
@@ -18,17 +25,133 @@ pub enum Value {
 impl Value {
     ///returns as a scalar or fails
     pub fn scalar(&self) -> Result<f64, EvalError> {
+        Ok(self.number()?.to_f64())
+    }
+    /// Returns the exact number of a scalar or fails.
+    pub fn number(&self) -> Result<&Number, EvalError> {
         match self {
-            Value::Scalar(val) => Ok(*val),
+            Value::Scalar(val) => Ok(val),
+            Value::Complex(_) => Err(EvalError::ExpectedReal {
+                found: self.to_string(),
+            }),
             Value::Matrix(m) => {
                 // Treat a 1x1 matrix as a scalar.
                 if m.row_count() == 1 && m.column_count() == 1 {
-                    return m.get(0, 0).scalar();
+                    return m.get(0, 0).number();
                 }
                 Err(EvalError::ExpectedScalar)
             }
         }
     }
+    /// Returns a real or complex scalar as a complex number.
+    pub fn complex(&self) -> Result<Complex64, EvalError> {
+        match self {
+            Value::Complex(val) => Ok(*val),
+            _ => self.scalar().map(Complex64::from),
+        }
+    }
+    /// Returns the value as an integer.
+    ///
+    /// # Errors
+    /// [EvalError::ExpectedInteger] if the value is not a finite integer, or
+    /// [EvalError::ExpectedScalar] if it is a matrix
+    pub fn integer(&self) -> Result<i64, EvalError> {
+        let found = self.number()?;
+        found
+            .to_integer()
+            .ok_or_else(|| EvalError::ExpectedInteger {
+                found: found.to_f64(),
+            })
+    }
+    /// Raises the value to the power of `exponent`, which is complex when
+    /// the real power does not exist, like `(-1)^{\frac{1}{2}}`.
+    ///
+    /// # Errors
+    /// [EvalError::ExpectedScalar] if either value is a matrix
+    pub fn pow(&self, exponent: &Value) -> Result<Value, EvalError> {
+        self.pow_with(exponent, Precision::Double)
+    }
+    /// Raises the value to the power of `exponent` like [Value::pow], but
+    /// approximates real powers with at least the given precision.
+    ///
+    /// A matrix to an integer power is the repeated matrix product, see
+    /// [Matrix::power], so `A^{-1}` is the inverse.
+    ///
+    /// # Errors
+    /// [EvalError::ExpectedScalar] if the exponent is a matrix or the base is
+    /// a matrix with a non-integer exponent, or [EvalError::SingularMatrix]
+    /// if a negative power does not exist
+    pub fn pow_with(
+        &self,
+        exponent: &Value,
+        precision: Precision,
+    ) -> Result<Value, EvalError> {
+        if let (Value::Matrix(m), Ok(exponent)) = (self, exponent.integer()) {
+            return Ok(Value::Matrix(m.power(exponent, precision)?));
+        }
+        if let (Ok(base), Ok(exponent)) = (self.number(), exponent.number()) {
+            let power = base.pow_with(exponent, precision);
+            if !power.is_nan() || base.is_nan() || exponent.is_nan() {
+                return Ok(Value::Scalar(power));
+            }
+        }
+        let base = self.complex()?;
+        // Repeated multiplication keeps i^2 exactly -1
+        Ok(Value::from(match exponent.integer().map(i32::try_from) {
+            Ok(Ok(exponent)) => base.powi(exponent),
+            _ => base.powc(exponent.complex()?),
+        }))
+    }
+    /// The principal root of the given degree, which is complex for even
+    /// roots of negative numbers.
+    ///
+    /// # Errors
+    /// [EvalError::ExpectedScalar] if either value is a matrix
+    pub fn root(&self, degree: &Value) -> Result<Value, EvalError> {
+        self.root_with(degree, Precision::Double)
+    }
+    /// The principal root like [Value::root], but approximates real roots
+    /// with at least the given precision.
+    ///
+    /// # Errors
+    /// [EvalError::ExpectedScalar] if either value is a matrix
+    pub fn root_with(
+        &self,
+        degree: &Value,
+        precision: Precision,
+    ) -> Result<Value, EvalError> {
+        if let (Ok(radicand), Ok(degree)) = (self.number(), degree.number()) {
+            let root = radicand.root_with(degree, precision);
+            if !root.is_nan() || radicand.is_nan() || degree.is_nan() {
+                return Ok(Value::Scalar(root));
+            }
+        }
+        let radicand = self.complex()?;
+        Ok(Value::from(match degree.integer() {
+            Ok(2) => radicand.sqrt(),
+            _ => radicand.powc(degree.complex()?.inv()),
+        }))
+    }
+    /// The value with a real number approximated with the given precision,
+    /// see [Precision::approximate].
+    pub fn approximate(&self, precision: Precision) -> Value {
+        match self {
+            Value::Scalar(number) => {
+                Value::Scalar(precision.approximate(number))
+            }
+            _ => self.clone(),
+        }
+    }
+    /// The absolute value, or the modulus of a complex number.
+    ///
+    /// # Errors
+    /// [EvalError::ExpectedScalar] if the value is a matrix
+    pub fn abs(&self) -> Result<Value, EvalError> {
+        match self {
+            Value::Complex(c) => Ok(Value::from(c.norm())),
+            _ => Ok(Value::Scalar(self.number()?.abs())),
+        }
+    }
     /// mapping it for a function where it is a scalar and if not it returns an
     /// error
     pub fn map_expecting_scalar(
@@ -36,8 +159,8 @@ impl Value {
         func: impl Fn(f64) -> f64,
     ) -> Result<Value, EvalError> {
         match self {
-            Value::Scalar(v) => Ok(Value::Scalar(func(*v))),
             Value::Matrix(_) => Err(EvalError::ExpectedScalar),
+            _ => Ok(Value::from(func(self.scalar()?))),
         }
     }
 }
@@ -46,7 +169,36 @@ impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Value::Scalar(val) => write!(f, "{}", val),
-            Value::Matrix(m) => write!(f, "{:?}", m), // TODO
+            Value::Complex(c) => {
+                // For example 2i, -i or 1-\frac{1}{2}i
+                let coefficient = match c.im.abs() {
+                    1.0 => String::new(),
+                    im => Number::from(im).to_string(),
+                };
+                match (c.re == 0.0, c.im < 0.0) {
+                    (true, false) => write!(f, "{coefficient}i"),
+                    (true, true) => write!(f, "-{coefficient}i"),
+                    (false, false) => {
+                        write!(f, "{}+{coefficient}i", Number::from(c.re))
+                    }
+                    (false, true) => {
+                        write!(f, "{}-{coefficient}i", Number::from(c.re))
+                    }
+                }
+            }
+            // Written like the LaTeX of a matrix in the AST
+            Value::Matrix(m) => {
+                let rows = (0..m.row_count())
+                    .map(|row| {
+                        (0..m.column_count())
+                            .map(|column| m.get(row, column).to_string())
+                            .collect::<Vec<_>>()
+                            .join(" & ")
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" \\\\");
+                write!(f, "\\begin{{bmatrix}}{rows}\\end{{bmatrix}}")
+            }
         }
     }
 }
@@ -64,12 +216,14 @@ impl Add for Value {
         Ok(match (self, rhs) {
             (Value::Scalar(a), Value::Scalar(b)) => Value::Scalar(a + b),
             (Value::Matrix(a), Value::Matrix(b)) => Value::Matrix((a + b)?),
-            (Value::Scalar(_), Value::Matrix(_)) => {
-                return type_err("Cannot add a scalar and a matrix.")
+            // A scalar is broadcast to every element
+            (Value::Matrix(a), b) => {
+                Value::Matrix(a.map(|value| value.clone() + b.clone())?)
             }
-            (Value::Matrix(_), Value::Scalar(_)) => {
-                return type_err("Cannot add a matrix and a scalar.")
+            (a, Value::Matrix(b)) => {
+                Value::Matrix(b.map(|value| a.clone() + value.clone())?)
             }
+            (a, b) => Value::from(a.complex()? + b.complex()?),
         })
     }
 }
@@ -81,12 +235,14 @@ impl Sub for Value {
         Ok(match (self, rhs) {
             (Value::Scalar(a), Value::Scalar(b)) => Value::Scalar(a - b),
             (Value::Matrix(a), Value::Matrix(b)) => Value::Matrix((a - b)?),
-            (Value::Scalar(_), Value::Matrix(_)) => {
-                return type_err("Cannot subtract a scalar and a matrix.")
+            // A scalar is broadcast to every element
+            (Value::Matrix(a), b) => {
+                Value::Matrix(a.map(|value| value.clone() - b.clone())?)
             }
-            (Value::Matrix(_), Value::Scalar(_)) => {
-                return type_err("Cannot subtract a matrix and a scalar.")
+            (a, Value::Matrix(b)) => {
+                Value::Matrix(b.map(|value| a.clone() - value.clone())?)
             }
+            (a, b) => Value::from(a.complex()? - b.complex()?),
         })
     }
 }
@@ -99,11 +255,19 @@ impl Value {
         rhs: &Self,
     ) -> Result<Value, EvalError> {
         Ok(match (self, rhs) {
-            (Value::Scalar(a), Value::Scalar(b)) => Value::Scalar(a * b),
+            _ if *mul_type == MulType::Oslash => self.element_wise_div(rhs)?,
+            (Value::Scalar(a), Value::Scalar(b)) => {
+                Value::Scalar(a.clone() * b.clone())
+            }
             (Value::Matrix(a), Value::Matrix(b)) => match mul_type {
                 MulType::Implicit => Value::Matrix((a.matrix_mul(b))?),
                 MulType::Cdot => a.dot_product(b)?,
                 MulType::Times => Value::Matrix(a.cross_product(b)?),
+                MulType::Odot => {
+                    Value::Matrix(a.pair_map(b.clone(), |a, b| {
+                        a.mul(&MulType::Implicit, &b)
+                    })?)
+                }
                 _ => {
                     return Err(EvalError::AmbiguousMulType {
                         r#type: mul_type.clone(),
@@ -111,13 +275,33 @@ impl Value {
                 }
             },
             (Value::Scalar(scalar), Value::Matrix(matrix)) => {
-                Value::Matrix((matrix.mul(*scalar))?)
+                Value::Matrix((matrix.mul(scalar.clone()))?)
             }
             (Value::Matrix(matrix), Value::Scalar(scalar)) => {
-                Value::Matrix((matrix * *scalar)?)
+                Value::Matrix((matrix * scalar.clone())?)
             }
+            (Value::Complex(_), Value::Matrix(matrix)) => Value::Matrix(
+                matrix.map(|value| self.mul(&MulType::Implicit, value))?,
+            ),
+            (Value::Matrix(matrix), Value::Complex(_)) => Value::Matrix(
+                matrix.map(|value| value.mul(&MulType::Implicit, rhs))?,
+            ),
+            (a, b) => Value::from(a.complex()? * b.complex()?),
         })
     }
+    /// Division element by element, where a scalar is divided by or divides
+    /// every element of a matrix.
+    fn element_wise_div(&self, rhs: &Value) -> Result<Value, EvalError> {
+        match (self, rhs) {
+            (Value::Matrix(a), Value::Matrix(b)) => {
+                Ok(Value::Matrix(a.pair_map(b.clone(), |a, b| a / b)?))
+            }
+            (a, Value::Matrix(b)) => {
+                Ok(Value::Matrix(b.map(|value| a.clone() / value.clone())?))
+            }
+            (a, b) => a.clone() / b.clone(),
+        }
+    }
 }
 
 impl Div for Value {
@@ -126,17 +310,55 @@ impl Div for Value {
     fn div(self, rhs: Self) -> Self::Output {
         Ok(match (self, rhs) {
             (Value::Scalar(a), Value::Scalar(b)) => Value::Scalar(a / b),
-            (_, _) => {
-                return type_err("Cannot perform division with matrices.")
+            (Value::Matrix(_), Value::Matrix(_)) => {
+                return type_err("Cannot divide a matrix by a matrix.")
             }
+            (Value::Matrix(a), b) => {
+                Value::Matrix(a.map(|value| value.clone() / b.clone())?)
+            }
+            // Scalars commute with matrices, so this is unambiguous
+            (a, Value::Matrix(b)) => {
+                a.mul(&MulType::Implicit, &Value::Matrix(b.inverse()?))?
+            }
+            (a, b) => Value::from(a.complex()? / b.complex()?),
         })
     }
 }
 
-impl Mul<f64> for &Value {
+impl Mul<Number> for &Value {
     type Output = Result<Value, EvalError>;
 
-    fn mul(self, rhs: f64) -> Self::Output {
+    fn mul(self, rhs: Number) -> Self::Output {
         Value::Scalar(rhs).mul(&MulType::Implicit, self)
     }
 }
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Scalar(value.into())
+    }
+}
+
+impl From<Number> for Value {
+    fn from(value: Number) -> Self {
+        Value::Scalar(value)
+    }
+}
+
+impl From<Complex64> for Value {
+    /// Parts that are negligible compared to the size of the number are
+    /// rounding errors and become zero, so `e^{i\pi}` is exactly `-1`. A
+    /// number without an imaginary part becomes a [Value::Scalar].
+    fn from(value: Complex64) -> Self {
+        let tolerance = f64::EPSILON * value.norm();
+        let tidy = |part: f64| match part.abs() <= tolerance {
+            true => 0.0,
+            false => part,
+        };
+        let (re, im) = (tidy(value.re), tidy(value.im));
+        match im == 0.0 {
+            true => Value::from(re),
+            false => Value::Complex(Complex64::new(re, im)),
+        }
+    }
+}
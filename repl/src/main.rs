@@ -1,12 +1,14 @@
 use std::ops::ControlFlow;
 
-use clap::{command, Parser as ClapParser};
+use clap::Parser as ClapParser;
 use colored::Colorize;
 use directories::ProjectDirs;
 use parser::{
-    ast::{simplify::Simplify, Factor, MathExpr, Term},
+    ast::{simplify::Simplify, to_latex::ToLaTeX, Factor, MathExpr, Term},
+    diagnostic::Diagnostic,
     identifier::MathIdentifier,
     prelude::*,
+    solver::{Domain, LinearSolution},
 };
 use rustyline::{
     error::ReadlineError, history::FileHistory, DefaultEditor, Editor,
@@ -81,15 +83,20 @@ impl Repl {
                 self.read(&line).await?;
                 let start_parse = Instant::now();
                 let ast = self.parse(&line).await.map_err(|e| {
-                    error!("{}", e);
+                    debug!("could not parse {:?}", e);
+                    let context = self.approximator.context();
+                    let diagnostic = Diagnostic::from_ast_error(&e, context);
+                    println!("{}", diagnostic.render(&line).red());
                     ControlFlow::Continue(())
                 })?;
                 let time_parse = start_parse.elapsed();
 
                 /*
                 use parser::ast::to_latex::ToLaTeX;
-                let deriv =
-                    ast.derivative(&MathIdentifier::from_single_ident("x"));
+                let deriv = ast.derivative(
+                    &MathIdentifier::from_single_ident("x"),
+                    self.approximator.context(),
+                );
 
                 match deriv {
                     Ok(val) => {
@@ -106,7 +113,9 @@ impl Repl {
                 */
                 let start_eval = Instant::now();
                 let s = self.eval(ast).map_err(|e| {
-                    error!("could not evaluate ast {:?}", e);
+                    debug!("could not evaluate ast {:?}", e);
+                    let diagnostic = Diagnostic::from_eval_error(&e);
+                    println!("{}", diagnostic.render(&line).red());
                     ControlFlow::Continue(())
                 })?;
                 let time_eval = start_eval.elapsed();
@@ -188,9 +197,18 @@ impl Repl {
                 if self.simple_ast_mode {
                     println!("{:#?}", simple_expr)
                 }
-                Ok(value_res_to_string(
-                    self.approximator.eval_expr(simple_expr),
-                ))
+                // Show the estimated error when evaluating a lone integral.
+                // The estimate is not carried through other operations, so
+                // for example `2\int_0^1 x dx` only shows the value.
+                if let Some(Factor::Integral(integral)) = simple_expr.factor() {
+                    let q = self
+                        .approximator
+                        .integrate(integral)
+                        .map_err(|e| e.at(integral.span.get()))?;
+                    return Ok(format!("> {} ± {:.1e}", q.value, q.error));
+                }
+                let value = self.approximator.eval_expr(simple_expr)?;
+                Ok(format!("> {}", value))
             }
             Ast::Equality(lhs, rhs) => {
                 let rhs_simple =
@@ -198,32 +216,136 @@ impl Repl {
                 if self.simple_ast_mode {
                     println!("{:#?}={:#?}", lhs, rhs_simple);
                 }
-                ast_equality_to_string(
-                    self.approximator.context_mut(),
-                    lhs,
-                    rhs,
-                )
+                ast_equality_to_string(&mut self.approximator, lhs, rhs)
             }
+            Ast::System(equations) => {
+                system_to_string(&self.approximator, &equations)
+            }
+        }
+    }
+}
+
+/// The free variables of the equations, in order of their first occurrence.
+/// Unless `defined` is set, the variables that are defined are left out.
+fn unknowns(
+    approximator: &Approximator,
+    equations: &[(MathExpr, MathExpr)],
+    defined: bool,
+) -> Vec<MathIdentifier> {
+    let mut unknowns: Vec<MathIdentifier> = Vec::new();
+    for (lhs, rhs) in equations {
+        for variable in
+            lhs.free_variables().into_iter().chain(rhs.free_variables())
+        {
+            if !unknowns.contains(&variable)
+                && variable != MathIdentifier::imaginary_unit()
+                && (defined
+                    || !approximator
+                        .context()
+                        .variables
+                        .contains_key(&variable))
+            {
+                unknowns.push(variable);
+            }
+        }
+    }
+    unknowns
+}
+
+/// Solves a system of linear equations for the variables that are not
+/// defined.
+fn system_to_string(
+    approximator: &Approximator,
+    equations: &[(MathExpr, MathExpr)],
+) -> Result<String, EvalError> {
+    let unknowns = unknowns(approximator, equations, false);
+    let name = |index: usize| unknowns[index].to_latex();
+    Ok(match approximator.solve_system(equations, &unknowns)? {
+        LinearSolution::Unique(x) => {
+            let solutions: Vec<String> = (0..unknowns.len())
+                .map(|i| format!("{}={}", name(i), x.get(i, 0)))
+                .collect();
+            format!("> {}", solutions.join(", "))
         }
+        LinearSolution::Infinite {
+            particular,
+            free,
+            directions,
+        } => {
+            let solutions: Vec<String> = (0..unknowns.len())
+                .filter(|i| !free.contains(i))
+                .map(|i| {
+                    let terms = free
+                        .iter()
+                        .zip(&directions)
+                        .map(|(&f, direction)| (direction.get(i, 0), name(f)));
+                    format!(
+                        "{}={}",
+                        name(i),
+                        combination(particular.get(i, 0), terms)
+                    )
+                })
+                .collect();
+            let free: Vec<String> = free.iter().map(|&f| name(f)).collect();
+            format!(
+                "> {} where {} can be anything",
+                solutions.join(", "),
+                free.join(", ")
+            )
+        }
+        LinearSolution::None => "> no solutions".to_owned(),
+    })
+}
+
+/// Writes `constant` plus the coefficients times the names, leaving out the
+/// terms that are zero.
+fn combination<'a>(
+    constant: &Value,
+    terms: impl Iterator<Item = (&'a Value, String)>,
+) -> String {
+    let is = |value: &Value, number: f64| value.scalar().ok() == Some(number);
+    let mut text = String::new();
+    for (coefficient, name) in terms {
+        if is(coefficient, 0.0) {
+            continue;
+        }
+        let coefficient = match coefficient.to_string() {
+            _ if is(coefficient, 1.0) => String::new(),
+            _ if is(coefficient, -1.0) => "-".to_owned(),
+            c => c,
+        };
+        if !text.is_empty() && !coefficient.starts_with('-') {
+            text.push('+');
+        }
+        text.push_str(&format!("{coefficient}{name}"));
+    }
+    match (is(constant, 0.0), text.is_empty()) {
+        (true, false) => text,
+        (_, true) => constant.to_string(),
+        (false, false) if text.starts_with('-') => format!("{constant}{text}"),
+        (false, false) => format!("{constant}+{text}"),
     }
 }
 
 fn ast_equality_to_string(
-    cont: &mut MathContext,
+    approximator: &mut Approximator,
     lhs: MathExpr,
     rhs: MathExpr,
 ) -> Result<String, EvalError> {
+    let cont = approximator.context_mut();
     if let MathExpr::Term(Term::Multiply(
         parser::ast::MulType::Implicit,
         var,
         factor,
     )) = lhs
     {
-        if let Term::Factor(Factor::Variable(function_name)) = &*var {
+        if let Term::Factor(Factor::Variable(function_name, _)) = &*var {
             match factor {
                 Factor::Parenthesis(f) => {
-                    if let MathExpr::Term(Term::Factor(Factor::Variable(arg))) =
-                        *f
+                    if let MathExpr::Term(Term::Factor(Factor::Variable(
+                        arg,
+                        _,
+                    ))) = *f
                     {
                         let variable_name = arg.clone();
                         cont.add_function(
@@ -235,7 +357,7 @@ fn ast_equality_to_string(
                         todo!("you cant have anything but a variable in a function definition")
                     }
                 }
-                Factor::Matrix(matrix) => {
+                Factor::Matrix(matrix, _) => {
                     if matrix.is_vector() {
                         let vec = matrix.get_all_vector_elements();
                         let args: Option<Vec<MathIdentifier>> = vec
@@ -243,7 +365,7 @@ fn ast_equality_to_string(
                             .cloned()
                             .map(|v| match v {
                                 MathExpr::Term(Term::Factor(
-                                    Factor::Variable(var),
+                                    Factor::Variable(var, _),
                                 )) => Some(var),
                                 _ => None,
                             })
@@ -276,19 +398,39 @@ fn ast_equality_to_string(
                 ))
             );
         }
-    } else if let MathExpr::Term(Term::Factor(Factor::Variable(ident))) = lhs {
+    } else if let MathExpr::Term(Term::Factor(Factor::Variable(ident, _))) = lhs
+    {
         cont.variables.insert(ident, rhs);
         Ok("added variable".to_owned())
     } else {
-        todo!("Could not understand equals. got:{:#?}", lhs);
-    }
-}
-fn value_res_to_string(result: Result<Value, EvalError>) -> String {
-    match result {
-        Ok(v) => format!("> {}", v),
-        Err(e) => {
-            error!("Could not evaluate {:?}", e);
-            format!("Could not evaluate {:?}", e)
+        // Other equations are solved for their only unknown, which may be
+        // defined like x in `x^2=4` after `x=1`
+        let equation = [(lhs, rhs)];
+        let unknowns = match unknowns(approximator, &equation, false) {
+            undefined if undefined.is_empty() => {
+                unknowns(approximator, &equation, true)
+            }
+            undefined => undefined,
+        };
+        let [unknown] = &unknowns[..] else {
+            return Err(EvalError::ExpectedSingleUnknown {
+                found: unknowns.len(),
+            });
+        };
+        let [(lhs, rhs)] = equation;
+        let solutions = approximator.solve(
+            &Ast::Equality(lhs, rhs),
+            unknown,
+            Domain::Complex,
+        )?;
+        if solutions.is_empty() {
+            return Ok("> no solutions".to_owned());
         }
+        let name = unknown.to_latex();
+        let solutions: Vec<String> = solutions
+            .iter()
+            .map(|value| format!("{name}={value}"))
+            .collect();
+        Ok(format!("> {}", solutions.join(", ")))
     }
 }
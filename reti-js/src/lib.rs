@@ -1,20 +1,15 @@
-use console_error_panic_hook;
-use lazy_static::lazy_static;
 use parser::{
     approximator::Approximator,
     ast::{simplify::Simplify, Ast},
     context::MathContext,
-    parse as parse_internal,
+    diagnostic::Diagnostic,
+    parse_sync, parse_with_recovery_sync,
 };
 
-lazy_static! {
-    static ref RT: Runtime = Builder::new_current_thread().build().unwrap();
-}
 #[wasm_bindgen(start)]
 pub fn init_wasm() {
     console_error_panic_hook::set_once();
 }
-use tokio::runtime::{Builder, Runtime};
 use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 #[wasm_bindgen]
 extern "C" {
@@ -23,21 +18,39 @@ extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
 }
+/// Evaluates the LaTeX, or throws a diagnostic with the `message`, the
+/// `span` of the error in the LaTeX and a `hint` on how to fix it.
 #[wasm_bindgen]
-pub fn parse(s: String) -> Result<JsValue, String> {
+pub fn parse(s: String) -> Result<JsValue, JsValue> {
     let aprox = Approximator::new(MathContext::default());
-    let parsed =
-        RT.block_on(async { parse_internal(&s, aprox.context()).await });
-    let parsed = parsed.map_err(|e| format!("{e}"))?;
+    let parsed = parse_sync(&s, aprox.context())
+        .map_err(|e| to_js(&Diagnostic::from_ast_error(&e, aprox.context())))?;
     match parsed {
         Ast::Expression(expr) => expr
             .simple(aprox.context())
             .and_then(|v| aprox.eval_expr(v))
-            .map_err(|e| format!("{e}"))
-            .map(|v| {
-                serde_wasm_bindgen::to_value(&v)
-                    .expect("could not make jsvalue")
-            }),
-        Ast::Equality(_, _) => Err("hello i cant do equality".to_owned()),
+            .map_err(|e| to_js(&Diagnostic::from_eval_error(&e)))
+            .map(|v| to_js(&v)),
+        Ast::Equality(_, _) | Ast::System(_) => Err(to_js(&Diagnostic {
+            message: "hello i cant do equality".to_owned(),
+            span: None,
+            hint: None,
+        })),
     }
 }
+/// Every problem in the LaTeX as a list of diagnostics, for showing them
+/// while the LaTeX is typed.
+#[wasm_bindgen]
+pub fn diagnostics(s: String) -> Result<JsValue, JsValue> {
+    let context = MathContext::default();
+    let (_, errors) = parse_with_recovery_sync(&s, &context)
+        .map_err(|e| to_js(&Diagnostic::from_ast_error(&e, &context)))?;
+    let diagnostics: Vec<Diagnostic> = errors
+        .iter()
+        .map(|e| Diagnostic::from_ast_error(e, &context))
+        .collect();
+    Ok(to_js(&diagnostics))
+}
+fn to_js(value: &impl serde::Serialize) -> JsValue {
+    serde_wasm_bindgen::to_value(value).expect("could not make jsvalue")
+}
@@ -1,4 +1 @@
 pub use parser;
-fn main() {
-    println!("Hello, world!");
-}